@@ -1,9 +1,15 @@
 use base64::Engine;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use super::SpreadsheetError;
+use super::google_sheets4::TokenProvider;
 
 /// OAuth2 token representation containing expiry information.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -58,6 +64,16 @@ pub trait TokenStore {
     fn save_token(&mut self, user_id: &str, token: OAuth2Token);
     /// Retrieve a previously stored token.
     fn get_token(&self, user_id: &str) -> Option<OAuth2Token>;
+    /// Remove the token of the given user, returning it if there was one.
+    /// Stores that cannot remove tokens keep them and return `None`.
+    fn remove_token(&mut self, _user_id: &str) -> Option<OAuth2Token> {
+        None
+    }
+    /// Users with a stored token, sorted. Stores that cannot enumerate their
+    /// users return an empty list.
+    fn user_ids(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 /// In-memory token storage used primarily for tests.
@@ -83,9 +99,31 @@ impl TokenStore for MemoryTokenStore {
     fn get_token(&self, user_id: &str) -> Option<OAuth2Token> {
         self.tokens.get(user_id).cloned()
     }
+
+    fn remove_token(&mut self, user_id: &str) -> Option<OAuth2Token> {
+        self.tokens.remove(user_id)
+    }
+
+    fn user_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.tokens.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
 }
 
+/// Magic bytes identifying a versioned token envelope.
+const ENVELOPE_MAGIC: &[u8; 4] = b"FMLT";
+/// Current token envelope format version.
+const ENVELOPE_VERSION: u8 = 1;
+/// Length of the AES-GCM nonce stored in each envelope.
+const NONCE_LEN: usize = 12;
+
 /// File-based token storage using JSON serialization.
+///
+/// Tokens are encrypted with AES-256-GCM and stored as
+/// `base64(magic || version || key_id || nonce || ciphertext)`. The key id is
+/// derived from the encryption key so that a store opened with several keys
+/// can pick the right one after a key rotation.
 pub struct FileTokenStore {
     path: PathBuf,
     key: [u8; 32],
@@ -95,41 +133,103 @@ pub struct FileTokenStore {
 impl FileTokenStore {
     /// Create a store backed by the given file path. Existing data is loaded if available.
     pub fn new(path: impl Into<PathBuf>, key: [u8; 32]) -> Self {
-        use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::Aead};
+        Self::with_keys(path, key, Vec::new())
+    }
+
+    /// Create a store that encrypts with `key` but can still load data written
+    /// with any of `previous_keys`. Data that cannot be decrypted with any key
+    /// results in an empty store.
+    pub fn with_keys(
+        path: impl Into<PathBuf>,
+        key: [u8; 32],
+        previous_keys: Vec<[u8; 32]>,
+    ) -> Self {
         let path = path.into();
+        let mut keys = vec![key];
+        keys.extend(previous_keys);
         let tokens = std::fs::read_to_string(&path)
             .ok()
             .and_then(|data| {
-                let bytes = base64::engine::general_purpose::STANDARD
-                    .decode(data)
-                    .ok()?;
-                if bytes.len() < 12 {
-                    return None;
-                }
-                let (nonce_bytes, cipher_text) = bytes.split_at(12);
-                let cipher = Aes256Gcm::new_from_slice(&key).ok()?;
-                cipher
-                    .decrypt(Nonce::from_slice(nonce_bytes), cipher_text)
+                base64::engine::general_purpose::STANDARD
+                    .decode(data.trim())
                     .ok()
             })
+            .and_then(|bytes| Self::open_envelope(&bytes, &keys))
             .and_then(|plain| serde_json::from_slice(&plain).ok())
             .unwrap_or_default();
         Self { path, key, tokens }
     }
 
+    /// Returns the short identifier stored in envelopes sealed with `key`.
+    fn key_id(key: &[u8; 32]) -> [u8; 4] {
+        use sha2::{Digest, Sha256};
+        let digest = Sha256::digest(key);
+        [digest[0], digest[1], digest[2], digest[3]]
+    }
+
+    fn decrypt(key: &[u8; 32], data: &[u8]) -> Option<Vec<u8>> {
+        use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::Aead};
+        if data.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce_bytes, cipher_text) = data.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+        cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), cipher_text)
+            .ok()
+    }
+
+    /// Decrypts a stored envelope, trying the key whose id matches first and
+    /// falling back to the legacy `nonce || ciphertext` layout.
+    fn open_envelope(bytes: &[u8], keys: &[[u8; 32]]) -> Option<Vec<u8>> {
+        let versioned = bytes.strip_prefix(ENVELOPE_MAGIC).and_then(|rest| {
+            let (&version, rest) = rest.split_first()?;
+            if version != ENVELOPE_VERSION || rest.len() < 4 {
+                return None;
+            }
+            let (id, payload) = rest.split_at(4);
+            let matching = keys.iter().filter(|k| Self::key_id(k) == id);
+            let others = keys.iter().filter(|k| Self::key_id(k) != id);
+            matching
+                .chain(others)
+                .find_map(|k| Self::decrypt(k, payload))
+        });
+        versioned.or_else(|| keys.iter().find_map(|k| Self::decrypt(k, bytes)))
+    }
+
+    /// Removes the token of `user_id` and writes the remaining tokens back,
+    /// returning the removed token if there was one.
+    pub fn remove(&mut self, user_id: &str) -> Option<OAuth2Token> {
+        let removed = self.tokens.remove(user_id)?;
+        self.persist();
+        Some(removed)
+    }
+
+    /// Removes every stored token.
+    pub fn clear(&mut self) {
+        self.tokens.clear();
+        self.persist();
+    }
+
     fn persist(&self) {
         use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::Aead};
         use rand::RngCore;
-        if let Ok(data) = serde_json::to_vec(&self.tokens) {
-            let cipher = Aes256Gcm::new_from_slice(&self.key).expect("key");
-            let mut nonce = [0u8; 12];
-            rand::rng().fill_bytes(&mut nonce);
-            if let Ok(mut encrypted) = cipher.encrypt(Nonce::from_slice(&nonce), data.as_ref()) {
-                let mut out = nonce.to_vec();
-                out.append(&mut encrypted);
-                let encoded = base64::engine::general_purpose::STANDARD.encode(out);
-                let _ = std::fs::write(&self.path, encoded);
-            }
+        let Ok(data) = serde_json::to_vec(&self.tokens) else {
+            return;
+        };
+        let Ok(cipher) = Aes256Gcm::new_from_slice(&self.key) else {
+            return;
+        };
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rng().fill_bytes(&mut nonce);
+        if let Ok(mut encrypted) = cipher.encrypt(Nonce::from_slice(&nonce), data.as_ref()) {
+            let mut out = ENVELOPE_MAGIC.to_vec();
+            out.push(ENVELOPE_VERSION);
+            out.extend_from_slice(&Self::key_id(&self.key));
+            out.extend_from_slice(&nonce);
+            out.append(&mut encrypted);
+            let encoded = base64::engine::general_purpose::STANDARD.encode(out);
+            let _ = std::fs::write(&self.path, encoded);
         }
     }
 }
@@ -143,6 +243,67 @@ impl TokenStore for FileTokenStore {
     fn get_token(&self, user_id: &str) -> Option<OAuth2Token> {
         self.tokens.get(user_id).cloned()
     }
+
+    fn remove_token(&mut self, user_id: &str) -> Option<OAuth2Token> {
+        self.remove(user_id)
+    }
+
+    fn user_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.tokens.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+/// Scopes requested by [`initial_oauth_login`].
+const LOGIN_SCOPES: &[&str] = &[
+    "https://www.googleapis.com/auth/drive.file",
+    "https://www.googleapis.com/auth/spreadsheets",
+];
+
+/// Token caches written by [`initial_oauth_login`], with one file per user
+/// id. The files belong to the OAuth library and are never parsed: a user is
+/// logged in while their file exists, and logging out deletes it, so users
+/// sharing a file are logged out together.
+pub struct TokenFiles {
+    paths: HashMap<String, PathBuf>,
+}
+
+impl TokenFiles {
+    /// Create a set for the given user ids and token file paths.
+    pub fn new(paths: impl IntoIterator<Item = (String, PathBuf)>) -> Self {
+        Self {
+            paths: paths.into_iter().collect(),
+        }
+    }
+
+    /// Token file of `user_id`, if it exists.
+    pub fn path(&self, user_id: &str) -> Option<&Path> {
+        self.paths
+            .get(user_id)
+            .map(PathBuf::as_path)
+            .filter(|path| path.exists())
+    }
+
+    /// Users whose token file exists, sorted.
+    pub fn user_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .paths
+            .keys()
+            .filter(|id| self.path(id).is_some())
+            .cloned()
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Deletes the token file of `user_id`. Returns `false` if there was none.
+    pub fn remove(&mut self, user_id: &str) -> std::io::Result<bool> {
+        match self.path(user_id) {
+            Some(path) => std::fs::remove_file(path).map(|()| true),
+            None => Ok(false),
+        }
+    }
 }
 
 /// Manages acquiring and refreshing tokens using a provider and store.
@@ -159,8 +320,18 @@ impl<P: AuthProvider, S: TokenStore> AuthManager<P, S> {
 
     /// Ensure a valid token exists for the given user.
     pub fn authenticate(&mut self, user_id: &str) -> Result<OAuth2Token, AuthError> {
+        self.authenticate_with_skew(user_id, Duration::zero())
+    }
+
+    /// Ensure a token exists for the given user that stays valid for at least
+    /// `skew`. Stored tokens expiring sooner are refreshed.
+    pub fn authenticate_with_skew(
+        &mut self,
+        user_id: &str,
+        skew: Duration,
+    ) -> Result<OAuth2Token, AuthError> {
         if let Some(token) = self.store.get_token(user_id) {
-            if token.expires_at > Utc::now() {
+            if token.expires_at > Utc::now() + skew {
                 return Ok(token);
             }
             // token expired - try refresh
@@ -175,6 +346,116 @@ impl<P: AuthProvider, S: TokenStore> AuthManager<P, S> {
     }
 }
 
+/// [`TokenProvider`] that refreshes tokens through an [`AuthManager`].
+///
+/// Each request checks the stored token's `expires_at`. Tokens expiring within
+/// the skew window are refreshed with the [`AuthProvider`] and persisted via
+/// the [`TokenStore`], so long-running imports keep a valid token.
+pub struct RefreshingTokenProvider<P: AuthProvider, S: TokenStore> {
+    manager: Mutex<AuthManager<P, S>>,
+    user_id: String,
+    skew: Duration,
+}
+
+impl<P: AuthProvider, S: TokenStore> RefreshingTokenProvider<P, S> {
+    /// Default window before expiry in which tokens are refreshed.
+    pub const DEFAULT_SKEW_SECONDS: i64 = 60;
+
+    /// Create a provider for `user_id` using the default skew window.
+    pub fn new(provider: P, store: S, user_id: impl Into<String>) -> Self {
+        Self::with_skew(
+            provider,
+            store,
+            user_id,
+            Duration::seconds(Self::DEFAULT_SKEW_SECONDS),
+        )
+    }
+
+    /// Create a provider that refreshes tokens expiring within `skew`.
+    pub fn with_skew(provider: P, store: S, user_id: impl Into<String>, skew: Duration) -> Self {
+        Self {
+            manager: Mutex::new(AuthManager::new(provider, store)),
+            user_id: user_id.into(),
+            skew,
+        }
+    }
+
+    /// Consume the provider and return the underlying manager.
+    pub fn into_manager(self) -> AuthManager<P, S> {
+        self.manager
+            .into_inner()
+            .expect("auth manager mutex poisoned")
+    }
+}
+
+impl<P, S> TokenProvider for RefreshingTokenProvider<P, S>
+where
+    P: AuthProvider + Send + 'static,
+    S: TokenStore + Send + 'static,
+{
+    fn token<'a>(
+        &'a self,
+        _scopes: &'a [&str],
+    ) -> Pin<Box<dyn Future<Output = Result<String, SpreadsheetError>> + Send + 'a>> {
+        let result = self
+            .manager
+            .lock()
+            .expect("auth manager mutex poisoned")
+            .authenticate_with_skew(&self.user_id, self.skew)
+            .map(|t| t.access_token)
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()));
+        Box::pin(async move { result })
+    }
+}
+
+/// Token provider that authenticates as a Google service account.
+///
+/// The service-account key is used to sign a JWT assertion which is exchanged
+/// for an access token at the key's token endpoint. Tokens are cached in
+/// memory and reused until shortly before they expire, so this provider works
+/// on headless servers and in CI where the interactive flow cannot run.
+pub struct ServiceAccountTokenProvider {
+    auth: yup_oauth2::authenticator::DefaultAuthenticator,
+}
+
+impl ServiceAccountTokenProvider {
+    /// Create a provider from a parsed service-account key.
+    pub async fn new(key: yup_oauth2::ServiceAccountKey) -> Result<Self, AuthError> {
+        let auth = yup_oauth2::ServiceAccountAuthenticator::builder(key)
+            .build()
+            .await
+            .map_err(|e| AuthError::Other(e.to_string()))?;
+        Ok(Self { auth })
+    }
+
+    /// Create a provider that exchanges assertions at `token_url` instead of
+    /// the endpoint named in the key.
+    pub async fn with_token_url(
+        mut key: yup_oauth2::ServiceAccountKey,
+        token_url: impl Into<String>,
+    ) -> Result<Self, AuthError> {
+        key.token_uri = token_url.into();
+        Self::new(key).await
+    }
+
+    /// Create a provider from a service-account JSON key file.
+    pub async fn from_key_file(path: impl AsRef<Path>) -> Result<Self, AuthError> {
+        let key = yup_oauth2::read_service_account_key(path)
+            .await
+            .map_err(|e| AuthError::Other(e.to_string()))?;
+        Self::new(key).await
+    }
+}
+
+impl TokenProvider for ServiceAccountTokenProvider {
+    fn token<'a>(
+        &'a self,
+        scopes: &'a [&str],
+    ) -> Pin<Box<dyn Future<Output = Result<String, SpreadsheetError>> + Send + 'a>> {
+        TokenProvider::token(&self.auth, scopes)
+    }
+}
+
 /// Perform the OAuth installed flow and persist tokens to disk.
 pub async fn initial_oauth_login(
     credentials_path: &str,
@@ -198,11 +479,6 @@ pub async fn initial_oauth_login(
         .persist_tokens_to_disk(token_path)
         .build()
         .await?;
-    let _ = auth
-        .token(&[
-            "https://www.googleapis.com/auth/drive.file",
-            "https://www.googleapis.com/auth/spreadsheets",
-        ])
-        .await?;
+    let _ = auth.token(LOGIN_SCOPES).await?;
     Ok(())
 }
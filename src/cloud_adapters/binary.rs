@@ -0,0 +1,213 @@
+use crate::cloud_adapters::file::{DEFAULT_LOCK_TIMEOUT, lock_file};
+use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// Size of the length prefix in front of each record.
+const LENGTH_BYTES: u64 = 4;
+/// Size of each row offset in the index file.
+const OFFSET_BYTES: u64 = 8;
+
+/// Adapter that stores each sheet as an append-only binary log.
+///
+/// Every row is written to `<id>.bin` as a little-endian `u32` length followed
+/// by the bincode-encoded cells, and its byte offset is appended to `<id>.idx`
+/// so `read_row` can seek straight to it. Loading is much faster than parsing
+/// CSV for large ledgers, at the cost of the files not being human-readable.
+/// A truncated or undecodable record fails with
+/// [`SpreadsheetError::Permanent`] rather than being skipped.
+///
+/// Sheets are locked like in [`FileAdapter`](super::FileAdapter): a lock not
+/// acquired within the timeout fails with [`SpreadsheetError::Transient`].
+pub struct BinAdapter {
+    base_dir: PathBuf,
+    next_id: usize,
+    lock_timeout: Duration,
+}
+
+impl BinAdapter {
+    /// Create a new adapter rooted at `base_dir`.
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+            next_id: 1,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+        }
+    }
+
+    /// Sets how long to wait for a lock held by another writer.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Paths of the record log and the row index of a sheet.
+    fn sheet_paths(&self, id: &str) -> (PathBuf, PathBuf) {
+        let sanitized: String = id
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        (
+            self.base_dir.join(format!("{sanitized}.bin")),
+            self.base_dir.join(format!("{sanitized}.idx")),
+        )
+    }
+
+    /// Opens a sheet's log under a shared lock, returning it with its length.
+    fn open_log(&self, sheet_id: &str) -> Result<(File, u64), SpreadsheetError> {
+        let (log_path, _) = self.sheet_paths(sheet_id);
+        if !log_path.exists() {
+            return Err(SpreadsheetError::SheetNotFound);
+        }
+        let file = File::open(&log_path).map_err(transient)?;
+        lock_file(&file, false, self.lock_timeout)?;
+        let len = file.metadata().map_err(transient)?.len();
+        Ok((file, len))
+    }
+}
+
+impl Default for BinAdapter {
+    fn default() -> Self {
+        Self::new(std::env::temp_dir())
+    }
+}
+
+fn transient(e: std::io::Error) -> SpreadsheetError {
+    SpreadsheetError::Transient(e.to_string())
+}
+
+fn corrupt(offset: u64) -> SpreadsheetError {
+    SpreadsheetError::Permanent(format!("corrupt record at byte {offset}"))
+}
+
+/// Reads the record starting at `offset`, where `end` is the length of the
+/// log, returning its cells and its size in bytes. A record running past
+/// `end` or failing to decode is corrupt.
+fn read_record(
+    reader: &mut impl Read,
+    offset: u64,
+    end: u64,
+) -> Result<(Vec<String>, u64), SpreadsheetError> {
+    if end - offset < LENGTH_BYTES {
+        return Err(corrupt(offset));
+    }
+    let mut len = [0; LENGTH_BYTES as usize];
+    reader.read_exact(&mut len).map_err(transient)?;
+    let len = u32::from_le_bytes(len) as u64;
+    if end - offset - LENGTH_BYTES < len {
+        return Err(corrupt(offset));
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload).map_err(transient)?;
+    let row = bincode::deserialize(&payload).map_err(|_| corrupt(offset))?;
+    Ok((row, LENGTH_BYTES + len))
+}
+
+impl CloudSpreadsheetService for BinAdapter {
+    fn create_sheet(&mut self, _title: &str) -> Result<String, SpreadsheetError> {
+        let id = format!("sheet{}", self.next_id);
+        self.next_id += 1;
+        let (log_path, index_path) = self.sheet_paths(&id);
+        File::create(&log_path)
+            .and_then(|_| File::create(&index_path))
+            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+        info!(id, "Created binary sheet");
+        Ok(id)
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        self.append_rows(sheet_id, vec![values])
+    }
+
+    fn append_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SpreadsheetError> {
+        let (log_path, index_path) = self.sheet_paths(sheet_id);
+        if !log_path.exists() {
+            return Err(SpreadsheetError::SheetNotFound);
+        }
+        debug!(
+            sheet_id,
+            rows = rows.len(),
+            "Appending rows to binary sheet"
+        );
+        let log = OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .map_err(transient)?;
+        // The log lock also guards the index, which is only written with it.
+        lock_file(&log, true, self.lock_timeout)?;
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index_path)
+            .map_err(transient)?;
+        let mut offset = log.metadata().map_err(transient)?.len();
+        let mut log = BufWriter::new(&log);
+        let mut index = BufWriter::new(&index);
+        for row in rows {
+            let payload =
+                bincode::serialize(&row).map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+            let len = u32::try_from(payload.len())
+                .map_err(|_| SpreadsheetError::Permanent("row too large".into()))?;
+            log.write_all(&len.to_le_bytes())
+                .and_then(|()| log.write_all(&payload))
+                .map_err(transient)?;
+            index.write_all(&offset.to_le_bytes()).map_err(transient)?;
+            offset += LENGTH_BYTES + payload.len() as u64;
+        }
+        // Flush the log first so an indexed offset always points at a record.
+        log.flush().and_then(|()| index.flush()).map_err(transient)
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        let (mut log, len) = self.open_log(sheet_id)?;
+        debug!(sheet_id, index, "Reading row");
+        let (_, index_path) = self.sheet_paths(sheet_id);
+        let mut offsets = File::open(index_path).map_err(transient)?;
+        let position = index as u64 * OFFSET_BYTES;
+        if position + OFFSET_BYTES > offsets.metadata().map_err(transient)?.len() {
+            return Err(SpreadsheetError::RowNotFound);
+        }
+        let mut offset = [0; OFFSET_BYTES as usize];
+        offsets
+            .seek(SeekFrom::Start(position))
+            .and_then(|_| offsets.read_exact(&mut offset))
+            .map_err(transient)?;
+        let offset = u64::from_le_bytes(offset);
+        if offset >= len {
+            return Err(corrupt(offset));
+        }
+        log.seek(SeekFrom::Start(offset)).map_err(transient)?;
+        read_record(&mut log, offset, len).map(|(row, _)| row)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let (log, len) = self.open_log(sheet_id)?;
+        debug!(sheet_id, "Listing rows");
+        let mut reader = BufReader::new(log);
+        let mut rows = Vec::new();
+        let mut offset = 0;
+        while offset < len {
+            let (row, size) = read_record(&mut reader, offset, len)?;
+            offset += size;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+
+    fn share_sheet(&self, sheet_id: &str, _email: &str) -> Result<(), SpreadsheetError> {
+        let (log_path, _) = self.sheet_paths(sheet_id);
+        if log_path.exists() {
+            info!(sheet_id, "Sharing sheet locally");
+            Ok(())
+        } else {
+            Err(SpreadsheetError::ShareFailed)
+        }
+    }
+}
@@ -2,6 +2,7 @@ use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
 
 use super::{CloudSpreadsheetService, SpreadsheetError};
+use crate::core::Permission;
 
 /// Policy used to evict cached entries.
 pub enum EvictionPolicy {
@@ -36,10 +37,11 @@ impl<S: CloudSpreadsheetService> BatchingCacheService<S> {
 
     /// Flush pending writes for a specific sheet.
     fn flush_sheet(&mut self, sheet_id: &str) -> Result<(), SpreadsheetError> {
-        if let Some(rows) = self.batches.borrow_mut().remove(sheet_id) {
-            if !rows.is_empty() {
-                self.inner.append_rows(sheet_id, rows)?;
-            }
+        let pending = self.batches.borrow_mut().remove(sheet_id);
+        if let Some(rows) = pending
+            && !rows.is_empty()
+        {
+            self.inner.append_rows(sheet_id, rows)?;
         }
         Ok(())
     }
@@ -69,10 +71,10 @@ impl<S: CloudSpreadsheetService> BatchingCacheService<S> {
                 }
                 cache.insert(key.clone(), row);
                 order.push_back(key.clone());
-                if order.len() > cap {
-                    if let Some(old) = order.pop_front() {
-                        cache.remove(&old);
-                    }
+                if order.len() > cap
+                    && let Some(old) = order.pop_front()
+                {
+                    cache.remove(&old);
                 }
             }
         }
@@ -132,10 +134,55 @@ impl<S: CloudSpreadsheetService> CloudSpreadsheetService for BatchingCacheServic
         self.inner.list_rows(sheet_id)
     }
 
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let cached: Option<Vec<Vec<String>>> =
+            (start..end).map(|i| self.cache_get(sheet_id, i)).collect();
+        if let Some(rows) = cached {
+            return Ok(rows);
+        }
+        let rows = self.inner.read_rows(sheet_id, start, end)?;
+        for (offset, row) in rows.iter().enumerate() {
+            self.cache_insert(sheet_id, start + offset, row.clone());
+        }
+        Ok(rows)
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        // Pending rows may include the one being updated.
+        self.flush_sheet(sheet_id)?;
+        self.inner.update_row(sheet_id, index, values.clone())?;
+        if self
+            .cache
+            .borrow()
+            .contains_key(&(sheet_id.to_string(), index))
+        {
+            self.cache_insert(sheet_id, index, values);
+        }
+        Ok(())
+    }
+
     fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
         self.inner.share_sheet(sheet_id, email)
     }
 
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.inner.revoke_share(sheet_id, email)
+    }
+
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        self.inner.list_shares(sheet_id)
+    }
+
     fn append_rows(
         &mut self,
         sheet_id: &str,
@@ -0,0 +1,143 @@
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use super::{CloudSpreadsheetService, SpreadsheetError};
+use crate::core::Permission;
+
+/// Wrapper that skips appending record rows whose id is already in the sheet.
+///
+/// An append can succeed on the server while its response is lost, so a
+/// retry would write the row a second time. Rows whose first cell is a
+/// record id are checked against the ids already in the sheet and dropped
+/// when present; other rows, such as headers and status rows, are always
+/// appended. The ids of a sheet are read with `list_rows` on the first
+/// append and read again after a failed append, so place this wrapper
+/// inside [`RetryingService`](super::RetryingService) to have each retry
+/// see what the failed attempt wrote.
+pub struct DedupOnAppend<S> {
+    inner: S,
+    ids: HashMap<String, HashSet<Uuid>>,
+}
+
+impl<S: CloudSpreadsheetService> DedupOnAppend<S> {
+    /// Wraps `inner`.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Returns the wrapped service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Appends the rows of `rows` not yet in the sheet.
+    fn append_new(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SpreadsheetError> {
+        if !self.ids.contains_key(sheet_id) {
+            let rows = match self.inner.list_rows(sheet_id) {
+                Err(SpreadsheetError::SheetNotFound) => Vec::new(),
+                rows => rows?,
+            };
+            let existing = rows.iter().filter_map(|row| row_id(row)).collect();
+            self.ids.insert(sheet_id.to_string(), existing);
+        }
+        let known = &self.ids[sheet_id];
+        let rows: Vec<Vec<String>> = rows
+            .into_iter()
+            .filter(|row| row_id(row).is_none_or(|id| !known.contains(&id)))
+            .collect();
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let added: Vec<Uuid> = rows.iter().filter_map(|row| row_id(row)).collect();
+        let result = if let [row] = rows.as_slice() {
+            self.inner.append_row(sheet_id, row.clone())
+        } else {
+            self.inner.append_rows(sheet_id, rows)
+        };
+        match result {
+            Ok(()) => {
+                if let Some(ids) = self.ids.get_mut(sheet_id) {
+                    ids.extend(added);
+                }
+                Ok(())
+            }
+            Err(e) => {
+                // The rows may have been written anyway, so read the sheet
+                // again before the next append.
+                self.ids.remove(sheet_id);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Record id in the first cell of `row`, if it holds one.
+fn row_id(row: &[String]) -> Option<Uuid> {
+    row.first().and_then(|cell| Uuid::parse_str(cell).ok())
+}
+
+impl<S: CloudSpreadsheetService> CloudSpreadsheetService for DedupOnAppend<S> {
+    fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+        self.inner.create_sheet(title)
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        self.append_new(sheet_id, vec![values])
+    }
+
+    fn append_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SpreadsheetError> {
+        self.append_new(sheet_id, rows)
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        self.inner.read_row(sheet_id, index)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        self.inner.list_rows(sheet_id)
+    }
+
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        self.inner.read_rows(sheet_id, start, end)
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        // The replaced row's id is no longer known, so read the ids again.
+        self.ids.remove(sheet_id);
+        self.inner.update_row(sheet_id, index, values)
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.inner.share_sheet(sheet_id, email)
+    }
+
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.inner.revoke_share(sheet_id, email)
+    }
+
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        self.inner.list_shares(sheet_id)
+    }
+}
@@ -0,0 +1,296 @@
+use super::google_sheets4::TokenProvider;
+use crate::cloud_adapters::http::{self, HttpsClient};
+use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
+use http_body_util::Full;
+use hyper::Method;
+use hyper::Request;
+use hyper::body::Bytes;
+use hyper::header;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde_json::json;
+use std::time::Duration;
+use yup_oauth2::hyper_rustls::HttpsConnectorBuilder;
+
+/// Default base URL for the Dropbox RPC API.
+pub const DEFAULT_API_BASE_URL: &str = "https://api.dropboxapi.com/2/";
+/// Default base URL for the Dropbox content API.
+pub const DEFAULT_CONTENT_BASE_URL: &str = "https://content.dropboxapi.com/2/";
+/// Default time to wait for a response to each request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const READ_SCOPE: &str = "files.content.read";
+const WRITE_SCOPE: &str = "files.content.write";
+const SHARING_SCOPE: &str = "sharing.write";
+
+/// Adapter storing each sheet as a CSV file in Dropbox.
+///
+/// Sheet ids are Dropbox file paths. Appends download the file, add the rows
+/// and upload it again, so concurrent writers may overwrite each other.
+pub struct DropboxAdapter {
+    client: HttpsClient,
+    auth: Box<dyn TokenProvider>,
+    /// Runtime owned by the adapter when no shared handle was supplied.
+    _rt: Option<tokio::runtime::Runtime>,
+    handle: tokio::runtime::Handle,
+    api_base_url: String,
+    content_base_url: String,
+    timeout: Duration,
+}
+
+impl DropboxAdapter {
+    /// Create a new adapter using the default Dropbox endpoints.
+    pub fn new<A: TokenProvider>(auth: A) -> Self {
+        Self::with_base_urls(auth, DEFAULT_API_BASE_URL, DEFAULT_CONTENT_BASE_URL)
+    }
+
+    /// Create an adapter with custom API and content base URLs.
+    ///
+    /// The adapter builds and owns its own Tokio runtime. Use
+    /// [`DropboxAdapter::with_runtime`] to share an existing runtime between
+    /// several adapters instead.
+    pub fn with_base_urls<A: TokenProvider>(
+        auth: A,
+        api_base_url: impl Into<String>,
+        content_base_url: impl Into<String>,
+    ) -> Self {
+        let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+        let handle = rt.handle().clone();
+        Self::build(auth, Some(rt), handle, api_base_url, content_base_url)
+    }
+
+    /// Create an adapter that runs its requests on an existing Tokio runtime.
+    ///
+    /// The runtime behind `handle` must be a multi-threaded runtime that
+    /// outlives the adapter. Blocking calls on the adapter must not be made
+    /// from within that runtime's asynchronous context.
+    pub fn with_runtime<A: TokenProvider>(
+        handle: tokio::runtime::Handle,
+        auth: A,
+        api_base_url: impl Into<String>,
+        content_base_url: impl Into<String>,
+    ) -> Self {
+        Self::build(auth, None, handle, api_base_url, content_base_url)
+    }
+
+    fn build<A: TokenProvider>(
+        auth: A,
+        rt: Option<tokio::runtime::Runtime>,
+        handle: tokio::runtime::Handle,
+        api_base_url: impl Into<String>,
+        content_base_url: impl Into<String>,
+    ) -> Self {
+        let https = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .expect("native roots")
+            .https_or_http()
+            .enable_http1()
+            .build();
+        let client = Client::builder(TokioExecutor::new()).build::<_, Full<Bytes>>(https);
+        Self {
+            client,
+            auth: Box::new(auth),
+            _rt: rt,
+            handle,
+            api_base_url: api_base_url.into(),
+            content_base_url: content_base_url.into(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set how long to wait for a response before failing the request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn get_token(&self, scopes: &[&str]) -> Result<String, SpreadsheetError> {
+        self.auth.token(scopes).await
+    }
+
+    /// Downloads the CSV file at `path` and parses it into rows.
+    async fn download(&self, path: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let token = self.get_token(&[READ_SCOPE]).await?;
+        let url = format!("{}files/download", self.content_base_url);
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header("Dropbox-API-Arg", api_arg(&json!({ "path": path })))
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        let status = res.status();
+        let bytes = res.into_body();
+        if status == hyper::StatusCode::CONFLICT {
+            // Dropbox reports missing files as a 409 with a `path/not_found` summary.
+            let body: serde_json::Value = serde_json::from_slice(&bytes[..]).unwrap_or_default();
+            let summary = body["error_summary"].as_str().unwrap_or_default();
+            return Err(if summary.contains("not_found") {
+                SpreadsheetError::SheetNotFound
+            } else {
+                SpreadsheetError::Permanent(summary.to_string())
+            });
+        }
+        if !status.is_success() {
+            return Err(SpreadsheetError::Transient("download failed".into()));
+        }
+        parse_csv(&bytes[..])
+    }
+
+    /// Uploads `rows` as the CSV file at `path` and returns the file metadata.
+    async fn upload(
+        &self,
+        path: &str,
+        rows: &[Vec<String>],
+        mode: &str,
+    ) -> Result<serde_json::Value, SpreadsheetError> {
+        let token = self.get_token(&[WRITE_SCOPE]).await?;
+        let url = format!("{}files/upload", self.content_base_url);
+        let arg = json!({
+            "path": path,
+            "mode": mode,
+            "autorename": mode == "add",
+            "mute": true
+        });
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header("Dropbox-API-Arg", api_arg(&arg))
+            .body(Full::from(Bytes::from(write_csv(rows)?)))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("upload failed".into()));
+        }
+        let bytes = res.into_body();
+        serde_json::from_slice(&bytes[..]).map_err(|e| SpreadsheetError::Transient(e.to_string()))
+    }
+}
+
+/// Serializes a `Dropbox-API-Arg` header value. HTTP headers must be ASCII,
+/// so other characters are escaped as JSON `\u` sequences.
+fn api_arg(value: &serde_json::Value) -> String {
+    let mut out = String::new();
+    for c in value.to_string().chars() {
+        if c.is_ascii() {
+            out.push(c);
+        } else {
+            let mut buf = [0u16; 2];
+            for unit in c.encode_utf16(&mut buf) {
+                out.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    out
+}
+
+fn parse_csv(bytes: &[u8]) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(bytes)
+        .records()
+        .map(|record| {
+            record
+                .map(|r| r.iter().map(|s| s.to_string()).collect())
+                .map_err(|e| SpreadsheetError::Permanent(e.to_string()))
+        })
+        .collect()
+}
+
+fn write_csv(rows: &[Vec<String>]) -> Result<Vec<u8>, SpreadsheetError> {
+    let mut wtr = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(Vec::new());
+    for row in rows {
+        wtr.write_record(row)
+            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+    }
+    wtr.into_inner()
+        .map_err(|e| SpreadsheetError::Permanent(e.to_string()))
+}
+
+impl CloudSpreadsheetService for DropboxAdapter {
+    fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+        self.handle.block_on(async {
+            // Dropbox renames the file if the path is taken; the returned
+            // metadata holds the path that was actually allocated.
+            let meta = self.upload(&format!("/{title}.csv"), &[], "add").await?;
+            meta["path_display"]
+                .as_str()
+                .map(|p| p.to_string())
+                .ok_or_else(|| SpreadsheetError::Transient("missing file path".into()))
+        })
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        self.append_rows(sheet_id, vec![values])
+    }
+
+    fn append_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SpreadsheetError> {
+        self.handle.block_on(async {
+            let mut existing = self.download(sheet_id).await?;
+            existing.extend(rows);
+            self.upload(sheet_id, &existing, "overwrite").await?;
+            Ok(())
+        })
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        self.list_rows(sheet_id)?
+            .into_iter()
+            .nth(index)
+            .ok_or(SpreadsheetError::RowNotFound)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        self.handle.block_on(self.download(sheet_id))
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        self.handle.block_on(async {
+            let mut rows = self.download(sheet_id).await?;
+            let row = rows.get_mut(index).ok_or(SpreadsheetError::RowNotFound)?;
+            *row = values;
+            self.upload(sheet_id, &rows, "overwrite").await?;
+            Ok(())
+        })
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.handle.block_on(async {
+            let token = self.get_token(&[SHARING_SCOPE]).await?;
+            let url = format!("{}sharing/add_file_member", self.api_base_url);
+            let body_json = json!({
+                "file": sheet_id,
+                "members": [{".tag": "email", "email": email}],
+                "access_level": "editor"
+            });
+            let req = Request::builder()
+                .method(Method::POST)
+                .uri(&url)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Full::from(Bytes::from(body_json.to_string())))
+                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+            if res.status().is_success() {
+                Ok(())
+            } else {
+                Err(SpreadsheetError::ShareFailed)
+            }
+        })
+    }
+}
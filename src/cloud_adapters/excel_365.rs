@@ -1,50 +1,96 @@
 use super::google_sheets4::TokenProvider;
+use crate::cloud_adapters::http::{self, HttpsClient};
 use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
-use http_body_util::BodyExt;
 use http_body_util::Full;
 use hyper::Method;
 use hyper::Request;
 use hyper::body::Bytes;
 use hyper::header;
 use hyper_util::client::legacy::Client;
-use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::rt::TokioExecutor;
 use serde_json::json;
+use std::time::Duration;
 use yup_oauth2::hyper_rustls::HttpsConnectorBuilder;
 
+/// Default base URL for the Microsoft Graph API.
+pub const DEFAULT_GRAPH_BASE_URL: &str = "https://graph.microsoft.com/v1.0/";
+/// Default worksheet name used when none is configured.
+pub const DEFAULT_SHEET_NAME: &str = "Ledger";
+/// Default number of rows fetched per request by `list_rows`.
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+/// Default time to wait for a response to each request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Name of the table rows are appended to.
+const TABLE_NAME: &str = "Table1";
+
+const HEADER_ROW: [&str; 17] = crate::core::ROW_HEADER;
+
 /// Adapter backed by the Microsoft Graph API for Excel 365.
 pub struct Excel365Adapter {
-    client: Client<yup_oauth2::hyper_rustls::HttpsConnector<HttpConnector>, Full<Bytes>>,
+    client: HttpsClient,
     auth: Box<dyn TokenProvider>,
-    rt: tokio::runtime::Runtime,
+    /// Runtime owned by the adapter when no shared handle was supplied.
+    _rt: Option<tokio::runtime::Runtime>,
+    handle: tokio::runtime::Handle,
     drive_base_url: String,
     sheets_base_url: String,
     sheet_name: String,
+    page_size: usize,
+    timeout: Duration,
 }
 
 impl Excel365Adapter {
     /// Create a new adapter using the default Graph endpoint.
     pub fn new<A: TokenProvider>(auth: A) -> Self {
-        Self::with_base_url_and_sheet_name(auth, "https://graph.microsoft.com/v1.0/", "Ledger")
+        Self::with_base_url_and_sheet_name(auth, DEFAULT_GRAPH_BASE_URL, DEFAULT_SHEET_NAME)
     }
 
     /// Create an adapter with a custom Graph base URL.
     pub fn with_base_url<A: TokenProvider>(auth: A, graph_base_url: impl Into<String>) -> Self {
-        Self::with_base_url_and_sheet_name(auth, graph_base_url, "Ledger")
+        Self::with_base_url_and_sheet_name(auth, graph_base_url, DEFAULT_SHEET_NAME)
     }
 
     /// Create an adapter with a custom sheet name.
     pub fn with_sheet_name<A: TokenProvider>(auth: A, sheet_name: impl Into<String>) -> Self {
-        Self::with_base_url_and_sheet_name(auth, "https://graph.microsoft.com/v1.0/", sheet_name)
+        Self::with_base_url_and_sheet_name(auth, DEFAULT_GRAPH_BASE_URL, sheet_name)
     }
 
     /// Create an adapter with custom base URL and sheet name.
+    ///
+    /// The adapter builds and owns its own Tokio runtime. Use
+    /// [`Excel365Adapter::with_runtime`] to share an existing runtime between
+    /// several adapters instead.
     pub fn with_base_url_and_sheet_name<A: TokenProvider>(
         auth: A,
         graph_base_url: impl Into<String>,
         sheet_name: impl Into<String>,
     ) -> Self {
         let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+        let handle = rt.handle().clone();
+        Self::build(auth, Some(rt), handle, graph_base_url, sheet_name)
+    }
+
+    /// Create an adapter that runs its requests on an existing Tokio runtime.
+    ///
+    /// The runtime behind `handle` must be a multi-threaded runtime that
+    /// outlives the adapter. Blocking calls on the adapter must not be made
+    /// from within that runtime's asynchronous context.
+    pub fn with_runtime<A: TokenProvider>(
+        handle: tokio::runtime::Handle,
+        auth: A,
+        graph_base_url: impl Into<String>,
+        sheet_name: impl Into<String>,
+    ) -> Self {
+        Self::build(auth, None, handle, graph_base_url, sheet_name)
+    }
+
+    fn build<A: TokenProvider>(
+        auth: A,
+        rt: Option<tokio::runtime::Runtime>,
+        handle: tokio::runtime::Handle,
+        graph_base_url: impl Into<String>,
+        sheet_name: impl Into<String>,
+    ) -> Self {
         let https = HttpsConnectorBuilder::new()
             .with_native_roots()
             .expect("native roots")
@@ -56,17 +102,85 @@ impl Excel365Adapter {
         Self {
             client,
             auth: Box::new(auth),
-            rt,
+            _rt: rt,
+            handle,
             drive_base_url: graph_base_url.clone(),
             sheets_base_url: graph_base_url,
             sheet_name: sheet_name.into(),
+            page_size: DEFAULT_PAGE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
         }
     }
 
+    /// Set the number of rows fetched per request when listing rows.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Set how long to wait for a response before failing the request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
     async fn get_token(&self, scopes: &[&str]) -> Result<String, SpreadsheetError> {
         self.auth.token(scopes).await
     }
 
+    /// Reads rows `start..end` (zero-based, end exclusive) of the worksheet,
+    /// dropping the blank rows Graph returns past the end of the data.
+    async fn fetch_range(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let token = self
+            .get_token(&["https://graph.microsoft.com/.default"])
+            .await?;
+        let url = format!(
+            "{}me/drive/items/{}/workbook/worksheets/{}/range(address='A{}:Z{}')",
+            self.sheets_base_url,
+            sheet_id,
+            self.sheet_name,
+            start + 1,
+            end
+        );
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("range read failed".into()));
+        }
+        let bytes = res.into_body();
+        let body: serde_json::Value = serde_json::from_slice(&bytes[..])
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let values = body["values"].as_array().cloned().unwrap_or_default();
+        let mut rows: Vec<Vec<String>> = values
+            .into_iter()
+            .map(|row| {
+                row.as_array()
+                    .unwrap_or(&vec![])
+                    .iter()
+                    .map(|v| v.as_str().unwrap_or_default().to_string())
+                    .collect()
+            })
+            .collect();
+        // Graph pads the range with blank rows past the end of the data.
+        while rows
+            .last()
+            .is_some_and(|row| row.iter().all(|cell| cell.is_empty()))
+        {
+            rows.pop();
+        }
+        Ok(rows)
+    }
+
     async fn ensure_sheet(&self, sheet_id: &str) -> Result<(), SpreadsheetError> {
         let token = self
             .get_token(&["https://graph.microsoft.com/.default"])
@@ -81,18 +195,9 @@ impl Excel365Adapter {
             .header(header::AUTHORIZATION, format!("Bearer {token}"))
             .body(Full::new(Bytes::new()))
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let res = self
-            .client
-            .request(req)
-            .await
-            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
         let exists = if res.status().is_success() {
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
+            let bytes = res.into_body();
             let body: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             body["value"].as_array().is_some_and(|sheets| {
@@ -118,11 +223,7 @@ impl Excel365Adapter {
             .header(header::CONTENT_TYPE, "application/json")
             .body(Full::from(Bytes::from(body_json.to_string())))
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let res = self
-            .client
-            .request(req)
-            .await
-            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
         if res.status().is_success() {
             Ok(())
         } else {
@@ -131,11 +232,109 @@ impl Excel365Adapter {
             ))
         }
     }
+
+    /// Creates the table rows are appended to when the worksheet has none,
+    /// writing [`HEADER_ROW`] as its header.
+    async fn ensure_table(&self, sheet_id: &str) -> Result<(), SpreadsheetError> {
+        let token = self
+            .get_token(&["https://graph.microsoft.com/.default"])
+            .await?;
+        let worksheet = format!(
+            "{}me/drive/items/{}/workbook/worksheets/{}",
+            self.sheets_base_url, sheet_id, self.sheet_name
+        );
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(format!("{worksheet}/tables"))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("table lookup failed".into()));
+        }
+        let bytes = res.into_body();
+        let body: serde_json::Value = serde_json::from_slice(&bytes[..])
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let exists = body["value"].as_array().is_some_and(|tables| {
+            tables
+                .iter()
+                .any(|t| t["name"].as_str() == Some(TABLE_NAME))
+        });
+        if exists {
+            return Ok(());
+        }
+
+        let address = format!("A1:{}1", column_name(HEADER_ROW.len() - 1));
+        let body_json = json!({ "values": [HEADER_ROW] });
+        let req = Request::builder()
+            .method(Method::PATCH)
+            .uri(format!("{worksheet}/range(address='{address}')"))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::from(Bytes::from(body_json.to_string())))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("header write failed".into()));
+        }
+
+        let body_json = json!({ "address": address, "hasHeaders": true });
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(format!("{worksheet}/tables/add"))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::from(Bytes::from(body_json.to_string())))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("table creation failed".into()));
+        }
+        let bytes = res.into_body();
+        let table: serde_json::Value = serde_json::from_slice(&bytes[..])
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        if table["name"].as_str() == Some(TABLE_NAME) {
+            return Ok(());
+        }
+
+        // Graph picks the next free default name, so rename it explicitly.
+        let name = table["name"].as_str().unwrap_or_default();
+        let body_json = json!({ "name": TABLE_NAME });
+        let req = Request::builder()
+            .method(Method::PATCH)
+            .uri(format!("{worksheet}/tables/{name}"))
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::from(Bytes::from(body_json.to_string())))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(SpreadsheetError::Transient("table rename failed".into()))
+        }
+    }
+}
+
+/// Spreadsheet column name for a zero-based column index: `A`, ..., `Z`,
+/// `AA`, ...
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("ASCII column name")
 }
 
 impl CloudSpreadsheetService for Excel365Adapter {
     fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             let token = self
                 .get_token(&["https://graph.microsoft.com/.default"])
                 .await?;
@@ -151,20 +350,11 @@ impl CloudSpreadsheetService for Excel365Adapter {
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if !res.status().is_success() {
                 return Err(SpreadsheetError::Transient("create failed".into()));
             }
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
+            let bytes = res.into_body();
             let body: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             let id = body["id"].as_str().unwrap_or_default().to_string();
@@ -174,13 +364,14 @@ impl CloudSpreadsheetService for Excel365Adapter {
     }
 
     fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             self.ensure_sheet(sheet_id).await?;
+            self.ensure_table(sheet_id).await?;
             let token = self
                 .get_token(&["https://graph.microsoft.com/.default"])
                 .await?;
             let url = format!(
-                "{}me/drive/items/{}/workbook/worksheets/{}/tables/Table1/rows/add",
+                "{}me/drive/items/{}/workbook/worksheets/{}/tables/{TABLE_NAME}/rows/add",
                 self.sheets_base_url, sheet_id, self.sheet_name
             );
             let row: Vec<serde_json::Value> =
@@ -193,11 +384,7 @@ impl CloudSpreadsheetService for Excel365Adapter {
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if res.status().is_success() {
                 Ok(())
             } else {
@@ -207,7 +394,7 @@ impl CloudSpreadsheetService for Excel365Adapter {
     }
 
     fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             self.ensure_sheet(sheet_id).await?;
             let token = self
                 .get_token(&["https://graph.microsoft.com/.default"])
@@ -226,20 +413,11 @@ impl CloudSpreadsheetService for Excel365Adapter {
                 .header(header::AUTHORIZATION, format!("Bearer {token}"))
                 .body(Full::new(Bytes::new()))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if !res.status().is_success() {
                 return Err(SpreadsheetError::RowNotFound);
             }
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
+            let bytes = res.into_body();
             let body: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             let row = body["values"]
@@ -257,13 +435,55 @@ impl CloudSpreadsheetService for Excel365Adapter {
     }
 
     fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
+            self.ensure_sheet(sheet_id).await?;
+            // A single usedRange read is limited in size by Graph, so page
+            // through the sheet until a short page marks the end.
+            let mut rows = Vec::new();
+            loop {
+                let start = rows.len();
+                let page = self
+                    .fetch_range(sheet_id, start, start + self.page_size)
+                    .await?;
+                let done = page.len() < self.page_size;
+                rows.extend(page);
+                if done {
+                    return Ok(rows);
+                }
+            }
+        })
+    }
+
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        self.handle.block_on(async {
+            self.ensure_sheet(sheet_id).await?;
+            self.fetch_range(sheet_id, start, end).await
+        })
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        self.handle.block_on(async {
             self.ensure_sheet(sheet_id).await?;
             let token = self
                 .get_token(&["https://graph.microsoft.com/.default"])
                 .await?;
+            // Any cell can be written, so compare against the used range to
+            // avoid silently growing the sheet.
             let url = format!(
-                "{}me/drive/items/{}/workbook/worksheets/{}/usedRange(valuesOnly=true)",
+                "{}me/drive/items/{}/workbook/worksheets/{}/usedRange(valuesOnly=true)?$select=rowIndex,rowCount,columnCount",
                 self.sheets_base_url, sheet_id, self.sheet_name
             );
             let req = Request::builder()
@@ -272,38 +492,55 @@ impl CloudSpreadsheetService for Excel365Adapter {
                 .header(header::AUTHORIZATION, format!("Bearer {token}"))
                 .body(Full::new(Bytes::new()))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if !res.status().is_success() {
-                return Err(SpreadsheetError::Transient("list failed".into()));
+                return Err(SpreadsheetError::Transient("range read failed".into()));
             }
             let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
-            let body: serde_json::Value = serde_json::from_slice(&bytes[..])
+                .into_body();
+            let used: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let rows = body["values"].as_array().cloned().unwrap_or_default();
-            Ok(rows
+            let count = |key: &str| used[key].as_u64().unwrap_or_default() as usize;
+            if index >= count("rowIndex") + count("rowCount") {
+                return Err(SpreadsheetError::RowNotFound);
+            }
+
+            // Blank out cells beyond the new values left over from a longer row.
+            let width = values.len().max(count("columnCount")).max(1);
+            let row: Vec<serde_json::Value> = values
                 .into_iter()
-                .map(|row| {
-                    row.as_array()
-                        .unwrap_or(&vec![])
-                        .iter()
-                        .map(|v| v.as_str().unwrap_or_default().to_string())
-                        .collect()
-                })
-                .collect())
+                .chain(std::iter::repeat(String::new()))
+                .take(width)
+                .map(serde_json::Value::String)
+                .collect();
+            let url = format!(
+                "{}me/drive/items/{}/workbook/worksheets/{}/range(address='A{}:{}{}')",
+                self.sheets_base_url,
+                sheet_id,
+                self.sheet_name,
+                index + 1,
+                column_name(width - 1),
+                index + 1
+            );
+            let body_json = json!({"values": [row]});
+            let req = Request::builder()
+                .method(Method::PATCH)
+                .uri(&url)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Full::from(Bytes::from(body_json.to_string())))
+                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+            if res.status().is_success() {
+                Ok(())
+            } else {
+                Err(SpreadsheetError::Transient("update failed".into()))
+            }
         })
     }
 
     fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             let token = self
                 .get_token(&["https://graph.microsoft.com/.default"])
                 .await?;
@@ -321,11 +558,7 @@ impl CloudSpreadsheetService for Excel365Adapter {
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if res.status().is_success() {
                 Ok(())
             } else {
@@ -1,12 +1,33 @@
 use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
 use csv::{ReaderBuilder, WriterBuilder};
+use std::fs::{File, TryLockError};
+use std::io::{Seek, SeekFrom, Write};
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
+/// How long to wait for another process to release a sheet by default.
+pub(crate) const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+const HEADER_ROW: [&str; 17] = crate::core::ROW_HEADER;
+
 /// Adapter that stores spreadsheet data in local CSV files.
+///
+/// Sheets are guarded by advisory OS file locks: appends take an exclusive
+/// lock and reads a shared one, so concurrent writers in other threads or
+/// processes are serialized instead of interleaving partial rows. If a lock
+/// cannot be acquired within the timeout the call fails with
+/// [`SpreadsheetError::Transient`].
+///
+/// Like the Google Sheets adapter, the first append to an empty sheet writes
+/// [`HEADER_ROW`] above the data. The header is returned as row 0 by reads,
+/// and the ledger loaders skip it, so files written without one load the
+/// same way.
 pub struct FileAdapter {
     base_dir: PathBuf,
     next_id: usize,
+    lock_timeout: Duration,
+    write_header: bool,
 }
 
 impl FileAdapter {
@@ -15,9 +36,36 @@ impl FileAdapter {
         Self {
             base_dir: base_dir.into(),
             next_id: 1,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            write_header: true,
         }
     }
 
+    /// Stops writing [`HEADER_ROW`] to empty sheets, for files read by tools
+    /// that expect data from the first line.
+    pub fn without_header(mut self) -> Self {
+        self.write_header = false;
+        self
+    }
+
+    /// Sets how long to wait for a lock held by another writer.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Acquires an exclusive or shared lock on `file`, see [`lock_file`].
+    fn lock(&self, file: &File, exclusive: bool) -> Result<(), SpreadsheetError> {
+        lock_file(file, exclusive, self.lock_timeout)
+    }
+
+    /// Opens a sheet for reading under a shared lock.
+    fn open_for_read(&self, path: &std::path::Path) -> Result<File, SpreadsheetError> {
+        let file = File::open(path).map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        self.lock(&file, false)?;
+        Ok(file)
+    }
+
     fn sheet_path(&self, id: &str) -> PathBuf {
         let sanitized: String = id
             .chars()
@@ -27,6 +75,62 @@ impl FileAdapter {
     }
 }
 
+/// Replaces the contents of the locked `file` with `rows`. The rows are
+/// serialized first and written in one call, so a failure while encoding them
+/// leaves the file untouched. The file is rewritten in place rather than
+/// replaced by a renamed copy, as other writers may already hold it open
+/// waiting for the lock and would otherwise append to the detached file.
+fn rewrite(file: &mut File, rows: Vec<Vec<String>>) -> Result<(), SpreadsheetError> {
+    let mut wtr = WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(Vec::new());
+    for row in rows {
+        wtr.write_record(row)
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+    }
+    let buf = wtr
+        .into_inner()
+        .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+    file.set_len(0)
+        .and_then(|()| file.seek(SeekFrom::Start(0)))
+        .and_then(|_| file.write_all(&buf))
+        .and_then(|()| file.sync_data())
+        .map_err(|e| SpreadsheetError::Transient(e.to_string()))
+}
+
+/// Acquires an exclusive or shared lock on `file`, polling until `timeout`
+/// expires and then failing with [`SpreadsheetError::Transient`]. The lock is
+/// released when the file is closed.
+pub(crate) fn lock_file(
+    file: &File,
+    exclusive: bool,
+    timeout: Duration,
+) -> Result<(), SpreadsheetError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let result = if exclusive {
+            file.try_lock()
+        } else {
+            file.try_lock_shared()
+        };
+        match result {
+            Ok(()) => return Ok(()),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(LOCK_POLL_INTERVAL)
+            }
+            Err(TryLockError::WouldBlock) => {
+                return Err(SpreadsheetError::Transient(
+                    "timed out waiting for sheet lock".into(),
+                ));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(SpreadsheetError::Transient(e.to_string()));
+            }
+        }
+    }
+}
+
 impl Default for FileAdapter {
     fn default() -> Self {
         Self::new(std::env::temp_dir())
@@ -61,7 +165,21 @@ impl CloudSpreadsheetService for FileAdapter {
             .append(true)
             .open(&path)
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let mut wtr = WriterBuilder::new().has_headers(false).from_writer(file);
+        self.lock(&file, true)?;
+        // Checked under the lock so concurrent first appends write one header.
+        let empty = file
+            .metadata()
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
+            .len()
+            == 0;
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_writer(file);
+        if empty && self.write_header {
+            wtr.write_record(HEADER_ROW)
+                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        }
         for row in rows {
             wtr.write_record(row)
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
@@ -76,9 +194,11 @@ impl CloudSpreadsheetService for FileAdapter {
             return Err(SpreadsheetError::SheetNotFound);
         }
         debug!(sheet_id, index, "Reading row");
-        let file =
-            std::fs::File::open(&path).map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(file);
+        let file = self.open_for_read(&path)?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(file);
         for (i, record) in rdr.records().enumerate() {
             let rec = record.map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             if i == index {
@@ -94,9 +214,11 @@ impl CloudSpreadsheetService for FileAdapter {
             return Err(SpreadsheetError::SheetNotFound);
         }
         debug!(sheet_id, "Listing rows");
-        let file =
-            std::fs::File::open(&path).map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(file);
+        let file = self.open_for_read(&path)?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(file);
         let mut rows = Vec::new();
         for record in rdr.records() {
             let rec = record.map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
@@ -105,6 +227,49 @@ impl CloudSpreadsheetService for FileAdapter {
         Ok(rows)
     }
 
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        self.update_rows(sheet_id, vec![(index, values)])
+    }
+
+    fn update_rows(
+        &mut self,
+        sheet_id: &str,
+        updates: Vec<(usize, Vec<String>)>,
+    ) -> Result<(), SpreadsheetError> {
+        let path = self.sheet_path(sheet_id);
+        if !path.exists() {
+            return Err(SpreadsheetError::SheetNotFound);
+        }
+        debug!(sheet_id, rows = updates.len(), "Updating rows");
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        // The whole file is rewritten, so hold the exclusive lock throughout.
+        self.lock(&file, true)?;
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(&file);
+        for record in rdr.records() {
+            let rec = record.map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            rows.push(rec.iter().map(|s| s.to_string()).collect());
+        }
+        // Every index is checked before anything is written.
+        for (index, values) in updates {
+            let row = rows.get_mut(index).ok_or(SpreadsheetError::RowNotFound)?;
+            *row = values;
+        }
+        rewrite(&mut file, rows)
+    }
+
     fn share_sheet(&self, sheet_id: &str, _email: &str) -> Result<(), SpreadsheetError> {
         let path = self.sheet_path(sheet_id);
         if path.exists() {
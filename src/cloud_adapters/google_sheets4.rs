@@ -1,34 +1,32 @@
+use crate::cloud_adapters::http::{self, HttpsClient};
 use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
-use http_body_util::BodyExt;
+use crate::core::Permission;
 use http_body_util::Full;
 use hyper::Method;
 use hyper::Request;
 use hyper::body::Bytes;
 use hyper::header;
 use hyper_util::client::legacy::Client;
-use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::rt::TokioExecutor;
 use serde_json::json;
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 use tracing::{debug, info};
 use yup_oauth2::hyper_rustls::HttpsConnectorBuilder;
 
-const HEADER_ROW: [&str; 13] = [
-    "id",
-    "timestamp",
-    "description",
-    "debit_account",
-    "credit_account",
-    "amount",
-    "currency",
-    "reference_id",
-    "external_reference",
-    "tags",
-    "splits",
-    "transaction_date",
-    "hash",
-];
+/// Default base URL for the Google Drive API.
+pub const DEFAULT_DRIVE_BASE_URL: &str = "https://www.googleapis.com/drive/v3/";
+/// Default base URL for the Google Sheets API.
+pub const DEFAULT_SHEETS_BASE_URL: &str = "https://sheets.googleapis.com/v4/";
+/// Default worksheet name used when none is configured.
+pub const DEFAULT_SHEET_NAME: &str = "Ledger";
+/// Default number of rows fetched per request by [`GoogleSheets4Adapter::list_rows`].
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+/// Default time to wait for a response to each request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const HEADER_ROW: [&str; 17] = crate::core::ROW_HEADER;
 /// Asynchronous token retrieval interface used by the adapter.
 pub trait TokenProvider: Send + Sync + 'static {
     fn token<'a>(
@@ -55,12 +53,19 @@ impl TokenProvider for yup_oauth2::authenticator::DefaultAuthenticator {
 
 /// Adapter backed by the Google Sheets REST API.
 pub struct GoogleSheets4Adapter {
-    client: Client<yup_oauth2::hyper_rustls::HttpsConnector<HttpConnector>, Full<Bytes>>,
+    client: HttpsClient,
     auth: Box<dyn TokenProvider>,
-    rt: tokio::runtime::Runtime,
+    /// Runtime owned by the adapter when no shared handle was supplied.
+    _rt: Option<tokio::runtime::Runtime>,
+    handle: tokio::runtime::Handle,
     drive_base_url: String,
     sheets_base_url: String,
     sheet_name: String,
+    /// First and last column of the ledger, when limited with
+    /// [`GoogleSheets4Adapter::with_columns`].
+    columns: Option<(String, String)>,
+    page_size: usize,
+    timeout: Duration,
 }
 
 impl GoogleSheets4Adapter {
@@ -68,9 +73,9 @@ impl GoogleSheets4Adapter {
     pub fn new<A: TokenProvider>(auth: A) -> Self {
         Self::with_base_urls_and_sheet_name(
             auth,
-            "https://www.googleapis.com/drive/v3/",
-            "https://sheets.googleapis.com/v4/",
-            "Ledger",
+            DEFAULT_DRIVE_BASE_URL,
+            DEFAULT_SHEETS_BASE_URL,
+            DEFAULT_SHEET_NAME,
         )
     }
 
@@ -82,8 +87,8 @@ impl GoogleSheets4Adapter {
         Self::with_base_urls_and_sheet_name(
             auth,
             drive_base_url,
-            "https://sheets.googleapis.com/v4/",
-            "Ledger",
+            DEFAULT_SHEETS_BASE_URL,
+            DEFAULT_SHEET_NAME,
         )
     }
 
@@ -91,13 +96,17 @@ impl GoogleSheets4Adapter {
     pub fn with_sheet_name<A: TokenProvider>(auth: A, sheet_name: impl Into<String>) -> Self {
         Self::with_base_urls_and_sheet_name(
             auth,
-            "https://www.googleapis.com/drive/v3/",
-            "https://sheets.googleapis.com/v4/",
+            DEFAULT_DRIVE_BASE_URL,
+            DEFAULT_SHEETS_BASE_URL,
             sheet_name,
         )
     }
 
     /// Create an adapter with custom base URLs and sheet name.
+    ///
+    /// The adapter builds and owns its own Tokio runtime. Use
+    /// [`GoogleSheets4Adapter::with_runtime`] to share an existing runtime
+    /// between several adapters instead.
     pub fn with_base_urls_and_sheet_name<A: TokenProvider>(
         auth: A,
         drive_base_url: impl Into<String>,
@@ -105,6 +114,47 @@ impl GoogleSheets4Adapter {
         sheet_name: impl Into<String>,
     ) -> Self {
         let rt = tokio::runtime::Runtime::new().expect("tokio runtime");
+        let handle = rt.handle().clone();
+        Self::build(
+            auth,
+            Some(rt),
+            handle,
+            drive_base_url,
+            sheets_base_url,
+            sheet_name,
+        )
+    }
+
+    /// Create an adapter that runs its requests on an existing Tokio runtime.
+    ///
+    /// The runtime behind `handle` must be a multi-threaded runtime that
+    /// outlives the adapter. Blocking calls on the adapter must not be made
+    /// from within that runtime's asynchronous context.
+    pub fn with_runtime<A: TokenProvider>(
+        handle: tokio::runtime::Handle,
+        auth: A,
+        drive_base_url: impl Into<String>,
+        sheets_base_url: impl Into<String>,
+        sheet_name: impl Into<String>,
+    ) -> Self {
+        Self::build(
+            auth,
+            None,
+            handle,
+            drive_base_url,
+            sheets_base_url,
+            sheet_name,
+        )
+    }
+
+    fn build<A: TokenProvider>(
+        auth: A,
+        rt: Option<tokio::runtime::Runtime>,
+        handle: tokio::runtime::Handle,
+        drive_base_url: impl Into<String>,
+        sheets_base_url: impl Into<String>,
+        sheet_name: impl Into<String>,
+    ) -> Self {
         let https = HttpsConnectorBuilder::new()
             .with_native_roots()
             .expect("native roots")
@@ -115,10 +165,66 @@ impl GoogleSheets4Adapter {
         Self {
             client,
             auth: Box::new(auth),
-            rt,
+            _rt: rt,
+            handle,
             drive_base_url: drive_base_url.into(),
             sheets_base_url: sheets_base_url.into(),
             sheet_name: sheet_name.into(),
+            columns: None,
+            page_size: DEFAULT_PAGE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set the number of rows fetched per request when listing rows.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Set how long to wait for a response before failing the request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Limit reads and appends to the columns from `first` to `last`, e.g.
+    /// `A` and `N`, so columns the user added beside the ledger are left
+    /// alone. Without a limit rows are read from columns `A` to `Z`.
+    pub fn with_columns(mut self, first: &str, last: &str) -> Self {
+        self.columns = Some((first.to_ascii_uppercase(), last.to_ascii_uppercase()));
+        self
+    }
+
+    /// A1 notation of rows `start` to `end` within the ledger's columns.
+    fn row_range(&self, start: usize, end: usize) -> String {
+        let (first, last) = self
+            .columns
+            .as_ref()
+            .map_or(("A", "Z"), |(f, l)| (f.as_str(), l.as_str()));
+        format!("{}!{first}{start}:{last}{end}", self.sheet_name)
+    }
+
+    /// Range holding the whole ledger: the sheet, or its configured columns.
+    fn table_range(&self) -> String {
+        match &self.columns {
+            Some((first, last)) => format!("{}!{first}:{last}", self.sheet_name),
+            None => self.sheet_name.clone(),
+        }
+    }
+
+    /// Returns an iterator that fetches the sheet one page of rows at a time.
+    ///
+    /// Each item holds up to the configured page size of rows, in sheet
+    /// order. Iteration stops after the first short or empty page, or after
+    /// the first error.
+    pub fn list_rows_paged<'a>(&'a self, sheet_id: &str) -> RowPages<'a> {
+        RowPages {
+            adapter: self,
+            sheet_id: sheet_id.to_string(),
+            next_start: 1,
+            ensured: false,
+            done: false,
         }
     }
 
@@ -132,7 +238,9 @@ impl GoogleSheets4Adapter {
             .await?;
         let url = format!(
             "{}spreadsheets/{}/values/{}",
-            self.sheets_base_url, sheet_id, self.sheet_name
+            self.sheets_base_url,
+            sheet_id,
+            self.table_range()
         );
         let req = Request::builder()
             .method(Method::GET)
@@ -140,20 +248,11 @@ impl GoogleSheets4Adapter {
             .header(header::AUTHORIZATION, format!("Bearer {token}"))
             .body(Full::new(Bytes::new()))
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let res = self
-            .client
-            .request(req)
-            .await
-            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
         if !res.status().is_success() {
             return Err(SpreadsheetError::Transient("list failed".into()));
         }
-        let bytes = res
-            .into_body()
-            .collect()
-            .await
-            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-            .to_bytes();
+        let bytes = res.into_body();
         let body: serde_json::Value = serde_json::from_slice(&bytes[..])
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
         let rows = body["values"].as_array().cloned().unwrap_or_default();
@@ -171,18 +270,9 @@ impl GoogleSheets4Adapter {
             .header(header::AUTHORIZATION, format!("Bearer {token}"))
             .body(Full::new(Bytes::new()))
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let res = self
-            .client
-            .request(req)
-            .await
-            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
         let exists = if res.status().is_success() {
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
+            let bytes = res.into_body();
             let body: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             body["sheets"].as_array().is_some_and(|sheets| {
@@ -212,22 +302,131 @@ impl GoogleSheets4Adapter {
             .header(header::CONTENT_TYPE, "application/json")
             .body(Full::from(Bytes::from(body_json.to_string())))
             .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-        let res = self
-            .client
-            .request(req)
-            .await
-            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
         if res.status().is_success() {
             Ok(())
         } else {
             Err(SpreadsheetError::Transient("batch update failed".into()))
         }
     }
+
+    async fn fetch_permissions(
+        &self,
+        sheet_id: &str,
+    ) -> Result<Vec<serde_json::Value>, SpreadsheetError> {
+        let token = self
+            .get_token(&["https://www.googleapis.com/auth/drive"])
+            .await?;
+        let url = format!(
+            "{}files/{}/permissions?fields=permissions(id,emailAddress,role)",
+            self.drive_base_url, sheet_id
+        );
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("permission list failed".into()));
+        }
+        let bytes = res.into_body();
+        let body: serde_json::Value = serde_json::from_slice(&bytes[..])
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        Ok(body["permissions"].as_array().cloned().unwrap_or_default())
+    }
+
+    async fn fetch_range(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let token = self
+            .get_token(&["https://www.googleapis.com/auth/spreadsheets"])
+            .await?;
+        let range = self.row_range(start, end);
+        let url = format!(
+            "{}spreadsheets/{}/values/{}",
+            self.sheets_base_url, sheet_id, range
+        );
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(&url)
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+        if !res.status().is_success() {
+            return Err(SpreadsheetError::Transient("list failed".into()));
+        }
+        let bytes = res.into_body();
+        let body: serde_json::Value = serde_json::from_slice(&bytes[..])
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let rows = body["values"].as_array().cloned().unwrap_or_default();
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                row.as_array()
+                    .unwrap_or(&vec![])
+                    .iter()
+                    .map(|v| v.as_str().unwrap_or_default().to_string())
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Iterator over pages of rows returned by [`GoogleSheets4Adapter::list_rows_paged`].
+pub struct RowPages<'a> {
+    adapter: &'a GoogleSheets4Adapter,
+    sheet_id: String,
+    next_start: usize,
+    ensured: bool,
+    done: bool,
+}
+
+impl Iterator for RowPages<'_> {
+    type Item = Result<Vec<Vec<String>>, SpreadsheetError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let adapter = self.adapter;
+        let start = self.next_start;
+        let end = start + adapter.page_size - 1;
+        let result = adapter.handle.block_on(async {
+            if !self.ensured {
+                adapter.ensure_sheet(&self.sheet_id).await?;
+                self.ensured = true;
+            }
+            debug!(sheet_id = %self.sheet_id, start, end, "Fetching page of rows");
+            adapter.fetch_range(&self.sheet_id, start, end).await
+        });
+        match result {
+            Ok(page) => {
+                if page.len() < adapter.page_size {
+                    self.done = true;
+                }
+                if page.is_empty() {
+                    return None;
+                }
+                self.next_start = end + 1;
+                Some(Ok(page))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
 }
 
 impl CloudSpreadsheetService for GoogleSheets4Adapter {
     fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             info!(title, "Creating sheet");
             let token = self
                 .get_token(&["https://www.googleapis.com/auth/spreadsheets"])
@@ -242,20 +441,11 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if !res.status().is_success() {
                 return Err(SpreadsheetError::Transient("create failed".into()));
             }
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
+            let bytes = res.into_body();
             let body: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             let id = body["spreadsheetId"]
@@ -277,7 +467,7 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
         sheet_id: &str,
         rows: Vec<Vec<String>>,
     ) -> Result<(), SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             self.ensure_sheet(sheet_id).await?;
             let mut rows = rows;
             if self.sheet_is_empty(sheet_id).await? {
@@ -288,7 +478,9 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
                 .await?;
             let url = format!(
                 "{}spreadsheets/{}/values/{}:append?valueInputOption=USER_ENTERED&insertDataOption=INSERT_ROWS",
-                self.sheets_base_url, sheet_id, self.sheet_name
+                self.sheets_base_url,
+                sheet_id,
+                self.table_range()
             );
             let rows_json: Vec<Vec<serde_json::Value>> = rows
                 .into_iter()
@@ -306,11 +498,7 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if res.status().is_success() {
                 Ok(())
             } else {
@@ -320,12 +508,12 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
     }
 
     fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             self.ensure_sheet(sheet_id).await?;
             let token = self
                 .get_token(&["https://www.googleapis.com/auth/spreadsheets"])
                 .await?;
-            let range = format!("{}!A{}:Z{}", self.sheet_name, index + 1, index + 1);
+            let range = self.row_range(index + 1, index + 1);
             let url = format!(
                 "{}spreadsheets/{}/values/{}",
                 self.sheets_base_url, sheet_id, range
@@ -336,20 +524,11 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
                 .header(header::AUTHORIZATION, format!("Bearer {token}"))
                 .body(Full::new(Bytes::new()))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if !res.status().is_success() {
                 return Err(SpreadsheetError::RowNotFound);
             }
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
+            let bytes = res.into_body();
             let body: serde_json::Value = serde_json::from_slice(&bytes[..])
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
             let row = body["values"]
@@ -367,53 +546,84 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
     }
 
     fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
-        self.rt.block_on(async {
+        let mut rows = Vec::new();
+        for page in self.list_rows_paged(sheet_id) {
+            rows.extend(page?);
+        }
+        Ok(rows)
+    }
+
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+        self.handle.block_on(async {
+            self.ensure_sheet(sheet_id).await?;
+            self.fetch_range(sheet_id, start + 1, end).await
+        })
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        self.handle.block_on(async {
             self.ensure_sheet(sheet_id).await?;
+            // Writing past the data would silently grow the sheet, so check
+            // that the row exists first.
+            let existing = self
+                .fetch_range(sheet_id, index + 1, index + 1)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or(SpreadsheetError::RowNotFound)?;
             let token = self
                 .get_token(&["https://www.googleapis.com/auth/spreadsheets"])
                 .await?;
+            let range = self.row_range(index + 1, index + 1);
             let url = format!(
-                "{}spreadsheets/{}/values/{}",
-                self.sheets_base_url, sheet_id, self.sheet_name
+                "{}spreadsheets/{}/values/{}?valueInputOption=USER_ENTERED",
+                self.sheets_base_url, sheet_id, range
             );
+            // Blank out cells beyond the new values left over from a longer row.
+            let width = values.len().max(existing.len());
+            let row: Vec<serde_json::Value> = values
+                .into_iter()
+                .chain(std::iter::repeat(String::new()))
+                .take(width)
+                .map(serde_json::Value::String)
+                .collect();
+            let body_json = json!({
+                "range": range,
+                "majorDimension": "ROWS",
+                "values": [row],
+            });
+            debug!(sheet_id, index, body = %body_json, "Update row request");
             let req = Request::builder()
-                .method(Method::GET)
+                .method(Method::PUT)
                 .uri(&url)
                 .header(header::AUTHORIZATION, format!("Bearer {token}"))
-                .body(Full::new(Bytes::new()))
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            if !res.status().is_success() {
-                return Err(SpreadsheetError::Transient("list failed".into()));
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+            if res.status().is_success() {
+                Ok(())
+            } else {
+                Err(SpreadsheetError::Transient("update failed".into()))
             }
-            let bytes = res
-                .into_body()
-                .collect()
-                .await
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
-                .to_bytes();
-            let body: serde_json::Value = serde_json::from_slice(&bytes[..])
-                .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let rows = body["values"].as_array().cloned().unwrap_or_default();
-            Ok(rows
-                .into_iter()
-                .map(|row| {
-                    row.as_array()
-                        .unwrap_or(&vec![])
-                        .iter()
-                        .map(|v| v.as_str().unwrap_or_default().to_string())
-                        .collect()
-                })
-                .collect())
         })
     }
 
     fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
-        self.rt.block_on(async {
+        self.handle.block_on(async {
             info!(sheet_id, email, "Sharing sheet");
             let token = self
                 .get_token(&["https://www.googleapis.com/auth/drive"])
@@ -428,11 +638,39 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
                 .header(header::CONTENT_TYPE, "application/json")
                 .body(Full::from(Bytes::from(body_json.to_string())))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
-            let res = self
-                .client
-                .request(req)
-                .await
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
+            if res.status().is_success() {
+                Ok(())
+            } else {
+                Err(SpreadsheetError::ShareFailed)
+            }
+        })
+    }
+
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.handle.block_on(async {
+            info!(sheet_id, email, "Revoking share");
+            let permissions = self.fetch_permissions(sheet_id).await?;
+            let permission_id = permissions
+                .iter()
+                .find(|p| p["emailAddress"].as_str() == Some(email))
+                .and_then(|p| p["id"].as_str())
+                .ok_or(SpreadsheetError::ShareFailed)?
+                .to_string();
+            let token = self
+                .get_token(&["https://www.googleapis.com/auth/drive"])
+                .await?;
+            let url = format!(
+                "{}files/{}/permissions/{}",
+                self.drive_base_url, sheet_id, permission_id
+            );
+            let req = Request::builder()
+                .method(Method::DELETE)
+                .uri(&url)
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Full::new(Bytes::new()))
                 .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+            let res = http::send_with_timeout(&self.client, req, self.timeout).await?;
             if res.status().is_success() {
                 Ok(())
             } else {
@@ -440,4 +678,21 @@ impl CloudSpreadsheetService for GoogleSheets4Adapter {
             }
         })
     }
+
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        self.handle.block_on(async {
+            let permissions = self.fetch_permissions(sheet_id).await?;
+            Ok(permissions
+                .iter()
+                .filter_map(|p| {
+                    let email = p["emailAddress"].as_str()?;
+                    let permission = match p["role"].as_str()? {
+                        "reader" | "commenter" => Permission::Read,
+                        _ => Permission::Write,
+                    };
+                    Some((email.to_string(), permission))
+                })
+                .collect())
+        })
+    }
 }
@@ -0,0 +1,41 @@
+//! HTTP helpers shared by the adapters that talk to a REST API.
+
+use http_body_util::{BodyExt, Full};
+use hyper::Request;
+use hyper::body::Bytes;
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use std::time::Duration;
+use yup_oauth2::hyper_rustls::HttpsConnector;
+
+use super::SpreadsheetError;
+
+/// HTTPS client used by the REST adapters.
+pub(crate) type HttpsClient = Client<HttpsConnector<HttpConnector>, Full<Bytes>>;
+
+/// Sends `req` and reads the whole response body, failing with
+/// [`SpreadsheetError::Transient`] unless both finish within `timeout`. A
+/// server that stalls partway through the body would otherwise hang the
+/// caller.
+pub(crate) async fn send_with_timeout(
+    client: &HttpsClient,
+    req: Request<Full<Bytes>>,
+    timeout: Duration,
+) -> Result<hyper::Response<Bytes>, SpreadsheetError> {
+    let exchange = async {
+        let res = client
+            .request(req)
+            .await
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?;
+        let (parts, body) = res.into_parts();
+        let bytes = body
+            .collect()
+            .await
+            .map_err(|e| SpreadsheetError::Transient(e.to_string()))?
+            .to_bytes();
+        Ok(hyper::Response::from_parts(parts, bytes))
+    };
+    tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| SpreadsheetError::Transient("request timed out".into()))?
+}
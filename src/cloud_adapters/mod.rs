@@ -2,18 +2,32 @@
 
 pub mod auth;
 pub mod retry;
-pub use retry::RetryingService;
+pub use retry::{RetryPredicate, RetryingService};
+pub mod dedup;
+pub use dedup::DedupOnAppend;
 pub mod buffered;
 pub use buffered::{BatchingCacheService, EvictionPolicy};
+pub mod partitioned;
+pub use partitioned::PartitionedService;
 pub mod google_sheets4;
+mod http;
 pub use google_sheets4::GoogleSheets4Adapter;
 pub mod excel_365;
 pub use excel_365::Excel365Adapter;
 pub mod file;
 pub use file::FileAdapter;
+pub mod binary;
+pub use binary::BinAdapter;
+#[cfg(feature = "dropbox")]
+pub mod dropbox;
+#[cfg(feature = "dropbox")]
+pub use dropbox::DropboxAdapter;
 
+use std::cell::RefCell;
 use std::collections::HashMap;
 
+use crate::core::Permission;
+
 /// Represents errors that can occur when interacting with a spreadsheet
 /// service.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -84,14 +98,135 @@ pub trait CloudSpreadsheetService {
     fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError>;
     /// Lists all rows from the spreadsheet.
     fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError>;
+    /// Reads the rows with indices `start..end`, using the same zero-based
+    /// indices as [`read_row`]. Fewer rows are returned when the sheet ends
+    /// before `end`. The default implementation slices [`list_rows`].
+    ///
+    /// [`read_row`]: CloudSpreadsheetService::read_row
+    /// [`list_rows`]: CloudSpreadsheetService::list_rows
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let rows = self.list_rows(sheet_id)?;
+        let end = end.min(rows.len());
+        Ok(rows.get(start..end).map(<[_]>::to_vec).unwrap_or_default())
+    }
+    /// Overwrites the row at the zero-based `index` with `values`, using the
+    /// same indices as [`read_row`]. Fails with
+    /// [`SpreadsheetError::RowNotFound`] when the row does not exist. The
+    /// default implementation reports [`SpreadsheetError::Unknown`], for
+    /// backends that can only append.
+    ///
+    /// [`read_row`]: CloudSpreadsheetService::read_row
+    fn update_row(
+        &mut self,
+        _sheet_id: &str,
+        _index: usize,
+        _values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        Err(SpreadsheetError::Unknown)
+    }
+    /// Overwrites several rows, given as `(index, values)` pairs, like
+    /// [`update_row`](Self::update_row) does for one. The default
+    /// implementation updates them one at a time and stops at the first
+    /// error; adapters that rewrite the whole sheet on every update override
+    /// it to do so once.
+    fn update_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<(usize, Vec<String>)>,
+    ) -> Result<(), SpreadsheetError> {
+        for (index, values) in rows {
+            self.update_row(sheet_id, index, values)?;
+        }
+        Ok(())
+    }
     /// Shares the spreadsheet with the given email.
     fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError>;
+    /// Revokes access to the spreadsheet previously granted to the given
+    /// email. The default implementation reports [`SpreadsheetError::Unknown`].
+    fn revoke_share(&self, _sheet_id: &str, _email: &str) -> Result<(), SpreadsheetError> {
+        Err(SpreadsheetError::Unknown)
+    }
+    /// Lists the users the spreadsheet is shared with and their access level.
+    /// The default implementation reports [`SpreadsheetError::Unknown`].
+    fn list_shares(&self, _sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        Err(SpreadsheetError::Unknown)
+    }
+}
+
+impl<S: CloudSpreadsheetService + ?Sized> CloudSpreadsheetService for Box<S> {
+    fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+        (**self).create_sheet(title)
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        (**self).append_row(sheet_id, values)
+    }
+
+    fn append_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SpreadsheetError> {
+        (**self).append_rows(sheet_id, rows)
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        (**self).read_row(sheet_id, index)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        (**self).list_rows(sheet_id)
+    }
+
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        (**self).read_rows(sheet_id, start, end)
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        (**self).update_row(sheet_id, index, values)
+    }
+
+    fn update_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<(usize, Vec<String>)>,
+    ) -> Result<(), SpreadsheetError> {
+        (**self).update_rows(sheet_id, rows)
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        (**self).share_sheet(sheet_id, email)
+    }
+
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        (**self).revoke_share(sheet_id, email)
+    }
+
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        (**self).list_shares(sheet_id)
+    }
 }
 
 /// Mock adapter simulating Google Sheets behaviour.
 #[derive(Default)]
 pub struct GoogleSheetsAdapter {
     sheets: HashMap<String, Vec<Vec<String>>>,
+    shares: RefCell<HashMap<String, Vec<(String, Permission)>>>,
     next_id: usize,
 }
 
@@ -100,6 +235,7 @@ impl GoogleSheetsAdapter {
     pub fn new() -> Self {
         Self {
             sheets: HashMap::new(),
+            shares: RefCell::new(HashMap::new()),
             next_id: 1,
         }
     }
@@ -154,11 +290,53 @@ impl CloudSpreadsheetService for GoogleSheetsAdapter {
         }
     }
 
-    fn share_sheet(&self, sheet_id: &str, _email: &str) -> Result<(), SpreadsheetError> {
-        if self.sheets.contains_key(sheet_id) {
-            Ok(())
-        } else {
-            Err(SpreadsheetError::ShareFailed)
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        match self.sheets.get_mut(sheet_id) {
+            Some(rows) => {
+                let row = rows.get_mut(index).ok_or(SpreadsheetError::RowNotFound)?;
+                *row = values;
+                Ok(())
+            }
+            None => Err(SpreadsheetError::SheetNotFound),
+        }
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        if !self.sheets.contains_key(sheet_id) {
+            return Err(SpreadsheetError::ShareFailed);
+        }
+        let mut shares = self.shares.borrow_mut();
+        let entries = shares.entry(sheet_id.to_string()).or_default();
+        if !entries.iter().any(|(e, _)| e == email) {
+            entries.push((email.to_string(), Permission::Write));
+        }
+        Ok(())
+    }
+
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        if !self.sheets.contains_key(sheet_id) {
+            return Err(SpreadsheetError::SheetNotFound);
+        }
+        if let Some(entries) = self.shares.borrow_mut().get_mut(sheet_id) {
+            entries.retain(|(e, _)| e != email);
+        }
+        Ok(())
+    }
+
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        if !self.sheets.contains_key(sheet_id) {
+            return Err(SpreadsheetError::SheetNotFound);
         }
+        Ok(self
+            .shares
+            .borrow()
+            .get(sheet_id)
+            .cloned()
+            .unwrap_or_default())
     }
 }
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate};
+
+use super::{CloudSpreadsheetService, SpreadsheetError};
+use crate::core::Permission;
+
+/// Column holding the record timestamp in ledger rows.
+const TIMESTAMP_COLUMN: usize = 1;
+/// Column holding the date of the original statement line in ledger rows.
+const TRANSACTION_DATE_COLUMN: usize = 12;
+/// Partition key for rows without a date, such as headers and status rows.
+const UNDATED: &str = "undated";
+
+/// Wrapper that splits a sheet into one sub-sheet per month.
+///
+/// The sheet returned by `create_sheet` is an index holding one
+/// `[period, sub_sheet_id]` row per partition; other rows in it, such as a
+/// header written by the inner service, are ignored. Appended rows are routed
+/// by their transaction date, or by their timestamp when they have none, to a
+/// sub-sheet named `<name>-YYYY-MM`, which is created the first time a month
+/// is seen. Rows without a date go to `<name>-undated`. `list_rows`
+/// concatenates the undated partition followed by the months in order,
+/// keeping only the first header row when the inner service writes one to
+/// every sub-sheet. Row indices used by `read_row` and `update_row` refer to
+/// that combined list. Sharing and revoking apply to the index and every
+/// sub-sheet.
+pub struct PartitionedService<S> {
+    inner: S,
+    name: String,
+    /// Columns tried in order for the date deciding a row's partition.
+    date_columns: Vec<usize>,
+}
+
+impl<S: CloudSpreadsheetService> PartitionedService<S> {
+    /// Wraps `inner`, naming sub-sheets after `name`.
+    pub fn new(inner: S, name: impl Into<String>) -> Self {
+        Self {
+            inner,
+            name: name.into(),
+            date_columns: vec![TRANSACTION_DATE_COLUMN, TIMESTAMP_COLUMN],
+        }
+    }
+
+    /// Sets the column whose date decides a row's partition, in place of the
+    /// transaction date and timestamp columns of ledger rows.
+    pub fn with_date_column(mut self, column: usize) -> Self {
+        self.date_columns = vec![column];
+        self
+    }
+
+    /// Returns the wrapped service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Sub-sheet ids of `sheet_id` by period, undated first and then by month.
+    pub fn partitions(&self, sheet_id: &str) -> Result<BTreeMap<String, String>, SpreadsheetError> {
+        Ok(self
+            .inner
+            .list_rows(sheet_id)?
+            .into_iter()
+            .filter_map(|row| match row.as_slice() {
+                [period, id, ..] if is_period(period) => Some((partition_key(period), id.clone())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Lists the rows of the months from `start` to `end`, inclusive, reading
+    /// only their partitions. Undated rows and headers are not included.
+    pub fn list_rows_between(
+        &self,
+        sheet_id: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        let range = month(start)..=month(end);
+        let mut rows = Vec::new();
+        for (key, id) in self.partitions(sheet_id)? {
+            if range.contains(&key) {
+                rows.extend(
+                    self.inner
+                        .list_rows(&id)?
+                        .into_iter()
+                        .filter(|row| !is_header(row)),
+                );
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Period of a row: `YYYY-MM` of the first of its date columns holding
+    /// a date, or undated.
+    fn period(&self, row: &[String]) -> String {
+        self.date_columns
+            .iter()
+            .filter_map(|&column| row.get(column))
+            .find_map(|cell| {
+                DateTime::parse_from_rfc3339(cell)
+                    .map(|t| t.date_naive())
+                    .or_else(|_| NaiveDate::parse_from_str(cell, "%Y-%m-%d"))
+                    .ok()
+            })
+            .map_or_else(|| UNDATED.to_string(), month)
+    }
+
+    /// Rows of every partition in combined order, each with its sub-sheet id
+    /// and its index within that sub-sheet. Header rows after the first are
+    /// left out.
+    fn combined_rows(
+        &self,
+        sheet_id: &str,
+    ) -> Result<Vec<(String, usize, Vec<String>)>, SpreadsheetError> {
+        let mut rows = Vec::new();
+        let mut header_seen = false;
+        for id in self.partitions(sheet_id)?.into_values() {
+            for (index, row) in self.inner.list_rows(&id)?.into_iter().enumerate() {
+                if is_header(&row) {
+                    if header_seen {
+                        continue;
+                    }
+                    header_seen = true;
+                }
+                rows.push((id.clone(), index, row));
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Finds the partition holding the combined row `index`, returning its
+    /// sub-sheet id and the row's index within it.
+    fn locate(&self, sheet_id: &str, index: usize) -> Result<(String, usize), SpreadsheetError> {
+        self.combined_rows(sheet_id)?
+            .into_iter()
+            .nth(index)
+            .map(|(id, index, _)| (id, index))
+            .ok_or(SpreadsheetError::RowNotFound)
+    }
+}
+
+/// Whether `row` is a header row; it starts with `id`.
+fn is_header(row: &[String]) -> bool {
+    row.first().map(String::as_str) == Some("id")
+}
+
+/// Whether an index entry names a partition: undated or a `YYYY-MM` month.
+fn is_period(period: &str) -> bool {
+    period == UNDATED
+        || (period.len() == 7
+            && NaiveDate::parse_from_str(&format!("{period}-01"), "%Y-%m-%d").is_ok())
+}
+
+/// Sort key of an index entry, so the undated partition comes first.
+fn partition_key(period: &str) -> String {
+    if period == UNDATED {
+        String::new()
+    } else {
+        period.to_string()
+    }
+}
+
+fn month(date: NaiveDate) -> String {
+    format!("{:04}-{:02}", date.year(), date.month())
+}
+
+impl<S: CloudSpreadsheetService> CloudSpreadsheetService for PartitionedService<S> {
+    fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+        self.inner.create_sheet(title)
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        self.append_rows(sheet_id, vec![values])
+    }
+
+    fn append_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), SpreadsheetError> {
+        let mut by_period: BTreeMap<String, Vec<Vec<String>>> = BTreeMap::new();
+        for row in rows {
+            by_period.entry(self.period(&row)).or_default().push(row);
+        }
+        let mut partitions = self.partitions(sheet_id)?;
+        for (period, rows) in by_period {
+            let key = partition_key(&period);
+            let id = match partitions.get(&key) {
+                Some(id) => id.clone(),
+                None => {
+                    let id = self
+                        .inner
+                        .create_sheet(&format!("{}-{period}", self.name))?;
+                    self.inner
+                        .append_row(sheet_id, vec![period.clone(), id.clone()])?;
+                    partitions.insert(key, id.clone());
+                    id
+                }
+            };
+            self.inner.append_rows(&id, rows)?;
+        }
+        Ok(())
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        let (id, index) = self.locate(sheet_id, index)?;
+        self.inner.read_row(&id, index)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        Ok(self
+            .combined_rows(sheet_id)?
+            .into_iter()
+            .map(|(_, _, row)| row)
+            .collect())
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        let (id, index) = self.locate(sheet_id, index)?;
+        self.inner.update_row(&id, index, values)
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.inner.share_sheet(sheet_id, email)?;
+        for id in self.partitions(sheet_id)?.into_values() {
+            self.inner.share_sheet(&id, email)?;
+        }
+        Ok(())
+    }
+
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.inner.revoke_share(sheet_id, email)?;
+        for id in self.partitions(sheet_id)?.into_values() {
+            self.inner.revoke_share(&id, email)?;
+        }
+        Ok(())
+    }
+
+    /// Lists the shares of the index sheet that every partition has as well,
+    /// so a user only shows up with access to the whole ledger.
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        let mut shares = self.inner.list_shares(sheet_id)?;
+        for id in self.partitions(sheet_id)?.into_values() {
+            let partition = self.inner.list_shares(&id)?;
+            shares.retain(|share| partition.contains(share));
+        }
+        Ok(shares)
+    }
+}
@@ -3,16 +3,22 @@ use std::thread::sleep;
 use std::time::Duration;
 
 use super::{CloudSpreadsheetService, SpreadsheetError};
+use crate::core::Permission;
+
+/// Decides whether a failed call is retried, given the error and the number
+/// of retries already made.
+pub type RetryPredicate = Box<dyn Fn(&SpreadsheetError, u32) -> bool>;
 
 /// Wrapper that adds retry logic with exponential backoff to a spreadsheet service.
 ///
 /// Transient errors are retried with exponential backoff until `max_retries`
 /// is reached. The delay starts at `base_delay` and doubles after each failed
-/// attempt.
+/// attempt. [`RetryingService::with_retry_predicate`] replaces that rule.
 pub struct RetryingService<S> {
     inner: RefCell<S>,
     max_retries: u32,
     base_delay: Duration,
+    retry_predicate: Option<RetryPredicate>,
 }
 
 impl<S> RetryingService<S> {
@@ -22,6 +28,27 @@ impl<S> RetryingService<S> {
             inner: RefCell::new(inner),
             max_retries,
             base_delay,
+            retry_predicate: None,
+        }
+    }
+
+    /// Retries a failed call whenever `predicate` returns `true` for the
+    /// error and the number of retries so far, instead of retrying
+    /// [transient](SpreadsheetError::is_retryable) errors up to
+    /// `max_retries` times. The predicate must stop eventually, e.g. by
+    /// checking the retry count.
+    pub fn with_retry_predicate(
+        mut self,
+        predicate: impl Fn(&SpreadsheetError, u32) -> bool + 'static,
+    ) -> Self {
+        self.retry_predicate = Some(Box::new(predicate));
+        self
+    }
+
+    fn should_retry(&self, error: &SpreadsheetError, attempt: u32) -> bool {
+        match &self.retry_predicate {
+            Some(predicate) => predicate(error, attempt),
+            None => error.is_retryable() && attempt < self.max_retries,
         }
     }
 
@@ -34,7 +61,7 @@ impl<S> RetryingService<S> {
             let result = op(&mut self.inner.borrow_mut());
             match result {
                 Ok(val) => return Ok(val),
-                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                Err(e) if self.should_retry(&e, attempt) => {
                     let factor = 2f64.powi(attempt as i32);
                     let delay = self.base_delay.mul_f64(factor);
                     sleep(delay);
@@ -71,7 +98,41 @@ impl<S: CloudSpreadsheetService> CloudSpreadsheetService for RetryingService<S>
         self.with_retry(|inner| inner.list_rows(sheet_id))
     }
 
+    fn read_rows(
+        &self,
+        sheet_id: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        self.with_retry(|inner| inner.read_rows(sheet_id, start, end))
+    }
+
+    fn update_row(
+        &mut self,
+        sheet_id: &str,
+        index: usize,
+        values: Vec<String>,
+    ) -> Result<(), SpreadsheetError> {
+        self.with_retry(|inner| inner.update_row(sheet_id, index, values.clone()))
+    }
+
+    fn update_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<(usize, Vec<String>)>,
+    ) -> Result<(), SpreadsheetError> {
+        self.with_retry(|inner| inner.update_rows(sheet_id, rows.clone()))
+    }
+
     fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
         self.with_retry(|inner| inner.share_sheet(sheet_id, email))
     }
+
+    fn revoke_share(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.with_retry(|inner| inner.revoke_share(sheet_id, email))
+    }
+
+    fn list_shares(&self, sheet_id: &str) -> Result<Vec<(String, Permission)>, SpreadsheetError> {
+        self.with_retry(|inner| inner.list_shares(sheet_id))
+    }
 }
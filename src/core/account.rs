@@ -26,16 +26,66 @@ impl<'de> Deserialize<'de> for Account {
     }
 }
 
+/// Errors that can occur when parsing an [`Account`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountParseError {
+    /// The account name was empty.
+    Empty,
+    /// A segment between separators was empty, as in `cash::food`.
+    EmptySegment,
+    /// A segment contained a character that cannot appear in account names.
+    InvalidCharacter(char),
+    /// A segment started or ended with whitespace.
+    SurroundingWhitespace(String),
+}
+
+impl fmt::Display for AccountParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountParseError::Empty => write!(f, "account name is empty"),
+            AccountParseError::EmptySegment => write!(f, "account contains an empty segment"),
+            AccountParseError::InvalidCharacter(c) => {
+                write!(f, "account contains invalid character {c:?}")
+            }
+            AccountParseError::SurroundingWhitespace(seg) => {
+                write!(
+                    f,
+                    "account segment {seg:?} has leading or trailing whitespace"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccountParseError {}
+
+/// Characters that cannot appear in an account name, because they would
+/// break the stored CSV row.
+fn is_invalid_char(c: char) -> bool {
+    matches!(c, ',' | '\n' | '\r')
+}
+
 impl FromStr for Account {
-    type Err = std::convert::Infallible;
+    type Err = AccountParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            parts: if s.is_empty() {
-                Vec::new()
-            } else {
-                s.split(':').map(|p| p.to_string()).collect()
-            },
-        })
+        if s.is_empty() {
+            return Err(AccountParseError::Empty);
+        }
+        let mut parts = Vec::new();
+        for seg in s.split(':') {
+            if let Some(c) = seg.chars().find(|&c| is_invalid_char(c)) {
+                return Err(AccountParseError::InvalidCharacter(c));
+            }
+            let trimmed = seg.trim();
+            if trimmed.is_empty() {
+                return Err(AccountParseError::EmptySegment);
+            }
+            if trimmed.len() != seg.len() {
+                return Err(AccountParseError::SurroundingWhitespace(seg.to_string()));
+            }
+            parts.push(trimmed.to_string());
+        }
+        Ok(Self { parts })
     }
 }
 
@@ -46,10 +96,141 @@ impl fmt::Display for Account {
 }
 
 impl Account {
+    /// Parses a free-form name such as a payee into an account that always
+    /// passes [`FromStr`]. Characters rejected there, such as `,`, become
+    /// spaces, runs of whitespace are collapsed, empty segments are dropped,
+    /// and a name with no segments left becomes `unknown`.
+    pub fn parse_lenient(s: &str) -> Self {
+        let mut parts: Vec<String> = s
+            .split(':')
+            .map(|seg| {
+                seg.replace(is_invalid_char, " ")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|p| !p.is_empty())
+            .collect();
+        if parts.is_empty() {
+            parts.push("unknown".to_string());
+        }
+        Self { parts }
+    }
+
     pub fn starts_with(&self, other: &Account) -> bool {
+        self.starts_with_case(other, false)
+    }
+
+    /// Returns `true` if `other` is this account or one of its ancestors,
+    /// optionally ignoring the case of each segment.
+    pub fn starts_with_case(&self, other: &Account, case_insensitive: bool) -> bool {
         if other.parts.len() > self.parts.len() {
             return false;
         }
-        self.parts.iter().zip(&other.parts).all(|(a, b)| a == b)
+        self.parts
+            .iter()
+            .zip(&other.parts)
+            .all(|(a, b)| segment_eq(a, b, case_insensitive))
+    }
+
+    /// Number of segments, so `assets:bank` has depth 2.
+    pub fn depth(&self) -> usize {
+        self.parts.len()
+    }
+
+    /// The account one level up, or `None` for a top-level account.
+    pub fn parent(&self) -> Option<Account> {
+        (self.parts.len() > 1).then(|| Account {
+            parts: self.parts[..self.parts.len() - 1].to_vec(),
+        })
+    }
+
+    /// The ancestor at depth `levels`, or the account itself when it is not
+    /// deeper than that. At least the top-level segment is always kept.
+    pub fn truncated(&self, levels: usize) -> Account {
+        let keep = levels.max(1).min(self.parts.len());
+        Account {
+            parts: self.parts[..keep].to_vec(),
+        }
+    }
+
+    /// Returns `true` if both accounts name the same path, optionally ignoring
+    /// the case of each segment. The original casing is left untouched.
+    pub fn matches(&self, other: &Account, case_insensitive: bool) -> bool {
+        self.parts.len() == other.parts.len() && self.starts_with_case(other, case_insensitive)
+    }
+}
+
+/// Renames accounts by prefix, so history recorded under an old name can be
+/// reported under a new one without rewriting immutable records.
+///
+/// An alias from `expenses:food` to `Expenses:Groceries` also renames
+/// `expenses:food:coffee` to `Expenses:Groceries:coffee`. When several
+/// aliases match, the longest prefix wins. Aliases are not chained: the
+/// result of a rename is not renamed again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AliasMap {
+    aliases: Vec<(Account, Account)>,
+}
+
+impl AliasMap {
+    /// Renames `from` and its subaccounts to `to`, replacing any alias
+    /// already set for `from`.
+    pub fn insert(&mut self, from: Account, to: Account) {
+        match self.aliases.iter_mut().find(|(f, _)| *f == from) {
+            Some(entry) => entry.1 = to,
+            None => self.aliases.push((from, to)),
+        }
+    }
+
+    /// Returns `true` if no aliases are set.
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    /// Returns the name `account` is reported under.
+    pub fn resolve(&self, account: &Account) -> Account {
+        let best = self
+            .aliases
+            .iter()
+            .filter(|(from, _)| account.starts_with(from))
+            .max_by_key(|(from, _)| from.depth());
+        match best {
+            Some((from, to)) => Account {
+                parts: to
+                    .parts
+                    .iter()
+                    .chain(&account.parts[from.depth()..])
+                    .cloned()
+                    .collect(),
+            },
+            None => account.clone(),
+        }
+    }
+}
+
+/// Currency for a record on `account`: `explicit` when given, otherwise the
+/// default of the longest prefix of `account` listed in `defaults`, so a
+/// default for `assets:bank` also covers `assets:bank:checking`.
+pub fn resolve_currency(
+    account: &Account,
+    defaults: &[(Account, String)],
+    explicit: Option<&str>,
+) -> Option<String> {
+    if let Some(currency) = explicit {
+        return Some(currency.to_string());
+    }
+    defaults
+        .iter()
+        .filter(|(prefix, _)| account.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.depth())
+        .map(|(_, currency)| currency.clone())
+}
+
+fn segment_eq(a: &str, b: &str, case_insensitive: bool) -> bool {
+    if case_insensitive {
+        a.to_lowercase() == b.to_lowercase()
+    } else {
+        a == b
     }
 }
@@ -0,0 +1,65 @@
+//! Balance assertions: checks that an account held an expected balance on a
+//! given date.
+
+use chrono::NaiveDate;
+
+use super::{Account, Ledger, PriceDatabase};
+
+/// Largest difference between expected and actual balances that still
+/// passes, absorbing rounding from currency conversion.
+pub const ASSERTION_TOLERANCE: f64 = 0.005;
+
+/// A balance assertion that did not hold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssertionError {
+    pub account: Account,
+    pub as_of: NaiveDate,
+    pub expected: f64,
+    pub actual: f64,
+    pub currency: String,
+}
+
+impl std::fmt::Display for AssertionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "balance assertion failed for {} on {}: expected {} {}, found {}",
+            self.account, self.as_of, self.expected, self.currency, self.actual
+        )
+    }
+}
+
+impl std::error::Error for AssertionError {}
+
+impl Ledger {
+    /// Checks that `account` and its sub-accounts had a balance of
+    /// `expected` at the end of `as_of`, converted to `target`.
+    ///
+    /// Balances within [`ASSERTION_TOLERANCE`] of `expected` pass. On a
+    /// mismatch the error carries the actual balance.
+    pub fn check_assertion(
+        &self,
+        account: &Account,
+        as_of: NaiveDate,
+        expected: f64,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<(), AssertionError> {
+        let actual: f64 = self
+            .indexed_records(|a| a.starts_with_case(account, self.case_insensitive))
+            .filter(|r| r.timestamp.date_naive() <= as_of)
+            .map(|r| self.tree_delta(r, account, target, prices))
+            .sum();
+        if (actual - expected).abs() <= ASSERTION_TOLERANCE {
+            Ok(())
+        } else {
+            Err(AssertionError {
+                account: account.clone(),
+                as_of,
+                expected,
+                actual,
+                currency: target.to_string(),
+            })
+        }
+    }
+}
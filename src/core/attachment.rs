@@ -0,0 +1,54 @@
+//! Receipts and other files linked from a record's external reference.
+
+use std::path::PathBuf;
+
+use super::Record;
+
+/// A file linked from [`Record::external_reference`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attachment {
+    /// A file on this machine, from a `file://` reference.
+    Local(PathBuf),
+    /// A file to download, from an `https://` reference.
+    Remote(String),
+}
+
+impl Attachment {
+    /// Parses a reference prefixed with `file://` or `https://`. Any other
+    /// reference, such as an invoice number, is not an attachment.
+    pub fn parse(reference: &str) -> Option<Attachment> {
+        let reference = reference.trim();
+        if let Some(path) = reference.strip_prefix("file://") {
+            // `file://localhost/path` names the same file as `file:///path`.
+            let path = path.strip_prefix("localhost").unwrap_or(path);
+            (!path.is_empty()).then(|| Attachment::Local(PathBuf::from(path)))
+        } else if reference.len() > "https://".len() && reference.starts_with("https://") {
+            Some(Attachment::Remote(reference.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if the file has to be downloaded.
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Attachment::Remote(_))
+    }
+}
+
+impl std::fmt::Display for Attachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Attachment::Local(path) => write!(f, "{}", path.display()),
+            Attachment::Remote(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+impl Record {
+    /// Returns the file linked from the external reference, if it is one.
+    pub fn attachment(&self) -> Option<Attachment> {
+        self.external_reference
+            .as_deref()
+            .and_then(Attachment::parse)
+    }
+}
@@ -4,7 +4,8 @@ use std::collections::HashMap;
 
 #[cfg(test)]
 use super::Record;
-use super::{Account, Ledger, PriceDatabase};
+use super::series::{account_sum, try_account_sum};
+use super::{Account, Ledger, MissingRate, PriceDatabase};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Period {
@@ -41,6 +42,8 @@ impl BudgetBook {
         }
     }
 
+    /// Budget left for `account` in the given month, or `None` without a
+    /// monthly budget. Postings without a known rate are left out.
     pub fn compare_month(
         &self,
         ledger: &Ledger,
@@ -49,18 +52,30 @@ impl BudgetBook {
         year: i32,
         month: u32,
     ) -> Option<f64> {
-        let b = self.monthly.get(&(account.clone(), year, month))?;
-        let start = NaiveDate::from_ymd_opt(year, month, 1)?;
-        let (next_y, next_m) = if month == 12 {
-            (year + 1, 1)
-        } else {
-            (year, month + 1)
-        };
-        let end = NaiveDate::from_ymd_opt(next_y, next_m, 1)?.pred_opt()?;
+        let (b, start, end) = self.month_budget(account, year, month)?;
         let actual = account_sum(ledger, account, start, end, &b.currency, prices);
         Some(b.amount - actual)
     }
 
+    /// Like [`compare_month`](Self::compare_month), but fails with every
+    /// missing rate instead of leaving those postings out.
+    pub fn try_compare_month(
+        &self,
+        ledger: &Ledger,
+        prices: &PriceDatabase,
+        account: &Account,
+        year: i32,
+        month: u32,
+    ) -> Result<Option<f64>, MissingRate> {
+        let Some((b, start, end)) = self.month_budget(account, year, month) else {
+            return Ok(None);
+        };
+        let actual = try_account_sum(ledger, account, start, end, &b.currency, prices)?;
+        Ok(Some(b.amount - actual))
+    }
+
+    /// Budget left for `account` in `year`, or `None` without a yearly
+    /// budget. Postings without a known rate are left out.
     pub fn compare_year(
         &self,
         ledger: &Ledger,
@@ -68,49 +83,56 @@ impl BudgetBook {
         account: &Account,
         year: i32,
     ) -> Option<f64> {
+        let (b, start, end) = self.year_budget(account, year)?;
+        let actual = account_sum(ledger, account, start, end, &b.currency, prices);
+        Some(b.amount - actual)
+    }
+
+    /// Like [`compare_year`](Self::compare_year), but fails with every
+    /// missing rate instead of leaving those postings out.
+    pub fn try_compare_year(
+        &self,
+        ledger: &Ledger,
+        prices: &PriceDatabase,
+        account: &Account,
+        year: i32,
+    ) -> Result<Option<f64>, MissingRate> {
+        let Some((b, start, end)) = self.year_budget(account, year) else {
+            return Ok(None);
+        };
+        let actual = try_account_sum(ledger, account, start, end, &b.currency, prices)?;
+        Ok(Some(b.amount - actual))
+    }
+
+    /// Monthly budget of `account` with the first and last day of the month.
+    fn month_budget(
+        &self,
+        account: &Account,
+        year: i32,
+        month: u32,
+    ) -> Option<(&Budget, NaiveDate, NaiveDate)> {
+        let b = self.monthly.get(&(account.clone(), year, month))?;
+        let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let (next_y, next_m) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+        let end = NaiveDate::from_ymd_opt(next_y, next_m, 1)?.pred_opt()?;
+        Some((b, start, end))
+    }
+
+    /// Yearly budget of `account` with the first and last day of the year.
+    fn year_budget(&self, account: &Account, year: i32) -> Option<(&Budget, NaiveDate, NaiveDate)> {
         let b = self.yearly.get(&(account.clone(), year))?;
         let start = NaiveDate::from_ymd_opt(year, 1, 1)?;
         let end = NaiveDate::from_ymd_opt(year, 12, 31)?;
-        let actual = account_sum(ledger, account, start, end, &b.currency, prices);
-        Some(b.amount - actual)
+        Some((b, start, end))
     }
 }
 
 use chrono::Utc;
 
-fn account_sum(
-    ledger: &Ledger,
-    account: &Account,
-    start: NaiveDate,
-    end: NaiveDate,
-    target: &str,
-    prices: &PriceDatabase,
-) -> f64 {
-    ledger.records().fold(0.0, |mut acc, r| {
-        let date = r.timestamp.date_naive();
-        if date < start || date > end {
-            return acc;
-        }
-        for p in r.postings() {
-            let mut amount = p.amount;
-            if r.currency != target {
-                if let Some(rate) = prices.get_rate(date, &r.currency, target) {
-                    amount *= rate;
-                } else {
-                    continue;
-                }
-            }
-            if p.debit_account.starts_with(account) {
-                acc += amount;
-            }
-            if p.credit_account.starts_with(account) {
-                acc -= amount;
-            }
-        }
-        acc
-    })
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
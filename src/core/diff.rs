@@ -0,0 +1,69 @@
+//! Comparing the records of two ledgers, e.g. two snapshots of one sheet.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use uuid::Uuid;
+
+use super::utils::hash_row;
+use super::{Ledger, Record};
+
+/// Outcome of [`diff`]. Ids are listed in the commit order of the ledger
+/// they come from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LedgerDiff {
+    /// Ids of records only in the first ledger.
+    pub only_in_a: Vec<Uuid>,
+    /// Ids of records only in the second ledger.
+    pub only_in_b: Vec<Uuid>,
+    /// Ids present in both ledgers with different content. Committed records
+    /// never change, so these point at rows edited outside the ledger.
+    pub changed: Vec<Uuid>,
+}
+
+impl LedgerDiff {
+    /// Returns `true` if both ledgers hold the same records.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Hash of the stored row of `record`. The cleared flag is not part of the
+/// row, so reconciling a record does not count as a change.
+fn content_hash(record: &Record) -> String {
+    hash_row(&record.to_row(), "")
+}
+
+/// Hashes the first record with each id, in commit order.
+fn hashes(ledger: &Ledger) -> (Vec<Uuid>, HashMap<Uuid, String>) {
+    let mut order = Vec::new();
+    let mut hashes = HashMap::new();
+    for record in ledger.records() {
+        if let Entry::Vacant(entry) = hashes.entry(record.id) {
+            order.push(record.id);
+            entry.insert(content_hash(record));
+        }
+    }
+    (order, hashes)
+}
+
+/// Compares `a` and `b` record by record, matching records by id and
+/// comparing their content hashes. Only the first record with a given id
+/// in each ledger is considered.
+pub fn diff(a: &Ledger, b: &Ledger) -> LedgerDiff {
+    let (a_order, a_hashes) = hashes(a);
+    let (b_order, b_hashes) = hashes(b);
+    let mut result = LedgerDiff::default();
+    for id in a_order {
+        match b_hashes.get(&id) {
+            None => result.only_in_a.push(id),
+            Some(hash) if *hash != a_hashes[&id] => result.changed.push(id),
+            Some(_) => {}
+        }
+    }
+    result.only_in_b = b_order
+        .into_iter()
+        .filter(|id| !a_hashes.contains_key(id))
+        .collect();
+    result
+}
@@ -0,0 +1,107 @@
+//! Folding the records of one ledger into another.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::DateTime;
+use uuid::Uuid;
+
+use super::{Ledger, Record};
+
+/// How [`Ledger::merge`] decides that a record is already present.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Only records whose id already exists are skipped.
+    ById,
+    /// Records are also skipped when an existing record has the same content
+    /// under a different id, e.g. when both sheets imported one statement.
+    #[default]
+    ByIdOrContent,
+}
+
+/// Outcome of [`Ledger::merge`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MergeReport {
+    /// Ids of records committed to the target ledger, in commit order.
+    pub merged: Vec<Uuid>,
+    /// Ids of source records skipped because they were already present.
+    pub duplicates: Vec<Uuid>,
+    /// Ids present in both ledgers with different content. The target's
+    /// version is kept.
+    pub conflicts: Vec<Uuid>,
+}
+
+/// Key identifying a record by content, ignoring its id, creation time and
+/// reconciliation state.
+fn content_key(record: &Record) -> String {
+    let mut normalized = record.clone();
+    normalized.id = Uuid::nil();
+    normalized.timestamp = DateTime::UNIX_EPOCH;
+    normalized.cleared = false;
+    normalized.to_json().unwrap_or_default()
+}
+
+impl Ledger {
+    /// Commits the records of `other` that are not already in this ledger.
+    ///
+    /// Records keep their ids so adjustment links between merged records stay
+    /// valid. When a record is skipped as a content duplicate, adjustments
+    /// pointing at it are relinked to the matching record in this ledger.
+    /// Records sharing an id but differing in content are reported as
+    /// conflicts and never overwrite the existing record.
+    pub fn merge(&mut self, other: &Ledger, strategy: MergeStrategy) -> MergeReport {
+        let mut report = MergeReport::default();
+        let mut by_id: HashMap<Uuid, String> = self
+            .records
+            .iter()
+            .map(|r| (r.id, content_key(r)))
+            .collect();
+        let mut by_content: HashMap<String, Uuid> = HashMap::new();
+        if strategy == MergeStrategy::ByIdOrContent {
+            for (id, key) in &by_id {
+                by_content.entry(key.clone()).or_insert(*id);
+            }
+        }
+
+        // Decide the fate of every record first so references to records
+        // later in `other` can be relinked too.
+        let mut relinked: HashMap<Uuid, Uuid> = HashMap::new();
+        let mut to_merge: Vec<&Record> = Vec::new();
+        let mut seen = HashSet::new();
+        for record in other.records() {
+            if !seen.insert(record.id) {
+                continue;
+            }
+            let key = content_key(record);
+            if let Some(existing) = by_id.get(&record.id) {
+                if *existing == key {
+                    report.duplicates.push(record.id);
+                } else {
+                    report.conflicts.push(record.id);
+                }
+                continue;
+            }
+            if strategy == MergeStrategy::ByIdOrContent {
+                if let Some(existing) = by_content.get(&key) {
+                    relinked.insert(record.id, *existing);
+                    report.duplicates.push(record.id);
+                    continue;
+                }
+                by_content.insert(key.clone(), record.id);
+            }
+            by_id.insert(record.id, key);
+            to_merge.push(record);
+        }
+
+        for record in to_merge {
+            let mut record = record.clone();
+            if let Some(reference) = record.reference_id
+                && let Some(target) = relinked.get(&reference)
+            {
+                record.reference_id = Some(*target);
+            }
+            report.merged.push(record.id);
+            self.commit(record);
+        }
+        report
+    }
+}
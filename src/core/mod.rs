@@ -1,25 +1,159 @@
 //! Core logic for the append-only immutable database.
 
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use iso_currency::Currency;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use uuid::Uuid;
 
 pub mod sharing;
 pub use sharing::{AccessError, Permission, SharedLedger};
 pub mod prices;
-pub use prices::PriceDatabase;
+pub use prices::{MissingRate, PriceDatabase, PriceLookup};
 pub mod query;
 pub mod utils;
 pub mod verification;
 pub use query::{ParseError as QueryParseError, Query};
-pub use verification::verify_sheet;
+pub use verification::{
+    SheetVerification, repair_sheet, rotate_signature, verify_sheet, verify_sheet_from,
+    verify_sheet_full, verify_sheet_with,
+};
 pub mod account;
-pub use account::Account;
+pub use account::{Account, AccountParseError, AliasMap, resolve_currency};
+pub mod assertion;
+pub use assertion::AssertionError;
+pub mod attachment;
+pub use attachment::Attachment;
 pub mod budget;
+pub mod diff;
+pub mod merge;
+pub use diff::LedgerDiff;
+pub use merge::{MergeReport, MergeStrategy};
+mod opening;
+pub use opening::OPENING_BALANCE_ACCOUNT;
 pub mod scheduler;
+mod series;
+pub mod snapshot;
+pub mod validation;
 pub use budget::{Budget, BudgetBook, Period};
-pub use scheduler::{RecordTemplate, ScheduleEntry, Scheduler};
+pub use scheduler::{
+    BusinessDayAdjust, CronError, RecordTemplate, ScheduleEntry, ScheduleError, Scheduler,
+};
+pub use validation::ValidationIssue;
+
+/// Row layout version written by [`Record::to_row`].
+///
+/// Version 1 rows (legacy) have no version column and store
+/// `transaction_date` at index 11. Version 2 inserts `transaction_description`
+/// at index 11, moves `transaction_date` to index 12 and writes the version at
+/// [`SCHEMA_VERSION_COLUMN`]. Version 3 adds a JSON `metadata` column at
+/// index 14. Version 4 adds a `cleared` column at [`CLEARED_COLUMN`]. Future
+/// versions keep the version at [`SCHEMA_VERSION_COLUMN`] and add new columns
+/// after it. Bump this whenever the layout changes.
+pub const SCHEMA_VERSION: u32 = 4;
+
+/// Index of the `schema_version` column in rows of version 2 and later.
+pub const SCHEMA_VERSION_COLUMN: usize = 13;
+
+/// Index of the `cleared` column in rows of version 4 and later. The cell is
+/// empty unless the reconciliation status is stored on the row itself; see
+/// [`row_cleared`].
+pub const CLEARED_COLUMN: usize = 15;
+
+/// Column names of a hashed row: the [`Record::to_row`] layout followed by the
+/// row hash.
+pub const ROW_HEADER: [&str; 17] = [
+    "id",
+    "timestamp",
+    "description",
+    "debit_account",
+    "credit_account",
+    "amount",
+    "currency",
+    "reference_id",
+    "external_reference",
+    "tags",
+    "splits",
+    "transaction_description",
+    "transaction_date",
+    "schema_version",
+    "metadata",
+    "cleared",
+    "hash",
+];
+
+/// Returns the layout version of a stored row. Rows without a version column
+/// are treated as version 1.
+pub fn row_schema_version(row: &[String]) -> u32 {
+    row.get(SCHEMA_VERSION_COLUMN)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Returns the cleared status stored on a record row, or `None` when the row
+/// predates version 4 or leaves the `cleared` cell empty. A stored status
+/// takes precedence over `status` rows naming the same record.
+pub fn row_cleared(row: &[String]) -> Option<bool> {
+    if row_schema_version(row) < 4 {
+        return None;
+    }
+    row.get(CLEARED_COLUMN).and_then(|c| c.parse().ok())
+}
+
+/// Returns the tags stored on a record row. Tags are joined with commas unless
+/// one of them would not survive that, see [`Record::to_row`], in which case
+/// the cell holds a JSON array.
+pub fn row_tags(row: &[String]) -> Vec<String> {
+    let cell = row.get(9).map(String::as_str).unwrap_or("");
+    if cell.is_empty() {
+        return Vec::new();
+    }
+    if cell.starts_with('[')
+        && let Ok(tags) = serde_json::from_str(cell)
+    {
+        return tags;
+    }
+    cell.split(',').map(str::to_string).collect()
+}
+
+/// Returns the split postings stored on a record row. Their accounts are read
+/// with [`Account::parse_lenient`], like the row's own accounts, so rows
+/// written before account names were validated still load.
+pub fn row_splits(row: &[String]) -> serde_json::Result<Vec<Posting>> {
+    let cell = row.get(10).map(String::as_str).unwrap_or("");
+    if cell.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut splits: Vec<serde_json::Value> = serde_json::from_str(cell)?;
+    for split in &mut splits {
+        for key in ["debit_account", "credit_account"] {
+            if let Some(account) = split.get_mut(key)
+                && let Some(name) = account.as_str()
+            {
+                *account = Account::parse_lenient(name).to_string().into();
+            }
+        }
+    }
+    splits.into_iter().map(serde_json::from_value).collect()
+}
+
+/// Formats `amount` with the number of decimal places used by `currency`,
+/// followed by the currency code, e.g. `9.50 USD`, `1200 JPY` or
+/// `1.250 BHD`. Unknown currencies and those without minor units in ISO 4217
+/// use two decimals.
+pub fn format_amount(amount: f64, currency: &str) -> String {
+    let decimals = Currency::from_code(currency)
+        .and_then(|c| c.exponent())
+        .unwrap_or(2) as usize;
+    let scale = 10f64.powi(decimals as i32);
+    // Avoid printing `-0.00` for amounts that round to zero.
+    let amount = if (amount * scale).round() == 0.0 {
+        0.0
+    } else {
+        amount
+    };
+    format!("{amount:.decimals$} {currency}")
+}
 
 /// Represents a single debit/credit posting within a transaction.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -30,6 +164,29 @@ pub struct Posting {
     pub credit_account: Account,
     /// Monetary amount of the posting.
     pub amount: f64,
+    /// Currency of this posting. When `None` the record currency applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Free-form note for this line, such as an invoice item. Only stored for
+    /// split postings; the record description covers the first posting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// Borrowed view of a posting, as yielded by [`Record::postings`]. The
+/// currency is already resolved against the record currency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostingRef<'a> {
+    /// Account that is debited.
+    pub debit_account: &'a Account,
+    /// Account that is credited.
+    pub credit_account: &'a Account,
+    /// Monetary amount of the posting.
+    pub amount: f64,
+    /// Currency of this posting.
+    pub currency: &'a str,
+    /// Free-form note for this line, if any.
+    pub memo: Option<&'a str>,
 }
 
 /// Errors that can occur when creating a [`Record`].
@@ -37,10 +194,12 @@ pub struct Posting {
 pub enum RecordError {
     /// The debit and credit accounts are identical.
     SameAccount,
-    /// The amount provided is not positive.
+    /// No postings were provided.
     NonAmount,
     /// The provided currency code is not supported.
     UnsupportedCurrency(String),
+    /// The first posting names a currency other than the record currency.
+    PrimaryCurrencyMismatch,
 }
 
 impl std::fmt::Display for RecordError {
@@ -55,6 +214,9 @@ impl std::fmt::Display for RecordError {
             RecordError::UnsupportedCurrency(code) => {
                 write!(f, "unsupported currency code: {code}")
             }
+            RecordError::PrimaryCurrencyMismatch => {
+                write!(f, "the first posting must use the record currency")
+            }
         }
     }
 }
@@ -87,6 +249,12 @@ pub struct Record {
     pub external_reference: Option<String>,
     /// Tags for categorizing the transaction.
     pub tags: Vec<String>,
+    /// Structured key-value pairs such as `invoice_no=123`.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Original description from an imported statement line, if available.
+    #[serde(default)]
+    pub transaction_description: Option<String>,
     /// Date from the original statement line, if available.
     #[serde(default)]
     pub transaction_date: Option<DateTime<Local>>,
@@ -114,6 +282,8 @@ impl Record {
                 debit_account,
                 credit_account,
                 amount,
+                currency: None,
+                memo: None,
             }],
             currency,
             reference_id,
@@ -123,6 +293,14 @@ impl Record {
     }
 
     /// Creates a record with multiple debit/credit postings.
+    ///
+    /// Split postings may carry their own currency; the first posting always
+    /// uses the record currency.
+    ///
+    /// Amounts are not required to be positive. A negative posting is a
+    /// contra entry, such as a refund within a purchase: it moves money from
+    /// its debit account to its credit account, and every balance adds it
+    /// with its sign.
     #[allow(clippy::too_many_arguments)]
     pub fn new_split(
         description: String,
@@ -142,6 +320,18 @@ impl Record {
             if p.debit_account == p.credit_account {
                 return Err(RecordError::SameAccount);
             }
+            if let Some(code) = &p.currency
+                && Currency::from_code(code).is_none()
+            {
+                return Err(RecordError::UnsupportedCurrency(code.clone()));
+            }
+        }
+        if postings[0]
+            .currency
+            .as_ref()
+            .is_some_and(|code| code != &currency)
+        {
+            return Err(RecordError::PrimaryCurrencyMismatch);
         }
         let mut iter = postings.into_iter();
         let first = iter.next().expect("postings.is_empty() checked above");
@@ -156,6 +346,8 @@ impl Record {
             reference_id,
             external_reference,
             tags,
+            metadata: BTreeMap::new(),
+            transaction_description: None,
             transaction_date: None,
             cleared: false,
             splits: iter.collect(),
@@ -172,23 +364,89 @@ impl Record {
         serde_json::from_str(input)
     }
 
-    /// Returns an iterator over all postings, including splits.
-    pub fn postings(&self) -> impl Iterator<Item = Posting> + '_ {
-        let first = Posting {
-            debit_account: self.debit_account.clone(),
-            credit_account: self.credit_account.clone(),
+    /// Returns an iterator over all postings, including splits. Each posting
+    /// has its currency resolved, falling back to the record currency.
+    ///
+    /// The postings borrow from the record, so iterating them in balance
+    /// loops does not clone accounts or currencies. Use
+    /// [`Record::into_postings`] for owned postings.
+    pub fn postings(&self) -> impl Iterator<Item = PostingRef<'_>> {
+        let first = PostingRef {
+            debit_account: &self.debit_account,
+            credit_account: &self.credit_account,
             amount: self.amount,
+            currency: &self.currency,
+            memo: None,
         };
-        std::iter::once(first).chain(self.splits.clone())
+        std::iter::once(first).chain(self.splits.iter().map(|p| PostingRef {
+            debit_account: &p.debit_account,
+            credit_account: &p.credit_account,
+            amount: p.amount,
+            currency: p.currency.as_deref().unwrap_or(&self.currency),
+            memo: p.memo.as_deref(),
+        }))
+    }
+
+    /// Consumes the record and returns its postings, including splits, with
+    /// each currency resolved as in [`Record::postings`].
+    pub fn into_postings(self) -> Vec<Posting> {
+        let currency = self.currency;
+        let mut postings = vec![Posting {
+            debit_account: self.debit_account,
+            credit_account: self.credit_account,
+            amount: self.amount,
+            currency: Some(currency.clone()),
+            memo: None,
+        }];
+        postings.extend(self.splits.into_iter().map(|p| Posting {
+            currency: p.currency.or_else(|| Some(currency.clone())),
+            ..p
+        }));
+        postings
+    }
+
+    /// Returns `true` if every posting moves money between accounts under
+    /// `asset_prefixes`, such as a checking to savings transfer. Transfers
+    /// change neither net worth nor income and expenses.
+    pub fn is_transfer(&self, asset_prefixes: &[Account]) -> bool {
+        let owned = |account: &Account| asset_prefixes.iter().any(|p| account.starts_with(p));
+        self.postings()
+            .all(|p| owned(p.debit_account) && owned(p.credit_account))
     }
 
-    /// Converts the record into a row for spreadsheet storage.
+    /// Converts the record into a row for spreadsheet storage using the
+    /// current [`SCHEMA_VERSION`] layout. Hashes computed over this row cover
+    /// the version column so it cannot be downgraded unnoticed. The `cleared`
+    /// cell is left empty, so `status` rows decide the record's status.
+    ///
+    /// Tags are joined with commas. If a tag is empty, contains a comma or
+    /// the first one starts with `[`, the tags are written as a JSON array
+    /// instead so [`row_tags`] reads back the same list.
     pub fn to_row(&self) -> Vec<String> {
+        self.row_with_cleared("")
+    }
+
+    fn tags_cell(&self) -> String {
+        let plain = self.tags.iter().all(|t| !t.is_empty() && !t.contains(','))
+            && !self.tags.first().is_some_and(|t| t.starts_with('['));
+        if plain {
+            self.tags.join(",")
+        } else {
+            serde_json::to_string(&self.tags).unwrap_or_default()
+        }
+    }
+
+    fn row_with_cleared(&self, cleared: &str) -> Vec<String> {
         let splits = if self.splits.is_empty() {
             String::new()
         } else {
             serde_json::to_string(&self.splits).unwrap_or_default()
         };
+        let metadata = if self.metadata.is_empty() {
+            String::new()
+        } else {
+            serde_json::to_string(&self.metadata).unwrap_or_default()
+        };
         vec![
             self.id.to_string(),
             self.timestamp.to_rfc3339(),
@@ -201,11 +459,15 @@ impl Record {
                 .map(|id| id.to_string())
                 .unwrap_or_default(),
             self.external_reference.clone().unwrap_or_default(),
-            self.tags.join(","),
+            self.tags_cell(),
             splits,
+            self.transaction_description.clone().unwrap_or_default(),
             self.transaction_date
                 .map(|d| d.format("%Y-%m-%d").to_string())
                 .unwrap_or_default(),
+            SCHEMA_VERSION.to_string(),
+            metadata,
+            cleared.to_string(),
         ]
     }
 
@@ -221,6 +483,67 @@ impl Record {
         row
     }
 
+    /// Like [`Record::to_row_hashed`], but writes the cleared flag to the
+    /// `cleared` column. Only write such rows with
+    /// [`CloudSpreadsheetService::update_row`]: the stored flag overrides any
+    /// `status` rows for the record.
+    ///
+    /// [`CloudSpreadsheetService::update_row`]: crate::cloud_adapters::CloudSpreadsheetService::update_row
+    pub fn to_row_hashed_with_status(&self, signature: &str) -> Vec<String> {
+        let mut row = self.row_with_cleared(&self.cleared.to_string());
+        let hash = utils::hash_row(&row, signature);
+        row.push(hash);
+        row
+    }
+
+    /// Converts the record into a row with a hash computed by `algo`.
+    pub fn to_row_hashed_with(&self, signature: &str, algo: utils::HashAlgo) -> Vec<String> {
+        let mut row = self.to_row();
+        let hash = utils::hash_row_with(&row, signature, algo);
+        row.push(hash);
+        row
+    }
+
+    /// Creates a record cancelling this one: every posting is swapped and the
+    /// new record references the original through `reference_id`.
+    pub fn reverse(&self) -> Record {
+        let mut reversed = self.clone();
+        reversed.id = Uuid::new_v4();
+        reversed.timestamp = Utc::now();
+        reversed.description = format!("Reversal of {}", self.description);
+        std::mem::swap(&mut reversed.debit_account, &mut reversed.credit_account);
+        for split in &mut reversed.splits {
+            std::mem::swap(&mut split.debit_account, &mut split.credit_account);
+        }
+        reversed.reference_id = Some(self.id);
+        // The statement reference belongs to the original; keeping it would
+        // make the reversal look like a re-import of the same line.
+        reversed.external_reference = None;
+        reversed.cleared = false;
+        reversed
+    }
+
+    /// Returns `true` if this record cancels `original`, as produced by
+    /// [`Record::reverse`].
+    pub fn reverses(&self, original: &Record) -> bool {
+        self.reference_id == Some(original.id)
+            && self.currency == original.currency
+            && self.postings().count() == original.postings().count()
+            && self.postings().zip(original.postings()).all(|(r, o)| {
+                r.debit_account == o.credit_account
+                    && r.credit_account == o.debit_account
+                    && r.amount == o.amount
+                    && r.currency == o.currency
+            })
+    }
+
+    /// Date the record applies from: the statement date when known, otherwise
+    /// the day it was recorded.
+    pub fn effective_date(&self) -> NaiveDate {
+        self.transaction_date
+            .map_or_else(|| self.timestamp.date_naive(), |d| d.date_naive())
+    }
+
     /// Converts the cleared status into a row for spreadsheet storage.
     pub fn status_row(&self) -> Vec<String> {
         vec![
@@ -238,6 +561,8 @@ pub enum LedgerError {
     RecordNotFound,
     /// Records are immutable once committed and cannot be modified or deleted.
     ImmutableRecord,
+    /// A record with the same identifier has already been committed.
+    DuplicateId(Uuid),
 }
 
 impl std::fmt::Display for LedgerError {
@@ -249,6 +574,9 @@ impl std::fmt::Display for LedgerError {
             LedgerError::ImmutableRecord => {
                 write!(f, "records are immutable and cannot be modified")
             }
+            LedgerError::DuplicateId(id) => {
+                write!(f, "a record with id {id} already exists")
+            }
         }
     }
 }
@@ -259,14 +587,83 @@ impl std::error::Error for LedgerError {}
 #[derive(Default)]
 pub struct Ledger {
     records: Vec<Record>,
+    case_insensitive: bool,
+    /// Positions in `records` of every record with a posting to each account.
+    index: HashMap<Account, Vec<usize>>,
+    /// Identifiers of every committed record, for [`commit_checked`].
+    ///
+    /// [`commit_checked`]: Ledger::commit_checked
+    ids: HashSet<Uuid>,
 }
 
 impl Ledger {
+    /// Controls whether balance lookups ignore the case of account names.
+    /// Records keep their original casing either way.
+    pub fn with_case_insensitive_accounts(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    /// Renames the accounts of every record through `aliases`, so reports
+    /// show the current name of accounts renamed after records were
+    /// committed. Record ids, amounts and the stored rows are unchanged.
+    pub fn with_aliases(self, aliases: &AliasMap) -> Self {
+        if aliases.is_empty() {
+            return self;
+        }
+        let mut ledger = Ledger {
+            case_insensitive: self.case_insensitive,
+            ..Ledger::default()
+        };
+        for mut record in self.records {
+            record.debit_account = aliases.resolve(&record.debit_account);
+            record.credit_account = aliases.resolve(&record.credit_account);
+            for split in &mut record.splits {
+                split.debit_account = aliases.resolve(&split.debit_account);
+                split.credit_account = aliases.resolve(&split.credit_account);
+            }
+            ledger.commit(record);
+        }
+        ledger
+    }
+
     /// Commits a record to the ledger.
     pub fn commit(&mut self, record: Record) {
+        let pos = self.records.len();
+        for p in record.postings() {
+            for account in [p.debit_account, p.credit_account] {
+                let positions = self.index.entry(account.clone()).or_default();
+                if positions.last() != Some(&pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        self.ids.insert(record.id);
         self.records.push(record);
     }
 
+    /// Commits a record unless one with the same identifier already exists.
+    pub fn commit_checked(&mut self, record: Record) -> Result<(), LedgerError> {
+        if self.ids.contains(&record.id) {
+            return Err(LedgerError::DuplicateId(record.id));
+        }
+        self.commit(record);
+        Ok(())
+    }
+
+    /// Returns identifiers that appear on more than one record, in the order
+    /// their first duplicate was committed.
+    pub fn duplicate_ids(&self) -> Vec<Uuid> {
+        let mut seen = std::collections::HashSet::new();
+        let mut duplicates = Vec::new();
+        for r in &self.records {
+            if !seen.insert(r.id) && !duplicates.contains(&r.id) {
+                duplicates.push(r.id);
+            }
+        }
+        duplicates
+    }
+
     /// Appends a record to the ledger.
     #[deprecated(note = "use `commit` instead")]
     pub fn append(&mut self, record: Record) {
@@ -278,6 +675,16 @@ impl Ledger {
         self.records.iter()
     }
 
+    /// Returns all records ordered by `(timestamp, id)`. Unlike [`records`],
+    /// which preserves commit order, this gives a stable chronological view.
+    ///
+    /// [`records`]: Ledger::records
+    pub fn records_sorted(&self) -> Vec<&Record> {
+        let mut records: Vec<&Record> = self.records.iter().collect();
+        records.sort_by_key(|r| (r.timestamp, r.id));
+        records
+    }
+
     /// Retrieves a record by its unique identifier.
     pub fn get_record(&self, id: Uuid) -> Result<&Record, LedgerError> {
         self.records
@@ -306,6 +713,7 @@ impl Ledger {
     /// timestamp from oldest to newest.
     pub fn adjustment_history(&self, id: Uuid) -> Vec<&Record> {
         let mut history = Vec::new();
+        let mut seen = HashSet::from([id]);
         let mut queue = vec![id];
 
         while let Some(current) = queue.pop() {
@@ -314,8 +722,11 @@ impl Ledger {
                 .iter()
                 .filter(|r| r.reference_id == Some(current))
             {
-                history.push(r);
-                queue.push(r.id);
+                // A reference cycle would otherwise be walked forever.
+                if seen.insert(r.id) {
+                    history.push(r);
+                    queue.push(r.id);
+                }
             }
         }
 
@@ -323,6 +734,55 @@ impl Ledger {
         history
     }
 
+    /// Follows `reference_id` from `id` back to the record the adjustment
+    /// chain started from. Returns `None` when `id` is not in the ledger. A
+    /// reference to a record missing from the ledger, or back into the chain,
+    /// ends the walk at the last record found.
+    pub fn original_of(&self, id: Uuid) -> Option<Uuid> {
+        let mut current = self.get_record(id).ok()?;
+        let mut seen = HashSet::from([id]);
+        while let Some(parent) = current
+            .reference_id
+            .filter(|parent| seen.insert(*parent))
+            .and_then(|parent| self.get_record(parent).ok())
+        {
+            current = parent;
+        }
+        Some(current.id)
+    }
+
+    /// The original of `id` followed by every adjustment descending from it,
+    /// ordered by timestamp. Empty when `id` is not in the ledger.
+    pub fn lineage(&self, id: Uuid) -> Vec<&Record> {
+        let Some(root) = self.original_of(id) else {
+            return Vec::new();
+        };
+        let mut lineage: Vec<&Record> = self.get_record(root).into_iter().collect();
+        lineage.extend(self.adjustment_history(root));
+        lineage.sort_by_key(|r| r.timestamp);
+        lineage
+    }
+
+    /// Returns `true` if a reversing entry for the record has been committed.
+    pub fn is_reversed(&self, id: Uuid) -> bool {
+        let Ok(original) = self.get_record(id) else {
+            return false;
+        };
+        self.records.iter().any(|r| r.reverses(original))
+    }
+
+    /// Uncleared records dated more than `days` days before `as_of`, oldest
+    /// first, so long-pending entries can be chased up at reconciliation.
+    pub fn stale_uncleared(&self, as_of: NaiveDate, days: i64) -> Vec<&Record> {
+        let mut stale: Vec<&Record> = self
+            .records
+            .iter()
+            .filter(|r| !r.cleared && (as_of - r.effective_date()).num_days() > days)
+            .collect();
+        stale.sort_by_key(|r| (r.effective_date(), r.timestamp, r.id));
+        stale
+    }
+
     /// Attempts to modify an existing record. Always fails because records are immutable.
     pub fn modify_record(&mut self, _id: Uuid, _record: Record) -> Result<(), LedgerError> {
         Err(LedgerError::ImmutableRecord)
@@ -335,59 +795,236 @@ impl Ledger {
 
     /// Calculates the balance for the specified account by summing debits and
     /// credits. Debits increase the balance while credits decrease it.
+    ///
+    /// This is lossy: postings without a known rate to `target` are left
+    /// out. Use [`try_account_balance`](Self::try_account_balance) to find
+    /// out which rates are missing.
     pub fn account_balance(&self, account: &str, target: &str, prices: &PriceDatabase) -> f64 {
-        self.records.iter().fold(0.0, |mut acc, r| {
-            for p in r.postings() {
-                let mut amount = p.amount;
-                if r.currency != target {
-                    if let Some(rate) =
-                        prices.get_rate(r.timestamp.date_naive(), &r.currency, target)
-                    {
-                        amount *= rate;
-                    } else {
-                        continue;
-                    }
-                }
-                if p.debit_account.to_string() == account {
-                    acc += amount;
-                }
-                if p.credit_account.to_string() == account {
-                    acc -= amount;
-                }
-            }
-            acc
-        })
+        let mut missing = BTreeSet::new();
+        self.checked_account_balance(account, target, prices, &mut missing)
+    }
+
+    /// Like [`account_balance`](Self::account_balance), but fails with every
+    /// missing rate instead of leaving those postings out.
+    pub fn try_account_balance(
+        &self,
+        account: &str,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<f64, MissingRate> {
+        let mut missing = BTreeSet::new();
+        let balance = self.checked_account_balance(account, target, prices, &mut missing);
+        MissingRate::check(balance, missing)
+    }
+
+    fn checked_account_balance(
+        &self,
+        account: &str,
+        target: &str,
+        prices: &PriceDatabase,
+        missing: &mut BTreeSet<(NaiveDate, String, String)>,
+    ) -> f64 {
+        let account: Account = match account.parse() {
+            Ok(a) => a,
+            Err(_) => return 0.0,
+        };
+        let matches = |a: &Account| a.matches(&account, self.case_insensitive);
+        self.indexed_records(matches)
+            .map(|r| {
+                let date = r.timestamp.date_naive();
+                Self::checked_delta(r, matches, target, prices, date, missing)
+            })
+            .sum()
     }
 
     /// Calculates the balance for an account and all of its subaccounts.
+    ///
+    /// This is lossy: postings without a known rate to `target` are left
+    /// out. Use [`try_account_tree_balance`](Self::try_account_tree_balance)
+    /// to find out which rates are missing.
     pub fn account_tree_balance(
         &self,
         account: &Account,
         target: &str,
         prices: &PriceDatabase,
     ) -> f64 {
-        self.records.iter().fold(0.0, |mut acc, r| {
-            for p in r.postings() {
-                let mut amount = p.amount;
-                if r.currency != target {
-                    if let Some(rate) =
-                        prices.get_rate(r.timestamp.date_naive(), &r.currency, target)
-                    {
-                        amount *= rate;
-                    } else {
-                        continue;
-                    }
-                }
-                if p.debit_account.starts_with(account) {
-                    acc += amount;
-                }
-                if p.credit_account.starts_with(account) {
-                    acc -= amount;
-                }
-            }
-            acc
+        self.indexed_records(|a| a.starts_with_case(account, self.case_insensitive))
+            .map(|r| self.tree_delta(r, account, target, prices))
+            .sum()
+    }
+
+    /// Like [`account_tree_balance`](Self::account_tree_balance), but fails
+    /// with every missing rate instead of leaving those postings out.
+    pub fn try_account_tree_balance(
+        &self,
+        account: &Account,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<f64, MissingRate> {
+        let select = |a: &Account| a.starts_with_case(account, self.case_insensitive);
+        let mut missing = BTreeSet::new();
+        let balance = self
+            .indexed_records(select)
+            .map(|r| {
+                let date = r.timestamp.date_naive();
+                Self::checked_delta(r, select, target, prices, date, &mut missing)
+            })
+            .sum();
+        MissingRate::check(balance, missing)
+    }
+
+    /// Calculates the balance of an account and its subaccounts at the end of
+    /// `as_of`. Records count from their statement date when they have one
+    /// and from their timestamp otherwise. Amounts are converted to `target`
+    /// at the rate effective on `as_of`.
+    ///
+    /// This is lossy: postings without a known rate are left out. Use
+    /// [`try_account_tree_balance_as_of`](Self::try_account_tree_balance_as_of)
+    /// to find out which rates are missing.
+    pub fn account_tree_balance_as_of(
+        &self,
+        account: &Account,
+        as_of: NaiveDate,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> f64 {
+        let select = |a: &Account| a.starts_with_case(account, self.case_insensitive);
+        self.indexed_records(select)
+            .filter(|r| r.effective_date() <= as_of)
+            .map(|r| Self::delta(r, select, target, prices, as_of))
+            .sum()
+    }
+
+    /// Like [`account_tree_balance_as_of`](Self::account_tree_balance_as_of),
+    /// but fails with every missing rate instead of leaving those postings
+    /// out.
+    pub fn try_account_tree_balance_as_of(
+        &self,
+        account: &Account,
+        as_of: NaiveDate,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<f64, MissingRate> {
+        let select = |a: &Account| a.starts_with_case(account, self.case_insensitive);
+        let mut missing = BTreeSet::new();
+        let balance = self
+            .indexed_records(select)
+            .filter(|r| r.effective_date() <= as_of)
+            .map(|r| Self::checked_delta(r, select, target, prices, as_of, &mut missing))
+            .sum();
+        MissingRate::check(balance, missing)
+    }
+
+    /// Records with a posting to any indexed account accepted by `select`,
+    /// in commit order and without repeats.
+    fn indexed_records(&self, select: impl Fn(&Account) -> bool) -> impl Iterator<Item = &Record> {
+        let positions: BTreeSet<usize> = self
+            .index
+            .iter()
+            .filter(|(account, _)| select(account))
+            .flat_map(|(_, positions)| positions.iter().copied())
+            .collect();
+        positions.into_iter().map(|pos| &self.records[pos])
+    }
+
+    /// Yields each record affecting `account` or one of its subaccounts in
+    /// timestamp order, together with the cumulative balance after it.
+    /// Postings without a known rate to `target` are left out.
+    pub fn running_balance<'a>(
+        &'a self,
+        account: &Account,
+        target: &str,
+        prices: &'a PriceDatabase,
+    ) -> impl Iterator<Item = (&'a Record, f64)> + 'a {
+        let account = account.clone();
+        let target = target.to_string();
+        let mut affected: Vec<&Record> = self
+            .indexed_records(|a| a.starts_with_case(&account, self.case_insensitive))
+            .collect();
+        affected.sort_by_key(|r| (r.timestamp, r.id));
+        affected.into_iter().scan(0.0, move |balance, r| {
+            *balance += self.tree_delta(r, &account, &target, prices);
+            Some((r, *balance))
         })
     }
+
+    /// Net change a record applies to `account` and its subaccounts,
+    /// converted to `target`. Postings without a known rate are skipped.
+    fn tree_delta(
+        &self,
+        r: &Record,
+        account: &Account,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> f64 {
+        Self::delta(
+            r,
+            |a| a.starts_with_case(account, self.case_insensitive),
+            target,
+            prices,
+            r.timestamp.date_naive(),
+        )
+    }
+
+    /// Net change a record applies to the accounts accepted by `select`,
+    /// converted to `target` at the rate of `rate_date`. Postings without a
+    /// known rate are skipped.
+    fn delta(
+        r: &Record,
+        select: impl Fn(&Account) -> bool,
+        target: &str,
+        prices: &PriceDatabase,
+        rate_date: NaiveDate,
+    ) -> f64 {
+        Self::checked_delta(r, select, target, prices, rate_date, &mut BTreeSet::new())
+    }
+
+    /// Like [`delta`](Self::delta), but adds the `(date, from, to)` of every
+    /// skipped posting to `missing`.
+    fn checked_delta(
+        r: &Record,
+        select: impl Fn(&Account) -> bool,
+        target: &str,
+        prices: &PriceDatabase,
+        rate_date: NaiveDate,
+        missing: &mut BTreeSet<(NaiveDate, String, String)>,
+    ) -> f64 {
+        let mut delta = 0.0;
+        for p in r.postings() {
+            let (debit, credit) = (select(p.debit_account), select(p.credit_account));
+            // Postings within the selection cancel out and need no rate.
+            if debit == credit {
+                continue;
+            }
+            let Some(amount) = Self::converted(&p, target, prices, rate_date) else {
+                missing.insert((rate_date, p.currency.to_string(), target.to_string()));
+                continue;
+            };
+            if debit {
+                delta += amount;
+            }
+            if credit {
+                delta -= amount;
+            }
+        }
+        delta
+    }
+
+    /// Amount of posting `p` in `target`, or `None` when `prices` has no
+    /// rate for `rate_date` in its [lookup mode](PriceDatabase::with_lookup).
+    fn converted(
+        p: &PostingRef,
+        target: &str,
+        prices: &PriceDatabase,
+        rate_date: NaiveDate,
+    ) -> Option<f64> {
+        if p.currency == target {
+            return Some(p.amount);
+        }
+        prices
+            .rate(rate_date, p.currency, target)
+            .map(|rate| p.amount * rate)
+    }
 }
 
 #[cfg(test)]
@@ -427,4 +1064,14 @@ mod tests {
         let amounts: Vec<_> = ledger.records().map(|r| r.amount).collect();
         assert_eq!(amounts, vec![1.0, 2.0]);
     }
+
+    #[test]
+    fn format_amount_uses_currency_decimals() {
+        assert_eq!(format_amount(9.5, "USD"), "9.50 USD");
+        assert_eq!(format_amount(-30.0, "USD"), "-30.00 USD");
+        assert_eq!(format_amount(1200.4, "JPY"), "1200 JPY");
+        assert_eq!(format_amount(1.25, "BHD"), "1.250 BHD");
+        assert_eq!(format_amount(-0.001, "USD"), "0.00 USD");
+        assert_eq!(format_amount(2.0, "XYZ"), "2.00 XYZ");
+    }
 }
@@ -0,0 +1,53 @@
+//! Opening balances for ledgers that start part way through an account's life.
+
+use chrono::NaiveDate;
+
+use super::{Account, Record, RecordError};
+
+/// Counter-account of every opening balance. Opening balances move equity
+/// rather than income or expenses, so they never show up as earnings.
+pub const OPENING_BALANCE_ACCOUNT: &str = "equity:opening-balances";
+
+/// Tag attached to records created by [`Record::opening_balance`].
+const OPENING_BALANCE_TAG: &str = "opening-balance";
+
+impl Record {
+    /// Creates a record setting the balance of `account` on `date`, against
+    /// [`OPENING_BALANCE_ACCOUNT`]. A positive amount debits `account`, as for
+    /// an asset; a negative amount credits it, as for a liability.
+    pub fn opening_balance(
+        account: Account,
+        amount: f64,
+        currency: String,
+        date: NaiveDate,
+    ) -> Result<Record, RecordError> {
+        let equity: Account = OPENING_BALANCE_ACCOUNT
+            .parse()
+            .expect("valid opening balance account");
+        let (debit, credit) = if amount < 0.0 {
+            (equity, account)
+        } else {
+            (account, equity)
+        };
+        let mut rec = Record::new(
+            "Opening balance".into(),
+            debit,
+            credit,
+            amount.abs(),
+            currency,
+            None,
+            None,
+            vec![OPENING_BALANCE_TAG.into()],
+        )?;
+        rec.timestamp = date.and_time(chrono::NaiveTime::MIN).and_utc();
+        Ok(rec)
+    }
+
+    /// Returns `true` if any posting uses [`OPENING_BALANCE_ACCOUNT`].
+    pub fn is_opening_balance(&self) -> bool {
+        self.postings().any(|p| {
+            p.debit_account.to_string() == OPENING_BALANCE_ACCOUNT
+                || p.credit_account.to_string() == OPENING_BALANCE_ACCOUNT
+        })
+    }
+}
@@ -1,13 +1,79 @@
 use chrono::NaiveDate;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Bound;
 use std::path::Path;
 
+/// Conversions that could not be made because no exchange rate was known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingRate {
+    /// `(date, from, to)` of every missing rate, in order and without repeats.
+    pub pairs: Vec<(NaiveDate, String, String)>,
+}
+
+impl MissingRate {
+    /// `Ok(value)` when nothing is missing, otherwise the missing rates.
+    pub(crate) fn check<T>(
+        value: T,
+        missing: BTreeSet<(NaiveDate, String, String)>,
+    ) -> Result<T, MissingRate> {
+        if missing.is_empty() {
+            Ok(value)
+        } else {
+            Err(MissingRate {
+                pairs: missing.into_iter().collect(),
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for MissingRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no exchange rate for ")?;
+        for (i, (date, from, to)) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{from} to {to} on {date}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingRate {}
+
+/// How a [`PriceDatabase`] picks the rate for a date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PriceLookup {
+    /// The most recent rate on or before the date.
+    #[default]
+    OnOrBefore,
+    /// Only a rate recorded for the date itself, so a stale rate is never
+    /// used for a volatile currency.
+    Exact,
+    /// The rate closest to the date in either direction. When two rates are
+    /// equally close the earlier one is used.
+    Nearest,
+}
+
 #[derive(Default)]
 pub struct PriceDatabase {
     rates: BTreeMap<NaiveDate, HashMap<(String, String), f64>>,
+    lookup: PriceLookup,
 }
 
 impl PriceDatabase {
+    /// Sets how [`rate`](Self::rate) picks a rate. Balance functions convert
+    /// amounts with [`rate`](Self::rate), so this decides their lookup too.
+    pub fn with_lookup(mut self, lookup: PriceLookup) -> Self {
+        self.lookup = lookup;
+        self
+    }
+
+    /// Returns the lookup mode set with [`with_lookup`](Self::with_lookup).
+    pub fn lookup(&self) -> PriceLookup {
+        self.lookup
+    }
+
     pub fn add_rate(&mut self, date: NaiveDate, from: &str, to: &str, rate: f64) {
         self.rates
             .entry(date)
@@ -15,14 +81,63 @@ impl PriceDatabase {
             .insert((from.to_string(), to.to_string()), rate);
     }
 
+    /// Returns the most recent rate on or before `date`.
     pub fn get_rate(&self, date: NaiveDate, from: &str, to: &str) -> Option<f64> {
+        self.get_rate_on_or_before(date, from, to)
+            .map(|(_, rate)| rate)
+    }
+
+    /// Returns the rate recorded for exactly `date`, if any.
+    pub fn get_rate_exact(&self, date: NaiveDate, from: &str, to: &str) -> Option<f64> {
         let pair = (from.to_string(), to.to_string());
-        for (_, map) in self.rates.range(..=date).rev() {
-            if let Some(rate) = map.get(&pair) {
-                return Some(*rate);
+        self.rates.get(&date)?.get(&pair).copied()
+    }
+
+    /// Returns the rate for `date` picked as `lookup` describes.
+    pub fn get_rate_with(
+        &self,
+        date: NaiveDate,
+        from: &str,
+        to: &str,
+        lookup: PriceLookup,
+    ) -> Option<f64> {
+        match lookup {
+            PriceLookup::OnOrBefore => self.get_rate(date, from, to),
+            PriceLookup::Exact => self.get_rate_exact(date, from, to),
+            PriceLookup::Nearest => {
+                let before = self.get_rate_on_or_before(date, from, to);
+                let pair = (from.to_string(), to.to_string());
+                let after = self
+                    .rates
+                    .range((Bound::Excluded(date), Bound::Unbounded))
+                    .find_map(|(d, map)| map.get(&pair).map(|rate| (*d, *rate)));
+                match (before, after) {
+                    (Some((b, rate)), Some((a, _))) if date - b <= a - date => Some(rate),
+                    (_, Some((_, rate))) | (Some((_, rate)), None) => Some(rate),
+                    (None, None) => None,
+                }
             }
         }
-        None
+    }
+
+    /// Returns the rate for `date` using the mode set with
+    /// [`with_lookup`](Self::with_lookup), which defaults to
+    /// [`PriceLookup::OnOrBefore`].
+    pub fn rate(&self, date: NaiveDate, from: &str, to: &str) -> Option<f64> {
+        self.get_rate_with(date, from, to, self.lookup)
+    }
+
+    fn get_rate_on_or_before(
+        &self,
+        date: NaiveDate,
+        from: &str,
+        to: &str,
+    ) -> Option<(NaiveDate, f64)> {
+        let pair = (from.to_string(), to.to_string());
+        self.rates
+            .range(..=date)
+            .rev()
+            .find_map(|(d, map)| map.get(&pair).map(|rate| (*d, *rate)))
     }
 
     pub fn from_csv(path: &Path) -> Result<Self, std::io::Error> {
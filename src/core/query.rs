@@ -2,14 +2,26 @@ use std::str::FromStr;
 
 use chrono::NaiveDate;
 
-use super::{Ledger, Record};
+use super::{Account, Ledger, Record};
 
 #[derive(Debug, Default, Clone)]
 pub struct Query {
     pub accounts: Vec<String>,
     pub tags: Vec<String>,
+    /// Metadata pairs that must all be present on a record.
+    pub metadata: Vec<(String, String)>,
     pub start: Option<NaiveDate>,
     pub end: Option<NaiveDate>,
+    /// Return matches ordered by `(timestamp, id)` instead of commit order.
+    pub sorted: bool,
+    /// Only match records with this cleared status.
+    pub cleared: Option<bool>,
+    /// Only match records that are (or are not) transfers between
+    /// [`Query::transfer_accounts`].
+    pub transfer: Option<bool>,
+    /// Own accounts used by the `transfer:` filter. When empty, every account
+    /// under `assets` counts as owned.
+    pub transfer_accounts: Vec<Account>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -43,6 +55,23 @@ impl FromStr for Query {
                 q.start = Some(parse_date(rest)?);
             } else if let Some(rest) = token.strip_prefix("end:") {
                 q.end = Some(parse_date(rest)?);
+            } else if let Some(rest) = token.strip_prefix("meta:") {
+                let (key, value) = rest
+                    .split_once('=')
+                    .ok_or_else(|| ParseError::InvalidToken(token.into()))?;
+                q.metadata.push((key.to_string(), value.to_string()));
+            } else if let Some(rest) = token.strip_prefix("cleared:") {
+                q.cleared = Some(
+                    rest.parse()
+                        .map_err(|_| ParseError::InvalidToken(token.into()))?,
+                );
+            } else if let Some(rest) = token.strip_prefix("transfer:") {
+                q.transfer = Some(
+                    rest.parse()
+                        .map_err(|_| ParseError::InvalidToken(token.into()))?,
+                );
+            } else if token == "sort:date" {
+                q.sorted = true;
             } else if let Some(rest) = token.strip_prefix("date:") {
                 let parts: Vec<&str> = rest.split("..").collect();
                 if parts.len() != 2 {
@@ -68,15 +97,15 @@ fn parse_date(s: &str) -> Result<NaiveDate, ParseError> {
 
 impl Query {
     pub fn matches(&self, rec: &Record) -> bool {
-        if let Some(start) = self.start {
-            if rec.timestamp.date_naive() < start {
-                return false;
-            }
+        if let Some(start) = self.start
+            && rec.timestamp.date_naive() < start
+        {
+            return false;
         }
-        if let Some(end) = self.end {
-            if rec.timestamp.date_naive() > end {
-                return false;
-            }
+        if let Some(end) = self.end
+            && rec.timestamp.date_naive() > end
+        {
+            return false;
         }
         if !self.accounts.is_empty()
             && !self.accounts.iter().any(|a| {
@@ -87,14 +116,42 @@ impl Query {
         {
             return false;
         }
+        if self.cleared.is_some_and(|cleared| rec.cleared != cleared) {
+            return false;
+        }
+        if let Some(transfer) = self.transfer {
+            let is_transfer = if self.transfer_accounts.is_empty() {
+                rec.is_transfer(&[Account::parse_lenient("assets")])
+            } else {
+                rec.is_transfer(&self.transfer_accounts)
+            };
+            if is_transfer != transfer {
+                return false;
+            }
+        }
         if !self.tags.is_empty() && !rec.tags.iter().any(|t| self.tags.contains(t)) {
             return false;
         }
+        if !self
+            .metadata
+            .iter()
+            .all(|(k, v)| rec.metadata.get(k) == Some(v))
+        {
+            return false;
+        }
         true
     }
 
     pub fn filter<'a>(&self, ledger: &'a Ledger) -> Vec<&'a Record> {
-        ledger.records().filter(|r| self.matches(r)).collect()
+        if self.sorted {
+            ledger
+                .records_sorted()
+                .into_iter()
+                .filter(|r| self.matches(r))
+                .collect()
+        } else {
+            ledger.records().filter(|r| self.matches(r)).collect()
+        }
     }
 }
 
@@ -1,9 +1,9 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
 use cron::Schedule;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
-use super::{Account, Record};
+use super::{Account, Record, RecordError};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordTemplate {
@@ -31,10 +31,148 @@ impl RecordTemplate {
     }
 }
 
+/// How a generated date falling on a weekend is moved to a business day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BusinessDayAdjust {
+    /// Keep weekend dates.
+    #[default]
+    None,
+    /// Move to the Friday before.
+    Previous,
+    /// Move to the Monday after.
+    Next,
+    /// Move Saturdays to Friday and Sundays to Monday.
+    Nearest,
+}
+
+impl BusinessDayAdjust {
+    /// Moves `datetime` off a weekend, keeping its time of day.
+    pub fn apply(self, datetime: DateTime<Utc>) -> DateTime<Utc> {
+        let days = match (self, datetime.weekday()) {
+            (BusinessDayAdjust::None, _) => 0,
+            (BusinessDayAdjust::Previous | BusinessDayAdjust::Nearest, Weekday::Sat) => -1,
+            (BusinessDayAdjust::Previous, Weekday::Sun) => -2,
+            (BusinessDayAdjust::Next, Weekday::Sat) => 2,
+            (BusinessDayAdjust::Next | BusinessDayAdjust::Nearest, Weekday::Sun) => 1,
+            _ => 0,
+        };
+        datetime + Duration::days(days)
+    }
+}
+
+impl FromStr for BusinessDayAdjust {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(BusinessDayAdjust::None),
+            "previous" => Ok(BusinessDayAdjust::Previous),
+            "next" => Ok(BusinessDayAdjust::Next),
+            "nearest" => Ok(BusinessDayAdjust::Nearest),
+            other => Err(format!(
+                "unknown adjustment {other:?}, expected none, previous, next or nearest"
+            )),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScheduleEntry {
     pub cron: String,
     pub template: RecordTemplate,
+    /// Moves occurrences that fall on a weekend to a business day.
+    #[serde(default)]
+    pub adjust: BusinessDayAdjust,
+}
+
+impl ScheduleEntry {
+    /// Checks that the cron expression parses.
+    pub fn validate(&self) -> Result<(), CronError> {
+        self.schedule().map(|_| ())
+    }
+
+    fn schedule(&self) -> Result<Schedule, CronError> {
+        Schedule::from_str(&self.cron).map_err(|e| CronError {
+            cron: self.cron.clone(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Occurrences of `schedule` in `(since, until]`, moved off weekends.
+    /// The window applies to the unadjusted dates, so consecutive windows
+    /// never yield an occurrence twice. Occurrences moved onto the same
+    /// date and time are only yielded once.
+    fn occurrences(
+        &self,
+        schedule: &Schedule,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Vec<DateTime<Utc>> {
+        let mut dates: Vec<_> = schedule
+            .after(&since)
+            .take_while(|d| *d <= until)
+            .map(|d| self.adjust.apply(d))
+            .collect();
+        // Adjusting keeps the order, so repeats are adjacent.
+        dates.dedup();
+        dates
+    }
+}
+
+/// A cron expression that could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronError {
+    pub cron: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for CronError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid cron '{}': {}", self.cron, self.message)
+    }
+}
+
+impl std::error::Error for CronError {}
+
+/// Errors reported by [`Scheduler::try_generate`] and [`Scheduler::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The cron expression of the entry at `index` could not be parsed.
+    InvalidCron {
+        index: usize,
+        cron: String,
+        message: String,
+    },
+    /// The template of the entry at `index` does not form a valid record.
+    InvalidTemplate { index: usize, error: RecordError },
+}
+
+impl std::fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::InvalidCron {
+                index,
+                cron,
+                message,
+            } => write!(f, "schedule {index}: invalid cron '{cron}': {message}"),
+            ScheduleError::InvalidTemplate { index, error } => {
+                write!(f, "schedule {index}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+impl ScheduleError {
+    fn invalid_cron(index: usize, error: CronError) -> Self {
+        ScheduleError::InvalidCron {
+            index,
+            cron: error.cron,
+            message: error.message,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -47,7 +185,7 @@ impl Scheduler {
         let mut out = Vec::new();
         for entry in &self.entries {
             if let Ok(schedule) = Schedule::from_str(&entry.cron) {
-                for datetime in schedule.after(&since).take_while(|d| *d <= until) {
+                for datetime in entry.occurrences(&schedule, since, until) {
                     if let Ok(rec) = entry.template.to_record(datetime) {
                         out.push(rec);
                     }
@@ -56,4 +194,48 @@ impl Scheduler {
         }
         out
     }
+
+    /// Collects an error for every entry whose cron expression does not
+    /// parse. [`generate`](Self::generate) skips such entries silently.
+    pub fn validate(&self) -> Result<(), Vec<ScheduleError>> {
+        let errors: Vec<_> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                entry
+                    .validate()
+                    .err()
+                    .map(|e| ScheduleError::invalid_cron(index, e))
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Like [`generate`](Self::generate), but fails on the first entry whose
+    /// cron expression or template is invalid instead of skipping it.
+    pub fn try_generate(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Record>, ScheduleError> {
+        let mut out = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let schedule = entry
+                .schedule()
+                .map_err(|e| ScheduleError::invalid_cron(index, e))?;
+            for datetime in entry.occurrences(&schedule, since, until) {
+                let rec = entry
+                    .template
+                    .to_record(datetime)
+                    .map_err(|error| ScheduleError::InvalidTemplate { index, error })?;
+                out.push(rec);
+            }
+        }
+        Ok(out)
+    }
 }
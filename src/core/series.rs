@@ -0,0 +1,266 @@
+//! Account totals over date ranges and periods, and totals per tag.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Datelike, NaiveDate};
+
+use super::{Account, Ledger, MissingRate, Period, PriceDatabase, Record};
+
+/// Net change of `account` and its sub-accounts caused by `record`, in
+/// `target` currency. Postings without a conversion rate are ignored and
+/// added to `missing`.
+fn record_delta(
+    record: &Record,
+    account: &Account,
+    target: &str,
+    prices: &PriceDatabase,
+    missing: &mut BTreeSet<(NaiveDate, String, String)>,
+) -> f64 {
+    Ledger::checked_delta(
+        record,
+        |a| a.starts_with(account),
+        target,
+        prices,
+        record.timestamp.date_naive(),
+        missing,
+    )
+}
+
+/// Net change of `account` between `start` and `end`, both inclusive.
+///
+/// This is lossy: postings without a conversion rate are left out. Use
+/// [`try_account_sum`] to find out which rates are missing.
+pub(crate) fn account_sum(
+    ledger: &Ledger,
+    account: &Account,
+    start: NaiveDate,
+    end: NaiveDate,
+    target: &str,
+    prices: &PriceDatabase,
+) -> f64 {
+    let mut missing = BTreeSet::new();
+    sum_between(ledger, account, start, end, target, prices, &mut missing)
+}
+
+/// Like [`account_sum`], but fails with every missing rate.
+pub(crate) fn try_account_sum(
+    ledger: &Ledger,
+    account: &Account,
+    start: NaiveDate,
+    end: NaiveDate,
+    target: &str,
+    prices: &PriceDatabase,
+) -> Result<f64, MissingRate> {
+    let mut missing = BTreeSet::new();
+    let sum = sum_between(ledger, account, start, end, target, prices, &mut missing);
+    MissingRate::check(sum, missing)
+}
+
+fn sum_between(
+    ledger: &Ledger,
+    account: &Account,
+    start: NaiveDate,
+    end: NaiveDate,
+    target: &str,
+    prices: &PriceDatabase,
+    missing: &mut BTreeSet<(NaiveDate, String, String)>,
+) -> f64 {
+    ledger
+        .records()
+        .filter(|r| (start..=end).contains(&r.timestamp.date_naive()))
+        .map(|r| record_delta(r, account, target, prices, missing))
+        .sum()
+}
+
+/// First day of the period containing `date`.
+fn period_start(date: NaiveDate, period: &Period) -> NaiveDate {
+    match period {
+        Period::Monthly => date.with_day(1).unwrap_or(date),
+        Period::Yearly => date.with_ordinal(1).unwrap_or(date),
+    }
+}
+
+/// First day of the period following the one starting at `date`.
+fn next_period(date: NaiveDate, period: &Period) -> Option<NaiveDate> {
+    match period {
+        Period::Monthly => date.checked_add_months(chrono::Months::new(1)),
+        Period::Yearly => date.checked_add_months(chrono::Months::new(12)),
+    }
+}
+
+impl Ledger {
+    /// Net balance of every account used by the ledger, keyed by account
+    /// name and converted to `target`. Debits count positive and credits
+    /// negative, so the balances sum to zero.
+    ///
+    /// This is lossy: postings without a conversion rate are left out on
+    /// both sides. Use [`try_trial_balance`](Self::try_trial_balance) to find
+    /// out which rates are missing.
+    pub fn trial_balance(&self, target: &str, prices: &PriceDatabase) -> BTreeMap<String, f64> {
+        self.totals_by(
+            |account| account.to_string(),
+            target,
+            prices,
+            &mut BTreeSet::new(),
+        )
+    }
+
+    /// Like [`trial_balance`](Self::trial_balance), but fails with every
+    /// missing rate instead of leaving those postings out.
+    pub fn try_trial_balance(
+        &self,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<BTreeMap<String, f64>, MissingRate> {
+        let mut missing = BTreeSet::new();
+        let totals = self.totals_by(|account| account.to_string(), target, prices, &mut missing);
+        MissingRate::check(totals, missing)
+    }
+
+    /// Like [`trial_balance`](Self::trial_balance), but with every account
+    /// rolled up into its ancestor at depth `level`, so level 1 combines
+    /// `assets:bank:checking` and `assets:bank:savings` under `assets`.
+    pub fn balances_by_level(
+        &self,
+        level: usize,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> BTreeMap<String, f64> {
+        self.totals_by(
+            |account| account.truncated(level).to_string(),
+            target,
+            prices,
+            &mut BTreeSet::new(),
+        )
+    }
+
+    /// Like [`balances_by_level`](Self::balances_by_level), but fails with
+    /// every missing rate instead of leaving those postings out.
+    pub fn try_balances_by_level(
+        &self,
+        level: usize,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<BTreeMap<String, f64>, MissingRate> {
+        let mut missing = BTreeSet::new();
+        let totals = self.totals_by(
+            |account| account.truncated(level).to_string(),
+            target,
+            prices,
+            &mut missing,
+        );
+        MissingRate::check(totals, missing)
+    }
+
+    /// Amount moved by the records carrying each tag, converted to `target`.
+    ///
+    /// Tags belong to a record rather than to its postings, so the full
+    /// record is attributed: the amounts of all its postings, splits
+    /// included, are added up. A record with several tags counts in full
+    /// towards each of them, so the totals can add up to more than the
+    /// ledger holds. Records without tags are left out.
+    ///
+    /// This is lossy: postings without a conversion rate are left out. Use
+    /// [`try_totals_by_tag`](Self::try_totals_by_tag) to find out which rates
+    /// are missing.
+    pub fn totals_by_tag(&self, target: &str, prices: &PriceDatabase) -> BTreeMap<String, f64> {
+        self.tag_totals(target, prices, &mut BTreeSet::new())
+    }
+
+    /// Like [`totals_by_tag`](Self::totals_by_tag), but fails with every
+    /// missing rate instead of leaving those postings out.
+    pub fn try_totals_by_tag(
+        &self,
+        target: &str,
+        prices: &PriceDatabase,
+    ) -> Result<BTreeMap<String, f64>, MissingRate> {
+        let mut missing = BTreeSet::new();
+        let totals = self.tag_totals(target, prices, &mut missing);
+        MissingRate::check(totals, missing)
+    }
+
+    fn tag_totals(
+        &self,
+        target: &str,
+        prices: &PriceDatabase,
+        missing: &mut BTreeSet<(NaiveDate, String, String)>,
+    ) -> BTreeMap<String, f64> {
+        let mut totals = BTreeMap::new();
+        for record in self.records().filter(|r| !r.tags.is_empty()) {
+            let date = record.timestamp.date_naive();
+            let mut amount = 0.0;
+            for posting in record.postings() {
+                match Ledger::converted(&posting, target, prices, date) {
+                    Some(converted) => amount += converted,
+                    None => {
+                        missing.insert((date, posting.currency.to_string(), target.to_string()));
+                    }
+                }
+            }
+            let tags: BTreeSet<&String> = record.tags.iter().collect();
+            for tag in tags {
+                *totals.entry(tag.clone()).or_insert(0.0) += amount;
+            }
+        }
+        totals
+    }
+
+    /// Totals per `key`, adding the `(date, from, to)` of every posting that
+    /// could not be converted to `missing`.
+    fn totals_by(
+        &self,
+        key: impl Fn(&Account) -> String,
+        target: &str,
+        prices: &PriceDatabase,
+        missing: &mut BTreeSet<(NaiveDate, String, String)>,
+    ) -> BTreeMap<String, f64> {
+        let mut totals = BTreeMap::new();
+        for record in self.records() {
+            let date = record.timestamp.date_naive();
+            for posting in record.postings() {
+                let Some(amount) = Ledger::converted(&posting, target, prices, date) else {
+                    missing.insert((date, posting.currency.to_string(), target.to_string()));
+                    continue;
+                };
+                *totals.entry(key(posting.debit_account)).or_insert(0.0) += amount;
+                *totals.entry(key(posting.credit_account)).or_insert(0.0) -= amount;
+            }
+        }
+        totals
+    }
+
+    /// Net change of `account` and its sub-accounts in each period between
+    /// `start` and `end`, both inclusive, converted to `target`.
+    ///
+    /// Buckets are keyed by the first day of their period and returned in
+    /// date order, including periods without activity. The first and last
+    /// bucket only cover the part of their period inside the range. Postings
+    /// without a conversion rate are left out.
+    pub fn account_series(
+        &self,
+        account: &Account,
+        target: &str,
+        prices: &PriceDatabase,
+        start: NaiveDate,
+        end: NaiveDate,
+        period: Period,
+    ) -> Vec<(NaiveDate, f64)> {
+        let mut buckets = Vec::new();
+        let mut current = Some(period_start(start, &period));
+        while let Some(date) = current.filter(|d| *d <= end) {
+            buckets.push((date, 0.0));
+            current = next_period(date, &period);
+        }
+        for record in self.records() {
+            let date = record.timestamp.date_naive();
+            if date < start || date > end {
+                continue;
+            }
+            let idx = buckets.partition_point(|(bucket, _)| *bucket <= date);
+            if let Some((_, total)) = idx.checked_sub(1).and_then(|i| buckets.get_mut(i)) {
+                *total += record_delta(record, account, target, prices, &mut BTreeSet::new());
+            }
+        }
+        buckets
+    }
+}
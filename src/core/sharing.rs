@@ -1,11 +1,12 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
 use chrono::{Local, NaiveDate, TimeZone};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
 
-use super::{Ledger, LedgerError, Record};
+use super::{Account, Ledger, LedgerError, Record};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Permission {
@@ -47,6 +48,11 @@ pub struct SharedLedger<S: CloudSpreadsheetService> {
     sheet_id: String,
     statuses: Mutex<HashMap<Uuid, bool>>,
     permissions: Mutex<HashMap<String, Permission>>,
+    duplicate_ids: Vec<Uuid>,
+    /// Store cleared flags on the record rows instead of appending status rows.
+    in_place_status: bool,
+    /// Last known sheet row of each record, used by in-place status updates.
+    row_indices: Mutex<HashMap<Uuid, usize>>,
 }
 
 impl<S: CloudSpreadsheetService> SharedLedger<S> {
@@ -60,6 +66,9 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
             sheet_id,
             statuses: Mutex::new(HashMap::new()),
             permissions: Mutex::new(permissions),
+            duplicate_ids: Vec::new(),
+            in_place_status: false,
+            row_indices: Mutex::new(HashMap::new()),
         })
     }
 
@@ -72,7 +81,14 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
         let sheet_id = sheet_id.into();
         let mut ledger = Ledger::default();
         let mut statuses = HashMap::new();
-        Self::load_existing_rows(&service, &mut ledger, &mut statuses, &sheet_id)?;
+        let mut row_indices = HashMap::new();
+        let duplicate_ids = Self::load_existing_rows(
+            &service,
+            &mut ledger,
+            &mut statuses,
+            &mut row_indices,
+            &sheet_id,
+        )?;
 
         let mut permissions = HashMap::new();
         permissions.insert(owner.to_string(), Permission::Write);
@@ -82,111 +98,105 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
             sheet_id,
             statuses: Mutex::new(statuses),
             permissions: Mutex::new(permissions),
+            duplicate_ids,
+            in_place_status: false,
+            row_indices: Mutex::new(row_indices),
         })
     }
 
+    /// Stores cleared flags on the record's own row with
+    /// [`CloudSpreadsheetService::update_row`] instead of appending a status
+    /// row per change. Backends that cannot update rows still get status rows.
+    pub fn with_in_place_status(mut self, enabled: bool) -> Self {
+        self.in_place_status = enabled;
+        self
+    }
+
+    /// Loads records and statuses from the sheet. Rows whose id was already
+    /// loaded are skipped and their ids returned. A status stored on a record
+    /// row takes precedence over status rows.
     fn load_existing_rows(
         service: &S,
         ledger: &mut Ledger,
         statuses: &mut HashMap<Uuid, bool>,
+        row_indices: &mut HashMap<Uuid, usize>,
         sheet_id: &str,
-    ) -> Result<(), SpreadsheetError> {
+    ) -> Result<Vec<Uuid>, SpreadsheetError> {
+        let mut duplicates = Vec::new();
+        let mut in_place = Vec::new();
         let rows = service.list_rows(sheet_id)?;
-        for row in rows {
+        for (index, row) in rows.into_iter().enumerate() {
+            // Adapters such as Google Sheets keep a header row at the top.
+            if row.first().map(|s| s.as_str()) == Some("id") {
+                continue;
+            }
             if row.first().map(|s| s.as_str()) == Some("status") {
-                if row.len() >= 3 {
-                    if let Ok(id) = uuid::Uuid::parse_str(&row[1]) {
-                        if let Ok(c) = row[2].parse::<bool>() {
-                            statuses.insert(id, c);
-                        }
-                    }
+                if row.len() >= 3
+                    && let Ok(id) = uuid::Uuid::parse_str(&row[1])
+                    && let Ok(c) = row[2].parse::<bool>()
+                {
+                    statuses.insert(id, c);
                 }
                 continue;
             }
-            let rec = Self::record_from_row(&row)?;
-            ledger.commit(rec);
+            let rec = record_from_row(&row)?;
+            let (id, cleared) = (rec.id, super::row_cleared(&row));
+            if let Err(LedgerError::DuplicateId(id)) = ledger.commit_checked(rec) {
+                warn!(%id, sheet_id, "skipping row with duplicate record id");
+                if !duplicates.contains(&id) {
+                    duplicates.push(id);
+                }
+                continue;
+            }
+            row_indices.insert(id, index);
+            if let Some(cleared) = cleared {
+                in_place.push((id, cleared));
+            }
         }
-        Ok(())
+        statuses.extend(in_place);
+        Ok(duplicates)
     }
 
-    fn record_from_row(row: &[String]) -> Result<Record, SpreadsheetError> {
-        if row.len() < 10 {
-            return Err(SpreadsheetError::Permanent("invalid row".into()));
+    /// Re-reads the sheet and adds the records other clients appended since
+    /// the ledger was loaded or last refreshed, returning how many were
+    /// added. Records already in memory are never replaced or duplicated.
+    /// Cleared flags found in the sheet replace the in-memory ones.
+    pub fn refresh(&self) -> Result<usize, SpreadsheetError> {
+        let mut remote = Ledger::default();
+        let mut remote_statuses = HashMap::new();
+        let mut remote_indices = HashMap::new();
+        {
+            let service = self.service.lock().expect("service mutex poisoned");
+            Self::load_existing_rows(
+                &service,
+                &mut remote,
+                &mut remote_statuses,
+                &mut remote_indices,
+                &self.sheet_id,
+            )?;
         }
+        let mut ledger = self.ledger.lock().expect("ledger mutex poisoned");
+        let known: HashSet<Uuid> = ledger.records().map(|r| r.id).collect();
+        let mut pulled = 0;
+        for record in remote.records().filter(|r| !known.contains(&r.id)) {
+            ledger.commit(record.clone());
+            pulled += 1;
+        }
+        self.statuses
+            .lock()
+            .expect("statuses mutex poisoned")
+            .extend(remote_statuses);
+        self.row_indices
+            .lock()
+            .expect("row indices mutex poisoned")
+            .extend(remote_indices);
+        Ok(pulled)
+    }
 
-        let id = uuid::Uuid::parse_str(&row[0])
-            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&row[1])
-            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?
-            .with_timezone(&chrono::Utc);
-        let amount = row[5]
-            .parse::<f64>()
-            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
-        let reference_id = if row[7].is_empty() {
-            None
-        } else {
-            Some(
-                uuid::Uuid::parse_str(&row[7])
-                    .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?,
-            )
-        };
-        let external_reference = if row[8].is_empty() {
-            None
-        } else {
-            Some(row[8].clone())
-        };
-        let tags = if row[9].is_empty() {
-            Vec::new()
-        } else {
-            row[9].split(',').map(|s| s.to_string()).collect()
-        };
-        let splits_col = if row.len() > 10 { &row[10] } else { "" };
-        let tx_date_str = if row.len() > 12 { &row[12] } else { "" };
-        let splits = if !splits_col.is_empty() {
-            serde_json::from_str(splits_col)
-                .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?
-        } else {
-            Vec::new()
-        };
-        let transaction_date = if tx_date_str.is_empty() {
-            None
-        } else {
-            let naive_date = NaiveDate::parse_from_str(tx_date_str, "%Y-%m-%d")
-                .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
-
-            let naive_datetime = naive_date.and_hms_opt(0, 0, 0).unwrap();
-
-            let local_datetime = Local.from_local_datetime(&naive_datetime)
-                .single() // We expect a unique mapping for midnight
-                .ok_or_else(|| {
-                    SpreadsheetError::Permanent(format!(
-                        "Could not convert date '{}' to a unique local time. It might be an invalid date during a DST transition.",
-                        tx_date_str
-                    ))
-                })?;
-
-            Some(local_datetime)
-        };
-
-        Ok(Record {
-            id,
-            timestamp,
-            description: row[2].clone(),
-            debit_account: row[3]
-                .parse()
-                .map_err(|e| SpreadsheetError::Permanent(format!("invalid account: {e}")))?,
-            credit_account: row[4]
-                .parse()
-                .map_err(|e| SpreadsheetError::Permanent(format!("invalid account: {e}")))?,
-            amount,
-            currency: row[6].clone(),
-            reference_id,
-            external_reference,
-            tags,
-            transaction_date,
-            cleared: false,
-            splits,
-        })
+    /// Returns the ids of records that appeared more than once in the sheet
+    /// when the ledger was loaded. Only the first occurrence is kept.
+    pub fn duplicate_ids(&self) -> &[Uuid] {
+        &self.duplicate_ids
     }
 
     /// Return the underlying spreadsheet identifier.
@@ -204,6 +214,25 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
         Ok(())
     }
 
+    /// Revokes the access previously granted to `email`.
+    pub fn revoke_share(&self, email: &str) -> Result<(), AccessError> {
+        let service = self.service.lock().expect("service mutex poisoned");
+        service
+            .revoke_share(&self.sheet_id, email)
+            .map_err(|_| AccessError::ShareFailed)?;
+        let mut perms = self.permissions.lock().expect("permissions mutex poisoned");
+        perms.remove(email);
+        Ok(())
+    }
+
+    /// Lists the users the underlying spreadsheet is shared with.
+    pub fn list_shares(&self) -> Result<Vec<(String, Permission)>, AccessError> {
+        let service = self.service.lock().expect("service mutex poisoned");
+        service
+            .list_shares(&self.sheet_id)
+            .map_err(|_| AccessError::ShareFailed)
+    }
+
     fn check(&self, user: &str, required: Permission) -> Result<(), AccessError> {
         let perms = self.permissions.lock().expect("permissions mutex poisoned");
         match perms.get(user) {
@@ -234,6 +263,32 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
         Ok(())
     }
 
+    /// Commits several records with a single `append_rows` call. Permission
+    /// is checked once and the in-memory ledger is only updated after the
+    /// rows were stored.
+    pub fn commit_many(&self, user: &str, records: Vec<Record>) -> Result<(), AccessError> {
+        self.check(user, Permission::Write)?;
+        if records.is_empty() {
+            return Ok(());
+        }
+        {
+            let mut service = self.service.lock().expect("service mutex poisoned");
+            let sig = crate::core::utils::generate_signature(user, None)
+                .map_err(|_| AccessError::ShareFailed)?;
+            let rows = records.iter().map(|r| r.to_row_hashed(&sig)).collect();
+            service
+                .append_rows(&self.sheet_id, rows)
+                .map_err(|_| AccessError::ShareFailed)?;
+        }
+        let mut ledger = self.ledger.lock().expect("ledger mutex poisoned");
+        let mut statuses = self.statuses.lock().expect("statuses mutex poisoned");
+        for record in records {
+            statuses.insert(record.id, record.cleared);
+            ledger.commit(record);
+        }
+        Ok(())
+    }
+
     pub fn get_record(&self, user: &str, id: Uuid) -> Result<Record, AccessError> {
         self.check(user, Permission::Read)?;
         let mut record = self
@@ -276,8 +331,19 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
             .map_err(AccessError::Ledger)
     }
 
+    /// Records whether `id` is cleared. With
+    /// [`SharedLedger::with_in_place_status`] the flag is written to the
+    /// record's row; otherwise, or when the backend cannot update rows, a
+    /// status row is appended.
     pub fn set_cleared(&self, user: &str, id: Uuid, cleared: bool) -> Result<(), AccessError> {
         self.check(user, Permission::Write)?;
+        if self.in_place_status && self.update_status_in_place(user, id, cleared)? {
+            self.statuses
+                .lock()
+                .expect("statuses mutex poisoned")
+                .insert(id, cleared);
+            return Ok(());
+        }
         {
             let mut service = self.service.lock().expect("service mutex poisoned");
             service
@@ -294,6 +360,64 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
         Ok(())
     }
 
+    /// Rewrites the record's row with the new cleared flag. Returns `false`
+    /// when the record or its row is unknown or the backend cannot update
+    /// rows, so the caller can fall back to a status row.
+    fn update_status_in_place(
+        &self,
+        user: &str,
+        id: Uuid,
+        cleared: bool,
+    ) -> Result<bool, AccessError> {
+        let Ok(mut record) = self
+            .ledger
+            .lock()
+            .expect("ledger mutex poisoned")
+            .get_record(id)
+            .cloned()
+        else {
+            return Ok(false);
+        };
+        record.cleared = cleared;
+        let mut service = self.service.lock().expect("service mutex poisoned");
+        let mut indices = self.row_indices.lock().expect("row indices mutex poisoned");
+        let is_record_row = |row: &[String]| row.first() == Some(&id.to_string());
+        // The cached index is checked before use; rows committed since the
+        // ledger was loaded are found by scanning the sheet.
+        let index = match indices.get(&id) {
+            Some(&idx)
+                if service
+                    .read_row(&self.sheet_id, idx)
+                    .is_ok_and(|row| is_record_row(&row)) =>
+            {
+                idx
+            }
+            _ => {
+                let rows = service
+                    .list_rows(&self.sheet_id)
+                    .map_err(|_| AccessError::ShareFailed)?;
+                match rows.iter().position(|row| is_record_row(row)) {
+                    Some(idx) => idx,
+                    None => return Ok(false),
+                }
+            }
+        };
+        let sig = crate::core::utils::generate_signature(user, None)
+            .map_err(|_| AccessError::ShareFailed)?;
+        match service.update_row(
+            &self.sheet_id,
+            index,
+            record.to_row_hashed_with_status(&sig),
+        ) {
+            Ok(()) => {
+                indices.insert(id, index);
+                Ok(true)
+            }
+            Err(SpreadsheetError::Unknown) => Ok(false),
+            Err(_) => Err(AccessError::ShareFailed),
+        }
+    }
+
     pub fn mark_cleared(&self, user: &str, id: Uuid) -> Result<(), AccessError> {
         self.set_cleared(user, id, true)
     }
@@ -309,3 +433,93 @@ impl<S: CloudSpreadsheetService> SharedLedger<S> {
         )
     }
 }
+
+/// Parses a stored record row, failing on any malformed column.
+pub(crate) fn record_from_row(row: &[String]) -> Result<Record, SpreadsheetError> {
+    if row.len() < 10 {
+        return Err(SpreadsheetError::Permanent("invalid row".into()));
+    }
+
+    let id =
+        uuid::Uuid::parse_str(&row[0]).map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+    let timestamp = chrono::DateTime::parse_from_rfc3339(&row[1])
+        .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?
+        .with_timezone(&chrono::Utc);
+    let amount = row[5]
+        .parse::<f64>()
+        .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+    let reference_id = if row[7].is_empty() {
+        None
+    } else {
+        Some(
+            uuid::Uuid::parse_str(&row[7])
+                .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?,
+        )
+    };
+    let external_reference = if row[8].is_empty() {
+        None
+    } else {
+        Some(row[8].clone())
+    };
+    let tags = super::row_tags(row);
+    let column = |idx: usize| row.get(idx).map(String::as_str).unwrap_or("");
+    let (tx_desc_str, tx_date_str, metadata_str) = match super::row_schema_version(row) {
+        1 => ("", column(11), ""),
+        2 => (column(11), column(12), ""),
+        3 | 4 => (column(11), column(12), column(14)),
+        v => {
+            return Err(SpreadsheetError::Permanent(format!(
+                "unsupported schema version {v}"
+            )));
+        }
+    };
+    let metadata = if metadata_str.is_empty() {
+        BTreeMap::new()
+    } else {
+        serde_json::from_str(metadata_str)
+            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?
+    };
+    let splits = super::row_splits(row).map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+    let transaction_date = if tx_date_str.is_empty() {
+        None
+    } else {
+        let naive_date = NaiveDate::parse_from_str(tx_date_str, "%Y-%m-%d")
+            .map_err(|e| SpreadsheetError::Permanent(e.to_string()))?;
+
+        let naive_datetime = naive_date.and_hms_opt(0, 0, 0).unwrap();
+
+        let local_datetime = Local.from_local_datetime(&naive_datetime)
+            .single() // We expect a unique mapping for midnight
+            .ok_or_else(|| {
+                SpreadsheetError::Permanent(format!(
+                    "Could not convert date '{}' to a unique local time. It might be an invalid date during a DST transition.",
+                    tx_date_str
+                ))
+            })?;
+
+        Some(local_datetime)
+    };
+
+    Ok(Record {
+        id,
+        timestamp,
+        description: row[2].clone(),
+        // Stored names may predate account validation.
+        debit_account: Account::parse_lenient(&row[3]),
+        credit_account: Account::parse_lenient(&row[4]),
+        amount,
+        currency: row[6].clone(),
+        reference_id,
+        external_reference,
+        tags,
+        metadata,
+        transaction_description: if tx_desc_str.is_empty() {
+            None
+        } else {
+            Some(tx_desc_str.to_string())
+        },
+        transaction_date,
+        cleared: super::row_cleared(row).unwrap_or(false),
+        splits,
+    })
+}
@@ -0,0 +1,140 @@
+//! Versioned JSON snapshots of a whole ledger for backup and restore.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
+
+use super::{Ledger, PriceDatabase, Record};
+
+/// Format version written by [`export`]. Bump this whenever the snapshot
+/// layout changes in a way older readers cannot load.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Errors that can occur when reading a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The document is not a valid snapshot.
+    Parse(String),
+    /// The snapshot was written by a newer, incompatible version.
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Parse(e) => write!(f, "invalid snapshot: {e}"),
+            SnapshotError::UnsupportedVersion(v) => {
+                write!(f, "unsupported snapshot version {v}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Exchange rate stored in a snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceRate {
+    pub date: NaiveDate,
+    pub from: String,
+    pub to: String,
+    pub rate: f64,
+}
+
+/// Full ledger state: every record in commit order, the reconciliation
+/// status of each record that has one, and the price database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub records: Vec<Record>,
+    #[serde(default)]
+    pub statuses: BTreeMap<Uuid, bool>,
+    #[serde(default)]
+    pub prices: Vec<PriceRate>,
+}
+
+/// Captures `ledger`, its reconciliation `statuses` and `prices`.
+pub fn export(ledger: &Ledger, statuses: &HashMap<Uuid, bool>, prices: &PriceDatabase) -> Snapshot {
+    Snapshot {
+        version: SNAPSHOT_VERSION,
+        records: ledger.records().cloned().collect(),
+        statuses: statuses.iter().map(|(id, c)| (*id, *c)).collect(),
+        prices: prices
+            .all_rates()
+            .into_iter()
+            .map(|(date, from, to, rate)| PriceRate {
+                date,
+                from,
+                to,
+                rate,
+            })
+            .collect(),
+    }
+}
+
+/// Parses a snapshot written by [`Snapshot::to_json`].
+pub fn import(json: &str) -> Result<Snapshot, SnapshotError> {
+    let snapshot: Snapshot =
+        serde_json::from_str(json).map_err(|e| SnapshotError::Parse(e.to_string()))?;
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(snapshot.version));
+    }
+    Ok(snapshot)
+}
+
+impl Snapshot {
+    /// Serializes the snapshot as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Rebuilds the ledger, with each record's cleared flag taken from the
+    /// stored statuses.
+    pub fn ledger(&self) -> Ledger {
+        let mut ledger = Ledger::default();
+        for record in &self.records {
+            let mut record = record.clone();
+            record.cleared = self.statuses.get(&record.id).copied().unwrap_or(false);
+            ledger.commit(record);
+        }
+        ledger
+    }
+
+    /// Rebuilds the price database.
+    pub fn price_database(&self) -> PriceDatabase {
+        let mut db = PriceDatabase::default();
+        for p in &self.prices {
+            db.add_rate(p.date, &p.from, &p.to, p.rate);
+        }
+        db
+    }
+
+    /// Sheet rows reproducing the snapshot: one row per record hashed with
+    /// `signature`, followed by a `status` row per reconciliation status.
+    /// Record rows hash exactly like rows written by `add` or `import`, so a
+    /// restored sheet verifies with the same signature.
+    pub fn rows(&self, signature: &str) -> Vec<Vec<String>> {
+        let records = self.records.iter().map(|r| r.to_row_hashed(signature));
+        let statuses = self
+            .statuses
+            .iter()
+            .map(|(id, cleared)| vec!["status".into(), id.to_string(), cleared.to_string()]);
+        records.chain(statuses).collect()
+    }
+
+    /// Writes [`Snapshot::rows`] into a new sheet titled `title` and returns
+    /// its id.
+    pub fn restore(
+        &self,
+        service: &mut dyn CloudSpreadsheetService,
+        title: &str,
+        signature: &str,
+    ) -> Result<String, SpreadsheetError> {
+        let sheet_id = service.create_sheet(title)?;
+        service.append_rows(&sheet_id, self.rows(signature))?;
+        Ok(sheet_id)
+    }
+}
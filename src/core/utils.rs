@@ -13,8 +13,19 @@
 
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
+use hmac::{Hmac, Mac};
 use sha2::{Digest, Sha256};
 
+/// Algorithm used to compute row hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgo {
+    /// SHA-256 over the row values with the signature appended as a salt.
+    #[default]
+    Sha256Salted,
+    /// HMAC-SHA256 over the row values keyed with the signature.
+    HmacSha256,
+}
+
 /// Generates a Base64-encoded signature string from a name and optional password.
 ///
 /// - If password is missing or empty, signature = Base64Encode(name)
@@ -54,13 +65,34 @@ pub fn generate_signature(name: &str, password: Option<&str>) -> Result<String,
 /// signature produces a different hash even when the row values are the same.
 /// This allows detection of tampering with stored rows.
 pub fn hash_row(values: &[String], signature: &str) -> String {
-    let mut hasher = Sha256::new();
-    for v in values {
-        hasher.update(v.as_bytes());
-        hasher.update([0u8]);
+    hash_row_with(values, signature, HashAlgo::Sha256Salted)
+}
+
+/// Computes a row hash using the given algorithm.
+///
+/// [`HashAlgo::HmacSha256`] uses the signature as the MAC key, so hashes
+/// cannot be forged without it even if the row format is known.
+pub fn hash_row_with(values: &[String], signature: &str, algo: HashAlgo) -> String {
+    match algo {
+        HashAlgo::Sha256Salted => {
+            let mut hasher = Sha256::new();
+            for v in values {
+                hasher.update(v.as_bytes());
+                hasher.update([0u8]);
+            }
+            hasher.update(signature.as_bytes());
+            format!("{:x}", hasher.finalize())
+        }
+        HashAlgo::HmacSha256 => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(signature.as_bytes())
+                .expect("HMAC accepts keys of any length");
+            for v in values {
+                mac.update(v.as_bytes());
+                mac.update(&[0u8]);
+            }
+            format!("{:x}", mac.finalize().into_bytes())
+        }
     }
-    hasher.update(signature.as_bytes());
-    format!("{:x}", hasher.finalize())
 }
 
 #[cfg(test)]
@@ -0,0 +1,85 @@
+//! Read-side integrity checks over committed records, complementing the row
+//! hashes checked by [`verify_sheet`](super::verify_sheet).
+
+use std::collections::HashSet;
+
+use iso_currency::Currency;
+use uuid::Uuid;
+
+use super::{Ledger, Record};
+
+/// A problem found by [`Ledger::validate`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// The record adjusts `reference_id`, which is not in the ledger.
+    DanglingReference { id: Uuid, reference_id: Uuid },
+    /// Posting `posting` of the record, counting the primary posting as 0,
+    /// names the same account on both sides or has an amount that is not a
+    /// finite number, so it cannot balance.
+    UnbalancedPosting { id: Uuid, posting: usize },
+    /// The record or one of its postings uses an unknown currency code.
+    UnsupportedCurrency { id: Uuid, currency: String },
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::DanglingReference { id, reference_id } => {
+                write!(f, "record {id} references missing record {reference_id}")
+            }
+            ValidationIssue::UnbalancedPosting { id, posting } => {
+                write!(f, "posting {posting} of record {id} does not balance")
+            }
+            ValidationIssue::UnsupportedCurrency { id, currency } => {
+                write!(f, "record {id} uses unsupported currency {currency}")
+            }
+        }
+    }
+}
+
+impl Ledger {
+    /// Checks every committed record and returns the problems found, in
+    /// commit order. Records built with [`Record::new`](super::Record::new)
+    /// are always valid, but rows loaded from a sheet bypass its checks and
+    /// adjustments may reference rows that were never loaded.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let ids: HashSet<Uuid> = self.records.iter().map(|r| r.id).collect();
+        let mut issues = Vec::new();
+        for r in &self.records {
+            if let Some(reference_id) = r.reference_id
+                && !ids.contains(&reference_id)
+            {
+                issues.push(ValidationIssue::DanglingReference {
+                    id: r.id,
+                    reference_id,
+                });
+            }
+            issues.extend(record_issues(r));
+        }
+        issues
+    }
+}
+
+/// Problems found in `r` on its own: postings that cannot balance and
+/// unknown currency codes.
+pub(crate) fn record_issues(r: &Record) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut unsupported = Vec::new();
+    for (posting, p) in r.postings().enumerate() {
+        if p.debit_account == p.credit_account || !p.amount.is_finite() {
+            issues.push(ValidationIssue::UnbalancedPosting { id: r.id, posting });
+        }
+        if Currency::from_code(p.currency).is_none() && !unsupported.contains(&p.currency) {
+            unsupported.push(p.currency);
+        }
+    }
+    issues.extend(
+        unsupported
+            .into_iter()
+            .map(|currency| ValidationIssue::UnsupportedCurrency {
+                id: r.id,
+                currency: currency.to_string(),
+            }),
+    );
+    issues
+}
@@ -1,5 +1,7 @@
 use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
-use crate::core::utils::hash_row;
+use crate::core::sharing::record_from_row;
+use crate::core::utils::{HashAlgo, hash_row, hash_row_with};
+use crate::core::validation::record_issues;
 use tracing::{debug, info};
 
 /// Recomputes hashes for all ledger rows and returns the zero-based indices
@@ -8,24 +10,228 @@ pub fn verify_sheet(
     adapter: &dyn CloudSpreadsheetService,
     sheet_id: &str,
     signature: &str,
+) -> Result<Vec<usize>, SpreadsheetError> {
+    verify_sheet_with(adapter, sheet_id, signature, HashAlgo::Sha256Salted)
+}
+
+/// Like [`verify_sheet`], but recomputes hashes with the given algorithm.
+pub fn verify_sheet_with(
+    adapter: &dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+    algo: HashAlgo,
 ) -> Result<Vec<usize>, SpreadsheetError> {
     let rows = adapter.list_rows(sheet_id)?;
     info!(sheet_id, row_count = rows.len(), "Verifying sheet");
-    let mut mismatched = Vec::new();
+    let mismatched = mismatched_rows(&rows, 0, signature, algo);
+    info!(mismatched = mismatched.len(), "Verification complete");
+    Ok(mismatched)
+}
+
+/// Outcome of [`verify_sheet_full`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SheetVerification {
+    /// Zero-based indices of rows whose stored hash does not match.
+    pub tampered: Vec<usize>,
+    /// Zero-based indices of rows that do not form a valid record, with the
+    /// reason. A row can be listed here and in `tampered`.
+    pub unparsable: Vec<(usize, String)>,
+}
+
+impl SheetVerification {
+    /// Returns `true` if every row verified and parsed.
+    pub fn is_ok(&self) -> bool {
+        self.tampered.is_empty() && self.unparsable.is_empty()
+    }
+}
+
+/// Like [`verify_sheet`], but also checks that every record row parses into
+/// a valid [`Record`](crate::core::Record), with a valid id, accounts and
+/// currencies. A hash only proves that a row was written by someone holding
+/// the signature, not that its content makes sense.
+pub fn verify_sheet_full(
+    adapter: &dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+) -> Result<SheetVerification, SpreadsheetError> {
+    let rows = adapter.list_rows(sheet_id)?;
+    info!(sheet_id, row_count = rows.len(), "Verifying sheet");
+    let tampered = mismatched_rows(&rows, 0, signature, HashAlgo::Sha256Salted);
+    let mut unparsable = Vec::new();
     for (idx, row) in rows.iter().enumerate() {
-        if row.len() < 2 || row.first().map(|s| s.as_str()) == Some("status") {
+        if is_record_row(row)
+            && let Some(reason) = parse_failure(row)
+        {
+            debug!(index = idx, %reason, "Row does not parse");
+            unparsable.push((idx, reason));
+        }
+    }
+    info!(
+        mismatched = tampered.len(),
+        unparsable = unparsable.len(),
+        "Verification complete"
+    );
+    Ok(SheetVerification {
+        tampered,
+        unparsable,
+    })
+}
+
+/// Why `row` does not form a valid record, if it does not.
+fn parse_failure(row: &[String]) -> Option<String> {
+    match record_from_row(row) {
+        Ok(record) => record_issues(&record).first().map(ToString::to_string),
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+/// Rows fetched per request by [`verify_sheet_from`].
+const PAGE_SIZE: usize = 1000;
+
+/// Like [`verify_sheet`], but only checks rows at or after the zero-based
+/// `start_index`, reading just those rows from the adapter. Returns the
+/// indices of mismatched rows and the total number of rows in the sheet, so
+/// the next run can start where this one ended. When `start_index` is at or
+/// past the end of the sheet nothing is checked and it is returned as the
+/// total.
+pub fn verify_sheet_from(
+    adapter: &dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+    start_index: usize,
+) -> Result<(Vec<usize>, usize), SpreadsheetError> {
+    info!(sheet_id, start_index, "Verifying sheet");
+    let mut mismatched = Vec::new();
+    let mut next = start_index;
+    loop {
+        let rows = adapter.read_rows(sheet_id, next, next + PAGE_SIZE)?;
+        mismatched.extend(mismatched_rows(
+            &rows,
+            next,
+            signature,
+            HashAlgo::Sha256Salted,
+        ));
+        next += rows.len();
+        if rows.len() < PAGE_SIZE {
+            break;
+        }
+    }
+    info!(
+        mismatched = mismatched.len(),
+        row_count = next - start_index,
+        "Verification complete"
+    );
+    Ok((mismatched, next))
+}
+
+/// Recomputes the hash of each row in `indices` from its other columns and
+/// writes it back, so a row corrected by hand verifies again. Status rows, the
+/// header row and rows without a hash column cannot be repaired and fail with
+/// [`SpreadsheetError::Permanent`] before anything is written.
+pub fn repair_sheet(
+    adapter: &mut dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+    indices: &[usize],
+) -> Result<(), SpreadsheetError> {
+    let mut repaired = Vec::with_capacity(indices.len());
+    for &idx in indices {
+        let mut row = adapter.read_row(sheet_id, idx)?;
+        if row.len() < 2 || matches!(row.first().map(|s| s.as_str()), Some("status" | "id")) {
+            return Err(SpreadsheetError::Permanent(format!(
+                "row {idx} has no hash to repair"
+            )));
+        }
+        let last = row.len() - 1;
+        row[last] = hash_row(&row[..last], signature);
+        repaired.push((idx, row));
+    }
+    for (idx, row) in repaired {
+        info!(sheet_id, index = idx, "Repairing row hash");
+        adapter.update_row(sheet_id, idx, row)?;
+    }
+    Ok(())
+}
+
+/// Re-hashes every record row under `new_signature`, e.g. after the ledger
+/// name or password changed. All rows are first checked against both
+/// signatures; if any verifies under neither, nothing is written and the call
+/// fails with [`SpreadsheetError::Permanent`] naming those rows. Rows already
+/// hashed under `new_signature` are left alone, so rerunning the rotation
+/// after it failed partway finishes the remaining rows. The rest are written
+/// in one [`update_rows`](CloudSpreadsheetService::update_rows) call. Returns
+/// the number of rows rewritten.
+pub fn rotate_signature(
+    adapter: &mut dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    old_signature: &str,
+    new_signature: &str,
+) -> Result<usize, SpreadsheetError> {
+    let rows = adapter.list_rows(sheet_id)?;
+    let pending = mismatched_rows(&rows, 0, new_signature, HashAlgo::Sha256Salted);
+    let mismatched: Vec<usize> = pending
+        .iter()
+        .copied()
+        .filter(|&idx| !row_verifies(&rows[idx], old_signature))
+        .collect();
+    if !mismatched.is_empty() {
+        return Err(SpreadsheetError::Permanent(format!(
+            "rows {mismatched:?} do not verify under the old or new signature"
+        )));
+    }
+    let updates: Vec<(usize, Vec<String>)> = pending
+        .into_iter()
+        .map(|idx| {
+            let mut row = rows[idx].clone();
+            let last = row.len() - 1;
+            row[last] = hash_row(&row[..last], new_signature);
+            debug!(sheet_id, index = idx, "Re-hashing row");
+            (idx, row)
+        })
+        .collect();
+    let rotated = updates.len();
+    adapter.update_rows(sheet_id, updates)?;
+    info!(sheet_id, rotated, "Signature rotated");
+    Ok(rotated)
+}
+
+/// Returns the indices, offset by `first_index`, of rows whose stored hash
+/// does not match. Status rows, the header row and rows without a hash are
+/// skipped.
+fn mismatched_rows(
+    rows: &[Vec<String>],
+    first_index: usize,
+    signature: &str,
+    algo: HashAlgo,
+) -> Vec<usize> {
+    let mut mismatched = Vec::new();
+    for (offset, row) in rows.iter().enumerate() {
+        if !is_record_row(row) {
             continue;
         }
         if let Some(stored_hash) = row.last() {
-            let computed = hash_row(&row[..row.len() - 1], signature);
+            let computed = hash_row_with(&row[..row.len() - 1], signature, algo);
             if &computed != stored_hash {
+                let idx = first_index + offset;
                 debug!(index = idx, "Row hash mismatch");
                 mismatched.push(idx);
             }
         }
     }
-    info!(mismatched = mismatched.len(), "Verification complete");
-    Ok(mismatched)
+    mismatched
+}
+
+/// Whether the stored hash of the record `row` matches `signature`.
+fn row_verifies(row: &[String], signature: &str) -> bool {
+    let last = row.len() - 1;
+    hash_row_with(&row[..last], signature, HashAlgo::Sha256Salted) == row[last]
+}
+
+/// Returns `false` for status rows, the header row and rows too short to
+/// carry a hash.
+fn is_record_row(row: &[String]) -> bool {
+    // The header row written by the adapters starts with `id`.
+    row.len() >= 2 && !matches!(row.first().map(|s| s.as_str()), Some("status" | "id"))
 }
 
 #[cfg(test)]
@@ -82,4 +288,94 @@ mod tests {
         let res = verify_sheet(&adapter, &sheet, &sig).unwrap();
         assert_eq!(res, vec![1]);
     }
+
+    /// Fails every update once `updates_left` reaches zero.
+    struct FailingUpdates {
+        inner: GoogleSheetsAdapter,
+        updates_left: usize,
+    }
+
+    impl CloudSpreadsheetService for FailingUpdates {
+        fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+            self.inner.create_sheet(title)
+        }
+
+        fn append_row(
+            &mut self,
+            sheet_id: &str,
+            values: Vec<String>,
+        ) -> Result<(), SpreadsheetError> {
+            self.inner.append_row(sheet_id, values)
+        }
+
+        fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+            self.inner.read_row(sheet_id, index)
+        }
+
+        fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+            self.inner.list_rows(sheet_id)
+        }
+
+        fn update_row(
+            &mut self,
+            sheet_id: &str,
+            index: usize,
+            values: Vec<String>,
+        ) -> Result<(), SpreadsheetError> {
+            if self.updates_left == 0 {
+                return Err(SpreadsheetError::Transient("connection reset".into()));
+            }
+            self.updates_left -= 1;
+            self.inner.update_row(sheet_id, index, values)
+        }
+
+        fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+            self.inner.share_sheet(sheet_id, email)
+        }
+    }
+
+    #[test]
+    fn rotation_resumes_after_failing_partway() {
+        let mut adapter = FailingUpdates {
+            inner: GoogleSheetsAdapter::new(),
+            updates_left: 1,
+        };
+        let sheet = adapter.create_sheet("test").unwrap();
+        let old = generate_signature("old", None).unwrap();
+        let new = generate_signature("new", None).unwrap();
+        for desc in ["coffee", "tea", "cake"] {
+            let record = Record::new(
+                desc.into(),
+                "cash".parse::<Account>().unwrap(),
+                "revenue".parse::<Account>().unwrap(),
+                5.0,
+                "USD".into(),
+                None,
+                None,
+                vec![],
+            )
+            .unwrap();
+            adapter
+                .append_row(&sheet, record.to_row_hashed(&old))
+                .unwrap();
+        }
+
+        // Only the first row is rewritten before the backend fails.
+        assert!(matches!(
+            rotate_signature(&mut adapter, &sheet, &old, &new),
+            Err(SpreadsheetError::Transient(_))
+        ));
+        assert_eq!(verify_sheet(&adapter, &sheet, &new).unwrap(), vec![1, 2]);
+
+        adapter.updates_left = usize::MAX;
+        assert_eq!(
+            rotate_signature(&mut adapter, &sheet, &old, &new).unwrap(),
+            2
+        );
+        assert!(verify_sheet(&adapter, &sheet, &new).unwrap().is_empty());
+        assert_eq!(
+            rotate_signature(&mut adapter, &sheet, &old, &new).unwrap(),
+            0
+        );
+    }
 }
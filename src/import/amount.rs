@@ -0,0 +1,62 @@
+//! Parsing of statement amounts that may carry a currency symbol.
+
+use super::ImportError;
+
+/// Character used as the decimal mark in statement amounts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecimalSeparator {
+    /// `1,234.50`. Commas are treated as grouping separators.
+    #[default]
+    Dot,
+    /// `1.234,50` as used in much of Europe. Dots and spaces are treated as
+    /// grouping separators.
+    Comma,
+}
+
+/// Maps a currency symbol to its ISO 4217 code.
+pub fn currency_for_symbol(symbol: char) -> Option<&'static str> {
+    match symbol {
+        '$' => Some("USD"),
+        '€' => Some("EUR"),
+        '£' => Some("GBP"),
+        '¥' => Some("JPY"),
+        _ => None,
+    }
+}
+
+/// Parses amounts such as `3.50`, `-$3.50`, `€4,20` or `10 £`. Returns the
+/// numeric value and the ISO code of a leading or trailing currency symbol,
+/// if there was one.
+pub fn parse_amount(
+    text: &str,
+    decimal: DecimalSeparator,
+) -> Result<(f64, Option<&'static str>), ImportError> {
+    let mut s = text.trim();
+    let negative = match s.strip_prefix('-') {
+        Some(rest) => {
+            s = rest.trim_start();
+            true
+        }
+        None => false,
+    };
+    let mut currency = None;
+    if let Some(first) = s.chars().next()
+        && let Some(code) = currency_for_symbol(first)
+    {
+        currency = Some(code);
+        s = s[first.len_utf8()..].trim_start();
+    } else if let Some(last) = s.chars().next_back()
+        && let Some(code) = currency_for_symbol(last)
+    {
+        currency = Some(code);
+        s = s[..s.len() - last.len_utf8()].trim_end();
+    }
+    let number = match decimal {
+        DecimalSeparator::Dot => s.replace(',', ""),
+        DecimalSeparator::Comma => s.replace(['.', ' ', '\u{a0}'], "").replace(',', "."),
+    };
+    let value = number
+        .parse::<f64>()
+        .map_err(|e| ImportError::Parse(format!("invalid amount {text:?}: {e}")))?;
+    Ok((if negative { -value } else { value }, currency))
+}
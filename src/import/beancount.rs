@@ -0,0 +1,146 @@
+//! Importer for Beancount journals.
+//!
+//! Transactions look like:
+//!
+//! ```text
+//! 2024-01-05 * "Cafe" "Morning coffee"
+//!   Expenses:Food      4.50 USD
+//!   Assets:Cash
+//! ```
+//!
+//! The `*` flag marks a cleared transaction and `!` a pending one. Other
+//! dated directives (`open`, `close`, `price`, `balance`, ...) are ignored.
+
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, TimeZone};
+
+use super::ledger::{JournalPosting, build_record, parse_amount};
+use super::{ImportError, StatementImporter};
+use crate::core::Record;
+
+pub struct BeancountImporter;
+
+/// Extracts the double-quoted strings from a transaction header.
+fn quoted_strings(text: &str) -> Vec<String> {
+    text.split('"')
+        .skip(1)
+        .step_by(2)
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(before, _)| before)
+}
+
+/// Parses a posting such as `Expenses:Food  4.50 USD`. Cost and price
+/// annotations after the amount are ignored.
+fn parse_posting(line: &str) -> Result<JournalPosting, ImportError> {
+    let mut tokens = line.split_whitespace();
+    let account = tokens.next().unwrap_or_default();
+    let account = account
+        .parse()
+        .map_err(|e| ImportError::Parse(format!("invalid account {account:?}: {e}")))?;
+    let amount = match (tokens.next(), tokens.next()) {
+        (Some(number), Some(currency)) => Some(parse_amount(&format!("{number} {currency}"))?),
+        (Some(number), None) => Some(parse_amount(number)?),
+        _ => None,
+    };
+    Ok(JournalPosting { account, amount })
+}
+
+impl BeancountImporter {
+    fn parse_internal(path: &Path) -> Result<Vec<Record>, ImportError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::parse_str(&content)
+    }
+
+    pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
+        let mut records = Vec::new();
+        let mut lines = input.lines().peekable();
+        while let Some(line) = lines.next() {
+            let trimmed = strip_comment(line).trim();
+            if trimmed.is_empty() || line.starts_with([' ', '\t']) {
+                continue;
+            }
+            let mut parts = trimmed.splitn(3, char::is_whitespace);
+            let (Some(date), Some(kind)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+                // Undated directives such as `option` or `plugin`.
+                continue;
+            };
+            let cleared = match kind {
+                "*" | "txn" => true,
+                "!" => false,
+                // `open`, `close`, `price`, `balance` and other directives.
+                _ => continue,
+            };
+            let strings = quoted_strings(parts.next().unwrap_or_default());
+            let (payee, narration) = match strings.as_slice() {
+                [payee, narration, ..] => (Some(payee.clone()), narration.clone()),
+                [narration] => (None, narration.clone()),
+                [] => (None, String::new()),
+            };
+
+            let mut postings = Vec::new();
+            while let Some(next) = lines.peek() {
+                if next.trim().is_empty() || !next.starts_with([' ', '\t']) {
+                    break;
+                }
+                let content = strip_comment(lines.next().unwrap_or_default()).trim();
+                // Skip blank lines and `key: value` metadata.
+                if content.is_empty()
+                    || content
+                        .split_whitespace()
+                        .next()
+                        .is_some_and(|t| t.ends_with(':') && t.starts_with(char::is_lowercase))
+                {
+                    continue;
+                }
+                postings.push(parse_posting(content)?);
+            }
+
+            let description = if narration.is_empty() {
+                payee.clone().unwrap_or_default()
+            } else {
+                narration
+            };
+            let mut rec = build_record(description, postings)?;
+            rec.cleared = cleared;
+            if let Some(payee) = payee {
+                rec.metadata.insert("payee".into(), payee);
+            }
+            rec.transaction_date = date
+                .and_hms_opt(0, 0, 0)
+                .and_then(|dt| Local.from_local_datetime(&dt).single());
+            records.push(rec);
+        }
+        Ok(records)
+    }
+}
+
+impl StatementImporter for BeancountImporter {
+    fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
+        Self::parse_internal(path)
+    }
+}
+
+pub fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
+    BeancountImporter::parse(path)
+}
+
+/// Parses a Beancount file and sets all record currencies to the provided value.
+pub fn parse_with_currency(path: &Path, currency: &str) -> Result<Vec<Record>, ImportError> {
+    let mut records = BeancountImporter::parse(path)?;
+    for rec in &mut records {
+        rec.currency = currency.to_string();
+    }
+    Ok(records)
+}
+
+pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
+    BeancountImporter::parse_str(input)
+}
@@ -2,8 +2,12 @@ use std::path::Path;
 
 use csv::{Reader, StringRecord};
 
-use super::{ImportError, StatementImporter};
-use crate::core::Record;
+use super::amount::{self, DecimalSeparator};
+use super::{ImportError, SignConvention, StatementImporter};
+use crate::core::sharing::record_from_row;
+use crate::core::{Account, ROW_HEADER, Record};
+
+pub mod presets;
 
 /// Mapping of CSV column names to [`Record`] fields.
 #[derive(Debug, Clone)]
@@ -13,6 +17,27 @@ pub struct CsvMapping {
     pub credit_account: String,
     pub amount: String,
     pub currency: String,
+    /// Money column for rows posted as mapped. When this or
+    /// `credit_amount` is set, the `amount` column is not read.
+    pub debit_amount: Option<String>,
+    /// Money column for rows flowing the other way; a value here swaps the
+    /// debit and credit accounts of the row.
+    pub credit_amount: Option<String>,
+    /// Posts every row against one account instead of reading the debit and
+    /// credit account columns, for exports that have none.
+    pub statement_account: Option<StatementAccount>,
+}
+
+/// The account a whole statement belongs to, such as a wallet or a card.
+///
+/// Like the OFX importer, each row moves money between this account and
+/// `income` when it flows in, or `expenses` when it flows out. Which amounts
+/// flow in is decided by `sign`; rows in the credit amount column of a
+/// split mapping count as negative.
+#[derive(Debug, Clone)]
+pub struct StatementAccount {
+    pub account: String,
+    pub sign: SignConvention,
 }
 
 impl Default for CsvMapping {
@@ -23,76 +48,484 @@ impl Default for CsvMapping {
             credit_account: "credit_account".into(),
             amount: "amount".into(),
             currency: "currency".into(),
+            debit_amount: None,
+            credit_amount: None,
+            statement_account: None,
         }
     }
 }
 
-pub struct CsvImporter;
+/// Header names recognised by [`suggest_mapping`] for each field, compared
+/// after lowercasing and dropping everything but letters and digits.
+const DESCRIPTION_NAMES: &[&str] = &[
+    "description",
+    "desc",
+    "payee",
+    "memo",
+    "narrative",
+    "details",
+    "merchant",
+    "name",
+];
+const DEBIT_ACCOUNT_NAMES: &[&str] = &["debitaccount", "debitacct", "toaccount", "category"];
+const CREDIT_ACCOUNT_NAMES: &[&str] = &["creditaccount", "creditacct", "fromaccount", "account"];
+const AMOUNT_NAMES: &[&str] = &["amount", "amt", "value", "transactionamount"];
+const CURRENCY_NAMES: &[&str] = &["currency", "ccy", "currencycode"];
+const DEBIT_AMOUNT_NAMES: &[&str] = &[
+    "debit",
+    "debitamount",
+    "withdrawal",
+    "withdrawals",
+    "paidout",
+    "moneyout",
+];
+const CREDIT_AMOUNT_NAMES: &[&str] = &[
+    "credit",
+    "creditamount",
+    "deposit",
+    "deposits",
+    "paidin",
+    "moneyin",
+];
 
-impl CsvImporter {
-    fn parse_internal(
-        path: &Path,
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Guesses a [`CsvMapping`] from a header row such as `Date,Payee,Amount`.
+///
+/// Headers are matched case-insensitively, ignoring spaces and punctuation,
+/// against common names for each field: `Payee` or `Memo` for the
+/// description, `Withdrawal` and `Deposit` for split amount columns and so
+/// on. A header that only contains a known name, like `Transaction Details`,
+/// is used when no header matches exactly. Split amount columns are only
+/// suggested when there is no single amount column, and fields without a
+/// match keep their [`CsvMapping::default`] names.
+pub fn suggest_mapping<S: AsRef<str>>(headers: &[S]) -> CsvMapping {
+    let normalized: Vec<String> = headers
+        .iter()
+        .map(|h| normalize_header(h.as_ref()))
+        .collect();
+    let mut used = vec![false; headers.len()];
+    // Resolve every exact match before any partial one, so `Debit Account`
+    // is not taken by the `debit` amount column.
+    let fields = [
+        DESCRIPTION_NAMES,
+        DEBIT_ACCOUNT_NAMES,
+        CREDIT_ACCOUNT_NAMES,
+        AMOUNT_NAMES,
+        CURRENCY_NAMES,
+        DEBIT_AMOUNT_NAMES,
+        CREDIT_AMOUNT_NAMES,
+    ];
+    let mut found: [Option<usize>; 7] = [None; 7];
+    for partial in [false, true] {
+        for (slot, names) in found.iter_mut().zip(fields) {
+            if slot.is_some() {
+                continue;
+            }
+            *slot = names.iter().find_map(|name| {
+                (0..normalized.len()).find(|&i| {
+                    let h = &normalized[i];
+                    !used[i] && if partial { h.contains(name) } else { h == name }
+                })
+            });
+            if let Some(i) = *slot {
+                used[i] = true;
+            }
+        }
+    }
+    let name = |slot: Option<usize>| slot.map(|i| headers[i].as_ref().to_string());
+    let [
+        description,
+        debit_account,
+        credit_account,
+        amount,
+        currency,
+        debit,
+        credit,
+    ] = found;
+    let defaults = CsvMapping::default();
+    let split = amount.is_none() && (debit.is_some() || credit.is_some());
+    CsvMapping {
+        description: name(description).unwrap_or(defaults.description),
+        debit_account: name(debit_account).unwrap_or(defaults.debit_account),
+        credit_account: name(credit_account).unwrap_or(defaults.credit_account),
+        amount: name(amount).unwrap_or(defaults.amount),
+        currency: name(currency).unwrap_or(defaults.currency),
+        debit_amount: if split { name(debit) } else { None },
+        credit_amount: if split { name(credit) } else { None },
+        statement_account: None,
+    }
+}
+
+/// Reads the header row of a CSV file.
+pub fn headers(path: &Path) -> Result<Vec<String>, ImportError> {
+    let mut rdr = Reader::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+    let headers = rdr
+        .headers()
+        .map_err(|e| ImportError::Parse(e.to_string()))?;
+    Ok(headers.iter().map(str::to_string).collect())
+}
+
+/// Where a row's amount is read from.
+enum AmountColumns {
+    Single(usize),
+    Split {
+        debit: Option<usize>,
+        credit: Option<usize>,
+    },
+}
+
+impl AmountColumns {
+    /// Returns the amount, its currency symbol if any, and whether the row
+    /// flows in the credit direction.
+    fn read<'a>(
+        &self,
+        row: &'a StringRecord,
+        decimal: DecimalSeparator,
+    ) -> Result<(f64, Option<&'a str>, bool), ImportError> {
+        match *self {
+            AmountColumns::Single(idx) => {
+                let raw = row
+                    .get(idx)
+                    .ok_or_else(|| ImportError::Parse("missing amount".into()))?;
+                let (value, symbol) = amount::parse_amount(raw, decimal)?;
+                Ok((value, symbol, false))
+            }
+            AmountColumns::Split { debit, credit } => {
+                // Banks leave the unused column blank or write a zero.
+                let cell = |idx: Option<usize>| -> Result<_, ImportError> {
+                    match idx.and_then(|i| row.get(i)).map(str::trim) {
+                        Some(raw) if !raw.is_empty() => {
+                            let (value, symbol) = amount::parse_amount(raw, decimal)?;
+                            Ok((value != 0.0).then_some((value.abs(), symbol)))
+                        }
+                        _ => Ok(None),
+                    }
+                };
+                match (cell(debit)?, cell(credit)?) {
+                    (Some((value, symbol)), None) => Ok((value, symbol, false)),
+                    (None, Some((value, symbol))) => Ok((value, symbol, true)),
+                    (Some(_), Some(_)) => Err(ImportError::Parse(
+                        "both debit and credit amounts are set".into(),
+                    )),
+                    (None, None) => Err(ImportError::Parse("missing amount".into())),
+                }
+            }
+        }
+    }
+}
+
+/// Where a row's accounts come from.
+enum AccountColumns {
+    Columns {
+        debit: usize,
+        credit: usize,
+    },
+    Statement {
+        account: Account,
+        sign: SignConvention,
+    },
+}
+
+/// Column positions resolved from the header row for a [`CsvMapping`].
+struct Columns {
+    description: usize,
+    accounts: AccountColumns,
+    amount: AmountColumns,
+    currency: Option<usize>,
+    currency_name: String,
+}
+
+impl Columns {
+    fn new<R: std::io::Read>(
+        rdr: &mut Reader<R>,
         mapping: &CsvMapping,
-        currency: Option<&str>,
-    ) -> Result<Vec<Record>, ImportError> {
-        let mut rdr = Reader::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+    ) -> Result<Self, ImportError> {
         let headers = rdr
             .headers()
-            .map_err(|e| ImportError::Parse(e.to_string()))?
-            .clone();
+            .map_err(|e| ImportError::Parse(e.to_string()))?;
         let idx = |name: &str| {
             headers
                 .iter()
                 .position(|h| h == name)
                 .ok_or_else(|| ImportError::Parse(format!("missing column {name}")))
         };
-        let desc_idx = idx(&mapping.description)?;
-        let debit_idx = idx(&mapping.debit_account)?;
-        let credit_idx = idx(&mapping.credit_account)?;
-        let amount_idx = idx(&mapping.amount)?;
-        let currency_idx = headers.iter().position(|h| h == mapping.currency.as_str());
-        if currency_idx.is_none() && currency.is_none() {
-            return Err(ImportError::Parse(format!(
-                "missing column {}",
-                mapping.currency
-            )));
+        let amount = if mapping.debit_amount.is_some() || mapping.credit_amount.is_some() {
+            AmountColumns::Split {
+                debit: mapping.debit_amount.as_deref().map(idx).transpose()?,
+                credit: mapping.credit_amount.as_deref().map(idx).transpose()?,
+            }
+        } else {
+            AmountColumns::Single(idx(&mapping.amount)?)
+        };
+        let accounts = match &mapping.statement_account {
+            Some(statement) => AccountColumns::Statement {
+                account: statement.account.parse().map_err(|e| {
+                    ImportError::Parse(format!("invalid account {:?}: {e}", statement.account))
+                })?,
+                sign: statement.sign,
+            },
+            None => AccountColumns::Columns {
+                debit: idx(&mapping.debit_account)?,
+                credit: idx(&mapping.credit_account)?,
+            },
+        };
+        Ok(Self {
+            description: idx(&mapping.description)?,
+            accounts,
+            amount,
+            currency: headers.iter().position(|h| h == mapping.currency.as_str()),
+            currency_name: mapping.currency.clone(),
+        })
+    }
+
+    /// Builds the record for one row. The currency comes from the currency
+    /// column, then `currency`, then a symbol on the amount.
+    fn record(
+        &self,
+        row: &StringRecord,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+    ) -> Result<Record, ImportError> {
+        let (amount_val, symbol_currency, reversed) = self.amount.read(row, decimal)?;
+        let (debit_acc, credit_acc, amount_val) = match &self.accounts {
+            AccountColumns::Columns { debit, credit } => {
+                let account = |idx: usize| {
+                    row.get(idx)
+                        .unwrap_or_default()
+                        .parse::<Account>()
+                        .map_err(|_| ImportError::Parse("invalid account".into()))
+                };
+                let (debit_acc, credit_acc) = (account(*debit)?, account(*credit)?);
+                if reversed {
+                    (credit_acc, debit_acc, amount_val)
+                } else {
+                    (debit_acc, credit_acc, amount_val)
+                }
+            }
+            AccountColumns::Statement { account, sign } => {
+                let positive = (amount_val >= 0.0) != reversed;
+                let inflow = positive == (*sign == SignConvention::DebitPositive);
+                let other = if inflow { "income" } else { "expenses" };
+                let other: Account = other.parse().expect("valid account");
+                if inflow {
+                    (account.clone(), other, amount_val.abs())
+                } else {
+                    (other, account.clone(), amount_val.abs())
+                }
+            }
+        };
+        let currency_val = self
+            .currency
+            .and_then(|idx| row.get(idx))
+            .filter(|c| !c.is_empty())
+            .or(currency)
+            .or(symbol_currency)
+            .ok_or_else(|| ImportError::Parse(format!("missing column {}", self.currency_name)))?
+            .to_string();
+        Record::new(
+            row.get(self.description).unwrap_or_default().to_string(),
+            debit_acc,
+            credit_acc,
+            amount_val,
+            currency_val,
+            None,
+            None,
+            vec![],
+        )
+        .map_err(ImportError::from)
+    }
+}
+
+/// Converts every row with `convert`, keeping going past rows that fail.
+/// Failures are paired with the line the row starts on.
+fn collect_rows<R, F>(
+    rdr: &mut Reader<R>,
+    mut convert: F,
+) -> (Vec<Record>, Vec<(usize, ImportError)>)
+where
+    R: std::io::Read,
+    F: FnMut(&StringRecord) -> Result<Record, ImportError>,
+{
+    let mut records = Vec::new();
+    let mut errors = Vec::new();
+    for result in rdr.records() {
+        let outcome = match result {
+            Ok(row) => {
+                let line = row.position().map_or(0, |p| p.line() as usize);
+                convert(&row).map_err(|e| (line, e))
+            }
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line() as usize);
+                Err((line, ImportError::Parse(e.to_string())))
+            }
+        };
+        match outcome {
+            Ok(rec) => records.push(rec),
+            Err(err) => errors.push(err),
         }
+    }
+    (records, errors)
+}
 
+/// A row read by [`CsvImporter::preview`]: the line it starts on and the
+/// record or error it produced.
+pub type PreviewRow = (usize, Result<Record, ImportError>);
+
+pub struct CsvImporter;
+
+impl CsvImporter {
+    fn parse_internal(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+    ) -> Result<Vec<Record>, ImportError> {
         let mut records = Vec::new();
+        Self::stream_internal(path, mapping, currency, decimal, |rec| {
+            records.push(rec);
+            Ok::<(), ImportError>(())
+        })?;
+        Ok(records)
+    }
+
+    /// Parses rows and hands each record to `on_record`.
+    fn stream_internal<F, E>(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+        mut on_record: F,
+    ) -> Result<usize, E>
+    where
+        F: FnMut(Record) -> Result<(), E>,
+        E: From<ImportError>,
+    {
+        let mut rdr = Reader::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+        let columns = Columns::new(&mut rdr, mapping)?;
+        let mut count = 0;
         for result in rdr.records() {
             let row: StringRecord = result.map_err(|e| ImportError::Parse(e.to_string()))?;
-            let amount_val: f64 = row
-                .get(amount_idx)
-                .ok_or_else(|| ImportError::Parse("missing amount".into()))?
-                .parse::<f64>()
-                .map_err(|e: std::num::ParseFloatError| ImportError::Parse(e.to_string()))?;
-            let debit_acc = row
-                .get(debit_idx)
-                .unwrap_or_default()
-                .parse()
-                .map_err(|_| ImportError::Parse("invalid account".into()))?;
-            let credit_acc = row
-                .get(credit_idx)
-                .unwrap_or_default()
-                .parse()
-                .map_err(|_| ImportError::Parse("invalid account".into()))?;
-            let currency_val = match currency_idx {
-                Some(idx) => row.get(idx).unwrap_or_default().to_string(),
-                None => currency.unwrap().to_string(),
-            };
-            let rec = Record::new(
-                row.get(desc_idx).unwrap_or_default().to_string(),
-                debit_acc,
-                credit_acc,
-                amount_val,
-                currency_val,
-                None,
-                None,
-                vec![],
-            )?;
-            records.push(rec);
+            on_record(columns.record(&row, currency, decimal)?)?;
+            count += 1;
         }
-        Ok(records)
+        Ok(count)
+    }
+
+    /// Parses every row, collecting the records that parse and the line
+    /// number and error of each row that does not. Errors that prevent
+    /// reading the file at all are reported for line 0.
+    pub fn parse_collect_errors_with(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+    ) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+        let mut rdr = match Reader::from_path(path) {
+            Ok(rdr) => rdr,
+            Err(e) => return (Vec::new(), vec![(0, ImportError::Parse(e.to_string()))]),
+        };
+        let columns = match Columns::new(&mut rdr, mapping) {
+            Ok(columns) => columns,
+            Err(e) => return (Vec::new(), vec![(0, e)]),
+        };
+        collect_rows(&mut rdr, |row| columns.record(row, currency, decimal))
+    }
+
+    /// Parses at most `limit` rows without stopping at rows that fail. Fails
+    /// only when the file or the columns named by `mapping` cannot be read.
+    pub fn preview(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+        limit: usize,
+    ) -> Result<Vec<PreviewRow>, ImportError> {
+        let mut rdr = Reader::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+        let columns = Columns::new(&mut rdr, mapping)?;
+        Ok(rdr
+            .records()
+            .take(limit)
+            .map(|result| match result {
+                Ok(row) => {
+                    let line = row.position().map_or(0, |p| p.line() as usize);
+                    (line, columns.record(&row, currency, decimal))
+                }
+                Err(e) => {
+                    let line = e.position().map_or(0, |p| p.line() as usize);
+                    (line, Err(ImportError::Parse(e.to_string())))
+                }
+            })
+            .collect())
+    }
+
+    /// Parses a CSV file row by row, handing each record to `on_record`
+    /// instead of collecting them. Returns the number of records processed.
+    /// An error from the callback stops parsing and is returned as is.
+    pub fn parse_streaming<F, E>(
+        path: &Path,
+        mapping: &CsvMapping,
+        on_record: F,
+    ) -> Result<usize, E>
+    where
+        F: FnMut(Record) -> Result<(), E>,
+        E: From<ImportError>,
+    {
+        Self::stream_internal(path, mapping, None, DecimalSeparator::default(), on_record)
+    }
+
+    /// Like [`CsvImporter::parse_streaming`] but overrides the currency of
+    /// every record.
+    pub fn parse_streaming_with_currency<F, E>(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: &str,
+        on_record: F,
+    ) -> Result<usize, E>
+    where
+        F: FnMut(Record) -> Result<(), E>,
+        E: From<ImportError>,
+    {
+        Self::stream_internal(
+            path,
+            mapping,
+            Some(currency),
+            DecimalSeparator::default(),
+            on_record,
+        )
+    }
+
+    /// Streaming variant of [`CsvImporter::parse_with_decimal_separator`].
+    pub fn parse_streaming_with_decimal_separator<F, E>(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+        on_record: F,
+    ) -> Result<usize, E>
+    where
+        F: FnMut(Record) -> Result<(), E>,
+        E: From<ImportError>,
+    {
+        Self::stream_internal(path, mapping, currency, decimal, on_record)
+    }
+
+    /// Parses a CSV file whose amounts use the given decimal mark. Amounts
+    /// may carry a currency symbol such as `$3.50`, which sets the record
+    /// currency when neither a currency column nor `currency` is given.
+    pub fn parse_with_decimal_separator(
+        path: &Path,
+        mapping: &CsvMapping,
+        currency: Option<&str>,
+        decimal: DecimalSeparator,
+    ) -> Result<Vec<Record>, ImportError> {
+        Self::parse_internal(path, mapping, currency, decimal)
     }
 
     /// Parses a CSV file using the provided column mapping.
@@ -100,7 +533,7 @@ impl CsvImporter {
         path: &Path,
         mapping: &CsvMapping,
     ) -> Result<Vec<Record>, ImportError> {
-        Self::parse_internal(path, mapping, None)
+        Self::parse_internal(path, mapping, None, DecimalSeparator::default())
     }
 
     /// Parses a CSV file using the provided mapping and overriding currency.
@@ -109,13 +542,23 @@ impl CsvImporter {
         mapping: &CsvMapping,
         currency: &str,
     ) -> Result<Vec<Record>, ImportError> {
-        Self::parse_internal(path, mapping, Some(currency))
+        Self::parse_internal(path, mapping, Some(currency), DecimalSeparator::default())
     }
 }
 
 impl StatementImporter for CsvImporter {
+    /// Parses a file with the default mapping, or with [`parse_full`] when it
+    /// was written by [`export`], so exported records come back unchanged.
     fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
-        Self::parse_internal(path, &CsvMapping::default(), None)
+        if is_full_export(path)? {
+            return parse_full(path);
+        }
+        Self::parse_internal(
+            path,
+            &CsvMapping::default(),
+            None,
+            DecimalSeparator::default(),
+        )
     }
 }
 
@@ -130,7 +573,12 @@ pub fn parse_with_mapping(path: &Path, mapping: &CsvMapping) -> Result<Vec<Recor
 
 /// Parses a CSV file and sets all record currencies to the provided value.
 pub fn parse_with_currency(path: &Path, currency: &str) -> Result<Vec<Record>, ImportError> {
-    CsvImporter::parse_internal(path, &CsvMapping::default(), Some(currency))
+    CsvImporter::parse_internal(
+        path,
+        &CsvMapping::default(),
+        Some(currency),
+        DecimalSeparator::default(),
+    )
 }
 
 /// Parses a CSV file using the provided mapping and overriding currency.
@@ -142,6 +590,37 @@ pub fn parse_with_mapping_and_currency(
     CsvImporter::parse_with_mapping_and_currency(path, mapping, currency)
 }
 
+/// Convenience wrapper around [`CsvImporter::parse_with_decimal_separator`].
+pub fn parse_with_decimal_separator(
+    path: &Path,
+    mapping: &CsvMapping,
+    currency: Option<&str>,
+    decimal: DecimalSeparator,
+) -> Result<Vec<Record>, ImportError> {
+    CsvImporter::parse_with_decimal_separator(path, mapping, currency, decimal)
+}
+
+/// Parses a CSV file with the default mapping, continuing past rows that
+/// fail. See [`CsvImporter::parse_collect_errors_with`].
+pub fn parse_collect_errors(path: &Path) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+    CsvImporter::parse_collect_errors_with(
+        path,
+        &CsvMapping::default(),
+        None,
+        DecimalSeparator::default(),
+    )
+}
+
+/// Convenience wrapper around [`CsvImporter::parse_collect_errors_with`].
+pub fn parse_collect_errors_with(
+    path: &Path,
+    mapping: &CsvMapping,
+    currency: Option<&str>,
+    decimal: DecimalSeparator,
+) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+    CsvImporter::parse_collect_errors_with(path, mapping, currency, decimal)
+}
+
 /// Writes the provided records to a CSV file using the given column mapping.
 pub fn export_with_mapping(
     path: &Path,
@@ -171,7 +650,158 @@ pub fn export_with_mapping(
     Ok(())
 }
 
-/// Convenience wrapper around [`export_with_mapping`].
+/// Header of a file `mapping` reads, with a value for each column that
+/// imports as a valid row.
+fn template_columns(mapping: &CsvMapping) -> Vec<(&str, &'static str)> {
+    let mut columns = vec![(mapping.description.as_str(), "Coffee")];
+    if mapping.statement_account.is_none() {
+        columns.push((mapping.debit_account.as_str(), "expenses:food"));
+        columns.push((mapping.credit_account.as_str(), "assets:checking"));
+    }
+    if mapping.debit_amount.is_some() || mapping.credit_amount.is_some() {
+        if let Some(debit) = &mapping.debit_amount {
+            columns.push((debit.as_str(), "3.50"));
+        }
+        if let Some(credit) = &mapping.credit_amount {
+            // The example row is a debit, so the credit cell stays blank.
+            let value = if mapping.debit_amount.is_some() {
+                ""
+            } else {
+                "3.50"
+            };
+            columns.push((credit.as_str(), value));
+        }
+    } else {
+        columns.push((mapping.amount.as_str(), "3.50"));
+    }
+    columns.push((mapping.currency.as_str(), "USD"));
+    columns
+}
+
+fn write_template_rows(
+    path: &Path,
+    mapping: &CsvMapping,
+    example: bool,
+) -> Result<(), ImportError> {
+    let columns = template_columns(mapping);
+    let mut wtr = csv::Writer::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+    wtr.write_record(columns.iter().map(|(name, _)| *name))
+        .map_err(|e| ImportError::Parse(e.to_string()))?;
+    if example {
+        wtr.write_record(columns.iter().map(|(_, value)| *value))
+            .map_err(|e| ImportError::Parse(e.to_string()))?;
+    }
+    wtr.flush().map_err(|e| ImportError::Parse(e.to_string()))?;
+    Ok(())
+}
+
+/// Writes the header row `mapping` expects, so the file can be filled in and
+/// imported with the same mapping.
+pub fn write_template(path: &Path, mapping: &CsvMapping) -> Result<(), ImportError> {
+    write_template_rows(path, mapping, false)
+}
+
+/// Like [`write_template`], followed by one example row.
+pub fn write_template_with_example(path: &Path, mapping: &CsvMapping) -> Result<(), ImportError> {
+    write_template_rows(path, mapping, true)
+}
+
+/// Writes every record column, so the file can be read back with
+/// [`parse_full`] without losing ids, timestamps, tags, references or splits.
+/// The header follows [`ROW_HEADER`] without the hash column.
+pub fn export_full(path: &Path, records: &[Record]) -> Result<(), ImportError> {
+    let mut wtr = csv::Writer::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+    wtr.write_record(&ROW_HEADER[..ROW_HEADER.len() - 1])
+        .map_err(|e| ImportError::Parse(e.to_string()))?;
+    for rec in records {
+        wtr.write_record(rec.to_row())
+            .map_err(|e| ImportError::Parse(e.to_string()))?;
+    }
+    wtr.flush().map_err(|e| ImportError::Parse(e.to_string()))?;
+    Ok(())
+}
+
+/// Reads a file written by [`export_full`].
+pub fn parse_full(path: &Path) -> Result<Vec<Record>, ImportError> {
+    let mut rdr = Reader::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+    rdr.records()
+        .map(|row| {
+            let row: Vec<String> = row
+                .map_err(|e| ImportError::Parse(e.to_string()))?
+                .iter()
+                .map(str::to_string)
+                .collect();
+            record_from_row(&row).map_err(|e| ImportError::Parse(e.to_string()))
+        })
+        .collect()
+}
+
+/// Like [`parse_full`] but continues past rows that fail, pairing each
+/// failure with its line number.
+pub fn parse_full_collect_errors(path: &Path) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+    let mut rdr = match Reader::from_path(path) {
+        Ok(rdr) => rdr,
+        Err(e) => return (Vec::new(), vec![(0, ImportError::Parse(e.to_string()))]),
+    };
+    collect_rows(&mut rdr, |row| {
+        let row: Vec<String> = row.iter().map(str::to_string).collect();
+        record_from_row(&row).map_err(|e| ImportError::Parse(e.to_string()))
+    })
+}
+
+/// Returns `true` if the CSV header is the one written by [`export_full`].
+pub fn is_full_export(path: &Path) -> Result<bool, ImportError> {
+    let mut rdr = Reader::from_path(path).map_err(|e| ImportError::Parse(e.to_string()))?;
+    let headers = rdr
+        .headers()
+        .map_err(|e| ImportError::Parse(e.to_string()))?;
+    Ok(headers
+        .iter()
+        .take(2)
+        .eq(ROW_HEADER.iter().take(2).copied()))
+}
+
+/// Writes all columns of the records; see [`export_full`].
 pub fn export(path: &Path, records: &[Record]) -> Result<(), ImportError> {
+    export_full(path, records)
+}
+
+/// Writes only the description, accounts, amount and currency using the
+/// default column names.
+pub fn export_minimal(path: &Path, records: &[Record]) -> Result<(), ImportError> {
     export_with_mapping(path, records, &CsvMapping::default())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggest_mapping_matches_synonyms_in_any_case() {
+        let mapping = suggest_mapping(&[
+            "DATE",
+            "Payee",
+            "Debit Account",
+            "credit_ACCOUNT",
+            "Withdrawals",
+            "Paid In",
+            "CCY",
+        ]);
+        assert_eq!(mapping.description, "Payee");
+        assert_eq!(mapping.debit_account, "Debit Account");
+        assert_eq!(mapping.credit_account, "credit_ACCOUNT");
+        assert_eq!(mapping.currency, "CCY");
+        assert_eq!(mapping.debit_amount.as_deref(), Some("Withdrawals"));
+        assert_eq!(mapping.credit_amount.as_deref(), Some("Paid In"));
+        assert_eq!(mapping.amount, "amount");
+
+        // A single amount column wins over split columns, and a header that
+        // only contains a known name is used when nothing matches exactly.
+        let mapping = suggest_mapping(&["Transaction Details", "AMT", "Debit", "Credit"]);
+        assert_eq!(mapping.description, "Transaction Details");
+        assert_eq!(mapping.amount, "AMT");
+        assert_eq!(mapping.debit_amount, None);
+        assert_eq!(mapping.credit_amount, None);
+        assert_eq!(mapping.debit_account, "debit_account");
+    }
+}
@@ -0,0 +1,85 @@
+//! Column layouts of wallet and card exports, so they import without a full
+//! set of `--map-*` flags.
+//!
+//! The exports have no account columns. Every row is posted against the
+//! preset's statement account and `income` or `expenses`, which import rules
+//! can then refine. All three use a dot as the decimal mark.
+
+use super::{CsvMapping, StatementAccount};
+use crate::import::SignConvention;
+
+/// A known CSV export layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// PayPal activity download. `Gross` is negative for payments and
+    /// positive for money received, in the row's `Currency`.
+    Paypal,
+    /// Apple Card monthly export. `Amount (USD)` is positive for purchases
+    /// and negative for payments and refunds, which reduce the balance owed.
+    AppleCard,
+    /// Google Pay transaction export. `Amount` is negative for payments and
+    /// positive for refunds, in the row's `Currency`.
+    GooglePay,
+}
+
+impl Preset {
+    /// Column mapping of the export.
+    pub fn mapping(self) -> CsvMapping {
+        let (description, amount, currency, account, sign) = match self {
+            Preset::Paypal => (
+                "Name",
+                "Gross",
+                "Currency",
+                "assets:paypal",
+                SignConvention::DebitPositive,
+            ),
+            Preset::AppleCard => (
+                "Merchant",
+                "Amount (USD)",
+                "currency",
+                "liabilities:applecard",
+                SignConvention::CreditPositive,
+            ),
+            Preset::GooglePay => (
+                "Description",
+                "Amount",
+                "Currency",
+                "assets:googlepay",
+                SignConvention::DebitPositive,
+            ),
+        };
+        CsvMapping {
+            description: description.into(),
+            amount: amount.into(),
+            currency: currency.into(),
+            statement_account: Some(StatementAccount {
+                account: account.into(),
+                sign,
+            }),
+            ..CsvMapping::default()
+        }
+    }
+
+    /// Currency of exports without a currency column.
+    pub fn currency(self) -> Option<&'static str> {
+        match self {
+            Preset::AppleCard => Some("USD"),
+            Preset::Paypal | Preset::GooglePay => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['_', '-'], "").as_str() {
+            "paypal" => Ok(Preset::Paypal),
+            "applecard" => Ok(Preset::AppleCard),
+            "gpay" | "googlepay" => Ok(Preset::GooglePay),
+            other => Err(format!(
+                "unknown preset {other:?}, expected paypal, applecard or gpay"
+            )),
+        }
+    }
+}
@@ -1,35 +1,199 @@
 use std::collections::HashSet;
 
 use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
-use crate::core::Record;
+use crate::core::utils::hash_row;
+use crate::core::{CLEARED_COLUMN, Record};
+
+/// Column holding the external reference in a stored row.
+const EXTERNAL_REFERENCE_COLUMN: usize = 8;
+/// Column holding the statement date in a stored row.
+const TRANSACTION_DATE_COLUMN: usize = 12;
+/// Leading row columns assigned to each record as it is parsed: the id and
+/// the timestamp.
+const ASSIGNED_COLUMNS: usize = 2;
+
+/// Hash of the record's row without the id and timestamp. Importers assign
+/// those afresh to every parsed line, so two copies of the same statement
+/// line only agree on the rest of the row. The cleared column is left out
+/// too, as reconciling a stored row sets it.
+fn content_hash(record: &Record, signature: &str) -> String {
+    hash_row(
+        &record.to_row()[ASSIGNED_COLUMNS..CLEARED_COLUMN],
+        signature,
+    )
+}
+
+/// [`content_hash`] of a record with a statement date or an external
+/// reference. Without either, identical lines such as a monthly
+/// subscription or two coffees on the same day are separate transactions,
+/// so they are only matched by their exact row hash.
+fn statement_hash(record: &Record, signature: &str) -> Option<String> {
+    let has_reference = record
+        .external_reference
+        .as_ref()
+        .is_some_and(|r| !r.is_empty());
+    (record.transaction_date.is_some() || has_reference).then(|| content_hash(record, signature))
+}
+
+/// [`statement_hash`] of a stored row, if it has the current layout.
+fn row_statement_hash(row: &[String], signature: &str) -> Option<String> {
+    if row.len() <= CLEARED_COLUMN
+        || (row[TRANSACTION_DATE_COLUMN].is_empty() && row[EXTERNAL_REFERENCE_COLUMN].is_empty())
+    {
+        return None;
+    }
+    Some(hash_row(&row[ASSIGNED_COLUMNS..CLEARED_COLUMN], signature))
+}
+
+/// Removes records that repeat an earlier record of the same batch, such as
+/// a transaction listed twice by overlapping statement exports. Records with
+/// a statement date or external reference are compared on every row column
+/// except the id and timestamp and the first copy is kept. Other records are
+/// only dropped when their whole row repeats, so two identical coffees or a
+/// monthly rent entry all stay.
+pub fn dedup_batch(records: Vec<Record>) -> Vec<Record> {
+    let mut seen = HashSet::new();
+    records
+        .into_iter()
+        .filter(|record| {
+            let key = statement_hash(record, "").unwrap_or_else(|| hash_row(&record.to_row(), ""));
+            seen.insert(key)
+        })
+        .collect()
+}
+
+/// Set of records already stored in a sheet, built once and updated as new
+/// records are written so several imports can share it.
+///
+/// A record is known if its hashed row matches a stored hash, if it carries
+/// an external reference that is already present, or if it has a statement
+/// date and repeats a stored or earlier inserted record on every column but
+/// the id and timestamp, as described for [`dedup_batch`]. The last check
+/// catches a statement imported again, whose records get fresh ids.
+#[derive(Debug, Clone, Default)]
+pub struct DedupIndex {
+    signature: String,
+    hashes: HashSet<String>,
+    external_refs: HashSet<String>,
+    /// Statement hashes of the stored rows and of the records inserted since
+    /// the index was built.
+    contents: HashSet<String>,
+}
+
+impl DedupIndex {
+    /// Creates an empty index hashing rows with `signature`.
+    pub fn new(signature: &str) -> Self {
+        Self {
+            signature: signature.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Reads the sheet once and indexes every stored row, skipping the header.
+    pub fn from_sheet(
+        adapter: &dyn CloudSpreadsheetService,
+        sheet_id: &str,
+        signature: &str,
+    ) -> Result<Self, SpreadsheetError> {
+        let mut index = Self::new(signature);
+        for row in adapter
+            .list_rows(sheet_id)?
+            .into_iter()
+            .filter(|r| !is_header(r))
+        {
+            if let Some(reference) = row.get(EXTERNAL_REFERENCE_COLUMN)
+                && !reference.is_empty()
+            {
+                index.external_refs.insert(reference.clone());
+            }
+            if let Some(hash) = row.last() {
+                index.hashes.insert(hash.clone());
+            }
+            if let Some(content) = row_statement_hash(&row, signature) {
+                index.contents.insert(content);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Returns `true` if the record is already in the index.
+    pub fn contains(&self, record: &Record) -> bool {
+        self.contains_row(record, &record.to_row_hashed(&self.signature))
+    }
+
+    /// Adds the record to the index. Returns `false` if it was already known.
+    pub fn insert(&mut self, record: &Record) -> bool {
+        let row = record.to_row_hashed(&self.signature);
+        if self.contains_row(record, &row) {
+            return false;
+        }
+        if let Some(reference) = record.external_reference.as_ref().filter(|r| !r.is_empty()) {
+            self.external_refs.insert(reference.clone());
+        }
+        if let Some(hash) = row.last() {
+            self.hashes.insert(hash.clone());
+        }
+        if let Some(content) = statement_hash(record, &self.signature) {
+            self.contents.insert(content);
+        }
+        true
+    }
+
+    /// Converts the records not yet in the index to hashed rows, dropping
+    /// repeats within `records` as well. The index itself is not updated.
+    pub fn new_rows(&self, records: Vec<Record>) -> Vec<Vec<String>> {
+        dedup_batch(records)
+            .into_iter()
+            .filter_map(|record| {
+                let row = record.to_row_hashed(&self.signature);
+                (!self.contains_row(&record, &row)).then_some(row)
+            })
+            .collect()
+    }
+
+    fn contains_row(&self, record: &Record, row: &[String]) -> bool {
+        let known_ref = record
+            .external_reference
+            .as_ref()
+            .is_some_and(|r| self.external_refs.contains(r));
+        known_ref
+            || row.last().is_some_and(|hash| self.hashes.contains(hash))
+            || statement_hash(record, &self.signature).is_some_and(|c| self.contents.contains(&c))
+    }
+}
 
 /// Filter out records already present in the target sheet.
 ///
-/// Existing rows are identified by their hash in the last column. Records whose
-/// hashed rows match existing hashes are discarded. The remaining records are
-/// converted to rows ready for appending.
+/// Existing rows are identified by their hash in the last column or by their
+/// external reference. Records matching either are discarded, as are repeats
+/// within `records` (see [`dedup_batch`]). The remaining records are converted
+/// to rows ready for appending. Build a [`DedupIndex`]
+/// instead when importing several batches into the same sheet.
 pub fn filter_new_records(
     adapter: &dyn CloudSpreadsheetService,
     sheet_id: &str,
     records: Vec<Record>,
     signature: &str,
 ) -> Result<Vec<Vec<String>>, SpreadsheetError> {
-    let existing: HashSet<String> = adapter
+    Ok(DedupIndex::from_sheet(adapter, sheet_id, signature)?.new_rows(records))
+}
+
+/// Whether `row` is the header some adapters return above the data; it
+/// starts with `id`.
+fn is_header(row: &[String]) -> bool {
+    row.first().map(String::as_str) == Some("id")
+}
+
+/// Collects the row hashes already stored in the target sheet, skipping the
+/// header row.
+pub fn existing_hashes(
+    adapter: &dyn CloudSpreadsheetService,
+    sheet_id: &str,
+) -> Result<HashSet<String>, SpreadsheetError> {
+    Ok(adapter
         .list_rows(sheet_id)?
         .into_iter()
-        .skip(1)
+        .filter(|r| !is_header(r))
         .filter_map(|row| row.last().cloned())
-        .collect();
-
-    let mut rows = Vec::new();
-    for record in records {
-        let row = record.to_row_hashed(signature);
-        if let Some(hash) = row.last() {
-            if existing.contains(hash) {
-                continue;
-            }
-        }
-        rows.push(row);
-    }
-    Ok(rows)
+        .collect())
 }
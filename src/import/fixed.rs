@@ -0,0 +1,143 @@
+//! Importer for fixed-width (positional) statement exports.
+//!
+//! Column positions are described by a [`FixedSpec`], usually loaded from a
+//! TOML file:
+//!
+//! ```toml
+//! date = { start = 0, len = 10 }
+//! description = { start = 10, len = 20 }
+//! amount = { start = 30, len = 10 }
+//! account = { start = 40, len = 16 }
+//! date_format = "%Y-%m-%d"
+//! ```
+
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::Deserialize;
+
+use super::ImportError;
+use crate::core::{Account, Record};
+
+/// Character range of a single field within a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct FieldRange {
+    /// Zero-based character offset where the field begins.
+    pub start: usize,
+    /// Number of characters in the field.
+    pub len: usize,
+}
+
+/// Layout of a fixed-width statement.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixedSpec {
+    pub description: FieldRange,
+    pub amount: FieldRange,
+    /// Transaction date column, stored as the record's `transaction_date`.
+    pub date: Option<FieldRange>,
+    /// Counter-account column. When absent, outflows are posted to
+    /// `expenses` and inflows to `income`.
+    pub account: Option<FieldRange>,
+    /// `chrono` format of the date column. Defaults to `%Y-%m-%d`.
+    pub date_format: Option<String>,
+    /// Currency for all records. Defaults to `USD`.
+    pub currency: Option<String>,
+}
+
+impl FixedSpec {
+    /// Loads a spec from a TOML file.
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_toml_str(&content)
+    }
+
+    /// Parses a spec from a TOML document.
+    pub fn from_toml_str(input: &str) -> Result<Self, ImportError> {
+        toml::from_str(input).map_err(|e| ImportError::Parse(e.to_string()))
+    }
+}
+
+fn field(line: &str, range: FieldRange) -> String {
+    line.chars()
+        .skip(range.start)
+        .take(range.len)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Parses an amount, treating a leading `-`, a trailing `-` or a trailing
+/// `CR` as a negative value.
+fn parse_amount(raw: &str) -> Result<f64, ImportError> {
+    let cleaned = raw.replace(',', "");
+    let mut s = cleaned.trim();
+    let mut negative = false;
+    if let Some(rest) = s.strip_suffix('-') {
+        negative = true;
+        s = rest.trim_end();
+    } else if let Some(split) = s.len().checked_sub(2)
+        && s.get(split..)
+            .is_some_and(|suffix| suffix.eq_ignore_ascii_case("CR"))
+    {
+        negative = true;
+        s = s[..split].trim_end();
+    }
+    let value = s
+        .parse::<f64>()
+        .map_err(|e| ImportError::Parse(format!("invalid amount {raw:?}: {e}")))?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Parses fixed-width statement text using the provided spec.
+pub fn parse_str_with_spec(input: &str, spec: &FixedSpec) -> Result<Vec<Record>, ImportError> {
+    let date_format = spec.date_format.as_deref().unwrap_or("%Y-%m-%d");
+    let currency = spec.currency.as_deref().unwrap_or("USD");
+    let mut records = Vec::new();
+    for line in input.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let amount = parse_amount(&field(line, spec.amount))?;
+        let counter = spec
+            .account
+            .map(|range| field(line, range))
+            .filter(|a| !a.is_empty());
+        let (debit, credit) = if amount < 0.0 {
+            (
+                counter.unwrap_or_else(|| "expenses".into()),
+                "bank".to_string(),
+            )
+        } else {
+            (
+                "bank".to_string(),
+                counter.unwrap_or_else(|| "income".into()),
+            )
+        };
+        let mut rec = Record::new(
+            field(line, spec.description),
+            Account::parse_lenient(&debit),
+            Account::parse_lenient(&credit),
+            amount.abs(),
+            currency.into(),
+            None,
+            None,
+            vec![],
+        )?;
+        if let Some(range) = spec.date {
+            let raw = field(line, range);
+            let date = NaiveDate::parse_from_str(&raw, date_format)
+                .map_err(|e| ImportError::Parse(format!("invalid date {raw:?}: {e}")))?;
+            rec.transaction_date = date
+                .and_hms_opt(0, 0, 0)
+                .and_then(|dt| Local.from_local_datetime(&dt).single());
+        }
+        records.push(rec);
+    }
+    Ok(records)
+}
+
+/// Parses a fixed-width statement file using the provided spec.
+pub fn parse_with_spec(path: &Path, spec: &FixedSpec) -> Result<Vec<Record>, ImportError> {
+    let content = std::fs::read_to_string(path)?;
+    parse_str_with_spec(&content, spec)
+}
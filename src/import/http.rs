@@ -0,0 +1,79 @@
+//! Plain HTTP downloads shared by the importers and the CLI.
+
+use super::ImportError;
+
+/// Performs a GET request and returns the response body, following at most
+/// one redirect. Credentials are only forwarded when the redirect stays on
+/// the same host.
+pub async fn get(url: &str, authorization: Option<String>) -> Result<Vec<u8>, ImportError> {
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+    use hyper_util::client::legacy::Client;
+    use hyper_util::rt::TokioExecutor;
+    use yup_oauth2::hyper_rustls::HttpsConnectorBuilder;
+    let https = HttpsConnectorBuilder::new()
+        .with_native_roots()? // Use ? to unwrap Result
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = Client::builder(TokioExecutor::new()).build::<_, Full<Bytes>>(https);
+    let mut uri: hyper::Uri = url
+        .parse::<hyper::Uri>()
+        .map_err(|e| ImportError::Parse(e.to_string()))?;
+    let mut authorization = authorization;
+    let mut redirected = false;
+    let res = loop {
+        let mut builder = hyper::Request::builder()
+            .method(hyper::Method::GET)
+            .uri(uri.clone());
+        if let Some(value) = &authorization {
+            builder = builder.header(hyper::header::AUTHORIZATION, value);
+        }
+        let req = builder
+            .body(Full::new(Bytes::new()))
+            .map_err(|e| ImportError::Parse(e.to_string()))?;
+        let res = client
+            .request(req)
+            .await
+            .map_err(|e| ImportError::Io(std::io::Error::other(e)))?;
+        if !res.status().is_redirection() || redirected {
+            break res;
+        }
+        let location = res
+            .headers()
+            .get(hyper::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .ok_or_else(|| ImportError::Parse("redirect without location".into()))?;
+        let next = resolve_redirect(&uri, location)?;
+        if next.authority() != uri.authority() {
+            authorization = None;
+        }
+        uri = next;
+        redirected = true;
+    };
+    let status = res.status();
+    if !status.is_success() {
+        return Err(ImportError::Parse(format!("unexpected status {status}")));
+    }
+    let bytes = res
+        .into_body()
+        .collect()
+        .await
+        .map_err(|e| ImportError::Io(std::io::Error::other(e)))?
+        .to_bytes();
+    Ok(bytes.to_vec())
+}
+
+/// Resolves a `Location` header against the URI of the original request.
+fn resolve_redirect(base: &hyper::Uri, location: &str) -> Result<hyper::Uri, ImportError> {
+    let target: hyper::Uri = location
+        .parse()
+        .map_err(|e: hyper::http::uri::InvalidUri| ImportError::Parse(e.to_string()))?;
+    if target.scheme().is_some() {
+        return Ok(target);
+    }
+    let mut parts = target.into_parts();
+    parts.scheme = base.scheme().cloned();
+    parts.authority = base.authority().cloned();
+    hyper::Uri::from_parts(parts).map_err(|e| ImportError::Parse(e.to_string()))
+}
@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use serde_json::value::RawValue;
+
 use super::{ImportError, StatementImporter};
 use crate::core::Record;
 
@@ -17,6 +19,34 @@ impl JsonImporter {
         Ok(records)
     }
 
+    /// Parses each array entry on its own, collecting the records that parse
+    /// and the line number and error of each entry that does not. A document
+    /// that is not a JSON array is reported as a single error.
+    pub fn parse_str_collect_errors(input: &str) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+        let entries: Vec<&RawValue> = match serde_json::from_str(input) {
+            Ok(entries) => entries,
+            Err(e) => {
+                return (
+                    Vec::new(),
+                    vec![(e.line(), ImportError::Parse(e.to_string()))],
+                );
+            }
+        };
+        let mut records = Vec::new();
+        let mut errors = Vec::new();
+        for entry in entries {
+            let text = entry.get();
+            // Entries borrow from `input`, so their offset gives the line.
+            let offset = text.as_ptr() as usize - input.as_ptr() as usize;
+            let line = input[..offset].matches('\n').count() + 1;
+            match serde_json::from_str(text) {
+                Ok(rec) => records.push(rec),
+                Err(e) => errors.push((line, ImportError::Parse(e.to_string()))),
+            }
+        }
+        (records, errors)
+    }
+
     fn write(path: &Path, records: &[Record]) -> Result<(), ImportError> {
         let data =
             serde_json::to_string_pretty(records).map_err(|e| ImportError::Parse(e.to_string()))?;
@@ -48,6 +78,15 @@ pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
     JsonImporter::parse_str(input)
 }
 
+/// Parses a JSON file, continuing past entries that fail. See
+/// [`JsonImporter::parse_str_collect_errors`].
+pub fn parse_collect_errors(path: &Path) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+    match std::fs::read_to_string(path) {
+        Ok(content) => JsonImporter::parse_str_collect_errors(&content),
+        Err(e) => (Vec::new(), vec![(0, ImportError::Io(e))]),
+    }
+}
+
 pub fn export(path: &Path, records: &[Record]) -> Result<(), ImportError> {
     JsonImporter::write(path, records)
 }
@@ -0,0 +1,109 @@
+//! Newline-delimited JSON: one [`Record`] object per line, in the same
+//! schema as the [`json`](super::json) format, so files can be read and
+//! written a record at a time.
+
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use super::{ImportError, StatementImporter};
+use crate::core::Record;
+
+pub struct JsonlImporter;
+
+impl JsonlImporter {
+    fn parse_internal(path: &Path) -> Result<Vec<Record>, ImportError> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let mut records = Vec::new();
+        for (idx, line) in reader.lines().enumerate() {
+            if let Some(rec) = Self::parse_line(idx + 1, &line?)? {
+                records.push(rec);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Parses one line, numbered from 1. Blank lines hold no record.
+    fn parse_line(number: usize, line: &str) -> Result<Option<Record>, ImportError> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        serde_json::from_str(line)
+            .map(Some)
+            .map_err(|e| ImportError::Parse(format!("line {number}: {e}")))
+    }
+
+    /// Parses one record per line, failing on the first line that is not a
+    /// valid record. Blank lines are skipped.
+    pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
+        let mut records = Vec::new();
+        for (idx, line) in input.lines().enumerate() {
+            if let Some(rec) = Self::parse_line(idx + 1, line)? {
+                records.push(rec);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Like [`parse_str`](Self::parse_str), but collects the line number and
+    /// error of each line that fails instead of stopping at the first.
+    pub fn parse_str_collect_errors(input: &str) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+        let mut records = Vec::new();
+        let mut errors = Vec::new();
+        for (idx, line) in input.lines().enumerate() {
+            match Self::parse_line(idx + 1, line) {
+                Ok(Some(rec)) => records.push(rec),
+                Ok(None) => {}
+                Err(e) => errors.push((idx + 1, e)),
+            }
+        }
+        (records, errors)
+    }
+
+    fn write(path: &Path, records: &[Record]) -> Result<(), ImportError> {
+        let mut writer = BufWriter::new(std::fs::File::create(path)?);
+        for rec in records {
+            serde_json::to_writer(&mut writer, rec)
+                .map_err(|e| ImportError::Parse(e.to_string()))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl StatementImporter for JsonlImporter {
+    fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
+        Self::parse_internal(path)
+    }
+}
+
+pub fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
+    JsonlImporter::parse(path)
+}
+
+/// Parses a JSON-lines file and sets all record currencies to the provided
+/// value.
+pub fn parse_with_currency(path: &Path, currency: &str) -> Result<Vec<Record>, ImportError> {
+    let mut records = JsonlImporter::parse(path)?;
+    for rec in &mut records {
+        rec.currency = currency.to_string();
+    }
+    Ok(records)
+}
+
+pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
+    JsonlImporter::parse_str(input)
+}
+
+/// Parses a JSON-lines file, continuing past lines that fail. See
+/// [`JsonlImporter::parse_str_collect_errors`].
+pub fn parse_collect_errors(path: &Path) -> (Vec<Record>, Vec<(usize, ImportError)>) {
+    match std::fs::read_to_string(path) {
+        Ok(content) => JsonlImporter::parse_str_collect_errors(&content),
+        Err(e) => (Vec::new(), vec![(0, ImportError::Io(e))]),
+    }
+}
+
+pub fn export(path: &Path, records: &[Record]) -> Result<(), ImportError> {
+    JsonlImporter::write(path, records)
+}
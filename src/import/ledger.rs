@@ -1,82 +1,357 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use super::{ImportError, StatementImporter};
-use crate::core::Record;
+use crate::core::{Account, Posting, Record};
+
+/// Currency used for amounts written without a commodity.
+const DEFAULT_CURRENCY: &str = "USD";
+
+/// A posting line as written in the journal, before balancing.
+pub(super) struct JournalPosting {
+    pub(super) account: Account,
+    pub(super) amount: Option<(f64, String)>,
+}
+
+/// Directives that carry no transactions and are skipped together with any
+/// indented lines that follow them.
+const DIRECTIVES: &[&str] = &[
+    "account",
+    "alias",
+    "apply",
+    "assert",
+    "bucket",
+    "check",
+    "commodity",
+    "decimal-mark",
+    "define",
+    "end",
+    "payee",
+    "tag",
+    "year",
+    "A",
+    "D",
+    "N",
+    "P",
+    "Y",
+];
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with([';', '#', '%', '*', '|'])
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(before, _)| before)
+}
+
+/// Extracts the description from a header such as
+/// `2024-01-01 * (42) Groceries ; note`.
+fn parse_header(header: &str) -> String {
+    let rest = strip_comment(header)
+        .split_once(char::is_whitespace)
+        .map_or("", |(_, rest)| rest)
+        .trim_start();
+    let rest = rest.strip_prefix(['*', '!']).map_or(rest, str::trim_start);
+    let rest = match rest.strip_prefix('(').and_then(|r| r.split_once(')')) {
+        Some((_, after)) => after.trim_start(),
+        None => rest,
+    };
+    rest.trim().to_string()
+}
+
+/// Parses an amount with an optional commodity before or after it, such as
+/// `5.00 USD`, `EUR -3`, or `$12.50`.
+pub(super) fn parse_amount(text: &str) -> Result<(f64, String), ImportError> {
+    let parse_number = |s: &str| {
+        s.replace(',', "")
+            .parse::<f64>()
+            .map_err(|e| ImportError::Parse(format!("invalid amount {text:?}: {e}")))
+    };
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    match tokens.as_slice() {
+        [single] => {
+            let (negative, body) = match single.strip_prefix('-') {
+                Some(body) => (true, body),
+                None => (false, *single),
+            };
+            let (currency, number) = match body.strip_prefix('$') {
+                Some(number) => ("USD", number),
+                None => (DEFAULT_CURRENCY, body),
+            };
+            let value = parse_number(number)?;
+            Ok((if negative { -value } else { value }, currency.to_string()))
+        }
+        [first, second] => match parse_number(first) {
+            Ok(value) => Ok((value, second.to_string())),
+            Err(_) => Ok((parse_number(second)?, first.to_string())),
+        },
+        _ => Err(ImportError::Parse(format!("invalid amount {text:?}"))),
+    }
+}
+
+/// Parses a posting such as `expenses:food  5.00 USD`. The account is
+/// separated from the amount by two spaces or a tab; a single space is
+/// accepted when the account itself has no spaces.
+fn parse_posting(line: &str) -> Result<JournalPosting, ImportError> {
+    let split = line
+        .find("  ")
+        .or_else(|| line.find('\t'))
+        .map(|idx| (&line[..idx], line[idx..].trim()))
+        .or_else(|| {
+            line.split_once(' ')
+                .filter(|(_, rest)| parse_amount(rest).is_ok())
+        });
+    let (account, amount) = match split {
+        Some((account, amount)) if !amount.is_empty() => (account, Some(parse_amount(amount)?)),
+        _ => (line, None),
+    };
+    let account = account
+        .trim()
+        .parse()
+        .map_err(|e| ImportError::Parse(format!("invalid account {account:?}: {e}")))?;
+    Ok(JournalPosting { account, amount })
+}
+
+/// Balances the journal postings and pairs debits with credits, emitting
+/// the pairs beyond the first as splits.
+pub(super) fn build_record(
+    description: String,
+    postings: Vec<JournalPosting>,
+) -> Result<Record, ImportError> {
+    if postings.len() < 2 {
+        return Err(ImportError::Parse(format!(
+            "transaction {description:?} needs at least two postings"
+        )));
+    }
+    let elided: Vec<usize> = postings
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.amount.is_none())
+        .map(|(i, _)| i)
+        .collect();
+    if elided.len() > 1 {
+        return Err(ImportError::Parse(format!(
+            "transaction {description:?} has more than one posting without an amount"
+        )));
+    }
+    let mut currencies: Vec<String> = Vec::new();
+    for (_, currency) in postings.iter().filter_map(|p| p.amount.as_ref()) {
+        if !currencies.contains(currency) {
+            currencies.push(currency.clone());
+        }
+    }
+    if !elided.is_empty() && currencies.len() > 1 {
+        return Err(ImportError::Parse(format!(
+            "transaction {description:?} cannot infer an amount across currencies"
+        )));
+    }
+    let record_currency = currencies
+        .first()
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
+
+    let mut pairs = Vec::new();
+    for currency in &currencies {
+        let mut debits = Vec::new();
+        let mut credits = Vec::new();
+        let mut total = 0.0;
+        for p in &postings {
+            if let Some((amount, c)) = &p.amount
+                && c == currency
+            {
+                total += amount;
+                if *amount >= 0.0 {
+                    debits.push((p.account.clone(), *amount));
+                } else {
+                    credits.push((p.account.clone(), -amount));
+                }
+            }
+        }
+        if let Some(&idx) = elided.first() {
+            let account = postings[idx].account.clone();
+            if total > 0.0 {
+                credits.push((account, total));
+            } else {
+                debits.push((account, -total));
+            }
+        } else if total.abs() > 1e-9 {
+            return Err(ImportError::Parse(format!(
+                "transaction {description:?} does not balance in {currency}"
+            )));
+        }
+        let posting_currency = (currency != &record_currency).then(|| currency.clone());
+        let (mut d, mut c) = (0, 0);
+        while d < debits.len() && c < credits.len() {
+            let amount = debits[d].1.min(credits[c].1);
+            if amount > 1e-9 {
+                pairs.push(Posting {
+                    debit_account: debits[d].0.clone(),
+                    credit_account: credits[c].0.clone(),
+                    amount,
+                    currency: posting_currency.clone(),
+                    memo: None,
+                });
+            }
+            debits[d].1 -= amount;
+            credits[c].1 -= amount;
+            if debits[d].1 <= 1e-9 {
+                d += 1;
+            }
+            if credits[c].1 <= 1e-9 {
+                c += 1;
+            }
+        }
+    }
+    Ok(Record::new_split(
+        description,
+        pairs,
+        record_currency,
+        None,
+        None,
+        vec![],
+    )?)
+}
 
 pub struct LedgerImporter;
 
 impl LedgerImporter {
     fn parse_internal(path: &Path) -> Result<Vec<Record>, ImportError> {
-        let content = std::fs::read_to_string(path)?;
-        Self::parse_str(&content)
+        Self::parse_file(path, &mut Vec::new())
+    }
+
+    /// Parses a journal file. `stack` holds the files currently being read
+    /// so circular includes can be reported instead of recursing forever.
+    fn parse_file(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Vec<Record>, ImportError> {
+        let canonical = path.canonicalize()?;
+        if stack.contains(&canonical) {
+            return Err(ImportError::Parse(format!(
+                "circular include of {}",
+                path.display()
+            )));
+        }
+        let content = std::fs::read_to_string(&canonical)?;
+        stack.push(canonical.clone());
+        let result = Self::parse_source(&content, canonical.parent(), stack);
+        stack.pop();
+        result
     }
 
+    /// Parses a Ledger/hledger journal. Each transaction is a header line
+    /// followed by indented postings up to the next blank or unindented line.
+    /// One posting may omit its amount, which is then inferred so the
+    /// transaction balances. Comment lines and directives such as `account`
+    /// or `commodity` are skipped; `include` paths are resolved against the
+    /// current directory.
     pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
+        Self::parse_source(input, None, &mut Vec::new())
+    }
+
+    fn parse_source(
+        input: &str,
+        base_dir: Option<&Path>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Record>, ImportError> {
         let mut records = Vec::new();
         let mut lines = input.lines().peekable();
         while let Some(header) = lines.next() {
-            if header.trim().is_empty() {
+            let trimmed = header.trim();
+            if trimmed.is_empty() || is_comment(trimmed) {
                 continue;
             }
-            let parts: Vec<&str> = header.trim().splitn(2, ' ').collect();
-            let description = parts.get(1).map(|s| s.trim()).unwrap_or("").to_string();
-            let debit_line = lines
-                .next()
-                .ok_or_else(|| ImportError::Parse("missing debit line".into()))?;
-            let credit_line = lines
-                .next()
-                .ok_or_else(|| ImportError::Parse("missing credit line".into()))?;
-            let mut debit_parts = debit_line.split_whitespace();
-            let debit_account = debit_parts
-                .next()
-                .ok_or_else(|| ImportError::Parse("missing debit account".into()))?
-                .parse()
-                .map_err(|_| ImportError::Parse("invalid account".into()))?;
-            let amount: f64 = debit_parts
-                .next()
-                .ok_or_else(|| ImportError::Parse("missing amount".into()))?
-                .parse()
-                .map_err(|e: std::num::ParseFloatError| ImportError::Parse(e.to_string()))?;
-            let currency = debit_parts
-                .next()
-                .ok_or_else(|| ImportError::Parse("missing currency".into()))?
-                .to_string();
-            let credit_account = credit_line
-                .trim()
-                .parse()
-                .map_err(|_| ImportError::Parse("invalid account".into()))?;
-            let rec = Record::new(
-                description,
-                debit_account,
-                credit_account,
-                amount,
-                currency,
-                None,
-                None,
-                vec![],
-            )?;
-            records.push(rec);
-            while let Some(l) = lines.peek() {
-                if l.trim().is_empty() {
+            if !trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+                let (keyword, argument) = trimmed
+                    .split_once(char::is_whitespace)
+                    .map_or((trimmed, ""), |(k, a)| (k, a.trim()));
+                if trimmed.starts_with(['~', '=']) {
+                    // Periodic and automated transactions are not imported.
+                } else if matches!(keyword, "comment" | "test") {
+                    let end = format!("end {keyword}");
+                    for line in lines.by_ref() {
+                        if line.trim() == end {
+                            break;
+                        }
+                    }
+                    continue;
+                } else if keyword == "include" {
+                    let target = strip_comment(argument).trim();
+                    let path = base_dir.map_or_else(|| PathBuf::from(target), |d| d.join(target));
+                    records.extend(Self::parse_file(&path, stack)?);
+                } else if !DIRECTIVES.contains(&keyword) {
+                    return Err(ImportError::Parse(format!("unknown directive {keyword:?}")));
+                }
+                // Skip the directive's indented sub-lines.
+                while lines
+                    .peek()
+                    .is_some_and(|l| l.starts_with([' ', '\t']) && !l.trim().is_empty())
+                {
                     lines.next();
-                } else {
+                }
+                continue;
+            }
+            let description = parse_header(trimmed);
+            let mut postings = Vec::new();
+            while let Some(line) = lines.peek() {
+                if line.trim().is_empty() || !line.starts_with([' ', '\t']) {
                     break;
                 }
+                let line = lines.next().unwrap_or_default();
+                let content = line.trim();
+                if is_comment(content) {
+                    continue;
+                }
+                let content = strip_comment(content).trim();
+                if content.is_empty() {
+                    continue;
+                }
+                postings.push(parse_posting(content)?);
             }
+            records.push(build_record(description, postings)?);
         }
         Ok(records)
     }
 
+    /// Writes each posting as a debit line and a credit line, so split
+    /// records keep every posting. The amount of the last line is left out
+    /// when all postings share one currency, as Ledger infers it. Posting
+    /// memos become comments on the debit line.
+    ///
+    /// The header carries the statement date when known, `*` for cleared
+    /// and `!` for uncleared records, and a comment with the tags and
+    /// metadata in hledger's `name:value` form, e.g. `; trip:, invoice:42`.
     fn export_internal(records: &[Record]) -> String {
         let mut out = String::new();
         for r in records {
-            let date = r.timestamp.format("%Y-%m-%d");
-            out.push_str(&format!("{date} {}\n", r.description));
-            out.push_str(&format!(
-                "    {}  {} {}\n",
-                r.debit_account, r.amount, r.currency
-            ));
-            out.push_str(&format!("    {}\n\n", r.credit_account));
+            let date = r.effective_date().format("%Y-%m-%d");
+            let status = if r.cleared { '*' } else { '!' };
+            let tags: Vec<String> = r
+                .tags
+                .iter()
+                .map(|tag| format!("{tag}:"))
+                .chain(r.metadata.iter().map(|(k, v)| format!("{k}:{v}")))
+                .collect();
+            let comment = if tags.is_empty() {
+                String::new()
+            } else {
+                format!("  ; {}", tags.join(", "))
+            };
+            out.push_str(&format!("{date} {status} {}{comment}\n", r.description));
+            let postings: Vec<_> = r.postings().collect();
+            let single_currency = postings.iter().all(|p| p.currency == r.currency);
+            for (i, p) in postings.iter().enumerate() {
+                let memo = p.memo.map(|m| format!("  ; {m}")).unwrap_or_default();
+                out.push_str(&format!(
+                    "    {}  {} {}{memo}\n",
+                    p.debit_account, p.amount, p.currency
+                ));
+                if single_currency && i + 1 == postings.len() {
+                    out.push_str(&format!("    {}\n", p.credit_account));
+                } else {
+                    out.push_str(&format!(
+                        "    {}  {} {}\n",
+                        p.credit_account, -p.amount, p.currency
+                    ));
+                }
+            }
+            out.push('\n');
         }
         out
     }
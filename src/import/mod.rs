@@ -41,13 +41,58 @@ impl From<RecordError> for ImportError {
     }
 }
 
+/// How statement amounts map onto debit and credit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignConvention {
+    /// Positive amounts are money coming into the statement account. This is
+    /// how bank statements are usually written and the importers' default.
+    #[default]
+    DebitPositive,
+    /// Positive amounts are charges against the statement account, as on many
+    /// credit card statements. The debit and credit accounts are swapped.
+    CreditPositive,
+}
+
+impl SignConvention {
+    /// Orders the accounts the importer would assign under the default
+    /// convention according to `self`.
+    pub fn assign<T>(self, debit: T, credit: T) -> (T, T) {
+        match self {
+            SignConvention::DebitPositive => (debit, credit),
+            SignConvention::CreditPositive => (credit, debit),
+        }
+    }
+}
+
+impl std::str::FromStr for SignConvention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "-").as_str() {
+            "debit-positive" => Ok(SignConvention::DebitPositive),
+            "credit-positive" => Ok(SignConvention::CreditPositive),
+            other => Err(format!(
+                "unknown sign convention {other:?}, expected debit-positive or credit-positive"
+            )),
+        }
+    }
+}
+
 pub trait StatementImporter {
     fn parse(path: &Path) -> Result<Vec<Record>, ImportError>;
 }
 
+pub mod amount;
+pub mod beancount;
 pub mod csv;
 pub mod dedup;
+pub mod fixed;
+#[cfg(feature = "bank-api")]
+pub mod http;
 pub mod json;
+pub mod jsonl;
 pub mod ledger;
 pub mod ofx;
+pub mod pipeline;
 pub mod qif;
+pub mod rules;
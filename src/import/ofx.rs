@@ -1,18 +1,40 @@
 use std::path::Path;
 
-use super::{ImportError, StatementImporter};
-use crate::core::Record;
-use chrono::{Local, NaiveDate, TimeZone};
+use super::amount::{self, DecimalSeparator};
+use super::{ImportError, SignConvention, StatementImporter};
+use crate::core::{Record, RecordError};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone};
+use iso_currency::Currency;
 
 pub struct OfxImporter;
 
 impl OfxImporter {
-    fn parse_internal(path: &Path, date_format: Option<&str>) -> Result<Vec<Record>, ImportError> {
+    fn parse_internal(
+        path: &Path,
+        date_format: Option<&str>,
+        sign: SignConvention,
+    ) -> Result<Vec<Record>, ImportError> {
         let content = std::fs::read_to_string(path)?;
-        Self::parse_str(&content, date_format)
+        Self::parse_str_with_sign(&content, date_format, sign)
     }
 
     pub fn parse_str(input: &str, date_format: Option<&str>) -> Result<Vec<Record>, ImportError> {
+        Self::parse_str_with_sign(input, date_format, SignConvention::default())
+    }
+
+    /// Parses OFX text, assigning debit and credit according to `sign`.
+    ///
+    /// Records use the transaction's own `<CURSYM>` when present, then a
+    /// currency symbol in the amount, then the statement's `<CURDEF>`, and
+    /// finally USD.
+    pub fn parse_str_with_sign(
+        input: &str,
+        date_format: Option<&str>,
+        sign: SignConvention,
+    ) -> Result<Vec<Record>, ImportError> {
+        let default_currency = Self::extract_value(input, "CURDEF")
+            .map(|code| Self::currency(&code))
+            .transpose()?;
         let mut records = Vec::new();
         let mut remaining = input;
         while let Some(start) = remaining.find("<STMTTRN>") {
@@ -25,40 +47,43 @@ impl OfxImporter {
             remaining = &remaining[end + "</STMTTRN>".len()..];
 
             if let Some(amt_str) = Self::extract_tag(block, "TRNAMT") {
-                let amount: f64 = amt_str
-                    .trim()
-                    .parse()
-                    .map_err(|e: std::num::ParseFloatError| ImportError::Parse(e.to_string()))?;
+                let (amount, symbol_currency) =
+                    amount::parse_amount(&amt_str, DecimalSeparator::Dot)?;
                 let name = Self::extract_tag(block, "NAME").unwrap_or_default();
+                let currency = match Self::extract_value(block, "CURSYM") {
+                    Some(code) => Self::currency(&code)?,
+                    None => symbol_currency
+                        .map(str::to_string)
+                        .or_else(|| default_currency.clone())
+                        .unwrap_or_else(|| "USD".to_string()),
+                };
                 let date = Self::extract_tag(block, "DTPOSTED").and_then(|s| {
                     let s = s.trim();
-                    if let Some(fmt) = date_format {
-                        let naive_date = NaiveDate::parse_from_str(s, fmt).ok();
-                        let naive_datetime = naive_date?.and_hms_opt(0, 0, 0).unwrap();
-                        let local_datetime = Local.from_local_datetime(&naive_datetime)
-                            .single()?;
-                        Some(local_datetime)
-                    } else if s.len() >= 8 {
-                        let naive_date = NaiveDate::parse_from_str(&s[..8], "%Y%m%d").ok();
-                        let naive_datetime = naive_date?.and_hms_opt(0, 0, 0).unwrap();
-                        let local_datetime = Local.from_local_datetime(&naive_datetime)
-                            .single()?;
-                        Some(local_datetime)
-                    } else {
-                        None
+                    match date_format {
+                        Some(fmt) => {
+                            let naive_date = NaiveDate::parse_from_str(s, fmt).ok()?;
+                            Local
+                                .from_local_datetime(&naive_date.and_hms_opt(0, 0, 0)?)
+                                .single()
+                        }
+                        None => Self::parse_datetime(s),
                     }
                 });
                 let (debit, credit) = if amount < 0.0 {
-                    ("expenses".to_string(), "bank".to_string())
+                    sign.assign("expenses".to_string(), "bank".to_string())
                 } else {
-                    ("bank".to_string(), "income".to_string())
+                    sign.assign("bank".to_string(), "income".to_string())
                 };
                 let mut rec = Record::new(
                     name.trim().to_string(),
-                    debit.parse().unwrap(),
-                    credit.parse().unwrap(),
-                    amount,
-                    "USD".into(),
+                    debit
+                        .parse()
+                        .map_err(|_| ImportError::Parse("invalid account".into()))?,
+                    credit
+                        .parse()
+                        .map_err(|_| ImportError::Parse("invalid account".into()))?,
+                    amount.abs(),
+                    currency,
                     None,
                     None,
                     vec![],
@@ -70,6 +95,57 @@ impl OfxImporter {
         Ok(records)
     }
 
+    /// Value following `<tag>`, up to the next tag or line end. Unlike
+    /// [`Self::extract_tag`] this accepts SGML-style elements without a
+    /// closing tag.
+    fn extract_value(block: &str, tag: &str) -> Option<String> {
+        let start_tag = format!("<{tag}>");
+        let start = block.find(&start_tag)? + start_tag.len();
+        let rest = &block[start..];
+        let end = rest.find(['<', '\n']).unwrap_or(rest.len());
+        Some(rest[..end].trim().to_string()).filter(|value| !value.is_empty())
+    }
+
+    /// Parses an OFX date-time: `YYYYMMDD`, optionally followed by
+    /// `HHMMSS`, fractional seconds and a `[offset:name]` timezone such as
+    /// `20240502120000.000[-5:EST]`.
+    ///
+    /// With a timezone the exact instant is kept, so a late-evening posting
+    /// lands on the local day it happened. Without one only the date is used,
+    /// at local midnight.
+    fn parse_datetime(value: &str) -> Option<DateTime<Local>> {
+        let (stamp, zone) = match value.split_once('[') {
+            Some((stamp, zone)) => (stamp.trim(), Some(zone.trim_end_matches(']'))),
+            None => (value, None),
+        };
+        let date = NaiveDate::parse_from_str(stamp.get(..8)?, "%Y%m%d").ok()?;
+        let Some(zone) = zone else {
+            return Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .single();
+        };
+        // Seconds may carry a fraction, e.g. `120000.000`, and trailing time
+        // fields may be left out, e.g. `1200`.
+        let time = stamp[8..].split('.').next().unwrap_or_default();
+        let time = NaiveTime::parse_from_str(&format!("{time:0<6}"), "%H%M%S").ok()?;
+        let naive = date.and_time(time);
+        // The offset is in hours and may be fractional, e.g. `+5.5`.
+        let hours: f64 = zone.split(':').next()?.trim().parse().ok()?;
+        let offset = FixedOffset::east_opt((hours * 3600.0).round() as i32)?;
+        let instant = offset.from_local_datetime(&naive).single()?;
+        Some(instant.with_timezone(&Local))
+    }
+
+    /// Validates an ISO 4217 code from the statement.
+    fn currency(code: &str) -> Result<String, ImportError> {
+        match Currency::from_code(code) {
+            Some(_) => Ok(code.to_string()),
+            None => Err(ImportError::Parse(
+                RecordError::UnsupportedCurrency(code.to_string()).to_string(),
+            )),
+        }
+    }
+
     fn extract_tag(block: &str, tag: &str) -> Option<String> {
         let start_tag = format!("<{tag}>");
         let end_tag = format!("</{tag}>");
@@ -82,7 +158,7 @@ impl OfxImporter {
 
 impl StatementImporter for OfxImporter {
     fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
-        Self::parse_internal(path, None)
+        Self::parse_internal(path, None, SignConvention::default())
     }
 }
 
@@ -100,7 +176,7 @@ pub fn parse_with_currency(path: &Path, currency: &str) -> Result<Vec<Record>, I
 
 /// Parses an OFX file using the provided date format for transaction dates.
 pub fn parse_with_date_format(path: &Path, fmt: &str) -> Result<Vec<Record>, ImportError> {
-    OfxImporter::parse_internal(path, Some(fmt))
+    OfxImporter::parse_internal(path, Some(fmt), SignConvention::default())
 }
 
 pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
@@ -111,37 +187,51 @@ pub fn parse_str_with_date_format(input: &str, fmt: &str) -> Result<Vec<Record>,
     OfxImporter::parse_str(input, Some(fmt))
 }
 
+/// Parses an OFX file, assigning debit and credit according to `sign`.
+pub fn parse_with_sign_convention(
+    path: &Path,
+    date_format: Option<&str>,
+    sign: SignConvention,
+) -> Result<Vec<Record>, ImportError> {
+    OfxImporter::parse_internal(path, date_format, sign)
+}
+
+pub fn parse_str_with_sign_convention(
+    input: &str,
+    sign: SignConvention,
+) -> Result<Vec<Record>, ImportError> {
+    OfxImporter::parse_str_with_sign(input, None, sign)
+}
+
+/// Downloads an OFX statement without authentication and parses it.
 #[cfg(feature = "bank-api")]
 pub async fn download(url: &str) -> Result<Vec<Record>, ImportError> {
-    use http_body_util::{BodyExt, Full};
-    use hyper::body::Bytes;
-    use hyper_util::client::legacy::Client;
-    use hyper_util::rt::TokioExecutor;
-    use yup_oauth2::hyper_rustls::HttpsConnectorBuilder;
-    let https = HttpsConnectorBuilder::new()
-        .with_native_roots()? // Use ? to unwrap Result
-        .https_or_http()
-        .enable_http1()
-        .build();
-    let client = Client::builder(TokioExecutor::new()).build::<_, Full<Bytes>>(https);
-    let uri: hyper::Uri = url
-        .parse::<hyper::Uri>()
-        .map_err(|e| ImportError::Parse(e.to_string()))?;
-    let req = hyper::Request::builder()
-        .method(hyper::Method::GET)
-        .uri(uri)
-        .body(Full::new(Bytes::new()))
-        .map_err(|e| ImportError::Parse(e.to_string()))?;
-    let res = client
-        .request(req)
-        .await
-        .map_err(|e| ImportError::Io(std::io::Error::other(e)))?;
-    let bytes = res
-        .into_body()
-        .collect()
-        .await
-        .map_err(|e| ImportError::Io(std::io::Error::other(e)))?
-        .to_bytes();
-    let text = String::from_utf8(bytes.to_vec()).map_err(|e| ImportError::Parse(e.to_string()))?;
+    fetch(url, None).await
+}
+
+/// Downloads an OFX statement using HTTP Basic authentication.
+#[cfg(feature = "bank-api")]
+pub async fn download_with_auth(
+    url: &str,
+    username: &str,
+    password: &str,
+) -> Result<Vec<Record>, ImportError> {
+    use base64::Engine;
+    let encoded =
+        base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+    fetch(url, Some(format!("Basic {encoded}"))).await
+}
+
+/// Downloads an OFX statement using a bearer token.
+#[cfg(feature = "bank-api")]
+pub async fn download_with_bearer(url: &str, token: &str) -> Result<Vec<Record>, ImportError> {
+    fetch(url, Some(format!("Bearer {token}"))).await
+}
+
+/// Downloads the statement and parses it.
+#[cfg(feature = "bank-api")]
+async fn fetch(url: &str, authorization: Option<String>) -> Result<Vec<Record>, ImportError> {
+    let bytes = super::http::get(url, authorization).await?;
+    let text = String::from_utf8(bytes).map_err(|e| ImportError::Parse(e.to_string()))?;
     parse_str(&text)
 }
@@ -0,0 +1,245 @@
+//! Concurrent import pipeline.
+//!
+//! Records are read from the parser on a worker thread, hashed into rows by a
+//! pool of workers and handed back to the calling thread, which reorders them
+//! and appends them in batches. The adapter is only used on the calling thread
+//! so it does not have to be `Send`.
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::ImportError;
+use crate::cloud_adapters::{CloudSpreadsheetService, SpreadsheetError};
+use crate::core::Record;
+
+/// Number of rows sent per `append_rows` call by [`run`].
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+/// Error that stopped a pipeline run.
+#[derive(Debug)]
+pub enum PipelineError {
+    Import(ImportError),
+    Spreadsheet(SpreadsheetError),
+}
+
+impl std::fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineError::Import(e) => write!(f, "import failed: {e}"),
+            PipelineError::Spreadsheet(e) => write!(f, "append failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::Import(e) => Some(e),
+            PipelineError::Spreadsheet(e) => Some(e),
+        }
+    }
+}
+
+impl From<ImportError> for PipelineError {
+    fn from(e: ImportError) -> Self {
+        PipelineError::Import(e)
+    }
+}
+
+impl From<SpreadsheetError> for PipelineError {
+    fn from(e: SpreadsheetError) -> Self {
+        PipelineError::Spreadsheet(e)
+    }
+}
+
+/// Outcome of a pipeline run.
+#[derive(Debug)]
+pub struct PipelineReport {
+    /// Rows successfully appended to the sheet.
+    pub appended: usize,
+    /// The first error encountered. Rows before it in input order are
+    /// appended; nothing after it is.
+    pub error: Option<PipelineError>,
+}
+
+/// Appends rows in batches and remembers the first failure.
+struct Writer<'a> {
+    adapter: &'a mut dyn CloudSpreadsheetService,
+    sheet_id: &'a str,
+    batch_size: usize,
+    batch: Vec<Vec<String>>,
+    appended: usize,
+}
+
+impl Writer<'_> {
+    fn push(&mut self, row: Vec<String>) -> Result<(), PipelineError> {
+        self.batch.push(row);
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), PipelineError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.batch);
+        let len = rows.len();
+        self.adapter.append_rows(self.sheet_id, rows)?;
+        self.appended += len;
+        Ok(())
+    }
+
+    fn finish(mut self, error: Option<PipelineError>) -> PipelineReport {
+        let flushed = self.flush().err();
+        PipelineReport {
+            appended: self.appended,
+            error: error.or(flushed),
+        }
+    }
+}
+
+/// Imports records using `threads` hashing workers and [`DEFAULT_BATCH_SIZE`].
+/// See [`run_batched`].
+pub fn run<I>(
+    parse_iter: I,
+    adapter: &mut dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+    threads: usize,
+) -> PipelineReport
+where
+    I: IntoIterator<Item = Result<Record, ImportError>>,
+    I::IntoIter: Send,
+{
+    run_batched(
+        parse_iter,
+        adapter,
+        sheet_id,
+        signature,
+        threads,
+        DEFAULT_BATCH_SIZE,
+    )
+}
+
+/// Parses, hashes and appends records concurrently while preserving input
+/// order. With `threads == 0` this falls back to [`run_serial`].
+pub fn run_batched<I>(
+    parse_iter: I,
+    adapter: &mut dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+    threads: usize,
+    batch_size: usize,
+) -> PipelineReport
+where
+    I: IntoIterator<Item = Result<Record, ImportError>>,
+    I::IntoIter: Send,
+{
+    if threads == 0 {
+        return run_serial(parse_iter, adapter, sheet_id, signature, batch_size);
+    }
+    let mut writer = Writer {
+        adapter,
+        sheet_id,
+        batch_size: batch_size.max(1),
+        batch: Vec::new(),
+        appended: 0,
+    };
+    let capacity = batch_size.max(1) * 2;
+    let parse_iter = parse_iter.into_iter();
+
+    let error = thread::scope(|scope| {
+        let (job_tx, job_rx) = sync_channel::<(usize, Record)>(capacity);
+        let (out_tx, out_rx) = sync_channel::<(usize, Result<Vec<String>, ImportError>)>(capacity);
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let parse_out = out_tx.clone();
+        scope.spawn(move || {
+            for (idx, item) in parse_iter.enumerate() {
+                let sent = match item {
+                    Ok(rec) => job_tx.send((idx, rec)).is_ok(),
+                    Err(e) => {
+                        let _ = parse_out.send((idx, Err(e)));
+                        false
+                    }
+                };
+                if !sent {
+                    break;
+                }
+            }
+        });
+        for _ in 0..threads {
+            let out_tx = out_tx.clone();
+            let job_rx = Arc::clone(&job_rx);
+            scope.spawn(move || {
+                loop {
+                    let job = job_rx.lock().map(|rx| rx.recv());
+                    let Ok(Ok((idx, rec))) = job else { break };
+                    if out_tx
+                        .send((idx, Ok(rec.to_row_hashed(signature))))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(out_tx);
+        drop(job_rx);
+
+        // Rows arrive out of order from the workers; hold them until every
+        // earlier row has been written.
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (idx, item) in out_rx {
+            pending.insert(idx, item);
+            while let Some(item) = pending.remove(&next) {
+                next += 1;
+                let result = item
+                    .map_err(PipelineError::from)
+                    .and_then(|row| writer.push(row));
+                if let Err(e) = result {
+                    // Dropping the receiver makes the workers and the parser
+                    // stop on their next send.
+                    return Some(e);
+                }
+            }
+        }
+        None
+    });
+    writer.finish(error)
+}
+
+/// Parses, hashes and appends records on the calling thread. Use this when
+/// the record source cannot be sent to another thread.
+pub fn run_serial<I>(
+    parse_iter: I,
+    adapter: &mut dyn CloudSpreadsheetService,
+    sheet_id: &str,
+    signature: &str,
+    batch_size: usize,
+) -> PipelineReport
+where
+    I: IntoIterator<Item = Result<Record, ImportError>>,
+{
+    let mut writer = Writer {
+        adapter,
+        sheet_id,
+        batch_size: batch_size.max(1),
+        batch: Vec::new(),
+        appended: 0,
+    };
+    for item in parse_iter {
+        let result = item
+            .map_err(PipelineError::from)
+            .and_then(|rec| writer.push(rec.to_row_hashed(signature)));
+        if let Err(e) = result {
+            return writer.finish(Some(e));
+        }
+    }
+    writer.finish(None)
+}
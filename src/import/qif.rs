@@ -1,18 +1,37 @@
 use std::path::Path;
 
-use super::{ImportError, StatementImporter};
-use crate::core::Record;
+use super::{ImportError, SignConvention, StatementImporter};
+use crate::core::{Account, Record};
 use chrono::{DateTime, Local, NaiveDate, TimeZone};
 
+/// Date formats tried in order when none are given.
+const DEFAULT_DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%m/%d/%Y"];
+
 pub struct QifImporter;
 
 impl QifImporter {
-    fn parse_internal(path: &Path, date_format: Option<&str>) -> Result<Vec<Record>, ImportError> {
+    fn parse_internal(
+        path: &Path,
+        date_formats: &[&str],
+        sign: SignConvention,
+    ) -> Result<Vec<Record>, ImportError> {
         let content = std::fs::read_to_string(path)?;
-        Self::parse_str(&content, date_format)
+        Self::parse_str(&content, date_formats, sign)
     }
 
-    fn parse_str(input: &str, date_format: Option<&str>) -> Result<Vec<Record>, ImportError> {
+    /// Parses QIF text. Each date is tried against `date_formats` in order
+    /// and the first that matches wins; an empty list means
+    /// [`DEFAULT_DATE_FORMATS`].
+    fn parse_str(
+        input: &str,
+        date_formats: &[&str],
+        sign: SignConvention,
+    ) -> Result<Vec<Record>, ImportError> {
+        let date_formats = if date_formats.is_empty() {
+            &DEFAULT_DATE_FORMATS[..]
+        } else {
+            date_formats
+        };
         let mut records = Vec::new();
         let mut amount: Option<f64> = None;
         let mut memo: Option<String> = None;
@@ -24,23 +43,22 @@ impl QifImporter {
                 continue;
             } else if let Some(rest) = line.strip_prefix('D') {
                 let s = rest.trim();
-                let parsed = if let Some(fmt) = date_format {
-                    NaiveDate::parse_from_str(s, fmt)
-                } else {
-                    NaiveDate::parse_from_str(s, "%Y-%m-%d")
-                        .or_else(|_| NaiveDate::parse_from_str(s, "%m/%d/%Y"))
-                };
-
-                let final_result = parsed
-                    .map_err(|e| e.to_string()) // Unify error type to String for the next step
-                    .and_then(|naive_date| {
-                        // This closure runs only if parsing was successful.
-                        // It converts the NaiveDate to a DateTime<Local> at midnight.
-                        let naive_datetime = naive_date.and_hms_opt(0, 0, 0).unwrap();
-                        Local.from_local_datetime(&naive_datetime)
-                            .single()
-                            .ok_or_else(|| format!("Could not convert date '{}' to a unique local time", s))
-                    });
+                let parsed = date_formats
+                    .iter()
+                    .find_map(|fmt| NaiveDate::parse_from_str(s, fmt).ok())
+                    .ok_or_else(|| format!("'{s}' matches none of the date formats"));
+
+                let final_result = parsed.and_then(|naive_date| {
+                    // This closure runs only if parsing was successful.
+                    // It converts the NaiveDate to a DateTime<Local> at midnight.
+                    let naive_datetime = naive_date.and_hms_opt(0, 0, 0).unwrap();
+                    Local
+                        .from_local_datetime(&naive_datetime)
+                        .single()
+                        .ok_or_else(|| {
+                            format!("Could not convert date '{}' to a unique local time", s)
+                        })
+                });
                 if let Ok(d) = final_result {
                     date = Some(d);
                 }
@@ -56,22 +74,21 @@ impl QifImporter {
                 memo = Some(rest.trim().to_string());
             } else if line.starts_with('^') {
                 if let Some(a) = amount {
+                    let description = memo
+                        .filter(|m| !m.is_empty())
+                        .or_else(|| vendor.clone())
+                        .unwrap_or_default();
+                    let payee = vendor.unwrap_or_else(|| "UNK".to_string());
                     let (debit, credit) = if a < 0.0 {
-                        (
-                            "bank".to_string(),
-                            vendor.or(Option::from("UNK".to_string())).unwrap(),
-                        )
+                        sign.assign("bank".to_string(), payee)
                     } else {
-                        (
-                            vendor.or(Option::from("UNK".to_string())).unwrap(),
-                            "bank".to_string(),
-                        )
+                        sign.assign(payee, "bank".to_string())
                     };
                     let mut rec = Record::new(
-                        memo.or(Option::from("".to_string())).unwrap(),
-                        debit.parse().unwrap(),
-                        credit.parse().unwrap(),
-                        a,
+                        description,
+                        Account::parse_lenient(&debit),
+                        Account::parse_lenient(&credit),
+                        a.abs(),
                         "USD".into(),
                         None,
                         None,
@@ -92,7 +109,7 @@ impl QifImporter {
 
 impl StatementImporter for QifImporter {
     fn parse(path: &Path) -> Result<Vec<Record>, ImportError> {
-        Self::parse_internal(path, None)
+        Self::parse_internal(path, &[], SignConvention::default())
     }
 }
 
@@ -110,13 +127,53 @@ pub fn parse_with_currency(path: &Path, currency: &str) -> Result<Vec<Record>, I
 }
 
 pub fn parse_with_date_format(path: &Path, fmt: &str) -> Result<Vec<Record>, ImportError> {
-    QifImporter::parse_internal(path, Some(fmt))
+    QifImporter::parse_internal(path, &[fmt], SignConvention::default())
+}
+
+/// Parses a QIF file whose dates may use any of `formats`, tried in order.
+/// Files exported by different tools often mix `%m/%d/%Y` and `%d.%m.%Y`.
+pub fn parse_with_date_formats(path: &Path, formats: &[&str]) -> Result<Vec<Record>, ImportError> {
+    QifImporter::parse_internal(path, formats, SignConvention::default())
 }
 
 pub fn parse_str(input: &str) -> Result<Vec<Record>, ImportError> {
-    QifImporter::parse_str(input, None)
+    QifImporter::parse_str(input, &[], SignConvention::default())
 }
 
 pub fn parse_str_with_date_format(input: &str, fmt: &str) -> Result<Vec<Record>, ImportError> {
-    QifImporter::parse_str(input, Some(fmt))
+    QifImporter::parse_str(input, &[fmt], SignConvention::default())
+}
+
+/// Parses QIF text whose dates may use any of `formats`, tried in order.
+pub fn parse_str_with_date_formats(
+    input: &str,
+    formats: &[&str],
+) -> Result<Vec<Record>, ImportError> {
+    QifImporter::parse_str(input, formats, SignConvention::default())
+}
+
+/// Parses a QIF file, assigning debit and credit according to `sign`.
+pub fn parse_with_sign_convention(
+    path: &Path,
+    date_format: Option<&str>,
+    sign: SignConvention,
+) -> Result<Vec<Record>, ImportError> {
+    QifImporter::parse_internal(path, date_format.as_slice(), sign)
+}
+
+/// Like [`parse_with_sign_convention`], trying each of `date_formats` in
+/// order.
+pub fn parse_with_date_formats_and_sign(
+    path: &Path,
+    date_formats: &[&str],
+    sign: SignConvention,
+) -> Result<Vec<Record>, ImportError> {
+    QifImporter::parse_internal(path, date_formats, sign)
+}
+
+pub fn parse_str_with_sign_convention(
+    input: &str,
+    sign: SignConvention,
+) -> Result<Vec<Record>, ImportError> {
+    QifImporter::parse_str(input, &[], sign)
 }
@@ -0,0 +1,147 @@
+//! Rules that assign accounts to imported records based on their description.
+//!
+//! A rules file lists regular expressions and the accounts that matching
+//! records should be posted to:
+//!
+//! ```toml
+//! [[rules]]
+//! pattern = "(?i)starbucks"
+//! debit = "expenses:coffee"
+//!
+//! [[rules]]
+//! pattern = "^PAYROLL"
+//! debit = "bank"
+//! credit = "income:salary"
+//! ```
+//!
+//! The same structure can be written as JSON.
+
+use std::path::Path;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use super::ImportError;
+use crate::core::{Account, Record};
+
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    rules: Vec<RuleDef>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleDef {
+    pattern: String,
+    debit: Option<String>,
+    credit: Option<String>,
+}
+
+/// A single description pattern and the accounts it assigns.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub pattern: Regex,
+    pub debit_account: Option<Account>,
+    pub credit_account: Option<Account>,
+}
+
+impl Rule {
+    /// Returns `true` if the rule applies to the given record.
+    pub fn matches(&self, record: &Record) -> bool {
+        self.pattern.is_match(&record.description)
+    }
+}
+
+/// Ordered collection of [`Rule`]s. The first matching rule wins.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Creates a rule set from already constructed rules.
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Loads rules from a file. Files ending in `.json` are parsed as JSON,
+    /// everything else as TOML.
+    pub fn load(path: &Path) -> Result<Self, ImportError> {
+        let content = std::fs::read_to_string(path)?;
+        let is_json = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            Self::from_json_str(&content)
+        } else {
+            Self::from_toml_str(&content)
+        }
+    }
+
+    /// Parses rules from a TOML document.
+    pub fn from_toml_str(input: &str) -> Result<Self, ImportError> {
+        let file: RuleFile =
+            toml::from_str(input).map_err(|e| ImportError::Parse(e.to_string()))?;
+        Self::from_defs(file.rules)
+    }
+
+    /// Parses rules from a JSON document.
+    pub fn from_json_str(input: &str) -> Result<Self, ImportError> {
+        let file: RuleFile =
+            serde_json::from_str(input).map_err(|e| ImportError::Parse(e.to_string()))?;
+        Self::from_defs(file.rules)
+    }
+
+    fn from_defs(defs: Vec<RuleDef>) -> Result<Self, ImportError> {
+        let parse_account = |s: Option<String>| -> Result<Option<Account>, ImportError> {
+            s.map(|s| {
+                s.parse()
+                    .map_err(|e| ImportError::Parse(format!("invalid account {s:?}: {e}")))
+            })
+            .transpose()
+        };
+        let mut rules = Vec::with_capacity(defs.len());
+        for def in defs {
+            let pattern = Regex::new(&def.pattern)
+                .map_err(|e| ImportError::Parse(format!("invalid pattern: {e}")))?;
+            rules.push(Rule {
+                pattern,
+                debit_account: parse_account(def.debit)?,
+                credit_account: parse_account(def.credit)?,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// Returns the rules in evaluation order.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Rewrites the accounts of records matching a rule. Records without a
+    /// matching rule keep the accounts assigned by the importer. A rule is
+    /// skipped for a record if it would make both sides the same account.
+    pub fn apply(&self, records: Vec<Record>) -> Vec<Record> {
+        records.into_iter().map(|rec| self.apply_one(rec)).collect()
+    }
+
+    /// Applies the first matching rule to a single record. See [`RuleSet::apply`].
+    pub fn apply_one(&self, mut rec: Record) -> Record {
+        if let Some(rule) = self.rules.iter().find(|r| r.matches(&rec)) {
+            let debit = rule
+                .debit_account
+                .clone()
+                .unwrap_or_else(|| rec.debit_account.clone());
+            let credit = rule
+                .credit_account
+                .clone()
+                .unwrap_or_else(|| rec.credit_account.clone());
+            if debit != credit {
+                rec.debit_account = debit;
+                rec.credit_account = credit;
+            }
+        }
+        rec
+    }
+}
@@ -7,3 +7,5 @@ pub mod cloud_adapters;
 pub mod core;
 pub mod import;
 pub mod script;
+#[cfg(feature = "tui")]
+pub mod tui;
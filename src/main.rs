@@ -1,20 +1,26 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 use chrono::{Local, TimeZone, Utc};
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use feed_my_ledger::cloud_adapters::{
-    CloudSpreadsheetService, FileAdapter, RetryingService, SpreadsheetError,
-    google_sheets4::GoogleSheets4Adapter,
+    BinAdapter, CloudSpreadsheetService, DedupOnAppend, FileAdapter, RetryingService,
+    SpreadsheetError,
+    auth::{ServiceAccountTokenProvider, TokenFiles},
+    google_sheets4::{self, GoogleSheets4Adapter},
 };
 use feed_my_ledger::core::{
-    Account, Budget, BudgetBook, Ledger, Period, Posting, PriceDatabase, Query, Record,
-    utils::generate_signature, verify_sheet,
+    Account, AccountParseError, AliasMap, Attachment, Budget, BudgetBook, BusinessDayAdjust,
+    Ledger, MergeStrategy, MissingRate, Period, Posting, PriceDatabase, Query, Record,
+    RecordTemplate, ScheduleEntry, Scheduler, format_amount, repair_sheet, resolve_currency,
+    rotate_signature, row_cleared, row_schema_version, row_splits, row_tags, snapshot,
+    utils::generate_signature, verify_sheet_from, verify_sheet_full,
 };
 use feed_my_ledger::import;
-use feed_my_ledger::import::dedup::filter_new_records;
+use feed_my_ledger::import::csv::presets::Preset;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::str::FromStr;
 use std::time::Duration;
 use tracing::{debug, info};
@@ -24,8 +30,14 @@ use yup_oauth2::{self, InstalledFlowAuthenticator, InstalledFlowReturnMethod};
 #[derive(Serialize, Deserialize, Default)]
 struct GoogleSheetsConfig {
     credentials_path: String,
+    /// Either `oauth` (default) or `service_account`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    credentials_type: Option<String>,
     spreadsheet_id: Option<String>,
     sheet_name: Option<String>,
+    /// Columns holding the ledger, such as `A:Q`, when others hold user data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    columns: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
@@ -44,23 +56,81 @@ struct ScheduleConfig {
     credit: String,
     amount: f64,
     currency: String,
+    /// `previous`, `next` or `nearest` to move weekend dates to a weekday.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    adjust: Option<BusinessDayAdjust>,
+}
+
+/// Credentials for downloading statements from a bank endpoint.
+#[derive(Serialize, Deserialize, Default)]
+struct BankConfig {
+    username: Option<String>,
+    password: Option<String>,
+    token: Option<String>,
+}
+
+/// One entry of the `[[ledgers]]` array.
+#[derive(Serialize, Deserialize, Default)]
+struct LedgerConfig {
+    /// Unique, non-empty name; also used for the ledger's row signature.
+    name: String,
+    spreadsheet_id: Option<String>,
+    /// Overrides `google_sheets.sheet_name` for this ledger.
+    sheet_name: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct Config {
-    /// The unique, non-empty name of this ledger instance (required).
+    /// The unique, non-empty name of this ledger instance. Required unless
+    /// `[[ledgers]]` entries are given.
+    #[serde(default)]
     name: String,
     /// Optional password for row signature generation (never logged).
     password: Option<String>,
     google_sheets: GoogleSheetsConfig,
+    /// OAuth token cache. Relative paths resolve against the config file's
+    /// directory; defaults to `tokens-<ledger>.json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token_path: Option<String>,
+    /// Price database used for currency conversion. Relative paths resolve
+    /// against the config file's directory; defaults to `prices-<ledger>.csv`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prices_path: Option<String>,
+    /// Currency reports are converted to when `--currency` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reporting_currency: Option<String>,
+    /// Store reconciliation status on the record rows instead of appending
+    /// `status` rows. Backends that cannot update rows keep appending.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    in_place_status: bool,
+    /// Named ledgers selectable with `--ledger`. When empty, `name` and
+    /// `google_sheets.spreadsheet_id` describe the only ledger.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ledgers: Vec<LedgerConfig>,
     #[serde(default)]
     budgets: Vec<BudgetConfig>,
     #[serde(default)]
     schedules: Vec<ScheduleConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bank: Option<BankConfig>,
+    /// Account renames applied by reports, e.g.
+    /// `"expenses:food" = "Expenses:Groceries"`. Subaccounts are renamed too;
+    /// stored records keep their original accounts.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    aliases: BTreeMap<String, String>,
+    /// Currency `add` uses for an account when `--currency` is not given,
+    /// e.g. `"assets:bank" = "EUR"`. Subaccounts share the default.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    account_currencies: BTreeMap<String, String>,
 }
 
-#[derive(Args, Debug, Default)]
+#[derive(Args, Debug, Default, Clone)]
 struct CsvMapArgs {
+    #[arg(
+        long,
+        help = "Column layout of a known export: paypal, applecard or gpay"
+    )]
+    preset: Option<Preset>,
     #[arg(long, help = "Column name for the description field")]
     map_description: Option<String>,
     #[arg(long, help = "Column name for the debit account field")]
@@ -69,6 +139,18 @@ struct CsvMapArgs {
     map_credit: Option<String>,
     #[arg(long, help = "Column name for the amount field")]
     map_amount: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "map_amount",
+        help = "Column name for amounts posted as mapped"
+    )]
+    map_debit_amount: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "map_amount",
+        help = "Column name for amounts that swap the debit and credit accounts"
+    )]
+    map_credit_amount: Option<String>,
     #[arg(long, help = "Column name for the currency field")]
     map_currency: Option<String>,
 }
@@ -78,30 +160,37 @@ struct CliPosting {
     debit: String,
     credit: String,
     amount: f64,
+    #[serde(default)]
+    currency: Option<String>,
+    #[serde(default)]
+    memo: Option<String>,
 }
 
 impl CsvMapArgs {
+    /// The preset's mapping, or the default one, with the given columns
+    /// replaced. `None` when no preset and no column was given.
     fn into_mapping(self) -> Option<import::csv::CsvMapping> {
-        if self.map_description.is_none()
+        if self.preset.is_none()
+            && self.map_description.is_none()
             && self.map_debit.is_none()
             && self.map_credit.is_none()
             && self.map_amount.is_none()
+            && self.map_debit_amount.is_none()
+            && self.map_credit_amount.is_none()
             && self.map_currency.is_none()
         {
             return None;
         }
+        let base = self.preset.map(Preset::mapping).unwrap_or_default();
         Some(import::csv::CsvMapping {
-            description: self
-                .map_description
-                .unwrap_or_else(|| "description".to_string()),
-            debit_account: self
-                .map_debit
-                .unwrap_or_else(|| "debit_account".to_string()),
-            credit_account: self
-                .map_credit
-                .unwrap_or_else(|| "credit_account".to_string()),
-            amount: self.map_amount.unwrap_or_else(|| "amount".to_string()),
-            currency: self.map_currency.unwrap_or_else(|| "currency".to_string()),
+            description: self.map_description.unwrap_or(base.description),
+            debit_account: self.map_debit.unwrap_or(base.debit_account),
+            credit_account: self.map_credit.unwrap_or(base.credit_account),
+            amount: self.map_amount.unwrap_or(base.amount),
+            currency: self.map_currency.unwrap_or(base.currency),
+            debit_amount: self.map_debit_amount.or(base.debit_amount),
+            credit_amount: self.map_credit_amount.or(base.credit_amount),
+            statement_account: base.statement_account,
         })
     }
 }
@@ -113,13 +202,56 @@ struct Cli {
     /// instead of a cloud service.
     #[arg(long)]
     local_dir: Option<PathBuf>,
+    /// Do not write a header row to new CSV sheets, for tools that expect
+    /// data from the first line
+    #[arg(long)]
+    no_header: bool,
+    /// Directory for local binary storage. When set, the CLI uses BinAdapter,
+    /// which loads large ledgers faster than CSV but is not human-readable.
+    #[arg(long, conflicts_with = "local_dir")]
+    bin_dir: Option<PathBuf>,
     /// Number of rows to append per request
     #[arg(long, default_value_t = 100)]
     batch_size: usize,
+    /// Output format for `list`, `register`, `balance`, `budget report` and
+    /// `verify`
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+    /// Name of the `[[ledgers]]` entry to use; defaults to the first one
+    #[arg(long, global = true)]
+    ledger: Option<String>,
+    /// Configuration file. Relative token and price paths resolve against
+    /// its directory.
+    #[arg(long, global = true, default_value = "config.toml")]
+    config: PathBuf,
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum OutputFormat {
+    /// Plain text for reading in a terminal
+    #[default]
+    Human,
+    /// One JSON document on stdout
+    Json,
+}
+
+/// A register line: the record plus the running balance when the register
+/// is limited to one account.
+#[derive(Serialize)]
+struct RegisterEntry<'a> {
+    #[serde(flatten)]
+    record: &'a Record,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance: Option<f64>,
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), serde_json::Error> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
 #[derive(Subcommand, Debug)]
 enum BudgetCommands {
     Add {
@@ -139,6 +271,10 @@ enum BudgetCommands {
         year: i32,
         #[arg(long)]
         month: Option<u32>,
+        /// Currency the remaining amount is converted to, at the rate
+        /// effective at the end of the period; defaults to `reporting_currency`
+        #[arg(long)]
+        currency: Option<String>,
     },
 }
 
@@ -157,6 +293,30 @@ enum ScheduleCommands {
         amount: f64,
         #[arg(long)]
         currency: String,
+        /// Move dates falling on a weekend: `previous`, `next` or `nearest`
+        #[arg(long)]
+        adjust: Option<BusinessDayAdjust>,
+    },
+    /// Show the records the configured schedules would generate
+    Preview {
+        /// First day of the range (YYYY-MM-DD)
+        #[arg(long)]
+        from: chrono::NaiveDate,
+        /// Last day of the range, inclusive (YYYY-MM-DD)
+        #[arg(long)]
+        to: chrono::NaiveDate,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenCommands {
+    /// Show the ledgers with a saved login and when its token expires
+    List,
+    /// Remove the saved login of one ledger
+    Logout {
+        /// Ledger name, as shown by `tokens list`
+        #[arg(long)]
+        user: String,
     },
 }
 
@@ -166,8 +326,12 @@ enum Commands {
     Budget(BudgetCommands),
     #[command(subcommand)]
     Schedule(ScheduleCommands),
+    #[command(subcommand)]
+    Tokens(TokenCommands),
     /// Perform OAuth login and store credentials
     Login,
+    /// Remove the saved logins of every configured ledger
+    Logout,
     /// Add a new record to the ledger
     Add {
         #[arg(long)]
@@ -178,8 +342,10 @@ enum Commands {
         credit: String,
         #[arg(long)]
         amount: f64,
+        /// Defaults to the `account_currencies` entry of the debit account,
+        /// then of the credit account
         #[arg(long)]
-        currency: String,
+        currency: Option<String>,
         #[arg(long, help = "JSON array of additional postings")]
         splits: Option<String>,
     },
@@ -189,6 +355,10 @@ enum Commands {
     Register {
         #[arg(long)]
         query: Option<String>,
+        /// Currency used for the running balance column; defaults to
+        /// `reporting_currency`, then USD
+        #[arg(long)]
+        currency: Option<String>,
     },
     /// Apply an adjustment referencing an existing record
     Adjust {
@@ -214,14 +384,61 @@ enum Commands {
     },
     /// Import transactions from a file
     Import {
-        #[arg(long)]
-        file: PathBuf,
+        /// Statement to import; repeat to import several files
+        #[arg(long, required = true)]
+        file: Vec<PathBuf>,
         #[arg(long)]
         format: Option<String>,
         #[arg(long)]
         currency: Option<String>,
+        /// Date format of QIF, OFX and fixed-width files. Repeat it to try
+        /// several formats in order on QIF files; other formats use the first
+        #[arg(long)]
+        date_format: Vec<String>,
+        /// Rules file (TOML or JSON) assigning accounts by description
+        #[arg(long)]
+        rules: Option<PathBuf>,
+        /// Column layout (TOML) for `--format fixed`
+        #[arg(long)]
+        fixed_spec: Option<PathBuf>,
+        /// `debit-positive` (default) or `credit-positive` for QIF and OFX files
+        #[arg(long, default_value = "debit-positive")]
+        sign_convention: import::SignConvention,
+        /// Treat `,` as the decimal mark in CSV amounts, e.g. `€4,20`
+        #[arg(long)]
+        decimal_comma: bool,
+        /// Print what would be imported without writing any rows
+        #[arg(long)]
+        dry_run: bool,
+        /// Check every row of CSV and JSON files first; report all bad rows
+        /// and import nothing if any are found
+        #[arg(long)]
+        validate: bool,
+        #[command(flatten)]
+        mapping: CsvMapArgs,
+    },
+    /// Show the columns of a CSV file, the mapping guessed from its header
+    /// and its first rows parsed with it, without importing anything
+    ImportPreview {
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long)]
+        currency: Option<String>,
+        /// Treat `,` as the decimal mark in amounts
+        #[arg(long)]
+        decimal_comma: bool,
+        /// Number of rows to parse
+        #[arg(long, default_value_t = 5)]
+        rows: usize,
+    },
+    /// Write an empty CSV file with the columns `import` expects, to fill in
+    /// and import
+    ImportTemplate {
         #[arg(long)]
-        date_format: Option<String>,
+        file: PathBuf,
+        /// Add an example row
+        #[arg(long)]
+        example: bool,
         #[command(flatten)]
         mapping: CsvMapArgs,
     },
@@ -237,6 +454,15 @@ enum Commands {
     Download {
         #[arg(long)]
         url: String,
+        /// Username for HTTP Basic authentication (overrides `[bank]` in config)
+        #[arg(long)]
+        username: Option<String>,
+        /// Password for HTTP Basic authentication (overrides `[bank]` in config)
+        #[arg(long)]
+        password: Option<String>,
+        /// Bearer token (overrides `[bank]` in config)
+        #[arg(long)]
+        token: Option<String>,
     },
     /// Display the balance for an account
     Balance {
@@ -244,6 +470,56 @@ enum Commands {
         account: String,
         #[arg(long)]
         query: Option<String>,
+        /// Only count records up to the end of this date (YYYY-MM-DD)
+        #[arg(long)]
+        as_of: Option<chrono::NaiveDate>,
+        /// Currency the balance, including sub-accounts, is converted to;
+        /// defaults to `reporting_currency`. As-of balances use the rate
+        /// effective on the as-of date
+        #[arg(long)]
+        currency: Option<String>,
+    },
+    /// Check that an account had the given balance at the end of a date
+    Assert {
+        #[arg(long)]
+        account: String,
+        /// Date of the assertion (YYYY-MM-DD)
+        #[arg(long)]
+        date: chrono::NaiveDate,
+        #[arg(long)]
+        amount: f64,
+        #[arg(long)]
+        currency: String,
+    },
+    /// Set the starting balance of an account against opening-balance equity
+    OpeningBalance {
+        #[arg(long)]
+        account: String,
+        /// Positive for assets, negative for liabilities
+        #[arg(long, allow_hyphen_values = true)]
+        amount: f64,
+        #[arg(long)]
+        currency: String,
+        /// Date the balance applies from (YYYY-MM-DD)
+        #[arg(long)]
+        date: chrono::NaiveDate,
+    },
+    /// Display the net balance of every account
+    TrialBalance {
+        /// Currency the balances are converted to; defaults to
+        /// `reporting_currency`, then USD
+        #[arg(long)]
+        currency: Option<String>,
+        /// Roll sub-accounts up into their ancestors at this depth
+        #[arg(long)]
+        depth: Option<usize>,
+    },
+    /// Display the amount moved by the records carrying each tag
+    TagReport {
+        /// Currency the totals are converted to; defaults to
+        /// `reporting_currency`, then USD
+        #[arg(long)]
+        currency: Option<String>,
     },
     /// Import price data from a CSV file
     ImportPrices {
@@ -252,6 +528,11 @@ enum Commands {
     },
     /// List loaded prices
     ListPrices,
+    /// Check the whole configuration file and report every problem found
+    ConfigCheck,
+    /// Check records for adjustments of missing records, postings that do
+    /// not balance and unknown currencies
+    Check,
     /// Switch active sheet using a link or ID
     Switch {
         #[arg(long)]
@@ -264,51 +545,519 @@ enum Commands {
         #[arg(long)]
         format: Option<String>,
     },
+    /// List uncleared records older than a number of days, oldest first
+    Stale {
+        /// Age in days beyond which an uncleared record is listed
+        #[arg(long, default_value_t = 30)]
+        days: i64,
+        /// Date ages are counted from; defaults to today
+        #[arg(long)]
+        as_of: Option<chrono::NaiveDate>,
+    },
     /// Execute a Rhai script against the current ledger
     RunScript {
         #[arg(long)]
         file: PathBuf,
     },
+    /// Print a record with its adjustments and reversal status
+    Show {
+        #[arg(long)]
+        id: String,
+    },
+    /// Print the receipt linked from a record, or save a copy of it
+    Attachment {
+        #[arg(long)]
+        id: String,
+        /// Where to save the file; downloading needs the `bank-api` feature
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Append reversing entries for the most recently added records
+    Undo {
+        /// Number of records to reverse, newest first
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+    },
+    #[cfg(feature = "tui")]
+    /// Browse and filter the register interactively
+    Tui {
+        /// Currency used for the running balance column; defaults to
+        /// `reporting_currency`, then USD
+        #[arg(long)]
+        currency: Option<String>,
+    },
     /// Verify stored rows against their hashes
-    Verify,
+    Verify {
+        /// Only verify rows from this zero-based index on, e.g. the row count
+        /// printed by the previous run
+        #[arg(long, default_value_t = 0)]
+        from: usize,
+        /// Also check that every row parses into a valid record; checks the
+        /// whole sheet
+        #[arg(long, conflicts_with = "from")]
+        strict: bool,
+    },
+    /// Re-hash rows that were corrected by hand so they verify again
+    Repair {
+        /// Zero-based row indices as reported by `verify`, e.g. `3,7`
+        #[arg(long, required = true, value_delimiter = ',')]
+        rows: Vec<usize>,
+        /// Skip the confirmation prompt
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Re-hash every row after the ledger name or password changed
+    RotateSignature {
+        /// Ledger name the rows were signed with; defaults to the current one
+        #[arg(long)]
+        old_name: Option<String>,
+        /// Password the rows were signed with; defaults to the current one.
+        /// Pass an empty value if there was none
+        #[arg(long)]
+        old_password: Option<String>,
+        /// Skip the confirmation prompt
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Append records from another sheet that are not already in this one
+    Merge {
+        /// Sheet link or id to merge from
+        #[arg(long)]
+        from: String,
+    },
+    /// List records only in this sheet, only in another one, or changed
+    /// between them
+    Diff {
+        /// Sheet link or id to compare with
+        #[arg(long)]
+        other: String,
+    },
+    /// Write every record, reconciliation status and price to a JSON file
+    Snapshot {
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Write a snapshot into a fresh sheet
+    Restore {
+        /// Snapshot file written by `snapshot`
+        #[arg(long = "in")]
+        input: PathBuf,
+        /// Where to create the sheet: `csv:<dir>`, `bin:<dir>` or `google`
+        #[arg(long)]
+        to: RestoreTarget,
+        /// Write the snapshot's prices to this CSV file
+        #[arg(long)]
+        prices: Option<PathBuf>,
+    },
+}
+
+/// Backend a snapshot is restored into.
+#[derive(Clone, Debug)]
+enum RestoreTarget {
+    /// CSV files in an empty directory.
+    Csv(PathBuf),
+    /// Binary logs in an empty directory.
+    Bin(PathBuf),
+    /// A new spreadsheet using the configured Google credentials.
+    Google,
+}
+
+impl FromStr for RestoreTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("csv", dir)) if !dir.is_empty() => Ok(RestoreTarget::Csv(dir.into())),
+            Some(("bin", dir)) if !dir.is_empty() => Ok(RestoreTarget::Bin(dir.into())),
+            None if s == "google" => Ok(RestoreTarget::Google),
+            _ => Err(format!(
+                "invalid restore target '{s}'; expected csv:<dir>, bin:<dir> or google"
+            )),
+        }
+    }
+}
+
+/// Creates `dir` for a restore, refusing one that already holds files so
+/// existing sheets are never overwritten.
+fn empty_restore_dir(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+        return Err(format!("restore target {} is not empty", dir.display()).into());
+    }
+    fs::create_dir_all(dir)?;
+    Ok(())
 }
 
+/// Failure of a CLI command. Each kind exits with its own status code so
+/// scripts can tell them apart.
 #[derive(Debug)]
 enum CliError {
     MissingConfig,
     InvalidConfig(String),
     MissingCredentials,
+    /// Signing in to the spreadsheet backend failed.
+    Auth(String),
+    Import(import::ImportError),
+    Spreadsheet(SpreadsheetError),
+    /// `verify` found this many rows whose hash does not match.
+    Tampered(usize),
+    Other(Box<dyn std::error::Error>),
+}
+
+impl CliError {
+    /// Process exit status: 2 for configuration, 3 for authentication, 4 for
+    /// detected tampering, 5 for imports, 6 for the spreadsheet backend and 1
+    /// for anything else.
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::MissingConfig | CliError::InvalidConfig(_) | CliError::MissingCredentials => {
+                2
+            }
+            CliError::Auth(_) => 3,
+            CliError::Tampered(_) => 4,
+            CliError::Import(_) => 5,
+            CliError::Spreadsheet(_) => 6,
+            CliError::Other(_) => 1,
+        }
+    }
 }
 
 impl std::fmt::Display for CliError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CliError::MissingConfig => write!(f, "config.toml file not found"),
+            CliError::MissingConfig => write!(f, "config file not found"),
             CliError::InvalidConfig(msg) => write!(f, "invalid configuration: {msg}"),
             CliError::MissingCredentials => write!(f, "credentials json file was not found"),
+            CliError::Auth(msg) => write!(f, "authentication failed: {msg}"),
+            CliError::Import(e) => write!(f, "import failed: {e}"),
+            CliError::Spreadsheet(e) => write!(f, "spreadsheet error: {e}"),
+            CliError::Tampered(n) => write!(f, "tampering detected in {n} rows"),
+            CliError::Other(e) => write!(f, "{e}"),
         }
     }
 }
 
 impl std::error::Error for CliError {}
 
+impl From<Box<dyn std::error::Error>> for CliError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let e = match e.downcast::<CliError>() {
+            Ok(e) => return *e,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<import::ImportError>() {
+            Ok(e) => return CliError::Import(*e),
+            Err(e) => e,
+        };
+        match e.downcast::<SpreadsheetError>() {
+            Ok(e) => CliError::Spreadsheet(*e),
+            Err(e) => CliError::Other(e),
+        }
+    }
+}
+
+/// CSV storage in `dir`, writing a header row to new sheets unless
+/// `no_header` is set.
+fn file_adapter(dir: &Path, no_header: bool) -> FileAdapter {
+    let adapter = FileAdapter::new(dir);
+    if no_header {
+        adapter.without_header()
+    } else {
+        adapter
+    }
+}
+
+/// Converts one `[[schedules]]` config entry into a scheduler entry.
+fn schedule_entry(s: &ScheduleConfig) -> Result<ScheduleEntry, AccountParseError> {
+    Ok(ScheduleEntry {
+        cron: s.cron.clone(),
+        template: RecordTemplate {
+            description: s.description.clone(),
+            debit: s.debit.parse()?,
+            credit: s.credit.parse()?,
+            amount: s.amount,
+            currency: s.currency.clone(),
+        },
+        adjust: s.adjust.unwrap_or_default(),
+    })
+}
+
+/// Builds a scheduler from the `[[schedules]]` entries of the config.
+fn scheduler_from_config(schedules: &[ScheduleConfig]) -> Result<Scheduler, String> {
+    let entries = schedules
+        .iter()
+        .enumerate()
+        .map(|(index, s)| schedule_entry(s).map_err(|e| format!("schedule {index}: {e}")))
+        .collect::<Result<_, _>>()?;
+    Ok(Scheduler { entries })
+}
+
 fn load_config(path: &PathBuf) -> Result<Config, CliError> {
     let data = fs::read_to_string(path).map_err(|_| CliError::MissingConfig)?;
-    let cfg: Config = toml::from_str(&data).map_err(|e| CliError::InvalidConfig(e.to_string()))?;
+    parse_config(&data)
+}
+
+fn parse_config(data: &str) -> Result<Config, CliError> {
+    let cfg: Config = toml::from_str(data).map_err(|e| CliError::InvalidConfig(e.to_string()))?;
+    match required_fields_problems(&cfg).into_iter().next() {
+        Some(problem) => Err(CliError::InvalidConfig(problem)),
+        // Never log or expose the password field
+        None => Ok(cfg),
+    }
+}
+
+/// Problems with the fields every command needs.
+fn required_fields_problems(cfg: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
     // Validate 'name' field: must be present and non-empty
-    if cfg.name.trim().is_empty() {
-        return Err(CliError::InvalidConfig(
-            "'name' field is missing or empty in config.toml".to_string(),
-        ));
+    if cfg.ledgers.is_empty() && cfg.name.trim().is_empty() {
+        problems.push("'name' field is missing or empty in config.toml".to_string());
+    }
+    // Ledger names feed the row signature, so they must tell ledgers apart
+    let mut names = HashSet::new();
+    for ledger in &cfg.ledgers {
+        if ledger.name.trim().is_empty() {
+            problems.push("ledgers.name is missing or empty".to_string());
+        } else if !names.insert(ledger.name.as_str()) {
+            problems.push(format!("duplicate ledger name '{}'", ledger.name));
+        }
     }
-    // Optionally: enforce uniqueness of 'name' if multiple ledgers are supported (not implemented here)
     if cfg.google_sheets.credentials_path.is_empty() {
-        return Err(CliError::InvalidConfig(
-            "google_sheets.credentials_path is missing".to_string(),
+        problems.push("google_sheets.credentials_path is missing".to_string());
+    }
+    if let Some(columns) = &cfg.google_sheets.columns
+        && parse_columns(columns).is_none()
+    {
+        problems.push(format!(
+            "google_sheets.columns must be a column range such as 'A:Q', got '{columns}'"
         ));
     }
-    // Never log or expose the password field
-    Ok(cfg)
+    problems
+}
+
+/// Splits a column range such as `A:Q` into its first and last column.
+fn parse_columns(range: &str) -> Option<(&str, &str)> {
+    let (first, last) = range.trim().split_once(':')?;
+    let is_column = |c: &str| !c.is_empty() && c.chars().all(|ch| ch.is_ascii_alphabetic());
+    (is_column(first) && is_column(last)).then_some((first, last))
+}
+
+/// Every problem found in the config text, including budgets and schedules
+/// that would only fail once a command uses them. Entries of the `budgets`
+/// and `schedules` arrays are named by index and, when written as `[[...]]`
+/// tables, by the line of their header.
+fn check_config(data: &str) -> Vec<String> {
+    let cfg: Config = match toml::from_str(data) {
+        Ok(cfg) => cfg,
+        Err(e) => return vec![e.to_string().trim_end().to_string()],
+    };
+    let mut problems = required_fields_problems(&cfg);
+    if let Some(code) = &cfg.reporting_currency {
+        problems.extend(currency_problem(code).map(|p| format!("reporting_currency: {p}")));
+    }
+    let entry = |table: &str, index: usize| {
+        let header = format!("[[{table}]]");
+        match data
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.trim() == header)
+            .nth(index)
+        {
+            Some((line, _)) => format!("{table}[{index}] (line {})", line + 1),
+            None => format!("{table}[{index}]"),
+        }
+    };
+    let account = |field: &str, value: &str| {
+        value
+            .parse::<Account>()
+            .err()
+            .map(|e| format!("{field} '{value}': {e}"))
+    };
+    for (from, to) in &cfg.aliases {
+        problems.extend(
+            [account("alias", from), account("alias target", to)]
+                .into_iter()
+                .flatten()
+                .map(|p| format!("aliases: {p}")),
+        );
+    }
+    for (name, code) in &cfg.account_currencies {
+        problems.extend(
+            [account("account", name), currency_problem(code)]
+                .into_iter()
+                .flatten()
+                .map(|p| format!("account_currencies: {p}")),
+        );
+    }
+    for (index, b) in cfg.budgets.iter().enumerate() {
+        let issues = [
+            account("account", &b.account),
+            currency_problem(&b.currency),
+            (!matches!(b.period.to_lowercase().as_str(), "monthly" | "yearly"))
+                .then(|| format!("period '{}' is not monthly or yearly", b.period)),
+        ];
+        let at = entry("budgets", index);
+        problems.extend(issues.into_iter().flatten().map(|p| format!("{at}: {p}")));
+    }
+    for (index, s) in cfg.schedules.iter().enumerate() {
+        let issues = [
+            cron::Schedule::from_str(&s.cron)
+                .err()
+                .map(|e| format!("invalid cron '{}': {e}", s.cron)),
+            account("debit", &s.debit),
+            account("credit", &s.credit),
+            currency_problem(&s.currency),
+        ];
+        let at = entry("schedules", index);
+        problems.extend(issues.into_iter().flatten().map(|p| format!("{at}: {p}")));
+    }
+    problems
+}
+
+/// Describes `code` if it is not a known ISO 4217 currency.
+fn currency_problem(code: &str) -> Option<String> {
+    iso_currency::Currency::from_code(code)
+        .is_none()
+        .then(|| format!("unknown currency '{code}'"))
+}
+
+impl Config {
+    /// Currency a report is converted to: `currency` when given, otherwise
+    /// the configured `reporting_currency`.
+    fn report_currency(&self, currency: Option<String>) -> Option<String> {
+        currency.or_else(|| self.reporting_currency.clone())
+    }
+
+    /// The configured `aliases`, failing on names that are not accounts.
+    fn alias_map(&self) -> Result<AliasMap, CliError> {
+        let parse = |name: &str| {
+            name.parse::<Account>()
+                .map_err(|e| CliError::InvalidConfig(format!("alias '{name}': {e}")))
+        };
+        let mut map = AliasMap::default();
+        for (from, to) in &self.aliases {
+            map.insert(parse(from)?, parse(to)?);
+        }
+        Ok(map)
+    }
+
+    /// The configured `account_currencies`, failing on names that are not
+    /// accounts.
+    fn account_currencies(&self) -> Result<Vec<(Account, String)>, CliError> {
+        self.account_currencies
+            .iter()
+            .map(|(name, currency)| {
+                let account = name.parse::<Account>().map_err(|e| {
+                    CliError::InvalidConfig(format!("account_currencies '{name}': {e}"))
+                })?;
+                Ok((account, currency.clone()))
+            })
+            .collect()
+    }
+
+    /// Position in `ledgers` of the ledger named `name`, or of the first one
+    /// when no name is given. `None` means the single top-level ledger.
+    fn ledger_index(&self, name: Option<&str>) -> Result<Option<usize>, CliError> {
+        match name {
+            None if self.ledgers.is_empty() => Ok(None),
+            None => Ok(Some(0)),
+            Some(name) if self.ledgers.is_empty() && name == self.name => Ok(None),
+            Some(name) => self
+                .ledgers
+                .iter()
+                .position(|l| l.name == name)
+                .map(Some)
+                .ok_or_else(|| CliError::InvalidConfig(format!("no ledger named '{name}'"))),
+        }
+    }
+
+    fn ledger_name(&self, index: Option<usize>) -> &str {
+        index.map_or(&self.name, |i| &self.ledgers[i].name)
+    }
+
+    fn sheet_name(&self, index: Option<usize>) -> Option<&str> {
+        index
+            .and_then(|i| self.ledgers[i].sheet_name.as_deref())
+            .or(self.google_sheets.sheet_name.as_deref())
+    }
+
+    fn token_path(&self, index: Option<usize>, config_path: &Path) -> PathBuf {
+        ledger_file(
+            config_path,
+            self.token_path.as_deref(),
+            self.ledger_name(index),
+            "tokens.json",
+        )
+    }
+
+    fn prices_path(&self, index: Option<usize>, config_path: &Path) -> PathBuf {
+        ledger_file(
+            config_path,
+            self.prices_path.as_deref(),
+            self.ledger_name(index),
+            "prices.csv",
+        )
+    }
+
+    fn spreadsheet_id_mut(&mut self, index: Option<usize>) -> &mut Option<String> {
+        match index {
+            Some(i) => &mut self.ledgers[i].spreadsheet_id,
+            None => &mut self.google_sheets.spreadsheet_id,
+        }
+    }
+}
+
+/// Resolves `path` against the directory holding the config file. Absolute
+/// paths are returned unchanged.
+fn config_relative(config_path: &Path, path: impl AsRef<Path>) -> PathBuf {
+    let dir = config_path.parent().unwrap_or(Path::new(""));
+    dir.join(path)
+}
+
+/// Token files of every configured ledger, keyed by ledger name.
+fn token_files(cfg: &Config, config_path: &Path) -> TokenFiles {
+    let indices: Vec<Option<usize>> = if cfg.ledgers.is_empty() {
+        vec![None]
+    } else {
+        (0..cfg.ledgers.len()).map(Some).collect()
+    };
+    TokenFiles::new(indices.into_iter().map(|index| {
+        (
+            cfg.ledger_name(index).to_string(),
+            cfg.token_path(index, config_path),
+        )
+    }))
+}
+
+/// Path of a per-ledger file such as the token cache. A configured path wins.
+/// Otherwise the ledger name is added to `shared`, e.g. `prices-home.csv`,
+/// unless only the `shared` file from older versions exists.
+fn ledger_file(
+    config_path: &Path,
+    configured: Option<&str>,
+    ledger: &str,
+    shared: &str,
+) -> PathBuf {
+    if let Some(path) = configured {
+        return config_relative(config_path, path);
+    }
+    let slug: String = ledger
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let (stem, ext) = shared.rsplit_once('.').unwrap_or((shared, ""));
+    let derived = config_relative(config_path, format!("{stem}-{slug}.{ext}"));
+    let legacy = config_relative(config_path, shared);
+    if !derived.exists() && legacy.exists() {
+        legacy
+    } else {
+        derived
+    }
 }
 
 fn save_config(path: &PathBuf, cfg: &Config) {
@@ -327,20 +1076,65 @@ fn parse_sheet_id(input: &str) -> String {
     }
 }
 
+/// Commits a record loaded from the sheet, skipping rows whose id was
+/// already seen so balances are not double counted.
+fn commit_or_warn(ledger: &mut Ledger, rec: Record) {
+    if let Err(e) = ledger.commit_checked(rec) {
+        eprintln!("warning: {e}; skipping duplicate row");
+    }
+}
+
+/// Rebuilds the ledger from sheet rows. Status rows written by `reconcile`
+/// set the cleared flag of the record they name; the latest one wins. A
+/// status stored on the record row itself takes precedence. The statuses are
+/// returned as well, keyed by record id.
+fn load_ledger_with_status(rows: &[Vec<String>]) -> (Ledger, HashMap<Uuid, bool>) {
+    let mut statuses: HashMap<Uuid, bool> =
+        rows.iter().filter_map(|row| status_from_row(row)).collect();
+    for row in rows {
+        if let (Some(cleared), Some(id)) = (
+            row_cleared(row),
+            row.first().and_then(|id| Uuid::parse_str(id).ok()),
+        ) {
+            statuses.insert(id, cleared);
+        }
+    }
+    let mut ledger = Ledger::default();
+    for row in rows {
+        if let Some(mut rec) = record_from_row(row) {
+            rec.cleared = statuses.get(&rec.id).copied().unwrap_or(false);
+            commit_or_warn(&mut ledger, rec);
+        }
+    }
+    (ledger, statuses)
+}
+
+fn ledger_from_rows(rows: &[Vec<String>]) -> Ledger {
+    load_ledger_with_status(rows).0
+}
+
 fn record_from_row(row: &[String]) -> Option<Record> {
     if row.len() < 10 || row.first().map(|s| s.as_str()) == Some("status") {
         return None;
     }
 
     let amount = row[5].parse::<f64>().ok()?;
-    let splits_col = if row.len() > 10 { &row[10] } else { "" };
-    let tx_date_str = if row.len() > 12 { &row[12] } else { "" };
+    let column = |idx: usize| row.get(idx).map(String::as_str).unwrap_or("");
+    let (tx_desc_str, tx_date_str, metadata_str) = match row_schema_version(row) {
+        1 => ("", column(11), ""),
+        2 => (column(11), column(12), ""),
+        3 | 4 => (column(11), column(12), column(14)),
+        _ => return None,
+    };
     Some(Record {
-        id: Uuid::nil(),
-        timestamp: Utc::now(),
+        id: Uuid::parse_str(&row[0]).ok()?,
+        timestamp: chrono::DateTime::parse_from_rfc3339(&row[1])
+            .ok()?
+            .with_timezone(&Utc),
         description: row[2].clone(),
-        debit_account: row[3].parse().ok()?,
-        credit_account: row[4].parse().ok()?,
+        // Stored names may predate account validation.
+        debit_account: Account::parse_lenient(&row[3]),
+        credit_account: Account::parse_lenient(&row[4]),
         amount,
         currency: row[6].clone(),
         reference_id: if row[7].is_empty() {
@@ -353,26 +1147,27 @@ fn record_from_row(row: &[String]) -> Option<Record> {
         } else {
             Some(row[8].clone())
         },
-        tags: if row[9].is_empty() {
-            Vec::new()
+        tags: row_tags(row),
+        metadata: if metadata_str.is_empty() {
+            Default::default()
         } else {
-            row[9].split(',').map(|s| s.to_string()).collect()
+            serde_json::from_str(metadata_str).ok()?
+        },
+        transaction_description: if tx_desc_str.is_empty() {
+            None
+        } else {
+            Some(tx_desc_str.to_string())
         },
         transaction_date: if tx_date_str.is_empty() {
             None
         } else {
             let naive_date = chrono::NaiveDate::parse_from_str(tx_date_str, "%Y-%m-%d").ok();
             let naive_datetime = naive_date?.and_hms_opt(0, 0, 0).unwrap();
-            let local_datetime = Local.from_local_datetime(&naive_datetime)
-                .single()?;
+            let local_datetime = Local.from_local_datetime(&naive_datetime).single()?;
             Some(local_datetime)
         },
-        cleared: false,
-        splits: if !splits_col.is_empty() {
-            serde_json::from_str(splits_col).ok()?
-        } else {
-            Vec::new()
-        },
+        cleared: row_cleared(row).unwrap_or(false),
+        splits: row_splits(row).ok()?,
     })
 }
 
@@ -388,71 +1183,169 @@ fn status_from_row(row: &[String]) -> Option<(Uuid, bool)> {
 
 async fn adapter_from_config(
     cfg: &GoogleSheetsConfig,
+    sheet_name: Option<&str>,
+    token_path: &Path,
+    handle: tokio::runtime::Handle,
 ) -> Result<GoogleSheets4Adapter, Box<dyn std::error::Error>> {
     if !std::path::Path::new(&cfg.credentials_path).exists() {
         return Err(Box::new(CliError::MissingCredentials));
     }
-    let secret = yup_oauth2::read_application_secret(&cfg.credentials_path)
-        .await
-        .map_err(|e| {
-            Box::new(std::io::Error::other(e.to_string())) as Box<dyn std::error::Error>
-        })?;
-    let auth = InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::Interactive)
-        .persist_tokens_to_disk("tokens.json")
-        .build()
-        .await?;
-
-    let adapter = match &cfg.sheet_name {
-        Some(name) => GoogleSheets4Adapter::with_sheet_name(auth, name.clone()),
-        None => GoogleSheets4Adapter::new(auth),
+    let sheet_name = sheet_name
+        .unwrap_or(google_sheets4::DEFAULT_SHEET_NAME)
+        .to_string();
+    let adapter = match cfg.credentials_type.as_deref().unwrap_or("oauth") {
+        "service_account" => {
+            let auth = ServiceAccountTokenProvider::from_key_file(&cfg.credentials_path)
+                .await
+                .map_err(|e| CliError::Auth(e.to_string()))?;
+            GoogleSheets4Adapter::with_runtime(
+                handle,
+                auth,
+                google_sheets4::DEFAULT_DRIVE_BASE_URL,
+                google_sheets4::DEFAULT_SHEETS_BASE_URL,
+                sheet_name,
+            )
+        }
+        "oauth" => {
+            let secret = yup_oauth2::read_application_secret(&cfg.credentials_path)
+                .await
+                .map_err(|e| CliError::Auth(e.to_string()))?;
+            let auth =
+                InstalledFlowAuthenticator::builder(secret, InstalledFlowReturnMethod::Interactive)
+                    .persist_tokens_to_disk(token_path)
+                    .build()
+                    .await
+                    .map_err(|e| CliError::Auth(e.to_string()))?;
+            GoogleSheets4Adapter::with_runtime(
+                handle,
+                auth,
+                google_sheets4::DEFAULT_DRIVE_BASE_URL,
+                google_sheets4::DEFAULT_SHEETS_BASE_URL,
+                sheet_name,
+            )
+        }
+        other => {
+            return Err(Box::new(CliError::InvalidConfig(format!(
+                "unsupported google_sheets.credentials_type: {other}"
+            ))));
+        }
+    };
+    let adapter = match cfg.columns.as_deref().and_then(parse_columns) {
+        Some((first, last)) => adapter.with_columns(first, last),
+        None => adapter,
     };
     Ok(adapter)
 }
 
+/// Asks a yes/no question on stdin; anything but `y` or `yes` is a no.
+fn confirm(question: &str) -> std::io::Result<bool> {
+    print!("{question} [y/N] ");
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Parses every row of a CSV or JSON statement and returns the line number
+/// and error of each row that would fail to import.
+fn validate_import(
+    file: &Path,
+    format: Option<&str>,
+    mapping: Option<&import::csv::CsvMapping>,
+    currency: Option<&str>,
+    decimal_comma: bool,
+) -> Result<Vec<(usize, import::ImportError)>, Box<dyn std::error::Error>> {
+    let fmt = format
+        .or_else(|| file.extension().and_then(|s| s.to_str()))
+        .ok_or_else(|| "could not determine file format".to_string())?;
+    let (_, errors) = match fmt.to_lowercase().as_str() {
+        "csv" if mapping.is_none() && import::csv::is_full_export(file)? => {
+            import::csv::parse_full_collect_errors(file)
+        }
+        "csv" => {
+            let decimal = if decimal_comma {
+                import::amount::DecimalSeparator::Comma
+            } else {
+                import::amount::DecimalSeparator::Dot
+            };
+            let default_mapping = import::csv::CsvMapping::default();
+            import::csv::parse_collect_errors_with(
+                file,
+                mapping.unwrap_or(&default_mapping),
+                currency,
+                decimal,
+            )
+        }
+        "json" => import::json::parse_collect_errors(file),
+        "jsonl" | "ndjson" => import::jsonl::parse_collect_errors(file),
+        other => return Err(format!("--validate does not support {other} files").into()),
+    };
+    Ok(errors)
+}
+
+/// Imports a single statement file into `writer`. The format is taken from
+/// `format` or, failing that, the file extension.
 #[allow(clippy::too_many_arguments)]
 fn import_with_progress(
-    adapter: &mut dyn CloudSpreadsheetService,
-    sheet_id: &str,
+    writer: &mut ImportWriter<'_>,
     file: &Path,
-    format: Option<String>,
-    mapping: CsvMapArgs,
-    currency: Option<String>,
-    signature: &str,
-    date_format: Option<String>,
-    batch_size: usize,
+    format: Option<&str>,
+    mapping: Option<&import::csv::CsvMapping>,
+    currency: Option<&str>,
+    date_formats: &[&str],
+    rules: Option<&import::rules::RuleSet>,
+    fixed_spec: Option<&Path>,
+    sign: import::SignConvention,
+    decimal_comma: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let fmt = format
+        .map(str::to_string)
         .or_else(|| {
             file.extension()
                 .and_then(|s| s.to_str())
                 .map(|s| s.to_string())
         })
         .ok_or_else(|| "could not determine file format".to_string())?;
-    let mapping = mapping.into_mapping();
-    let date_fmt = date_format.as_deref();
+    let apply_rules = |rec: Record| match rules {
+        Some(set) => set.apply_one(rec),
+        None => rec,
+    };
+    writer.start_file(file);
 
-    let records = match fmt.to_lowercase().as_str() {
-        "csv" => {
-            if let Some(cur) = currency.as_deref() {
-                if let Some(ref map) = mapping {
-                    import::csv::parse_with_mapping_and_currency(file, map, cur)
-                } else {
-                    import::csv::parse_with_currency(file, cur)
-                }
-            } else if let Some(ref map) = mapping {
-                import::csv::parse_with_mapping(file, map)
-            } else {
-                import::csv::parse(file)
-            }
+    if fmt.eq_ignore_ascii_case("csv") {
+        // CSV exports can be huge, so rows are written as they are parsed.
+        let decimal = if decimal_comma {
+            import::amount::DecimalSeparator::Comma
+        } else {
+            import::amount::DecimalSeparator::Dot
+        };
+        if mapping.is_none() && import::csv::is_full_export(file)? {
+            for rec in import::csv::parse_full(file)? {
+                writer.push(apply_rules(rec))?;
+            }
+            return Ok(());
         }
+        let default_mapping = import::csv::CsvMapping::default();
+        import::csv::CsvImporter::parse_streaming_with_decimal_separator(
+            file,
+            mapping.unwrap_or(&default_mapping),
+            currency,
+            decimal,
+            |rec| -> Result<(), Box<dyn std::error::Error>> {
+                writer.push(apply_rules(rec))?;
+                Ok(())
+            },
+        )?;
+        return Ok(());
+    }
 
+    let records = match fmt.to_lowercase().as_str() {
         "qif" => {
-            let mut recs = if let Some(fmt) = date_fmt {
-                import::qif::parse_with_date_format(file, fmt)?
-            } else {
-                import::qif::parse(file)?
-            };
-            if let Some(cur) = currency.as_deref() {
+            let mut recs = import::qif::parse_with_date_formats_and_sign(file, date_formats, sign)?;
+            if let Some(cur) = currency {
                 for rec in &mut recs {
                     rec.currency = cur.to_string();
                 }
@@ -460,12 +1353,9 @@ fn import_with_progress(
             Ok(recs)
         }
         "ofx" => {
-            let mut recs = if let Some(fmt) = date_fmt {
-                import::ofx::parse_with_date_format(file, fmt)?
-            } else {
-                import::ofx::parse(file)?
-            };
-            if let Some(cur) = currency.as_deref() {
+            let mut recs =
+                import::ofx::parse_with_sign_convention(file, date_formats.first().copied(), sign)?;
+            if let Some(cur) = currency {
                 for rec in &mut recs {
                     rec.currency = cur.to_string();
                 }
@@ -473,87 +1363,411 @@ fn import_with_progress(
             Ok(recs)
         }
 
-        "ledger" => match currency.as_deref() {
+        "ledger" => match currency {
             Some(cur) => import::ledger::parse_with_currency(file, cur),
             None => import::ledger::parse(file),
         },
-        "json" => match currency.as_deref() {
+        "json" => match currency {
             Some(cur) => import::json::parse_with_currency(file, cur),
             None => import::json::parse(file),
         },
+        "jsonl" | "ndjson" => match currency {
+            Some(cur) => import::jsonl::parse_with_currency(file, cur),
+            None => import::jsonl::parse(file),
+        },
+        "beancount" | "bean" => match currency {
+            Some(cur) => import::beancount::parse_with_currency(file, cur),
+            None => import::beancount::parse(file),
+        },
+        "fixed" => {
+            let spec_path = fixed_spec.ok_or("--fixed-spec is required for fixed-width files")?;
+            let mut spec = import::fixed::FixedSpec::load(spec_path)?;
+            if let Some(cur) = currency {
+                spec.currency = Some(cur.to_string());
+            }
+            if let Some(fmt) = date_formats.first() {
+                spec.date_format = Some(fmt.to_string());
+            }
+            import::fixed::parse_with_spec(file, &spec)
+        }
         other => return Err(format!("unsupported format: {other}").into()),
     }?;
+    for rec in records {
+        writer.push(apply_rules(rec))?;
+    }
+    Ok(())
+}
 
-    let rows = filter_new_records(adapter, sheet_id, records, signature)?;
-    append_rows_with_progress(adapter, sheet_id, rows, batch_size)?;
+/// Prints the header of a CSV file, the mapping [`import::csv::suggest_mapping`]
+/// guesses from it as `--map-*` flags, and the first `rows` rows parsed with
+/// that mapping.
+fn import_preview(
+    file: &Path,
+    currency: Option<&str>,
+    decimal_comma: bool,
+    rows: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let headers = import::csv::headers(file)?;
+    println!("Columns: {}", headers.join(", "));
+    let mapping = import::csv::suggest_mapping(&headers);
+    let fields = [
+        ("description", Some(&mapping.description)),
+        ("debit", Some(&mapping.debit_account)),
+        ("credit", Some(&mapping.credit_account)),
+        (
+            "amount",
+            Some(&mapping.amount)
+                .filter(|_| mapping.debit_amount.is_none() && mapping.credit_amount.is_none()),
+        ),
+        ("debit-amount", mapping.debit_amount.as_ref()),
+        ("credit-amount", mapping.credit_amount.as_ref()),
+        ("currency", Some(&mapping.currency)),
+    ];
+    println!("Suggested mapping:");
+    for (flag, column) in fields {
+        let Some(column) = column else { continue };
+        if headers.contains(column) {
+            println!("  --map-{flag} {column:?}");
+        } else {
+            println!("  --map-{flag} (no matching column)");
+        }
+    }
+    let decimal = if decimal_comma {
+        import::amount::DecimalSeparator::Comma
+    } else {
+        import::amount::DecimalSeparator::Dot
+    };
+    let parsed = match import::csv::CsvImporter::preview(file, &mapping, currency, decimal, rows) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            println!("Rows cannot be parsed with this mapping: {e}");
+            return Ok(());
+        }
+    };
+    println!("First {} rows:", parsed.len());
+    for (line, result) in parsed {
+        match result {
+            Ok(rec) => println!(
+                "  line {line}: {} | {} -> {} | {:.2} {}",
+                rec.description, rec.debit_account, rec.credit_account, rec.amount, rec.currency
+            ),
+            Err(e) => println!("  line {line}: {e}"),
+        }
+    }
     Ok(())
 }
 
-fn append_rows_with_progress(
-    adapter: &mut dyn CloudSpreadsheetService,
-    sheet_id: &str,
-    rows: Vec<Vec<String>>,
+/// New count, skipped duplicates and per-account net amounts of the new
+/// records.
+#[derive(Debug, Default)]
+struct ImportSummary {
+    new: usize,
+    /// One line per skipped record naming its file and position.
+    duplicates: Vec<String>,
+    totals: BTreeMap<(String, String), f64>,
+}
+
+impl ImportSummary {
+    fn add_new(&mut self, rec: &Record) {
+        self.new += 1;
+        for p in rec.postings() {
+            let currency = p.currency.to_string();
+            *self
+                .totals
+                .entry((p.debit_account.to_string(), currency.clone()))
+                .or_default() += p.amount;
+            *self
+                .totals
+                .entry((p.credit_account.to_string(), currency))
+                .or_default() -= p.amount;
+        }
+    }
+
+    fn print_dry_run(&self) {
+        println!(
+            "Dry run: {} new, {} duplicate; nothing written",
+            self.new,
+            self.duplicates.len()
+        );
+        for ((account, currency), total) in &self.totals {
+            println!("  {account} {total:.2} {currency}");
+        }
+    }
+}
+
+/// Collects imported records from any number of files, skips ones already in
+/// the sheet or seen earlier in the run, and appends the rest in batches
+/// behind a single progress spinner.
+struct ImportWriter<'a> {
+    adapter: &'a mut dyn CloudSpreadsheetService,
+    sheet_id: &'a str,
+    index: import::dedup::DedupIndex,
+    signature: &'a str,
+    dry_run: bool,
     batch_size: usize,
-) -> Result<(), SpreadsheetError> {
-    let pb = indicatif::ProgressBar::new(rows.len() as u64);
-    for chunk in rows.chunks(batch_size) {
-        adapter.append_rows(sheet_id, chunk.to_vec())?;
-        pb.inc(chunk.len() as u64);
+    batch: Vec<Vec<String>>,
+    summary: ImportSummary,
+    pb: indicatif::ProgressBar,
+    /// File being imported and the number of records read from it so far.
+    file: String,
+    position: usize,
+}
+
+impl<'a> ImportWriter<'a> {
+    fn new(
+        adapter: &'a mut dyn CloudSpreadsheetService,
+        sheet_id: &'a str,
+        signature: &'a str,
+        dry_run: bool,
+        batch_size: usize,
+    ) -> Result<Self, SpreadsheetError> {
+        let index = import::dedup::DedupIndex::from_sheet(&*adapter, sheet_id, signature)?;
+        Ok(Self {
+            adapter,
+            sheet_id,
+            index,
+            signature,
+            dry_run,
+            batch_size: batch_size.max(1),
+            batch: Vec::new(),
+            summary: ImportSummary::default(),
+            pb: indicatif::ProgressBar::new_spinner(),
+            file: String::new(),
+            position: 0,
+        })
+    }
+
+    fn start_file(&mut self, file: &Path) {
+        self.file = file.display().to_string();
+        self.position = 0;
+        self.pb.set_message(self.file.clone());
+    }
+
+    fn push(&mut self, rec: Record) -> Result<(), SpreadsheetError> {
+        self.pb.inc(1);
+        self.position += 1;
+        if !self.index.insert(&rec) {
+            let line = format!(
+                "{}: record {}: skipping duplicate '{}' {:.2} {}",
+                self.file, self.position, rec.description, rec.amount, rec.currency
+            );
+            self.pb.suspend(|| eprintln!("warning: {line}"));
+            self.summary.duplicates.push(line);
+            return Ok(());
+        }
+        self.summary.add_new(&rec);
+        if self.dry_run {
+            return Ok(());
+        }
+        self.batch.push(rec.to_row_hashed(self.signature));
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), SpreadsheetError> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        self.adapter
+            .append_rows(self.sheet_id, std::mem::take(&mut self.batch))
+    }
+
+    fn finish(mut self) -> Result<ImportSummary, SpreadsheetError> {
+        self.flush()?;
+        self.pb.finish_with_message("done");
+        if self.dry_run {
+            self.summary.print_dry_run();
+        }
+        Ok(self.summary)
     }
-    pb.finish_with_message("done");
-    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .with_writer(std::io::stdout)
         .init();
     info!("Starting feed-my-ledger");
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let e = CliError::from(e);
+            eprintln!("error: {e}");
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
     let rt = tokio::runtime::Runtime::new()?;
     let cli = Cli::parse();
     debug!(?cli, "Parsed CLI arguments");
     let Cli {
         local_dir,
+        no_header,
+        bin_dir,
         batch_size,
+        output,
+        ledger,
+        config: config_path,
         command,
     } = cli;
-    let config_path = PathBuf::from("config.toml");
+    if matches!(command, Commands::ConfigCheck) {
+        let data = fs::read_to_string(&config_path).map_err(|_| CliError::MissingConfig)?;
+        let problems = check_config(&data);
+        if problems.is_empty() {
+            println!("{}: configuration is valid", config_path.display());
+            return Ok(());
+        }
+        for problem in &problems {
+            eprintln!("{}: {problem}", config_path.display());
+        }
+        return Err(CliError::InvalidConfig(format!("{} problems found", problems.len())).into());
+    }
+    if let Commands::ImportPreview {
+        file,
+        currency,
+        decimal_comma,
+        rows,
+    } = &command
+    {
+        return import_preview(file, currency.as_deref(), *decimal_comma, *rows);
+    }
+    if let Commands::ImportTemplate {
+        file,
+        example,
+        mapping,
+    } = &command
+    {
+        let mapping = mapping.clone().into_mapping().unwrap_or_default();
+        if *example {
+            import::csv::write_template_with_example(file, &mapping)?;
+        } else {
+            import::csv::write_template(file, &mapping)?;
+        }
+        println!("Wrote template to {}", file.display());
+        return Ok(());
+    }
     let mut cfg =
         load_config(&config_path).map_err(|e| Box::new(e) as Box<dyn std::error::Error>)?;
-    let signature = generate_signature(&cfg.name, cfg.password.as_deref())
+    let ledger_index = cfg.ledger_index(ledger.as_deref())?;
+    let token_path = cfg.token_path(ledger_index, &config_path);
+    let prices_path = cfg.prices_path(ledger_index, &config_path);
+    let signature = generate_signature(cfg.ledger_name(ledger_index), cfg.password.as_deref())
         .map_err(|e| Box::new(CliError::InvalidConfig(e)) as Box<dyn std::error::Error>)?;
 
     if matches!(command, Commands::Login) {
         rt.block_on(feed_my_ledger::cloud_adapters::auth::initial_oauth_login(
             &cfg.google_sheets.credentials_path,
-            "tokens.json",
-        ))?;
+            &token_path.to_string_lossy(),
+        ))
+        .map_err(|e| CliError::Auth(e.to_string()))?;
         println!("Login successful");
         return Ok(());
     }
 
+    if let Commands::Tokens(_) | Commands::Logout = &command {
+        let mut store = token_files(&cfg, &config_path);
+        match command {
+            Commands::Tokens(TokenCommands::List) => {
+                let users = store.user_ids();
+                if users.is_empty() {
+                    println!("No saved logins");
+                }
+                for user in users {
+                    let saved = store
+                        .path(&user)
+                        .and_then(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+                        .map(|at| chrono::DateTime::<Utc>::from(at).to_rfc3339());
+                    match saved {
+                        Some(at) => println!("{user} | saved {at}"),
+                        None => println!("{user}"),
+                    }
+                }
+            }
+            Commands::Tokens(TokenCommands::Logout { user }) => {
+                if !store.user_ids().contains(&user) {
+                    return Err(format!("no saved login for {user}").into());
+                }
+                store.remove(&user)?;
+                println!("Logged out {user}");
+            }
+            _ => {
+                let users = store.user_ids();
+                for user in &users {
+                    store.remove(user)?;
+                }
+                println!("Removed {} saved logins", users.len());
+            }
+        }
+        return Ok(());
+    }
+
     if let Commands::Switch { link } = &command {
         let id = parse_sheet_id(link);
-        cfg.google_sheets.spreadsheet_id = Some(id.clone());
+        *cfg.spreadsheet_id_mut(ledger_index) = Some(id.clone());
         save_config(&config_path, &cfg);
         println!("Active sheet set to {id}");
         return Ok(());
     }
 
+    if let Commands::Restore { input, to, prices } = &command {
+        let snapshot = snapshot::import(&fs::read_to_string(input)?)?;
+        let mut target: Box<dyn CloudSpreadsheetService> = match to {
+            RestoreTarget::Csv(dir) => {
+                empty_restore_dir(dir)?;
+                Box::new(file_adapter(dir, no_header))
+            }
+            RestoreTarget::Bin(dir) => {
+                empty_restore_dir(dir)?;
+                Box::new(BinAdapter::new(dir))
+            }
+            RestoreTarget::Google => Box::new(rt.block_on(adapter_from_config(
+                &cfg.google_sheets,
+                cfg.sheet_name(ledger_index),
+                &token_path,
+                rt.handle().clone(),
+            ))?),
+        };
+        let id = snapshot.restore(target.as_mut(), cfg.ledger_name(ledger_index), &signature)?;
+        if let Some(path) = prices {
+            snapshot.price_database().to_csv(path)?;
+        }
+        println!(
+            "Restored {} records into sheet {id}",
+            snapshot.records.len()
+        );
+        return Ok(());
+    }
+
     let mut adapter: Box<dyn CloudSpreadsheetService> = if let Some(dir) = &local_dir {
         std::fs::create_dir_all(dir)?;
-        let inner = FileAdapter::new(dir);
+        let inner = file_adapter(dir, no_header);
+        Box::new(RetryingService::new(inner, 3, Duration::from_millis(500)))
+    } else if let Some(dir) = &bin_dir {
+        std::fs::create_dir_all(dir)?;
+        let inner = BinAdapter::new(dir);
         Box::new(RetryingService::new(inner, 3, Duration::from_millis(500)))
     } else {
-        let inner = rt.block_on(adapter_from_config(&cfg.google_sheets))?;
+        let inner = rt.block_on(adapter_from_config(
+            &cfg.google_sheets,
+            cfg.sheet_name(ledger_index),
+            &token_path,
+            rt.handle().clone(),
+        ))?;
+        // A retried append whose first response was lost must not write the
+        // record twice.
+        let inner = DedupOnAppend::new(inner);
         Box::new(RetryingService::new(inner, 3, Duration::from_millis(500)))
     };
-    let sheet_id = match &cfg.google_sheets.spreadsheet_id {
+    let sheet_id = match cfg.spreadsheet_id_mut(ledger_index) {
         Some(id) => id.clone(),
         None => {
-            let id = adapter.create_sheet("ledger")?;
-            cfg.google_sheets.spreadsheet_id = Some(id.clone());
+            let title = ledger_index.map_or("ledger", |i| cfg.ledgers[i].name.as_str());
+            let id = adapter.create_sheet(title)?;
+            *cfg.spreadsheet_id_mut(ledger_index) = Some(id.clone());
             save_config(&config_path, &cfg);
             id
         }
@@ -580,16 +1794,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             account,
             year,
             month,
+            currency,
         }) => {
             let rows = adapter.list_rows(&sheet_id)?;
-            let mut ledger = Ledger::default();
-            for row in rows {
-                if let Some(rec) = record_from_row(&row) {
-                    ledger.commit(rec);
-                }
-            }
-            let prices = if Path::new("prices.csv").exists() {
-                PriceDatabase::from_csv(Path::new("prices.csv"))?
+            let ledger = ledger_from_rows(&rows).with_aliases(&cfg.alias_map()?);
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
             } else {
                 PriceDatabase::default()
             };
@@ -611,13 +1821,46 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 );
             }
             let acc: Account = account.parse()?;
-            let diff = if let Some(m) = month {
-                book.compare_month(&ledger, &prices, &acc, year, m)
-            } else {
-                book.compare_year(&ledger, &prices, &acc, year)
+            let diff = match month {
+                Some(m) => book.try_compare_month(&ledger, &prices, &acc, year, m)?,
+                None => book.try_compare_year(&ledger, &prices, &acc, year)?,
+            };
+            let budget_currency = cfg
+                .budgets
+                .iter()
+                .find(|b| b.account == account)
+                .map(|b| b.currency.clone());
+            let (diff, currency) = match (diff, cfg.report_currency(currency), budget_currency) {
+                (Some(d), Some(target), Some(from)) if target != from => {
+                    let end = match month {
+                        Some(m) => chrono::NaiveDate::from_ymd_opt(year, m, 1)
+                            .and_then(|d| d.checked_add_months(chrono::Months::new(1)))
+                            .and_then(|d| d.pred_opt()),
+                        None => chrono::NaiveDate::from_ymd_opt(year, 12, 31),
+                    }
+                    .ok_or_else(|| format!("invalid budget period {year}-{month:?}"))?;
+                    let rate = prices
+                        .rate(end, &from, &target)
+                        .ok_or_else(|| MissingRate {
+                            pairs: vec![(end, from.clone(), target.clone())],
+                        })?;
+                    (Some(d * rate), Some(target))
+                }
+                (diff, _, from) => (diff, from),
             };
-            if let Some(d) = diff {
-                println!("{d}");
+            if output == OutputFormat::Json {
+                print_json(&serde_json::json!({
+                    "account": account,
+                    "year": year,
+                    "month": month,
+                    "remaining": diff,
+                    "currency": currency,
+                }))?;
+            } else if let Some(d) = diff {
+                match &currency {
+                    Some(currency) => println!("{}", format_amount(d, currency)),
+                    None => println!("{d}"),
+                }
             }
         }
         Commands::Schedule(ScheduleCommands::Add {
@@ -627,18 +1870,50 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             credit,
             amount,
             currency,
+            adjust,
         }) => {
-            cfg.schedules.push(ScheduleConfig {
+            let schedule = ScheduleConfig {
                 cron,
                 description,
                 debit,
                 credit,
                 amount,
                 currency,
-            });
+                adjust,
+            };
+            // Reject typos now; `generate` would silently skip the entry.
+            schedule_entry(&schedule)?
+                .validate()
+                .map_err(|e| e.to_string())?;
+            cfg.schedules.push(schedule);
             save_config(&config_path, &cfg);
             println!("Schedule added");
         }
+        Commands::Schedule(ScheduleCommands::Preview { from, to }) => {
+            let scheduler = scheduler_from_config(&cfg.schedules)?;
+            // `generate` starts strictly after `since`, so step back one
+            // second to include occurrences at midnight on `from`.
+            let since =
+                from.and_time(chrono::NaiveTime::MIN).and_utc() - chrono::Duration::seconds(1);
+            let until = to.and_hms_opt(23, 59, 59).expect("valid time").and_utc();
+            let records = scheduler
+                .try_generate(since, until)
+                .map_err(|e| e.to_string())?;
+            if output == OutputFormat::Json {
+                print_json(&records)?;
+            } else {
+                for rec in &records {
+                    println!(
+                        "{} | {} | {} | {} | {}",
+                        rec.timestamp.to_rfc3339(),
+                        rec.debit_account,
+                        rec.credit_account,
+                        rec.amount,
+                        rec.description
+                    );
+                }
+            }
+        }
         Commands::Add {
             description,
             debit,
@@ -647,10 +1922,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             currency,
             splits,
         } => {
+            let (debit, credit): (Account, Account) = (debit.parse()?, credit.parse()?);
+            let defaults = cfg.account_currencies()?;
+            let currency = resolve_currency(&debit, &defaults, currency.as_deref())
+                .or_else(|| resolve_currency(&credit, &defaults, None))
+                .ok_or_else(|| {
+                    format!(
+                        "no --currency given and neither {debit} nor {credit} has a default \
+                         in account_currencies"
+                    )
+                })?;
             let mut postings = vec![Posting {
-                debit_account: debit.parse()?,
-                credit_account: credit.parse()?,
+                debit_account: debit,
+                credit_account: credit,
                 amount,
+                currency: None,
+                memo: None,
             }];
             if let Some(data) = splits {
                 let extra: Vec<CliPosting> = serde_json::from_str(&data)?;
@@ -659,6 +1946,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         debit_account: p.debit.parse()?,
                         credit_account: p.credit.parse()?,
                         amount: p.amount,
+                        currency: p.currency,
+                        memo: p.memo,
                     });
                 }
             }
@@ -667,31 +1956,96 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         Commands::List => {
             let rows = adapter.list_rows(&sheet_id)?;
-            for row in rows {
-                println!("{}", row.join(" | "));
+            if output == OutputFormat::Json {
+                print_json(&rows)?;
+            } else {
+                let (_, statuses) = load_ledger_with_status(&rows);
+                for row in &rows {
+                    match record_from_row(row) {
+                        Some(rec) => {
+                            let cleared = statuses.get(&rec.id).copied().unwrap_or(false);
+                            let status = if cleared { "cleared" } else { "uncleared" };
+                            println!("{} | {status}", row.join(" | "));
+                        }
+                        None => println!("{}", row.join(" | ")),
+                    }
+                }
             }
         }
-        Commands::Register { query } => {
+        Commands::Register { query, currency } => {
+            let currency = cfg
+                .report_currency(currency)
+                .unwrap_or_else(|| "USD".to_string());
             let rows = adapter.list_rows(&sheet_id)?;
-            let mut ledger = Ledger::default();
-            for row in rows {
-                if let Some(rec) = record_from_row(&row) {
-                    ledger.commit(rec);
-                }
-            }
-            let q = match query {
+            let ledger = ledger_from_rows(&rows).with_aliases(&cfg.alias_map()?);
+            let mut q = match query {
                 Some(expr) => Query::from_str(&expr)?,
                 None => Query::default(),
             };
-            for rec in q.filter(&ledger) {
-                println!(
-                    "{} | {} | {} | {} | {}",
+            q.sorted = true;
+            let print_row = |rec: &Record| {
+                format!(
+                    "{} | {} | {} | {} | {}{}",
                     rec.timestamp.to_rfc3339(),
                     rec.debit_account,
                     rec.credit_account,
-                    rec.amount,
+                    format_amount(rec.amount, &rec.currency),
+                    if rec.cleared { "* " } else { "" },
                     rec.description
-                );
+                )
+            };
+            let prices;
+            let entries: Vec<RegisterEntry> = if let [account] = q.accounts.as_slice() {
+                let account: Account = account.parse()?;
+                prices = if prices_path.exists() {
+                    PriceDatabase::from_csv(&prices_path)?
+                } else {
+                    PriceDatabase::default()
+                };
+                ledger.try_account_tree_balance(&account, &currency, &prices)?;
+                ledger
+                    .running_balance(&account, &currency, &prices)
+                    .filter(|(rec, _)| q.matches(rec))
+                    .map(|(record, balance)| RegisterEntry {
+                        record,
+                        balance: Some(balance),
+                    })
+                    .collect()
+            } else {
+                q.filter(&ledger)
+                    .into_iter()
+                    .map(|record| RegisterEntry {
+                        record,
+                        balance: None,
+                    })
+                    .collect()
+            };
+            if output == OutputFormat::Json {
+                print_json(&entries)?;
+            } else {
+                for entry in &entries {
+                    match entry.balance {
+                        Some(balance) => println!(
+                            "{} | {}",
+                            print_row(entry.record),
+                            format_amount(balance, &currency)
+                        ),
+                        None => println!("{}", print_row(entry.record)),
+                    }
+                    for split in &entry.record.splits {
+                        if let Some(memo) = &split.memo {
+                            println!(
+                                "    {} | {} | {} | {memo}",
+                                split.debit_account,
+                                split.credit_account,
+                                format_amount(
+                                    split.amount,
+                                    split.currency.as_ref().unwrap_or(&entry.record.currency)
+                                )
+                            );
+                        }
+                    }
+                }
             }
         }
         Commands::Adjust {
@@ -727,25 +2081,70 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             format,
             currency,
             date_format,
+            rules,
+            fixed_spec,
+            sign_convention,
+            decimal_comma,
+            dry_run,
+            validate,
             mapping,
         } => {
-            import_with_progress(
-                &mut *adapter,
-                &sheet_id,
-                &file,
-                format,
-                mapping,
-                currency,
-                &signature,
-                date_format,
-                batch_size,
-            )?;
+            let currency = currency.or_else(|| {
+                mapping
+                    .preset
+                    .and_then(Preset::currency)
+                    .map(str::to_string)
+            });
+            let mapping = mapping.into_mapping();
+            if validate {
+                let mut invalid = 0;
+                for file in &file {
+                    let errors = validate_import(
+                        file,
+                        format.as_deref(),
+                        mapping.as_ref(),
+                        currency.as_deref(),
+                        decimal_comma,
+                    )?;
+                    for (line, err) in &errors {
+                        println!("{}:{line}: {err}", file.display());
+                    }
+                    invalid += errors.len();
+                }
+                if invalid > 0 {
+                    return Err(format!("{invalid} invalid rows; nothing was imported").into());
+                }
+            }
+            let rules = rules
+                .as_deref()
+                .map(import::rules::RuleSet::load)
+                .transpose()?;
+            let date_formats: Vec<&str> = date_format.iter().map(String::as_str).collect();
+            let mut writer =
+                ImportWriter::new(&mut *adapter, &sheet_id, &signature, dry_run, batch_size)?;
+            for file in &file {
+                import_with_progress(
+                    &mut writer,
+                    file,
+                    format.as_deref(),
+                    mapping.as_ref(),
+                    currency.as_deref(),
+                    &date_formats,
+                    rules.as_ref(),
+                    fixed_spec.as_deref(),
+                    sign_convention,
+                    decimal_comma,
+                )?;
+            }
+            writer.finish()?;
         }
         Commands::Export { file, format } => {
             let rows = adapter.list_rows(&sheet_id)?;
+            let (_, statuses) = load_ledger_with_status(&rows);
             let mut records = Vec::new();
             for row in rows {
-                if let Some(rec) = record_from_row(&row) {
+                if let Some(mut rec) = record_from_row(&row) {
+                    rec.cleared = statuses.get(&rec.id).copied().unwrap_or(false);
                     records.push(rec);
                 }
             }
@@ -757,27 +2156,86 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 })
                 .ok_or_else(|| "could not determine file format".to_string())?;
             match fmt.to_lowercase().as_str() {
-                "csv" => import::csv::export(&file, &records)?,
+                "csv" => import::csv::export_full(&file, &records)?,
                 "ledger" => import::ledger::export(&file, &records)?,
                 "json" => import::json::export(&file, &records)?,
+                "jsonl" | "ndjson" => import::jsonl::export(&file, &records)?,
                 other => return Err(format!("unsupported format: {other}").into()),
             }
         }
         #[cfg(feature = "bank-api")]
-        Commands::Download { url } => {
-            let records = rt.block_on(import::ofx::download(&url))?;
+        Commands::Download {
+            url,
+            username,
+            password,
+            token,
+        } => {
+            let bank = cfg.bank.as_ref();
+            let username = username.or_else(|| bank.and_then(|b| b.username.clone()));
+            let password = password.or_else(|| bank.and_then(|b| b.password.clone()));
+            let token = token.or_else(|| bank.and_then(|b| b.token.clone()));
+            let records = match (token, username) {
+                (Some(token), _) => rt.block_on(import::ofx::download_with_bearer(&url, &token))?,
+                (None, Some(user)) => rt.block_on(import::ofx::download_with_auth(
+                    &url,
+                    &user,
+                    password.as_deref().unwrap_or_default(),
+                ))?,
+                (None, None) => rt.block_on(import::ofx::download(&url))?,
+            };
             for rec in records {
                 adapter.append_row(&sheet_id, rec.to_row_hashed(&signature))?;
             }
         }
-        Commands::Balance { account, query } => {
+        Commands::Balance {
+            account,
+            query,
+            as_of,
+            currency,
+        } if as_of.is_some() || cfg.report_currency(currency.clone()).is_some() => {
+            let currency = cfg.report_currency(currency).ok_or_else(|| {
+                CliError::InvalidConfig(
+                    "--as-of needs --currency or reporting_currency".to_string(),
+                )
+            })?;
             let rows = adapter.list_rows(&sheet_id)?;
-            let mut ledger = Ledger::default();
-            for row in rows {
-                if let Some(rec) = record_from_row(&row) {
-                    ledger.commit(rec);
+            let mut ledger = ledger_from_rows(&rows).with_aliases(&cfg.alias_map()?);
+            if let Some(expr) = query {
+                let q = Query::from_str(&expr)?;
+                let mut filtered = Ledger::default();
+                for rec in q.filter(&ledger) {
+                    filtered.commit(rec.clone());
                 }
+                ledger = filtered;
+            }
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
+            } else {
+                PriceDatabase::default()
+            };
+            let balance = match as_of {
+                Some(as_of) => ledger.try_account_tree_balance_as_of(
+                    &account.parse()?,
+                    as_of,
+                    &currency,
+                    &prices,
+                )?,
+                None => ledger.try_account_tree_balance(&account.parse()?, &currency, &prices)?,
+            };
+            if output == OutputFormat::Json {
+                print_json(&serde_json::json!({
+                    "account": account,
+                    "amount": balance,
+                    "as_of": as_of,
+                    "currency": currency,
+                }))?;
+            } else {
+                println!("{}", format_amount(balance, &currency));
             }
+        }
+        Commands::Balance { account, query, .. } => {
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows).with_aliases(&cfg.alias_map()?);
             let mut q = match query {
                 Some(expr) => Query::from_str(&expr)?,
                 None => Query::default(),
@@ -785,25 +2243,109 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             q.accounts.push(account.clone());
             let account_parsed: Account = account.parse()?;
             let mut balance = 0.0;
+            let mut currencies = BTreeSet::new();
             for rec in q.filter(&ledger) {
                 if rec.debit_account.starts_with(&account_parsed) {
                     balance += rec.amount;
+                    currencies.insert(rec.currency.as_str());
                 }
                 if rec.credit_account.starts_with(&account_parsed) {
                     balance -= rec.amount;
+                    currencies.insert(rec.currency.as_str());
+                }
+            }
+            if output == OutputFormat::Json {
+                print_json(&serde_json::json!({ "account": account, "amount": balance }))?;
+            } else if let [currency] = Vec::from_iter(currencies).as_slice() {
+                println!("{}", format_amount(balance, currency));
+            } else {
+                // Mixed or no currencies: there is no single unit to format with.
+                println!("{balance}");
+            }
+        }
+        Commands::Assert {
+            account,
+            date,
+            amount,
+            currency,
+        } => {
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
+            } else {
+                PriceDatabase::default()
+            };
+            ledger.check_assertion(&account.parse()?, date, amount, &currency, &prices)?;
+            println!("Balance of {account} on {date} is {amount} {currency}");
+        }
+        Commands::OpeningBalance {
+            account,
+            amount,
+            currency,
+            date,
+        } => {
+            let record = Record::opening_balance(account.parse()?, amount, currency, date)?;
+            adapter.append_row(&sheet_id, record.to_row_hashed(&signature))?;
+            println!("Opening balance of {account} on {date} recorded");
+        }
+        Commands::TrialBalance { currency, depth } => {
+            let currency = cfg
+                .report_currency(currency)
+                .unwrap_or_else(|| "USD".to_string());
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows).with_aliases(&cfg.alias_map()?);
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
+            } else {
+                PriceDatabase::default()
+            };
+            let balances = match depth {
+                Some(level) => ledger.try_balances_by_level(level, &currency, &prices)?,
+                None => ledger.try_trial_balance(&currency, &prices)?,
+            };
+            if output == OutputFormat::Json {
+                print_json(&balances)?;
+            } else {
+                for (account, amount) in &balances {
+                    println!("{account} | {amount:.2}");
+                }
+                // Rounding noise could otherwise print as -0.00.
+                let total: f64 = balances.values().sum();
+                println!(
+                    "total | {:.2}",
+                    if total.abs() < 1e-9 { 0.0 } else { total }
+                );
+            }
+        }
+        Commands::TagReport { currency } => {
+            let currency = cfg
+                .report_currency(currency)
+                .unwrap_or_else(|| "USD".to_string());
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
+            } else {
+                PriceDatabase::default()
+            };
+            let totals = ledger.try_totals_by_tag(&currency, &prices)?;
+            if output == OutputFormat::Json {
+                print_json(&totals)?;
+            } else {
+                for (tag, amount) in &totals {
+                    println!("{tag} | {}", format_amount(*amount, &currency));
                 }
             }
-            println!("{balance}");
         }
         Commands::ImportPrices { file } => {
             let db = PriceDatabase::from_csv(&file)?;
-            db.to_csv(Path::new("prices.csv"))?;
+            db.to_csv(&prices_path)?;
             println!("Imported {} prices", db.all_rates().len());
         }
         Commands::ListPrices => {
-            let path = Path::new("prices.csv");
-            if path.exists() {
-                let db = PriceDatabase::from_csv(path)?;
+            if prices_path.exists() {
+                let db = PriceDatabase::from_csv(&prices_path)?;
                 for (date, from, to, rate) in db.all_rates() {
                     println!("{date} {from}->{to} {rate}");
                 }
@@ -822,19 +2364,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "qif" => import::qif::parse(&file),
                 "ofx" => import::ofx::parse(&file),
                 "ledger" => import::ledger::parse(&file),
+                "beancount" | "bean" => import::beancount::parse(&file),
                 "json" => import::json::parse(&file),
+                "jsonl" | "ndjson" => import::jsonl::parse(&file),
                 other => return Err(format!("unsupported format: {other}").into()),
             }?;
             let rows = adapter.list_rows(&sheet_id)?;
-            let mut ledger = Ledger::default();
-            let mut statuses: HashMap<Uuid, bool> = HashMap::new();
-            for row in rows {
-                if let Some(rec) = record_from_row(&row) {
-                    ledger.commit(rec);
-                } else if let Some((id, cleared)) = status_from_row(&row) {
-                    statuses.insert(id, cleared);
-                }
-            }
+            let (ledger, statuses) = load_ledger_with_status(&rows);
+            let mut in_place = cfg.in_place_status;
             for rec in ledger.records() {
                 let mut matched = false;
                 for stmt in &statements {
@@ -845,36 +2382,365 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         break;
                     }
                 }
-                if statuses.get(&rec.id).copied() != Some(matched) {
-                    adapter.append_row(
+                if statuses.get(&rec.id).copied() == Some(matched) {
+                    continue;
+                }
+                let mut rec = rec.clone();
+                rec.cleared = matched;
+                if in_place
+                    && let Some(index) = rows
+                        .iter()
+                        .position(|row| row.first() == Some(&rec.id.to_string()))
+                {
+                    match adapter.update_row(
                         &sheet_id,
-                        vec!["status".into(), rec.id.to_string(), matched.to_string()],
-                    )?;
+                        index,
+                        rec.to_row_hashed_with_status(&signature),
+                    ) {
+                        Ok(()) => continue,
+                        // The backend can only append; use status rows.
+                        Err(SpreadsheetError::Unknown) => in_place = false,
+                        Err(e) => return Err(e.into()),
+                    }
                 }
+                adapter.append_row(&sheet_id, rec.status_row())?;
             }
         }
-        Commands::RunScript { file } => {
+        Commands::Stale { days, as_of } => {
+            let as_of = as_of.unwrap_or_else(|| Local::now().date_naive());
             let rows = adapter.list_rows(&sheet_id)?;
-            let mut ledger = Ledger::default();
-            for row in rows {
-                if let Some(rec) = record_from_row(&row) {
-                    ledger.commit(rec);
+            let ledger = ledger_from_rows(&rows);
+            let stale = ledger.stale_uncleared(as_of, days);
+            if output == OutputFormat::Json {
+                print_json(&stale)?;
+            } else {
+                for rec in stale {
+                    println!(
+                        "{} | {} | {} | {}",
+                        rec.effective_date(),
+                        rec.id,
+                        format_amount(rec.amount, &rec.currency),
+                        rec.description
+                    );
                 }
             }
+        }
+        Commands::RunScript { file } => {
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
             let script = std::fs::read_to_string(file)?;
             let result = feed_my_ledger::script::run_script(&script, &ledger)?;
             println!("{result}");
         }
-        Commands::Verify => {
-            let mismatched = verify_sheet(&*adapter, &sheet_id, &signature)?;
-            if mismatched.is_empty() {
-                println!("All rows verified");
+        #[cfg(feature = "tui")]
+        Commands::Tui { currency } => {
+            let currency = cfg
+                .report_currency(currency)
+                .unwrap_or_else(|| "USD".to_string());
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
             } else {
+                PriceDatabase::default()
+            };
+            let owner = cfg.ledger_name(ledger_index).to_string();
+            let shared = feed_my_ledger::core::SharedLedger::from_sheet(adapter, sheet_id, &owner)?
+                .with_in_place_status(cfg.in_place_status);
+            feed_my_ledger::tui::run(&shared, &owner, &currency, &prices)?;
+        }
+        Commands::Verify { strict: true, .. } => {
+            let report = verify_sheet_full(&*adapter, &sheet_id, &signature)?;
+            if output == OutputFormat::Json {
+                print_json(&serde_json::json!({
+                    "verified": report.is_ok(),
+                    "tampered_rows": report.tampered,
+                    "unparsable_rows": report
+                        .unparsable
+                        .iter()
+                        .map(|(row, reason)| serde_json::json!({"row": row, "reason": reason}))
+                        .collect::<Vec<_>>(),
+                }))?;
+            } else if report.is_ok() {
+                println!("All rows verified and parsed");
+            } else {
+                if !report.tampered.is_empty() {
+                    println!("Tampered rows: {:?}", report.tampered);
+                }
+                for (row, reason) in &report.unparsable {
+                    println!("Unparsable row {row}: {reason}");
+                }
+            }
+            if !report.tampered.is_empty() {
+                return Err(CliError::Tampered(report.tampered.len()).into());
+            }
+            if !report.unparsable.is_empty() {
+                return Err(format!("{} rows do not parse", report.unparsable.len()).into());
+            }
+        }
+        Commands::Verify { from, .. } => {
+            let (mismatched, row_count) =
+                verify_sheet_from(&*adapter, &sheet_id, &signature, from)?;
+            if output == OutputFormat::Json {
+                print_json(&serde_json::json!({
+                    "verified": mismatched.is_empty(),
+                    "tampered_rows": mismatched,
+                    "row_count": row_count,
+                }))?;
+            } else if !mismatched.is_empty() {
                 println!("Tampered rows: {mismatched:?}");
-                return Err("tampering detected".into());
+            } else if from == 0 {
+                println!("All rows verified ({row_count} rows)");
+            } else {
+                println!("Rows {from}..{row_count} verified");
+            }
+            if !mismatched.is_empty() {
+                return Err(CliError::Tampered(mismatched.len()).into());
+            }
+        }
+        Commands::Check => {
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
+            let issues = ledger.validate();
+            if issues.is_empty() {
+                println!("All {} records are consistent", ledger.records().count());
+                return Ok(());
+            }
+            for issue in &issues {
+                eprintln!("{issue}");
+            }
+            return Err(format!("{} problems found", issues.len()).into());
+        }
+        Commands::Repair { rows, yes } => {
+            for &idx in &rows {
+                println!("{idx}: {}", adapter.read_row(&sheet_id, idx)?.join(","));
+            }
+            if !yes && !confirm(&format!("Re-hash {} rows?", rows.len()))? {
+                println!("Nothing was changed");
+                return Ok(());
+            }
+            repair_sheet(&mut *adapter, &sheet_id, &signature, &rows)?;
+            println!("Repaired {} rows", rows.len());
+        }
+        Commands::RotateSignature {
+            old_name,
+            old_password,
+            yes,
+        } => {
+            let old_name = old_name.as_deref().unwrap_or(cfg.ledger_name(ledger_index));
+            let old_password = old_password.as_deref().or(cfg.password.as_deref());
+            let old_signature = generate_signature(old_name, old_password)?;
+            if old_signature == signature {
+                return Err("the old name and password match the current ones".into());
             }
+            if !yes && !confirm("Re-hash every row under the current name and password?")? {
+                println!("Nothing was changed");
+                return Ok(());
+            }
+            let rotated = rotate_signature(&mut *adapter, &sheet_id, &old_signature, &signature)?;
+            println!("Re-hashed {rotated} rows");
+        }
+        Commands::Attachment { id, file } => {
+            let id = uuid::Uuid::parse_str(&id)?;
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
+            let rec = ledger
+                .get_record(id)
+                .map_err(|_| format!("record {id} not found"))?;
+            let attachment = rec
+                .attachment()
+                .ok_or_else(|| format!("record {id} has no attachment"))?;
+            match (file, attachment) {
+                (None, attachment) => println!("{attachment}"),
+                (Some(file), Attachment::Local(path)) => {
+                    std::fs::copy(&path, &file)?;
+                    println!("Copied {} to {}", path.display(), file.display());
+                }
+                #[cfg(feature = "bank-api")]
+                (Some(file), Attachment::Remote(url)) => {
+                    let bytes = rt.block_on(import::http::get(&url, None))?;
+                    std::fs::write(&file, bytes)?;
+                    println!("Downloaded {url} to {}", file.display());
+                }
+                #[cfg(not(feature = "bank-api"))]
+                (Some(_), Attachment::Remote(url)) => {
+                    return Err(format!("downloading {url} needs the bank-api feature").into());
+                }
+            }
+        }
+        Commands::Show { id } => {
+            let id = uuid::Uuid::parse_str(&id)?;
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
+            let rec = ledger
+                .get_record(id)
+                .map_err(|_| format!("record {id} not found"))?;
+            let history = ledger.adjustment_history(id);
+            let lineage = ledger.lineage(id);
+            let reversed = ledger.is_reversed(id);
+            if output == OutputFormat::Json {
+                print_json(&serde_json::json!({
+                    "record": rec,
+                    "reversed": reversed,
+                    "adjustments": history,
+                    "original": ledger.original_of(id),
+                    "lineage": lineage,
+                }))?;
+            } else {
+                println!("id: {}", rec.id);
+                println!("timestamp: {}", rec.timestamp.to_rfc3339());
+                println!("description: {}", rec.description);
+                println!("debit: {}", rec.debit_account);
+                println!("credit: {}", rec.credit_account);
+                println!("amount: {} {}", rec.amount, rec.currency);
+                for split in &rec.splits {
+                    println!(
+                        "split: {} | {} | {} {}{}",
+                        split.debit_account,
+                        split.credit_account,
+                        split.amount,
+                        split.currency.as_deref().unwrap_or(&rec.currency),
+                        split
+                            .memo
+                            .as_deref()
+                            .map(|m| format!(" | {m}"))
+                            .unwrap_or_default()
+                    );
+                }
+                if let Some(reference) = rec.reference_id {
+                    println!("reference: {reference}");
+                }
+                if let Some(external) = &rec.external_reference {
+                    println!("external reference: {external}");
+                }
+                if !rec.tags.is_empty() {
+                    println!("tags: {}", rec.tags.join(", "));
+                }
+                for (key, value) in &rec.metadata {
+                    println!("metadata: {key}={value}");
+                }
+                if let Some(desc) = &rec.transaction_description {
+                    println!("transaction description: {desc}");
+                }
+                if let Some(date) = rec.transaction_date {
+                    println!("transaction date: {}", date.to_rfc3339());
+                }
+                println!("cleared: {}", rec.cleared);
+                println!("reversed: {reversed}");
+                println!("adjustments: {}", history.len());
+                // The whole chain from the original, with this record marked.
+                println!("lineage:");
+                for entry in &lineage {
+                    println!(
+                        "{} {} | {} | {} | {} | {} {} | {}",
+                        if entry.id == id { "*" } else { " " },
+                        entry.timestamp.to_rfc3339(),
+                        entry.id,
+                        entry.debit_account,
+                        entry.credit_account,
+                        entry.amount,
+                        entry.currency,
+                        entry.description
+                    );
+                }
+            }
+        }
+        Commands::Undo { count } => {
+            let rows = adapter.list_rows(&sheet_id)?;
+            let ledger = ledger_from_rows(&rows);
+            let records: Vec<&Record> = ledger.records().collect();
+            let latest: Vec<&Record> = records.into_iter().rev().take(count).collect();
+            if let Some(done) = latest.iter().find(|rec| ledger.is_reversed(rec.id)) {
+                return Err(format!("record {} has already been reversed", done.id).into());
+            }
+            let reversals: Vec<Record> = latest.iter().map(|rec| rec.reverse()).collect();
+            if !reversals.is_empty() {
+                adapter.append_rows(
+                    &sheet_id,
+                    reversals
+                        .iter()
+                        .map(|rec| rec.to_row_hashed(&signature))
+                        .collect(),
+                )?;
+            }
+            for rec in &latest {
+                println!(
+                    "Reversed {}: {} {} {}",
+                    rec.id, rec.description, rec.amount, rec.currency
+                );
+            }
+        }
+        Commands::Merge { from } => {
+            let source_id = parse_sheet_id(&from);
+            let source = ledger_from_rows(&adapter.list_rows(&source_id)?);
+            let mut target = ledger_from_rows(&adapter.list_rows(&sheet_id)?);
+            let report = target.merge(&source, MergeStrategy::default());
+            let rows: Vec<Vec<String>> = report
+                .merged
+                .iter()
+                .filter_map(|id| target.get_record(*id).ok())
+                .map(|rec| rec.to_row_hashed(&signature))
+                .collect();
+            if !rows.is_empty() {
+                adapter.append_rows(&sheet_id, rows)?;
+            }
+            println!(
+                "Merged {} records, skipped {} duplicates",
+                report.merged.len(),
+                report.duplicates.len()
+            );
+            for id in &report.conflicts {
+                eprintln!(
+                    "conflict: record {id} differs between sheets; kept the existing version"
+                );
+            }
+        }
+        Commands::Diff { other } => {
+            let other_id = parse_sheet_id(&other);
+            let ours = ledger_from_rows(&adapter.list_rows(&sheet_id)?);
+            let theirs = ledger_from_rows(&adapter.list_rows(&other_id)?);
+            let diff = feed_my_ledger::core::diff::diff(&ours, &theirs);
+            if diff.is_empty() {
+                println!("Both sheets hold the same records");
+                return Ok(());
+            }
+            let sections = [
+                ("Only in this sheet", &diff.only_in_a, &ours),
+                ("Only in the other sheet", &diff.only_in_b, &theirs),
+                ("Changed", &diff.changed, &theirs),
+            ];
+            for (title, ids, ledger) in sections {
+                println!("{title}: {}", ids.len());
+                for rec in ids.iter().filter_map(|id| ledger.get_record(*id).ok()) {
+                    println!(
+                        "  {} {} {} {}",
+                        rec.id, rec.description, rec.amount, rec.currency
+                    );
+                }
+            }
+        }
+        Commands::Snapshot { out } => {
+            let rows = adapter.list_rows(&sheet_id)?;
+            let (ledger, statuses) = load_ledger_with_status(&rows);
+            let prices = if prices_path.exists() {
+                PriceDatabase::from_csv(&prices_path)?
+            } else {
+                PriceDatabase::default()
+            };
+            let snapshot = snapshot::export(&ledger, &statuses, &prices);
+            fs::write(&out, snapshot.to_json()?)?;
+            println!(
+                "Wrote {} records to {}",
+                snapshot.records.len(),
+                out.display()
+            );
         }
-        Commands::Switch { .. } | Commands::Login => unreachable!(),
+        Commands::Switch { .. }
+        | Commands::Login
+        | Commands::Tokens(_)
+        | Commands::Logout
+        | Commands::ConfigCheck
+        | Commands::ImportPreview { .. }
+        | Commands::ImportTemplate { .. }
+        | Commands::Restore { .. } => unreachable!(),
     }
 
     Ok(())
@@ -882,8 +2748,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(test)]
 mod tests {
-    use super::{CloudSpreadsheetService, CsvMapArgs, SpreadsheetError, append_rows_with_progress};
+    use super::{
+        CliError, CloudSpreadsheetService, CsvMapArgs, ImportWriter, Posting, Preset, Record,
+        SpreadsheetError, check_config, config_relative, ledger_from_rows, load_config,
+        parse_columns, parse_config, record_from_row,
+    };
+    use chrono::{Local, TimeZone};
     use std::cell::RefCell;
+    use std::path::{Path, PathBuf};
 
     struct MockAdapter {
         calls: RefCell<Vec<Vec<Vec<String>>>>,
@@ -950,6 +2822,7 @@ mod tests {
             map_credit: Some("credit".into()),
             map_amount: Some("amount".into()),
             map_currency: Some("curr".into()),
+            ..CsvMapArgs::default()
         };
         let mapping = args.into_mapping().unwrap();
         assert_eq!(mapping.description, "desc");
@@ -957,22 +2830,397 @@ mod tests {
         assert_eq!(mapping.credit_account, "credit");
         assert_eq!(mapping.amount, "amount");
         assert_eq!(mapping.currency, "curr");
+        assert_eq!(mapping.debit_amount, None);
     }
 
     #[test]
-    fn append_rows_batches_input() {
+    fn mapping_conversion_split_amounts() {
+        let args = CsvMapArgs {
+            map_debit_amount: Some("Paid out".into()),
+            map_credit_amount: Some("Paid in".into()),
+            ..CsvMapArgs::default()
+        };
+        let mapping = args.into_mapping().unwrap();
+        assert_eq!(mapping.debit_amount.as_deref(), Some("Paid out"));
+        assert_eq!(mapping.credit_amount.as_deref(), Some("Paid in"));
+    }
+
+    #[test]
+    fn mapping_conversion_preset_with_override() {
+        let args = CsvMapArgs {
+            preset: Some(Preset::Paypal),
+            map_description: Some("Subject".into()),
+            ..CsvMapArgs::default()
+        };
+        let mapping = args.into_mapping().unwrap();
+        assert_eq!(mapping.description, "Subject");
+        assert_eq!(mapping.amount, "Gross");
+        assert_eq!(
+            mapping.statement_account.map(|s| s.account).as_deref(),
+            Some("assets:paypal")
+        );
+    }
+
+    fn record(desc: &str) -> Record {
+        Record::new(
+            desc.into(),
+            "cash".parse().unwrap(),
+            "revenue".parse().unwrap(),
+            1.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn import_writer_batches_input() {
         let mut adapter = MockAdapter::new();
-        let rows: Vec<Vec<String>> = (0..105).map(|i| vec![i.to_string()]).collect();
-        append_rows_with_progress(&mut adapter, "sheet", rows.clone(), 100).unwrap();
+        let records: Vec<Record> = (0..105).map(|i| record(&i.to_string())).collect();
+        let mut writer = ImportWriter::new(&mut adapter, "sheet", "sig", false, 100).unwrap();
+        for rec in &records {
+            writer.push(rec.clone()).unwrap();
+        }
+        let summary = writer.finish().unwrap();
+        assert_eq!(summary.new, 105);
         let calls = adapter.calls.borrow();
         assert_eq!(calls.len(), 2);
         assert_eq!(calls[0].len(), 100);
         assert_eq!(calls[1].len(), 5);
-        let collected: Vec<Vec<String>> = calls
+        let collected: Vec<String> = calls
             .iter()
-            .cloned()
-            .flat_map(|chunk| chunk.into_iter())
+            .flat_map(|chunk| chunk.iter().map(|row| row[0].clone()))
             .collect();
-        assert_eq!(collected, rows);
+        let expected: Vec<String> = records.iter().map(|r| r.id.to_string()).collect();
+        assert_eq!(collected, expected);
+    }
+
+    #[test]
+    fn import_writer_skips_repeats_across_files() {
+        let mut adapter = MockAdapter::new();
+        let shared = record("shared");
+        let mut writer = ImportWriter::new(&mut adapter, "sheet", "sig", false, 10).unwrap();
+        writer.push(shared.clone()).unwrap();
+        writer.push(record("first only")).unwrap();
+        writer.push(shared).unwrap();
+        writer.push(record("second only")).unwrap();
+        let summary = writer.finish().unwrap();
+        assert_eq!(summary.new, 3);
+        assert_eq!(summary.duplicates.len(), 1);
+        let calls = adapter.calls.borrow();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].len(), 3);
+    }
+
+    #[test]
+    fn import_writer_reports_each_skipped_line() {
+        let mut adapter = MockAdapter::new();
+        let date = Local.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let dated = |desc: &str| {
+            let mut rec = record(desc);
+            rec.transaction_date = Some(date);
+            rec
+        };
+        let mut writer = ImportWriter::new(&mut adapter, "sheet", "sig", true, 10).unwrap();
+        writer.start_file(Path::new("march.qif"));
+        writer.push(dated("Coffee")).unwrap();
+        writer.push(dated("Coffee")).unwrap();
+        writer.push(record("Rent")).unwrap();
+        writer.push(record("Rent")).unwrap();
+        let summary = writer.finish().unwrap();
+        assert_eq!(summary.new, 3);
+        assert_eq!(
+            summary.duplicates,
+            vec!["march.qif: record 2: skipping duplicate 'Coffee' 1.00 USD"]
+        );
+    }
+
+    #[test]
+    fn ledger_from_rows_keeps_row_ids() {
+        let make = |desc: &str| {
+            Record::new(
+                desc.into(),
+                "cash".parse().unwrap(),
+                "revenue".parse().unwrap(),
+                1.0,
+                "USD".into(),
+                None,
+                None,
+                vec![],
+            )
+            .unwrap()
+        };
+        let first = make("first");
+        let second = make("second");
+        let rows = vec![first.to_row(), second.to_row(), first.to_row()];
+        let ledger = ledger_from_rows(&rows);
+        let ids: Vec<_> = ledger.records().map(|r| r.id).collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+    }
+
+    #[test]
+    fn split_memos_round_trip_through_rows() {
+        let posting = |debit: &str, amount: f64, memo: Option<&str>| Posting {
+            debit_account: debit.parse().unwrap(),
+            credit_account: "liabilities:card".parse().unwrap(),
+            amount,
+            currency: None,
+            memo: memo.map(Into::into),
+        };
+        let record = Record::new_split(
+            "invoice 42".into(),
+            vec![
+                posting("expenses:office", 50.0, None),
+                posting("expenses:office", 20.0, Some("toner")),
+                posting("expenses:office", 5.0, None),
+            ],
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        let parsed = record_from_row(&record.to_row()).unwrap();
+        assert_eq!(parsed.splits, record.splits);
+        let memos: Vec<_> = parsed.postings().map(|p| p.memo).collect();
+        assert_eq!(memos, vec![None, Some("toner"), None]);
+
+        // Splits written before memos existed still parse.
+        let mut row = record.to_row();
+        row[10] = r#"[{"debit_account":"expenses:office","credit_account":"liabilities:card","amount":20.0}]"#.into();
+        let legacy = record_from_row(&row).unwrap();
+        assert_eq!(legacy.splits[0].memo, None);
+    }
+
+    #[test]
+    fn record_from_row_keeps_accounts_stored_before_validation() {
+        let mut row = record("dinner").to_row();
+        row[3] = "Joe's Diner, Inc.".into();
+        row[10] =
+            r#"[{"debit_account":"Joe's Diner, Inc.","credit_account":" revenue","amount":5.0}]"#
+                .into();
+        let rec = record_from_row(&row).unwrap();
+        assert_eq!(rec.debit_account.to_string(), "Joe's Diner Inc.");
+        assert_eq!(rec.splits[0].debit_account, rec.debit_account);
+        assert_eq!(rec.splits[0].credit_account.to_string(), "revenue");
+    }
+
+    #[test]
+    fn ledger_from_rows_applies_status_rows() {
+        let rec = Record::new(
+            "rent".into(),
+            "expenses".parse().unwrap(),
+            "cash".parse().unwrap(),
+            1.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        let status = |cleared: bool| vec!["status".into(), rec.id.to_string(), cleared.to_string()];
+        let rows = vec![status(false), rec.to_row(), status(true)];
+        let ledger = ledger_from_rows(&rows);
+        assert!(ledger.get_record(rec.id).unwrap().cleared);
+    }
+
+    #[test]
+    fn check_config_reports_every_problem() {
+        let problems = check_config(
+            r#"
+name = "test"
+reporting_currency = "EURO"
+[google_sheets]
+credentials_path = "cred.json"
+
+[[budgets]]
+account = "expenses:food"
+amount = 100.0
+currency = "XYZ"
+period = "monthly"
+
+[[schedules]]
+cron = "every day"
+description = "Rent"
+debit = "expenses:rent"
+credit = "cash"
+amount = 1000.0
+currency = "USD"
+"#,
+        );
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert_eq!(problems[0], "reporting_currency: unknown currency 'EURO'");
+        assert_eq!(problems[1], "budgets[0] (line 7): unknown currency 'XYZ'");
+        assert!(
+            problems[2].starts_with("schedules[0] (line 13): invalid cron 'every day'"),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn check_config_accepts_valid_config() {
+        assert!(check_config(LEDGERS).is_empty());
+    }
+
+    const LEDGERS: &str = r#"
+password = "secret"
+[google_sheets]
+credentials_path = "cred.json"
+sheet_name = "Ledger"
+
+[[ledgers]]
+name = "personal"
+spreadsheet_id = "abc"
+
+[[ledgers]]
+name = "business"
+spreadsheet_id = "def"
+sheet_name = "Books"
+"#;
+
+    #[test]
+    fn selects_named_ledger() {
+        let cfg = parse_config(LEDGERS).unwrap();
+        let first = cfg.ledger_index(None).unwrap();
+        assert_eq!(cfg.ledger_name(first), "personal");
+        assert_eq!(cfg.sheet_name(first), Some("Ledger"));
+
+        let business = cfg.ledger_index(Some("business")).unwrap();
+        assert_eq!(cfg.ledger_name(business), "business");
+        assert_eq!(cfg.sheet_name(business), Some("Books"));
+        assert_eq!(
+            cfg.ledgers[business.unwrap()].spreadsheet_id.as_deref(),
+            Some("def")
+        );
+
+        assert!(matches!(
+            cfg.ledger_index(Some("missing")),
+            Err(CliError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_column_range() {
+        let data = LEDGERS.replace("sheet_name = \"Ledger\"", "columns = \"A-Q\"");
+        match parse_config(&data) {
+            Err(CliError::InvalidConfig(msg)) => assert_eq!(
+                msg,
+                "google_sheets.columns must be a column range such as 'A:Q', got 'A-Q'"
+            ),
+            _ => panic!("malformed column ranges must be rejected"),
+        }
+        assert_eq!(parse_columns("a:q"), Some(("a", "q")));
+    }
+
+    #[test]
+    fn rejects_duplicate_ledger_names() {
+        let data = LEDGERS.replace("\"business\"", "\"personal\"");
+        match parse_config(&data) {
+            Err(CliError::InvalidConfig(msg)) => {
+                assert_eq!(msg, "duplicate ledger name 'personal'")
+            }
+            _ => panic!("duplicate names must be rejected"),
+        }
+    }
+
+    #[test]
+    fn single_ledger_config_still_works() {
+        let cfg =
+            parse_config("name = \"solo\"\n[google_sheets]\ncredentials_path = \"cred.json\"\n")
+                .unwrap();
+        let index = cfg.ledger_index(None).unwrap();
+        assert_eq!(index, None);
+        assert_eq!(cfg.ledger_name(index), "solo");
+        assert_eq!(cfg.ledger_index(Some("solo")).unwrap(), None);
+    }
+
+    #[test]
+    fn loads_config_from_custom_path() {
+        let dir = std::env::temp_dir().join(format!("fml_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("staging.toml");
+        std::fs::write(
+            &path,
+            "name = \"staging\"\n[google_sheets]\ncredentials_path = \"cred.json\"\n",
+        )
+        .unwrap();
+
+        let cfg = load_config(&path).unwrap();
+        assert_eq!(cfg.name, "staging");
+        assert_eq!(config_relative(&path, "prices.csv"), dir.join("prices.csv"));
+        assert_eq!(cfg.prices_path(None, &path), dir.join("prices-staging.csv"));
+        assert!(matches!(
+            load_config(&dir.join("missing.toml")),
+            Err(CliError::MissingConfig)
+        ));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn config_relative_paths() {
+        assert_eq!(
+            config_relative(Path::new("config.toml"), "tokens.json"),
+            PathBuf::from("tokens.json")
+        );
+        let absolute = std::env::temp_dir().join("tokens.json");
+        assert_eq!(
+            config_relative(Path::new("envs/prod.toml"), &absolute),
+            absolute
+        );
+    }
+
+    #[test]
+    fn ledger_files_resolve_against_config() {
+        let dir = std::env::temp_dir().join(format!("fml_paths_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let cfg = parse_config(
+            "name = \"home\"\ntoken_path = \"secrets/tokens.json\"\n\
+             [google_sheets]\ncredentials_path = \"cred.json\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.token_path(None, &path),
+            dir.join("secrets").join("tokens.json")
+        );
+        assert_eq!(cfg.prices_path(None, &path), dir.join("prices-home.csv"));
+
+        // an existing shared file from older versions is still used
+        std::fs::write(dir.join("prices.csv"), "").unwrap();
+        assert_eq!(cfg.prices_path(None, &path), dir.join("prices.csv"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn configs_in_one_directory_do_not_collide() {
+        let path = Path::new("ledgers/config.toml");
+        let home =
+            parse_config("name = \"home\"\n[google_sheets]\ncredentials_path = \"c.json\"\n")
+                .unwrap();
+        let work =
+            parse_config("name = \"work books\"\n[google_sheets]\ncredentials_path = \"c.json\"\n")
+                .unwrap();
+        assert_eq!(
+            home.token_path(None, path),
+            PathBuf::from("ledgers/tokens-home.json")
+        );
+        assert_eq!(
+            work.token_path(None, path),
+            PathBuf::from("ledgers/tokens-work_books.json")
+        );
+        assert_ne!(home.prices_path(None, path), work.prices_path(None, path));
+
+        let multi = parse_config(
+            "[google_sheets]\ncredentials_path = \"c.json\"\n\
+             [[ledgers]]\nname = \"a\"\n[[ledgers]]\nname = \"b\"\n",
+        )
+        .unwrap();
+        let a = multi.ledger_index(Some("a")).unwrap();
+        let b = multi.ledger_index(Some("b")).unwrap();
+        assert_ne!(multi.token_path(a, path), multi.token_path(b, path));
     }
 }
@@ -10,6 +10,23 @@ fn record_map(record: &crate::core::Record) -> Map {
     map.insert("amount".into(), record.amount.into());
     map.insert("currency".into(), record.currency.clone().into());
     map.insert("cleared".into(), record.cleared.into());
+    let postings: Array = record.postings().map(posting_map).map(Into::into).collect();
+    map.insert("postings".into(), postings.into());
+    map
+}
+
+fn posting_map(posting: crate::core::PostingRef) -> Map {
+    let mut map = Map::new();
+    map.insert("debit".into(), posting.debit_account.to_string().into());
+    map.insert("credit".into(), posting.credit_account.to_string().into());
+    map.insert("amount".into(), posting.amount.into());
+    map.insert("currency".into(), posting.currency.to_string().into());
+    map.insert(
+        "memo".into(),
+        posting
+            .memo
+            .map_or(Dynamic::UNIT, |memo| memo.to_string().into()),
+    );
     map
 }
 
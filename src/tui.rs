@@ -0,0 +1,250 @@
+//! Terminal register browser.
+//!
+//! Shows the records of a [`SharedLedger`] as a scrollable register that can
+//! be filtered with [`Query`] syntax while typing. Toggling a record's cleared
+//! status goes through [`SharedLedger::set_cleared`].
+
+use std::str::FromStr;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::cloud_adapters::CloudSpreadsheetService;
+use crate::core::{Account, Ledger, PriceDatabase, Query, QueryParseError, Record, SharedLedger};
+
+/// One line of the register.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterLine {
+    pub record: Record,
+    /// Running balance of the queried account, when the query names exactly
+    /// one account.
+    pub balance: Option<f64>,
+}
+
+/// Records of `ledger` matching `query`, ordered by timestamp. When the query
+/// names a single account, each line carries that account's running balance
+/// in `currency`.
+pub fn filter_register(
+    ledger: &Ledger,
+    query: &str,
+    currency: &str,
+    prices: &PriceDatabase,
+) -> Result<Vec<RegisterLine>, QueryParseError> {
+    let mut q = Query::from_str(query)?;
+    q.sorted = true;
+    if let [account] = q.accounts.as_slice()
+        && let Ok(account) = account.parse::<Account>()
+    {
+        return Ok(ledger
+            .running_balance(&account, currency, prices)
+            .filter(|(rec, _)| q.matches(rec))
+            .map(|(rec, balance)| RegisterLine {
+                record: rec.clone(),
+                balance: Some(balance),
+            })
+            .collect());
+    }
+    Ok(q.filter(ledger)
+        .into_iter()
+        .map(|rec| RegisterLine {
+            record: rec.clone(),
+            balance: None,
+        })
+        .collect())
+}
+
+struct App<'a, S: CloudSpreadsheetService> {
+    shared: &'a SharedLedger<S>,
+    user: &'a str,
+    currency: &'a str,
+    prices: &'a PriceDatabase,
+    ledger: Ledger,
+    query: String,
+    editing: bool,
+    lines: Vec<RegisterLine>,
+    table: TableState,
+    message: Option<String>,
+}
+
+impl<'a, S: CloudSpreadsheetService> App<'a, S> {
+    fn new(
+        shared: &'a SharedLedger<S>,
+        user: &'a str,
+        currency: &'a str,
+        prices: &'a PriceDatabase,
+    ) -> Self {
+        let mut app = Self {
+            shared,
+            user,
+            currency,
+            prices,
+            ledger: Ledger::default(),
+            query: String::new(),
+            editing: false,
+            lines: Vec::new(),
+            table: TableState::default(),
+            message: None,
+        };
+        app.reload();
+        app
+    }
+
+    /// Rebuilds the ledger from the shared ledger so cleared flags are current.
+    fn reload(&mut self) {
+        let mut ledger = Ledger::default();
+        match self.shared.records(self.user) {
+            Ok(records) => records.into_iter().for_each(|r| ledger.commit(r)),
+            Err(e) => self.message = Some(e.to_string()),
+        }
+        self.ledger = ledger;
+        self.refilter();
+    }
+
+    /// Applies the current query. An invalid query keeps the previous lines.
+    fn refilter(&mut self) {
+        match filter_register(&self.ledger, &self.query, self.currency, self.prices) {
+            Ok(lines) => {
+                self.lines = lines;
+                self.message = None;
+                let selected = match self.table.selected() {
+                    _ if self.lines.is_empty() => None,
+                    Some(i) => Some(i.min(self.lines.len() - 1)),
+                    None => Some(0),
+                };
+                self.table.select(selected);
+            }
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    fn toggle_cleared(&mut self) {
+        let Some(line) = self.table.selected().and_then(|i| self.lines.get(i)) else {
+            return;
+        };
+        let id = line.record.id;
+        let cleared = !line.record.cleared;
+        match self.shared.set_cleared(self.user, id, cleared) {
+            Ok(()) => self.reload(),
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    /// Handles a key press. Returns `false` when the browser should exit.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if self.editing {
+            match code {
+                KeyCode::Enter | KeyCode::Esc => self.editing = false,
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.refilter();
+                }
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.refilter();
+                }
+                _ => {}
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Char('c') => self.toggle_cleared(),
+            KeyCode::Down => self.table.select_next(),
+            KeyCode::Up => self.table.select_previous(),
+            KeyCode::PageDown => self.table.scroll_down_by(10),
+            KeyCode::PageUp => self.table.scroll_up_by(10),
+            KeyCode::Home => self.table.select_first(),
+            KeyCode::End => self.table.select_last(),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [register, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let rows = self.lines.iter().map(|line| {
+            let rec = &line.record;
+            Row::new(vec![
+                rec.timestamp.format("%Y-%m-%d").to_string(),
+                if rec.cleared { "*" } else { " " }.to_string(),
+                rec.description.clone(),
+                rec.debit_account.to_string(),
+                rec.credit_account.to_string(),
+                format!("{:.2} {}", rec.amount, rec.currency),
+                line.balance.map(|b| format!("{b:.2}")).unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(1),
+                Constraint::Fill(2),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Length(14),
+                Constraint::Length(12),
+            ],
+        )
+        .header(
+            Row::new(vec![
+                "Date",
+                "C",
+                "Description",
+                "Debit",
+                "Credit",
+                "Amount",
+                "Balance",
+            ])
+            .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .block(Block::new().borders(Borders::ALL).title("Register"));
+        frame.render_stateful_widget(table, register, &mut self.table);
+
+        let text = if self.editing {
+            format!("/{}", self.query)
+        } else if let Some(message) = &self.message {
+            message.clone()
+        } else {
+            format!(
+                "{} records  [/] filter: {}  [c] toggle cleared  [q] quit",
+                self.lines.len(),
+                self.query
+            )
+        };
+        frame.render_widget(Paragraph::new(Line::from(text)), status);
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.handle_key(key.code)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Runs the register browser until the user quits. `user` must have write
+/// access to toggle cleared flags.
+pub fn run<S: CloudSpreadsheetService>(
+    shared: &SharedLedger<S>,
+    user: &str,
+    currency: &str,
+    prices: &PriceDatabase,
+) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = App::new(shared, user, currency, prices).run(&mut terminal);
+    ratatui::restore();
+    result
+}
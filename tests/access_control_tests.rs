@@ -72,3 +72,26 @@ fn access_is_required_for_reads() {
     let err = ledger.get_record("unknown@example.com", id).unwrap_err();
     assert_eq!(err, AccessError::Unauthorized);
 }
+
+#[test]
+fn revoked_user_loses_access() {
+    let adapter = GoogleSheetsAdapter::new();
+    let ledger = SharedLedger::new(adapter, "owner@example.com").unwrap();
+    ledger
+        .share_with("reader@example.com", Permission::Read)
+        .unwrap();
+    let shares = ledger.list_shares().unwrap();
+    assert!(
+        shares
+            .iter()
+            .any(|(email, _)| email == "reader@example.com")
+    );
+    assert!(ledger.records("reader@example.com").is_ok());
+
+    ledger.revoke_share("reader@example.com").unwrap();
+
+    let shares = ledger.list_shares().unwrap();
+    assert!(shares.is_empty());
+    let err = ledger.records("reader@example.com").unwrap_err();
+    assert_eq!(err, AccessError::Unauthorized);
+}
@@ -0,0 +1,218 @@
+use feed_my_ledger::core::{
+    Account, AccountParseError, AliasMap, Ledger, PriceDatabase, Record, resolve_currency,
+};
+
+#[test]
+fn parses_valid_account() {
+    let acc: Account = "Assets:Bank Account:Checking".parse().unwrap();
+    assert_eq!(acc.to_string(), "Assets:Bank Account:Checking");
+}
+
+#[test]
+fn rejects_empty_account() {
+    assert_eq!("".parse::<Account>(), Err(AccountParseError::Empty));
+}
+
+#[test]
+fn rejects_empty_middle_segment() {
+    assert_eq!(
+        "cash::food".parse::<Account>(),
+        Err(AccountParseError::EmptySegment)
+    );
+}
+
+#[test]
+fn rejects_trailing_colon() {
+    assert_eq!(
+        "cash:".parse::<Account>(),
+        Err(AccountParseError::EmptySegment)
+    );
+    assert_eq!(
+        ":cash".parse::<Account>(),
+        Err(AccountParseError::EmptySegment)
+    );
+}
+
+#[test]
+fn rejects_whitespace_only_segment() {
+    assert_eq!(
+        "cash: :food".parse::<Account>(),
+        Err(AccountParseError::EmptySegment)
+    );
+}
+
+#[test]
+fn rejects_comma() {
+    assert_eq!(
+        "expenses:food,drink".parse::<Account>(),
+        Err(AccountParseError::InvalidCharacter(','))
+    );
+}
+
+#[test]
+fn rejects_newline() {
+    assert_eq!(
+        "expenses\nfood".parse::<Account>(),
+        Err(AccountParseError::InvalidCharacter('\n'))
+    );
+}
+
+#[test]
+fn rejects_surrounding_whitespace() {
+    assert_eq!(
+        " Assets:Bank".parse::<Account>(),
+        Err(AccountParseError::SurroundingWhitespace(" Assets".into()))
+    );
+    assert_eq!(
+        "Assets :Bank".parse::<Account>(),
+        Err(AccountParseError::SurroundingWhitespace("Assets ".into()))
+    );
+}
+
+#[test]
+fn lenient_parse_trims_and_skips_empty_segments() {
+    let acc = Account::parse_lenient(" Assets : :Smith, John:");
+    assert_eq!(acc.to_string(), "Assets:Smith John");
+    assert_eq!(acc.to_string().parse::<Account>(), Ok(acc));
+}
+
+#[test]
+fn lenient_parse_always_round_trips() {
+    for name in ["", " : ", "A,B\r\nC", "Café, Inc.:Store #4"] {
+        let acc = Account::parse_lenient(name);
+        assert_eq!(
+            acc.to_string().parse::<Account>(),
+            Ok(acc.clone()),
+            "{name:?}"
+        );
+    }
+    assert_eq!(Account::parse_lenient(",").to_string(), "unknown");
+}
+
+#[test]
+fn deserialize_rejects_invalid_account() {
+    let result: Result<Account, _> = serde_json::from_str("\"cash::food\"");
+    assert!(result.is_err());
+}
+
+#[test]
+fn parent_and_depth() {
+    let acc: Account = "Assets:Bank:Checking".parse().unwrap();
+    assert_eq!(acc.depth(), 3);
+    let parent = acc.parent().unwrap();
+    assert_eq!(parent.to_string(), "Assets:Bank");
+    assert_eq!(parent.parent().unwrap().to_string(), "Assets");
+    assert_eq!(parent.parent().unwrap().parent(), None);
+
+    assert_eq!(acc.truncated(1).to_string(), "Assets");
+    assert_eq!(acc.truncated(5), acc);
+    // truncating to zero keeps the top-level segment
+    assert_eq!(acc.truncated(0).to_string(), "Assets");
+    assert_eq!(acc.truncated(0).depth(), 1);
+}
+
+#[test]
+fn balances_by_level_rolls_up_subaccounts() {
+    let mut ledger = Ledger::default();
+    for (debit, credit, amount) in [
+        ("Assets:Bank:Checking", "Equity:Opening", 100.0),
+        ("Assets:Bank:Savings", "Equity:Opening", 50.0),
+        ("Expenses:Food", "Assets:Bank:Checking", 20.0),
+    ] {
+        ledger.commit(
+            Record::new(
+                "entry".into(),
+                debit.parse().unwrap(),
+                credit.parse().unwrap(),
+                amount,
+                "USD".into(),
+                None,
+                None,
+                vec![],
+            )
+            .unwrap(),
+        );
+    }
+    let prices = PriceDatabase::default();
+
+    let level1 = ledger.balances_by_level(1, "USD", &prices);
+    assert_eq!(level1["Assets"], 130.0);
+    assert_eq!(level1["Equity"], -150.0);
+    assert_eq!(level1["Expenses"], 20.0);
+    assert_eq!(level1.len(), 3);
+
+    let level2 = ledger.balances_by_level(2, "USD", &prices);
+    assert_eq!(level2["Assets:Bank"], 130.0);
+    assert_eq!(level2["Equity:Opening"], -150.0);
+}
+
+#[test]
+fn prefix_alias_renames_subaccounts_in_balances() {
+    let acc = |s: &str| s.parse::<Account>().unwrap();
+    let mut aliases = AliasMap::default();
+    aliases.insert(acc("expenses:food"), acc("Expenses:Groceries"));
+    aliases.insert(acc("expenses:food:dining"), acc("Expenses:Restaurants"));
+    assert_eq!(
+        aliases.resolve(&acc("expenses:food:coffee")),
+        acc("Expenses:Groceries:coffee")
+    );
+    // The longest matching prefix wins.
+    assert_eq!(
+        aliases.resolve(&acc("expenses:food:dining:lunch")),
+        acc("Expenses:Restaurants:lunch")
+    );
+    // Segments are matched whole, not as string prefixes.
+    assert_eq!(
+        aliases.resolve(&acc("expenses:foodbank")),
+        acc("expenses:foodbank")
+    );
+
+    let mut ledger = Ledger::default();
+    ledger.commit(
+        Record::new(
+            "Latte".into(),
+            acc("expenses:food:coffee"),
+            acc("cash"),
+            4.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap(),
+    );
+    let ledger = ledger.with_aliases(&aliases);
+    let prices = PriceDatabase::default();
+    let groceries = acc("Expenses:Groceries");
+    assert_eq!(ledger.account_tree_balance(&groceries, "USD", &prices), 4.0);
+    assert_eq!(
+        ledger.account_balance("Expenses:Groceries:coffee", "USD", &prices),
+        4.0
+    );
+    assert_eq!(
+        ledger.account_balance("expenses:food:coffee", "USD", &prices),
+        0.0
+    );
+}
+
+#[test]
+fn currency_defaults_by_account_prefix_unless_explicit() {
+    let acc = |s: &str| s.parse::<Account>().unwrap();
+    let defaults = vec![
+        (acc("assets:bank"), "EUR".to_string()),
+        (acc("assets:bank:us"), "USD".to_string()),
+    ];
+    assert_eq!(
+        resolve_currency(&acc("assets:bank:checking"), &defaults, None).as_deref(),
+        Some("EUR")
+    );
+    assert_eq!(
+        resolve_currency(&acc("assets:bank:us:savings"), &defaults, None).as_deref(),
+        Some("USD")
+    );
+    assert_eq!(
+        resolve_currency(&acc("assets:bank"), &defaults, Some("GBP")).as_deref(),
+        Some("GBP")
+    );
+    assert_eq!(resolve_currency(&acc("cash"), &defaults, None), None);
+}
@@ -0,0 +1,64 @@
+use chrono::{NaiveDate, TimeZone, Utc};
+use feed_my_ledger::core::{AssertionError, Ledger, PriceDatabase, Record};
+
+fn record(debit: &str, amount: f64, currency: &str, day: u32) -> Record {
+    let mut rec = Record::new(
+        "purchase".into(),
+        debit.parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        currency.into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap();
+    rec
+}
+
+fn setup() -> (Ledger, PriceDatabase) {
+    let mut ledger = Ledger::default();
+    ledger.commit(record("expenses:food", 20.0, "USD", 1));
+    ledger.commit(record("expenses:travel", 100.0, "EUR", 5));
+    ledger.commit(record("expenses:food", 50.0, "USD", 20));
+    let mut prices = PriceDatabase::default();
+    prices.add_rate(
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        "EUR",
+        "USD",
+        1.1,
+    );
+    (ledger, prices)
+}
+
+#[test]
+fn assertion_passes_with_converted_balance() {
+    let (ledger, prices) = setup();
+    let expenses = "expenses".parse().unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+    // 20 USD + 100 EUR at 1.1; the record on the 20th is excluded
+    ledger
+        .check_assertion(&expenses, date, 130.0, "USD", &prices)
+        .unwrap();
+}
+
+#[test]
+fn assertion_failure_reports_actual_balance() {
+    let (ledger, prices) = setup();
+    let expenses = "expenses".parse().unwrap();
+    let date = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+    let err = ledger
+        .check_assertion(&expenses, date, 130.0, "USD", &prices)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        AssertionError {
+            account: expenses,
+            as_of: date,
+            expected: 130.0,
+            actual: 180.0,
+            currency: "USD".into(),
+        }
+    );
+}
@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use feed_my_ledger::core::{Attachment, Record};
+
+fn record_with_reference(reference: &str) -> Record {
+    Record::new(
+        "Office chair".into(),
+        "expenses:office".parse().unwrap(),
+        "cash".parse().unwrap(),
+        120.0,
+        "USD".into(),
+        None,
+        Some(reference.into()),
+        vec![],
+    )
+    .unwrap()
+}
+
+#[test]
+fn file_reference_resolves_to_a_local_path() {
+    let record = record_with_reference("file:///home/me/receipts/chair.pdf");
+    let attachment = record.attachment().unwrap();
+    assert_eq!(
+        attachment,
+        Attachment::Local(PathBuf::from("/home/me/receipts/chair.pdf"))
+    );
+    assert!(!attachment.is_remote());
+    assert_eq!(
+        Attachment::parse("file://localhost/tmp/r.png"),
+        Some(Attachment::Local(PathBuf::from("/tmp/r.png")))
+    );
+}
+
+#[test]
+fn https_reference_is_remote() {
+    let record = record_with_reference("https://example.com/receipts/42.pdf");
+    let attachment = record.attachment().unwrap();
+    assert!(attachment.is_remote());
+    assert_eq!(
+        attachment.to_string(),
+        "https://example.com/receipts/42.pdf"
+    );
+}
+
+#[test]
+fn other_references_are_not_attachments() {
+    assert_eq!(record_with_reference("INV-2024-001").attachment(), None);
+    assert_eq!(Attachment::parse("http://example.com/r.pdf"), None);
+    assert_eq!(Attachment::parse("https://"), None);
+}
@@ -0,0 +1,73 @@
+use feed_my_ledger::import::beancount;
+
+#[test]
+fn parses_two_posting_transaction() {
+    let journal = r#"
+option "title" "Personal"
+2024-01-01 open Assets:Cash USD
+2024-01-01 open Expenses:Food
+
+2024-01-05 * "Cafe" "Morning coffee"
+  Expenses:Food      4.50 USD
+  Assets:Cash       -4.50 USD
+
+2024-01-31 balance Assets:Cash  -4.50 USD
+2024-02-01 price EUR 1.10 USD
+"#;
+    let records = beancount::parse_str(journal).unwrap();
+    assert_eq!(records.len(), 1);
+    let rec = &records[0];
+    assert_eq!(rec.description, "Morning coffee");
+    assert_eq!(rec.metadata.get("payee").map(String::as_str), Some("Cafe"));
+    assert_eq!(rec.debit_account.to_string(), "Expenses:Food");
+    assert_eq!(rec.credit_account.to_string(), "Assets:Cash");
+    assert_eq!(rec.amount, 4.5);
+    assert_eq!(rec.currency, "USD");
+    assert!(rec.splits.is_empty());
+    assert!(rec.cleared);
+}
+
+#[test]
+fn parses_three_posting_transaction_as_split() {
+    let journal = r#"
+2024-02-01 * "Employer" "Paycheck"
+  Assets:Checking    1800.00 EUR
+  Expenses:Tax        200.00 EUR
+  invoice: "P-12"
+  Income:Salary
+"#;
+    let records = beancount::parse_str(journal).unwrap();
+    assert_eq!(records.len(), 1);
+    let rec = &records[0];
+    assert_eq!(rec.splits.len(), 1);
+    let total: f64 = rec.postings().map(|p| p.amount).sum();
+    assert_eq!(total, 2000.0);
+    assert!(
+        rec.postings()
+            .all(|p| p.credit_account.to_string() == "Income:Salary")
+    );
+}
+
+#[test]
+fn pending_flag_is_not_cleared() {
+    let journal = r#"
+2024-03-01 ! "Pending transfer"
+  Assets:Savings     100 USD
+  Assets:Checking   -100 USD
+"#;
+    let records = beancount::parse_str(journal).unwrap();
+    assert_eq!(records.len(), 1);
+    assert!(!records[0].cleared);
+    assert_eq!(records[0].description, "Pending transfer");
+    assert!(records[0].metadata.is_empty());
+}
+
+#[test]
+fn rejects_unbalanced_transaction() {
+    let journal = r#"
+2024-03-02 * "Oops"
+  Expenses:Food   5 USD
+  Assets:Cash    -4 USD
+"#;
+    assert!(beancount::parse_str(journal).is_err());
+}
@@ -0,0 +1,130 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use feed_my_ledger::cloud_adapters::{BinAdapter, CloudSpreadsheetService, SpreadsheetError};
+use uuid::Uuid;
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ledger_bin_{}", Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn row(cells: &[&str]) -> Vec<String> {
+    cells.iter().map(|c| c.to_string()).collect()
+}
+
+#[test]
+fn append_read_and_list_round_trip() {
+    let dir = temp_dir();
+    let mut adapter = BinAdapter::new(&dir);
+    let id = adapter.create_sheet("test").unwrap();
+    adapter.append_row(&id, row(&["a", "1"])).unwrap();
+    adapter
+        .append_rows(
+            &id,
+            vec![row(&["b", "with,comma"]), row(&["c", "", "line\nbreak"])],
+        )
+        .unwrap();
+
+    assert_eq!(adapter.read_row(&id, 0).unwrap(), row(&["a", "1"]));
+    assert_eq!(
+        adapter.read_row(&id, 2).unwrap(),
+        row(&["c", "", "line\nbreak"])
+    );
+    assert_eq!(
+        adapter.read_row(&id, 3).unwrap_err(),
+        SpreadsheetError::RowNotFound
+    );
+    assert_eq!(
+        adapter.list_rows(&id).unwrap(),
+        vec![
+            row(&["a", "1"]),
+            row(&["b", "with,comma"]),
+            row(&["c", "", "line\nbreak"]),
+        ]
+    );
+
+    // A second adapter over the same directory sees the same rows.
+    let reopened = BinAdapter::new(&dir);
+    assert_eq!(
+        reopened.read_row(&id, 1).unwrap(),
+        row(&["b", "with,comma"])
+    );
+    assert_eq!(
+        reopened.list_rows("missing").unwrap_err(),
+        SpreadsheetError::SheetNotFound
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn truncated_trailing_record_is_detected() {
+    let dir = temp_dir();
+    let mut adapter = BinAdapter::new(&dir);
+    let id = adapter.create_sheet("test").unwrap();
+    adapter
+        .append_rows(&id, vec![row(&["a"]), row(&["b", "c"])])
+        .unwrap();
+    let log = dir.join(format!("{id}.bin"));
+    let len = std::fs::metadata(&log).unwrap().len();
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&log)
+        .unwrap()
+        .set_len(len - 3)
+        .unwrap();
+
+    assert_eq!(adapter.read_row(&id, 0).unwrap(), row(&["a"]));
+    assert!(matches!(
+        adapter.read_row(&id, 1),
+        Err(SpreadsheetError::Permanent(_))
+    ));
+    assert!(matches!(
+        adapter.list_rows(&id),
+        Err(SpreadsheetError::Permanent(_))
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupt_trailing_record_is_detected() {
+    let dir = temp_dir();
+    let mut adapter = BinAdapter::new(&dir);
+    let id = adapter.create_sheet("test").unwrap();
+    adapter.append_row(&id, row(&["a"])).unwrap();
+    // A complete length prefix followed by bytes that do not decode.
+    let mut log = std::fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join(format!("{id}.bin")))
+        .unwrap();
+    log.write_all(&4u32.to_le_bytes()).unwrap();
+    log.write_all(&[0xff; 4]).unwrap();
+
+    let err = adapter.list_rows(&id).unwrap_err();
+    assert!(
+        matches!(&err, SpreadsheetError::Permanent(msg) if msg.contains("corrupt")),
+        "{err:?}"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn lock_timeout_is_transient() {
+    let dir = temp_dir();
+    let mut adapter = BinAdapter::new(&dir).with_lock_timeout(std::time::Duration::from_millis(50));
+    let id = adapter.create_sheet("test").unwrap();
+    let held = std::fs::File::open(dir.join(format!("{id}.bin"))).unwrap();
+    held.lock().unwrap();
+
+    let err = adapter.append_row(&id, row(&["a"])).unwrap_err();
+    assert!(matches!(err, SpreadsheetError::Transient(_)));
+    assert!(matches!(
+        adapter.list_rows(&id).unwrap_err(),
+        SpreadsheetError::Transient(_)
+    ));
+    drop(held);
+    adapter.append_row(&id, row(&["a"])).unwrap();
+    assert_eq!(adapter.list_rows(&id).unwrap(), vec![row(&["a"])]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
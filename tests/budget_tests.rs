@@ -1,5 +1,7 @@
-use chrono::{TimeZone, Utc};
-use feed_my_ledger::core::{Budget, BudgetBook, Ledger, Period, PriceDatabase, Record};
+use chrono::{NaiveDate, TimeZone, Utc};
+use feed_my_ledger::core::{
+    Budget, BudgetBook, Ledger, MissingRate, Period, PriceDatabase, Record,
+};
 
 #[test]
 fn monthly_budget_diff() {
@@ -40,6 +42,55 @@ fn monthly_budget_diff() {
     assert_eq!(diff, 20.0);
 }
 
+#[test]
+fn monthly_budget_reports_missing_rates() {
+    let mut ledger = Ledger::default();
+    let mut rec = Record::new(
+        "lunch in London".into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        12.0,
+        "GBP".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, 5, 2, 0, 0, 0).unwrap();
+    ledger.commit(rec);
+    let mut book = BudgetBook::default();
+    book.add(
+        Budget {
+            account: "expenses:food".parse().unwrap(),
+            amount: 50.0,
+            currency: "USD".into(),
+            period: Period::Monthly,
+        },
+        Some(2024),
+        Some(5),
+    );
+    let account = "expenses:food".parse().unwrap();
+    let prices = PriceDatabase::default();
+    assert_eq!(
+        book.compare_month(&ledger, &prices, &account, 2024, 5),
+        Some(50.0)
+    );
+    assert_eq!(
+        book.try_compare_month(&ledger, &prices, &account, 2024, 5),
+        Err(MissingRate {
+            pairs: vec![(
+                NaiveDate::from_ymd_opt(2024, 5, 2).unwrap(),
+                "GBP".into(),
+                "USD".into()
+            )],
+        })
+    );
+    assert_eq!(
+        book.try_compare_month(&ledger, &prices, &account, 2024, 6),
+        Ok(None)
+    );
+}
+
 #[test]
 fn yearly_budget_diff() {
     let mut ledger = Ledger::default();
@@ -79,3 +130,55 @@ fn yearly_budget_diff() {
         .unwrap();
     assert_eq!(diff, 30.0);
 }
+
+#[test]
+fn account_series_buckets_monthly_expenses() {
+    use chrono::NaiveDate;
+
+    let mut ledger = Ledger::default();
+    for month in 1..=12 {
+        let mut rec = Record::new(
+            "rent".into(),
+            "expenses:housing".parse().unwrap(),
+            "cash".parse().unwrap(),
+            100.0 + month as f64,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        rec.timestamp = Utc.with_ymd_and_hms(2024, month, 3, 0, 0, 0).unwrap();
+        ledger.commit(rec);
+    }
+    // outside the requested range
+    let mut late = Record::new(
+        "rent".into(),
+        "expenses:housing".parse().unwrap(),
+        "cash".parse().unwrap(),
+        500.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    late.timestamp = Utc.with_ymd_and_hms(2025, 1, 3, 0, 0, 0).unwrap();
+    ledger.commit(late);
+
+    let account = "expenses".parse().unwrap();
+    let prices = PriceDatabase::default();
+    let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let end = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+
+    let series = ledger.account_series(&account, "USD", &prices, start, end, Period::Monthly);
+    assert_eq!(series.len(), 12);
+    for (i, (date, total)) in series.iter().enumerate() {
+        let month = i as u32 + 1;
+        assert_eq!(*date, NaiveDate::from_ymd_opt(2024, month, 1).unwrap());
+        assert_eq!(*total, 100.0 + month as f64);
+    }
+
+    let yearly = ledger.account_series(&account, "USD", &prices, start, end, Period::Yearly);
+    assert_eq!(yearly, vec![(start, 1278.0)]);
+}
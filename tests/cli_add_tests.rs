@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"add\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n\
+         [account_currencies]\n\"expenses:travel\" = \"EUR\"\n\"assets:bank\" = \"CHF\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn add(dir: &Path, debit: &str, credit: &str, currency: Option<&str>) -> Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"));
+    cmd.current_dir(dir)
+        .args(["--local-dir", "data", "add", "--description", "Train"])
+        .args(["--debit", debit, "--credit", credit, "--amount", "12"]);
+    if let Some(currency) = currency {
+        cmd.args(["--currency", currency]);
+    }
+    cmd.output().unwrap()
+}
+
+fn currencies(dir: &Path) -> Vec<String> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(dir.join("data/ledger.csv"))
+        .unwrap();
+    rdr.records()
+        .map(|row| row.unwrap()[6].to_string())
+        .collect()
+}
+
+#[test]
+fn add_falls_back_to_account_default_currency() {
+    let dir = workspace("add_currency");
+    // The debit account's default wins over the credit account's.
+    let out = add(&dir, "expenses:travel:rail", "assets:bank", None);
+    assert!(out.status.success(), "{out:?}");
+    let out = add(&dir, "expenses:food", "assets:bank", None);
+    assert!(out.status.success(), "{out:?}");
+    let out = add(&dir, "expenses:travel", "assets:bank", Some("USD"));
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(currencies(&dir), vec!["EUR", "CHF", "USD"]);
+
+    let out = add(&dir, "expenses:food", "cash", None);
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("no --currency given"), "{stderr}");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
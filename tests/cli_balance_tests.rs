@@ -0,0 +1,258 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::{TimeZone, Utc};
+use feed_my_ledger::core::Record;
+use feed_my_ledger::core::utils::generate_signature;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"balance\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn write_records(dir: &Path, records: &[Record]) {
+    let sig = generate_signature("balance", None).unwrap();
+    let rows: Vec<String> = records
+        .iter()
+        .map(|r| {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(r.to_row_hashed(&sig)).unwrap();
+            String::from_utf8(writer.into_inner().unwrap()).unwrap()
+        })
+        .collect();
+    std::fs::write(dir.join("data/ledger.csv"), rows.concat()).unwrap();
+}
+
+fn record(debit: &str, amount: f64, currency: &str, month: u32) -> Record {
+    let mut rec = Record::new(
+        "Purchase".into(),
+        debit.parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        currency.into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, month, 15, 12, 0, 0).unwrap();
+    rec
+}
+
+#[test]
+fn as_of_balance_excludes_later_records() {
+    let dir = workspace("balance_as_of");
+    write_records(
+        &dir,
+        &[
+            record("expenses:food", 10.0, "USD", 4),
+            record("expenses:food:coffee", 5.0, "USD", 6),
+            record("expenses:food", 20.0, "EUR", 6),
+            record("expenses:food", 100.0, "USD", 7),
+        ],
+    );
+    // The EUR record converts at the rate effective on the as-of date, not
+    // the one from its own date.
+    std::fs::write(
+        dir.join("prices.csv"),
+        "date,from,to,rate\n2024-06-01,EUR,USD,1.5\n2024-06-20,EUR,USD,2\n",
+    )
+    .unwrap();
+
+    let balance = |as_of: &str| {
+        run(
+            &dir,
+            &[
+                "balance",
+                "--account",
+                "expenses:food",
+                "--as-of",
+                as_of,
+                "--currency",
+                "USD",
+            ],
+        )
+    };
+    assert_eq!(balance("2024-05-31").trim(), "10.00 USD");
+    assert_eq!(balance("2024-06-15").trim(), "45.00 USD");
+    assert_eq!(balance("2024-06-30").trim(), "55.00 USD");
+    assert_eq!(balance("2024-12-31").trim(), "155.00 USD");
+
+    let json = run(
+        &dir,
+        &[
+            "--output",
+            "json",
+            "balance",
+            "--account",
+            "expenses:food",
+            "--as-of",
+            "2024-05-31",
+            "--currency",
+            "USD",
+        ],
+    );
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["amount"], 10.0);
+    assert_eq!(value["as_of"], "2024-05-31");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reporting_currency_is_used_and_missing_rates_fail() {
+    let dir = workspace("balance_reporting_currency");
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"balance\"\nreporting_currency = \"USD\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n\
+         [[budgets]]\naccount = \"expenses:food\"\namount = 100.0\ncurrency = \"USD\"\nperiod = \"yearly\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("prices.csv"),
+        "date,from,to,rate\n2024-06-01,EUR,USD,1.5\n",
+    )
+    .unwrap();
+    write_records(
+        &dir,
+        &[
+            record("expenses:food", 10.0, "USD", 4),
+            record("expenses:food", 20.0, "EUR", 6),
+        ],
+    );
+    let out = run(&dir, &["balance", "--account", "expenses:food"]);
+    assert_eq!(out.trim(), "40.00 USD");
+
+    // Without a GBP rate the balance must fail rather than leave it out.
+    write_records(
+        &dir,
+        &[
+            record("expenses:food", 10.0, "USD", 4),
+            record("expenses:food", 20.0, "EUR", 6),
+            record("expenses:food", 7.0, "GBP", 7),
+        ],
+    );
+    for args in [
+        &["balance", "--account", "expenses:food"][..],
+        &["trial-balance"][..],
+        &[
+            "budget",
+            "report",
+            "--account",
+            "expenses:food",
+            "--year",
+            "2024",
+        ][..],
+        &["register", "--query", "account:expenses:food"][..],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+            .current_dir(&dir)
+            .args(["--local-dir", "data"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{args:?} succeeded");
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("no exchange rate for GBP to USD on 2024-07-15"),
+            "{stderr}"
+        );
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn aliases_rename_accounts_in_reports() {
+    let dir = workspace("balance_aliases");
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"balance\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n\n[aliases]\n\"expenses:food\" = \"Expenses:Groceries\"\n",
+    )
+    .unwrap();
+    write_records(
+        &dir,
+        &[
+            record("expenses:food", 10.0, "USD", 4),
+            record("expenses:food:coffee", 5.0, "USD", 6),
+            record("expenses:rent", 100.0, "USD", 6),
+        ],
+    );
+
+    let balance = |account: &str| {
+        run(
+            &dir,
+            &["balance", "--account", account, "--currency", "USD"],
+        )
+    };
+    assert_eq!(balance("Expenses:Groceries").trim(), "15.00 USD");
+    assert_eq!(balance("Expenses:Groceries:coffee").trim(), "5.00 USD");
+    assert_eq!(balance("expenses:food").trim(), "0.00 USD");
+
+    let out = run(&dir, &["trial-balance"]);
+    assert!(out.contains("Expenses:Groceries | 10.00"), "{out}");
+    assert!(out.contains("Expenses:Groceries:coffee | 5.00"), "{out}");
+    assert!(out.contains("expenses:rent | 100.00"), "{out}");
+    assert!(!out.contains("expenses:food"), "{out}");
+
+    // The stored rows keep the original accounts.
+    let stored = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert!(stored.contains("expenses:food:coffee"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn tag_report_prints_totals_sorted_by_tag() {
+    let dir = workspace("tag_report");
+    let mut website = record("expenses:design", 40.0, "USD", 1);
+    website.tags = vec!["website".into(), "client-a".into()];
+    let mut hosting = record("expenses:hosting", 10.0, "USD", 2);
+    hosting.tags = vec!["website".into()];
+    write_records(
+        &dir,
+        &[website, hosting, record("expenses:food", 3.0, "USD", 2)],
+    );
+
+    let out = run(&dir, &["tag-report", "--currency", "USD"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines, vec!["client-a | 40.00 USD", "website | 50.00 USD"]);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn stale_lists_old_uncleared_records() {
+    let dir = workspace("stale");
+    let old = record("expenses:rent", 500.0, "USD", 1);
+    let id = old.id;
+    write_records(&dir, &[record("expenses:food", 3.0, "USD", 3), old]);
+
+    let out = run(&dir, &["stale", "--days", "30", "--as-of", "2024-03-20"]);
+    assert_eq!(
+        out.lines().collect::<Vec<_>>(),
+        vec![format!("2024-01-15 | {id} | 500.00 USD | Purchase")]
+    );
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use feed_my_ledger::core::Record;
+use feed_my_ledger::core::utils::generate_signature;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    dir
+}
+
+fn cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn missing_config_exits_with_config_code() {
+    let dir = workspace("exit_missing_config");
+    let out = cli(&dir, &["--config", "missing.toml", "list"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("error: config file not found"), "{stderr}");
+}
+
+#[test]
+fn unknown_sheet_exits_with_spreadsheet_code() {
+    let dir = workspace("exit_no_sheet");
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"exit\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    let out = cli(&dir, &["list"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(6), "{stderr}");
+}
+
+#[test]
+fn verify_exits_with_tamper_code() {
+    let dir = workspace("exit_tampered");
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"exit\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,cleared,hash\n",
+    )
+    .unwrap();
+    let add = cli(
+        &dir,
+        &[
+            "add",
+            "--description",
+            "Coffee",
+            "--debit",
+            "expenses:food",
+            "--credit",
+            "cash",
+            "--amount",
+            "3.50",
+            "--currency",
+            "USD",
+        ],
+    );
+    assert!(add.status.success(), "{add:?}");
+    let path = dir.join("data/ledger.csv");
+    let data = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, data.replace("Coffee", "Tea")).unwrap();
+
+    let out = cli(&dir, &["verify"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(4), "{stderr}");
+    assert!(stderr.contains("tampering detected in 1 rows"), "{stderr}");
+}
+
+#[test]
+fn config_check_reports_problems_and_fails() {
+    let dir = workspace("exit_config_check");
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"exit\"\n[google_sheets]\ncredentials_path = \"unused.json\"\n\n\
+         [[budgets]]\naccount = \"expenses\"\namount = 1.0\ncurrency = \"XYZ\"\nperiod = \"monthly\"\n\n\
+         [[schedules]]\ncron = \"0 0 1 * *\"\ndescription = \"Rent\"\ndebit = \"expenses:rent\"\n\
+         credit = \"cash\"\namount = 1.0\ncurrency = \"USD\"\n",
+    )
+    .unwrap();
+    let out = cli(&dir, &["config-check"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(2), "{stderr}");
+    assert!(
+        stderr.contains("budgets[0] (line 5): unknown currency 'XYZ'"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("schedules[0] (line 11): invalid cron '0 0 1 * *'"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("error: invalid configuration: 2 problems found"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn check_reports_dangling_references_and_fails() {
+    let dir = workspace("exit_check");
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"exit\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    let mut rec = Record::new(
+        "Refund".into(),
+        "cash".parse().unwrap(),
+        "expenses:food".parse().unwrap(),
+        3.5,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    let write = |rec: &Record| {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record(rec.to_row_hashed(&generate_signature("exit", None).unwrap()))
+            .unwrap();
+        std::fs::write(dir.join("data/ledger.csv"), writer.into_inner().unwrap()).unwrap();
+    };
+    write(&rec);
+    let out = cli(&dir, &["check"]);
+    assert!(out.status.success(), "{out:?}");
+    assert!(String::from_utf8_lossy(&out.stdout).contains("All 1 records are consistent"));
+
+    let missing = uuid::Uuid::new_v4();
+    rec.reference_id = Some(missing);
+    write(&rec);
+    let out = cli(&dir, &["check"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(1), "{stderr}");
+    assert!(
+        stderr.contains(&format!(
+            "record {} references missing record {missing}",
+            rec.id
+        )),
+        "{stderr}"
+    );
+    assert!(stderr.contains("error: 1 problems found"), "{stderr}");
+}
@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use feed_my_ledger::core::Record;
+use feed_my_ledger::import::json;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"dry-run\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    // The first row of a sheet is treated as the header.
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn record(desc: &str, amount: f64) -> Record {
+    Record::new(
+        desc.into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data", "import"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn dry_run_reports_without_writing() {
+    let dir = workspace("dry_run");
+    let coffee = record("Coffee", 3.5);
+    let tea = record("Tea", 2.0);
+    let lunch = record("Lunch", 12.0);
+    json::export(&dir.join("first.json"), &[coffee.clone(), tea.clone()]).unwrap();
+    json::export(&dir.join("second.json"), &[coffee, tea, lunch]).unwrap();
+
+    run(&dir, &["--file", "first.json"]);
+    let before = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(before.lines().count(), 3);
+
+    let out = run(&dir, &["--file", "second.json", "--dry-run"]);
+    assert!(out.contains("Dry run: 1 new, 2 duplicate"), "{out}");
+    assert!(out.contains("expenses:food 12.00 USD"), "{out}");
+    assert!(out.contains("cash -12.00 USD"), "{out}");
+    let after = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(before, after);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn multiple_files_are_deduplicated_together() {
+    let dir = workspace("multi_file");
+    let coffee = record("Coffee", 3.5);
+    let tea = record("Tea", 2.0);
+    let lunch = record("Lunch", 12.0);
+    json::export(&dir.join("january.json"), &[coffee, tea.clone()]).unwrap();
+    json::export(&dir.join("february.json"), &[tea, lunch]).unwrap();
+    std::fs::write(
+        dir.join("march.ofx"),
+        "<OFX><STMTTRN><TRNAMT>-4.00</TRNAMT><NAME>Bakery</NAME></STMTTRN></OFX>",
+    )
+    .unwrap();
+
+    run(
+        &dir,
+        &[
+            "--file",
+            "january.json",
+            "--file",
+            "february.json",
+            "--file",
+            "march.ofx",
+        ],
+    );
+    let sheet = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    let descriptions: Vec<&str> = sheet
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').nth(2).unwrap())
+        .collect();
+    assert_eq!(descriptions, vec!["Coffee", "Tea", "Lunch", "Bakery"]);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn validate_reports_every_bad_row_without_writing() {
+    let dir = workspace("validate");
+    std::fs::write(
+        dir.join("statement.csv"),
+        "description,debit_account,credit_account,amount,currency\n\
+         Coffee,expenses:food,cash,3.50,USD\n\
+         Broken,expenses:food,cash,abc,USD\n\
+         Tea,expenses:food,cash,2.00,USD\n\
+         Nowhere,,cash,1.00,USD\n\
+         Lunch,expenses:food,cash,12.00,USD\n",
+    )
+    .unwrap();
+    let before = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(&dir)
+        .args(["--local-dir", "data", "import"])
+        .args(["--file", "statement.csv", "--validate"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let out = String::from_utf8_lossy(&output.stdout);
+    assert!(out.contains("statement.csv:3:"), "{out}");
+    assert!(out.contains("statement.csv:5:"), "{out}");
+    let err = String::from_utf8_lossy(&output.stderr);
+    assert!(err.contains("2 invalid rows"), "{err}");
+    let after = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(before, after);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn import_preview_suggests_mapping_without_writing() {
+    let dir = workspace("import_preview");
+    let before = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    std::fs::write(
+        dir.join("bank.csv"),
+        "Date,PAYEE,Debit Account,Credit Account,Withdrawal,Deposit\n\
+         2024-01-02,Coffee,expenses:food,cash,4.50,\n\
+         2024-01-03,Salary,cash,income:salary,,100\n\
+         2024-01-04,Rent,expenses:rent,cash,900,\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(&dir)
+        .args([
+            "--local-dir",
+            "data",
+            "import-preview",
+            "--file",
+            "bank.csv",
+        ])
+        .args(["--currency", "USD", "--rows", "2"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let out = String::from_utf8(output.stdout).unwrap();
+    assert!(out.contains("--map-description \"PAYEE\""), "{out}");
+    assert!(out.contains("--map-debit-amount \"Withdrawal\""), "{out}");
+    assert!(out.contains("--map-credit-amount \"Deposit\""), "{out}");
+    assert!(
+        out.contains("line 3: Salary | income:salary -> cash | 100.00 USD"),
+        "{out}"
+    );
+    assert!(!out.contains("Rent"), "{out}");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap(),
+        before
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn import_template_with_mapping_imports_back() {
+    let dir = workspace("import_template");
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(&dir)
+        .args(["import-template", "--file", "template.csv", "--example"])
+        .args(["--map-description", "Payee"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let template = std::fs::read_to_string(dir.join("template.csv")).unwrap();
+    assert!(
+        template.starts_with("Payee,debit_account,credit_account,amount,currency\n"),
+        "{template}"
+    );
+
+    run(
+        &dir,
+        &["--file", "template.csv", "--map-description", "Payee"],
+    );
+    let ledger = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert!(ledger.contains("Coffee"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reimporting_a_dated_statement_writes_nothing() {
+    let dir = workspace("reimport_qif");
+    std::fs::write(
+        dir.join("statement.qif"),
+        "!Type:Bank\nD01/05/2024\nT-3.50\nPCoffee\n^\nD01/06/2024\nT-2.00\nPTea\n^\n",
+    )
+    .unwrap();
+
+    run(&dir, &["--file", "statement.qif"]);
+    let before = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(before.lines().count(), 3);
+
+    // Every line gets a fresh id on the second import.
+    run(&dir, &["--file", "statement.qif"]);
+    let after = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(before, after);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn identical_undated_lines_in_different_statements_are_both_written() {
+    let dir = workspace("recurring_csv");
+    let header = "description,debit_account,credit_account,amount,currency\n";
+    std::fs::write(
+        dir.join("march.csv"),
+        format!("{header}Netflix,expenses:subs,bank,15.99,USD\nRent,expenses:rent,bank,900,USD\n"),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("april.csv"),
+        format!("{header}Netflix,expenses:subs,bank,15.99,USD\n"),
+    )
+    .unwrap();
+
+    run(&dir, &["--file", "march.csv"]);
+    run(&dir, &["--file", "april.csv"]);
+    let ledger = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(ledger.matches("Netflix").count(), 2, "{ledger}");
+    assert_eq!(ledger.lines().count(), 4);
+    let _ = std::fs::remove_dir_all(dir);
+}
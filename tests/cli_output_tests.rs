@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"output\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn add(dir: &Path, description: &str, amount: &str) {
+    run(
+        dir,
+        &[
+            "add",
+            "--description",
+            description,
+            "--debit",
+            "expenses:food",
+            "--credit",
+            "cash",
+            "--amount",
+            amount,
+            "--currency",
+            "USD",
+        ],
+    );
+}
+
+#[test]
+fn balance_prints_json_object() {
+    let dir = workspace("balance_json");
+    add(&dir, "Coffee", "3.5");
+    add(&dir, "Lunch", "12");
+
+    let out = run(
+        &dir,
+        &["balance", "--account", "expenses:food", "--output", "json"],
+    );
+    let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+    assert_eq!(value["account"], "expenses:food");
+    assert_eq!(value["amount"], 15.5);
+
+    // human output stays the default
+    let out = run(&dir, &["balance", "--account", "expenses:food"]);
+    assert_eq!(out.trim(), "15.50 USD");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn register_prints_records_as_json() {
+    let dir = workspace("register_json");
+    add(&dir, "Coffee", "3.5");
+    add(&dir, "Lunch", "12");
+
+    let out = run(
+        &dir,
+        &["--output", "json", "register", "--query", "account:cash"],
+    );
+    let value: serde_json::Value = serde_json::from_str(&out).unwrap();
+    let entries = value.as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["description"], "Coffee");
+    assert_eq!(entries[0]["debit_account"], "expenses:food");
+    assert_eq!(entries[1]["balance"], -15.5);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
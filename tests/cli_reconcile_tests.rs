@@ -0,0 +1,86 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"reconcile\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn add(dir: &Path, description: &str, amount: &str) {
+    run(
+        dir,
+        &[
+            "add",
+            "--description",
+            description,
+            "--debit",
+            "expenses:food",
+            "--credit",
+            "cash",
+            "--amount",
+            amount,
+            "--currency",
+            "USD",
+        ],
+    );
+}
+
+#[test]
+fn reconciled_records_are_listed_as_cleared() {
+    let dir = workspace("reconcile_list");
+    add(&dir, "Groceries", "40");
+    add(&dir, "Coffee", "3.5");
+    std::fs::write(
+        dir.join("statement.csv"),
+        "description,debit_account,credit_account,amount,currency\n\
+         Groceries,expenses:food,cash,40,USD\n",
+    )
+    .unwrap();
+    run(&dir, &["reconcile", "--file", "statement.csv"]);
+
+    let list = run(&dir, &["list"]);
+    let line = |desc: &str| {
+        list.lines()
+            .find(|l| l.contains(&format!(" | {desc} | ")))
+            .unwrap_or_else(|| panic!("{desc} missing from {list}"))
+            .to_string()
+    };
+    assert!(line("Groceries").ends_with(" | cleared"), "{list}");
+    assert!(line("Coffee").ends_with(" | uncleared"), "{list}");
+
+    let register = run(&dir, &["register", "--query", "cleared:true"]);
+    let lines: Vec<_> = register.lines().collect();
+    assert_eq!(lines.len(), 1, "{register}");
+    assert!(lines[0].ends_with("* Groceries"), "{register}");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,161 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"repair\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn cli(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn repair_rehashes_after_confirmation() {
+    let dir = workspace("repair");
+    let add = cli(
+        &dir,
+        &[
+            "add",
+            "--description",
+            "Coffee",
+            "--debit",
+            "expenses:food",
+            "--credit",
+            "cash",
+            "--amount",
+            "3.50",
+            "--currency",
+            "USD",
+        ],
+        "",
+    );
+    assert!(add.status.success(), "{add:?}");
+    let ledger = dir.join("data/ledger.csv");
+    let edited = std::fs::read_to_string(&ledger)
+        .unwrap()
+        .replace("Coffee", "Tea");
+    std::fs::write(&ledger, edited).unwrap();
+    assert!(!cli(&dir, &["verify"], "").status.success());
+
+    let declined = cli(&dir, &["repair", "--rows", "1"], "n\n");
+    assert!(declined.status.success());
+    assert!(String::from_utf8_lossy(&declined.stdout).contains("Nothing was changed"));
+    assert!(!cli(&dir, &["verify"], "").status.success());
+
+    let repaired = cli(&dir, &["repair", "--rows", "1"], "y\n");
+    assert!(repaired.status.success(), "{repaired:?}");
+    assert!(String::from_utf8_lossy(&repaired.stdout).contains("Repaired 1 rows"));
+    let verify = cli(&dir, &["verify"], "");
+    assert!(verify.status.success(), "{verify:?}");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn rotate_signature_rehashes_rows_under_new_name() {
+    let dir = workspace("rotate");
+    for description in ["Coffee", "Lunch"] {
+        let add = cli(
+            &dir,
+            &[
+                "add",
+                "--description",
+                description,
+                "--debit",
+                "expenses:food",
+                "--credit",
+                "cash",
+                "--amount",
+                "3.50",
+                "--currency",
+                "USD",
+            ],
+            "",
+        );
+        assert!(add.status.success(), "{add:?}");
+    }
+    let config = dir.join("config.toml");
+    let old_config = std::fs::read_to_string(&config).unwrap();
+    let new_config =
+        old_config.replace("name = \"repair\"", "name = \"renamed\"\npassword = \"pw\"");
+    std::fs::write(&config, &new_config).unwrap();
+    assert!(!cli(&dir, &["verify"], "").status.success());
+
+    // A row that does not verify under the old signature stops the rotation
+    // before anything is written.
+    let ledger = dir.join("data/ledger.csv");
+    let original = std::fs::read_to_string(&ledger).unwrap();
+    std::fs::write(&ledger, original.replace("Lunch", "Dinner")).unwrap();
+    let refused = cli(
+        &dir,
+        &[
+            "rotate-signature",
+            "--old-name",
+            "repair",
+            "--old-password",
+            "",
+            "--yes",
+        ],
+        "",
+    );
+    assert!(!refused.status.success());
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(stderr.contains("rows [2] do not verify"), "{stderr}");
+    assert_eq!(
+        std::fs::read_to_string(&ledger).unwrap(),
+        original.replace("Lunch", "Dinner")
+    );
+    std::fs::write(&ledger, &original).unwrap();
+
+    let rotated = cli(
+        &dir,
+        &[
+            "rotate-signature",
+            "--old-name",
+            "repair",
+            "--old-password",
+            "",
+        ],
+        "y\n",
+    );
+    assert!(rotated.status.success(), "{rotated:?}");
+    assert!(String::from_utf8_lossy(&rotated.stdout).contains("Re-hashed 2 rows"));
+    let verify = cli(&dir, &["verify"], "");
+    assert!(verify.status.success(), "{verify:?}");
+
+    std::fs::write(&config, &old_config).unwrap();
+    assert!(!cli(&dir, &["verify"], "").status.success());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
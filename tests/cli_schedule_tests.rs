@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn workspace(name: &str, cron: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        format!(
+            "name = \"schedule\"\n[google_sheets]\ncredentials_path = \"unused.json\"\n\
+             spreadsheet_id = \"ledger\"\n\n[[schedules]]\ncron = \"{cron}\"\n\
+             description = \"rent\"\ndebit = \"expenses:rent\"\ncredit = \"assets:bank\"\n\
+             amount = 1000.0\ncurrency = \"USD\"\n"
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+const PREVIEW: [&str; 6] = [
+    "schedule",
+    "preview",
+    "--from",
+    "2024-01-01",
+    "--to",
+    "2024-01-31",
+];
+
+#[test]
+fn preview_lists_occurrences_without_committing() {
+    let dir = workspace("schedule_preview", "0 0 0 1,15 * *");
+    let output = cli(&dir, &PREVIEW);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("2024-01-01T00:00:00"));
+    assert!(lines[1].starts_with("2024-01-15T00:00:00"));
+
+    let ledger = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(ledger.lines().count(), 1);
+}
+
+#[test]
+fn preview_reports_invalid_cron() {
+    let dir = workspace("schedule_invalid", "every day");
+    let output = cli(&dir, &PREVIEW);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("schedule 0"), "{stderr}");
+    assert!(stderr.contains("every day"), "{stderr}");
+}
+
+fn add_schedule(dir: &Path, cron: &str) -> Output {
+    cli(
+        dir,
+        &[
+            "schedule",
+            "add",
+            "--cron",
+            cron,
+            "--description",
+            "gym",
+            "--debit",
+            "expenses:gym",
+            "--credit",
+            "assets:bank",
+            "--amount",
+            "30",
+            "--currency",
+            "USD",
+        ],
+    )
+}
+
+#[test]
+fn add_rejects_invalid_cron() {
+    let dir = workspace("schedule_add_bad", "0 0 0 1 * *");
+    let before = std::fs::read_to_string(dir.join("config.toml")).unwrap();
+    let output = add_schedule(&dir, "0 0 1 * *");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid cron '0 0 1 * *'"), "{stderr}");
+    let after = std::fs::read_to_string(dir.join("config.toml")).unwrap();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn add_accepts_valid_cron() {
+    let dir = workspace("schedule_add_good", "0 0 0 1 * *");
+    let output = add_schedule(&dir, "0 0 6 * * Mon");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let config = std::fs::read_to_string(dir.join("config.toml")).unwrap();
+    assert!(config.contains("0 0 6 * * Mon"));
+}
@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"show\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = cli(dir, args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn show_prints_record_with_adjustment() {
+    let dir = workspace("show");
+    run(
+        &dir,
+        &[
+            "add",
+            "--description",
+            "Groceries",
+            "--debit",
+            "expenses:food",
+            "--credit",
+            "cash",
+            "--amount",
+            "40",
+            "--currency",
+            "USD",
+        ],
+    );
+    let list = run(&dir, &["list"]);
+    let id = list
+        .lines()
+        .filter_map(|line| line.split(" | ").next())
+        .find(|cell| uuid::Uuid::parse_str(cell).is_ok())
+        .unwrap()
+        .to_string();
+    run(
+        &dir,
+        &[
+            "adjust",
+            "--id",
+            &id,
+            "--description",
+            "Correct groceries",
+            "--debit",
+            "cash",
+            "--credit",
+            "expenses:food",
+            "--amount",
+            "4",
+            "--currency",
+            "USD",
+        ],
+    );
+
+    let out = run(&dir, &["show", "--id", &id]);
+    assert!(out.contains(&format!("id: {id}")), "{out}");
+    assert!(out.contains("description: Groceries"), "{out}");
+    assert!(out.contains("amount: 40 USD"), "{out}");
+    assert!(out.contains("reversed: false"), "{out}");
+    assert!(out.contains("adjustments: 1"), "{out}");
+    assert!(out.contains("Correct groceries"), "{out}");
+    assert!(out.contains("lineage:"), "{out}");
+    assert!(
+        out.lines()
+            .any(|line| line.starts_with("* ") && line.contains(&id)),
+        "{out}"
+    );
+
+    let adjustment = out
+        .lines()
+        .filter(|line| line.contains("Correct groceries"))
+        .find_map(|line| line.split(" | ").nth(1))
+        .unwrap()
+        .to_string();
+    let out = run(&dir, &["show", "--id", &adjustment]);
+    assert!(out.contains("adjustments: 0"), "{out}");
+    assert!(
+        out.lines().any(|line| line.starts_with("  ")
+            && line.contains(&id)
+            && line.ends_with("Groceries")),
+        "{out}"
+    );
+    assert!(
+        out.lines()
+            .any(|line| line.starts_with("* ") && line.ends_with("Correct groceries")),
+        "{out}"
+    );
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn show_reports_unknown_id() {
+    let dir = workspace("show_unknown");
+    let id = uuid::Uuid::new_v4().to_string();
+    let output = cli(&dir, &["show", "--id", &id]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("record {id} not found")),
+        "{stderr}"
+    );
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn attachment_prints_and_copies_linked_file() {
+    use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, FileAdapter};
+    use feed_my_ledger::core::Record;
+
+    let dir = workspace("attachment");
+    let receipt = dir.join("receipt.txt");
+    std::fs::write(&receipt, "paid in full").unwrap();
+    let record = Record::new(
+        "Printer".into(),
+        "expenses:office".parse().unwrap(),
+        "cash".parse().unwrap(),
+        80.0,
+        "USD".into(),
+        None,
+        Some(format!("file://{}", receipt.display())),
+        vec![],
+    )
+    .unwrap();
+    let plain = Record::new(
+        "Paper".into(),
+        "expenses:office".parse().unwrap(),
+        "cash".parse().unwrap(),
+        5.0,
+        "USD".into(),
+        None,
+        Some("INV-7".into()),
+        vec![],
+    )
+    .unwrap();
+    let mut adapter = FileAdapter::new(dir.join("data"));
+    adapter.append_row("ledger", record.to_row()).unwrap();
+    adapter.append_row("ledger", plain.to_row()).unwrap();
+
+    let id = record.id.to_string();
+    let out = run(&dir, &["attachment", "--id", &id]);
+    assert_eq!(out.trim(), receipt.display().to_string());
+
+    let copy = dir.join("copy.txt");
+    run(
+        &dir,
+        &["attachment", "--id", &id, "--file", copy.to_str().unwrap()],
+    );
+    assert_eq!(std::fs::read_to_string(&copy).unwrap(), "paid in full");
+
+    let output = cli(&dir, &["attachment", "--id", &plain.id.to_string()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("has no attachment"), "{stderr}");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,75 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"snapshot\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("data/ledger.csv"), "").unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn snapshot_restores_into_an_empty_directory() {
+    let dir = workspace("snapshot_restore");
+    for (description, amount) in [("Groceries", "42.50"), ("Coffee", "3.10")] {
+        run(
+            &dir,
+            &[
+                "add",
+                "--description",
+                description,
+                "--debit",
+                "expenses:food",
+                "--credit",
+                "cash",
+                "--amount",
+                amount,
+                "--currency",
+                "USD",
+            ],
+        );
+    }
+    let out = run(&dir, &["snapshot", "--out", "backup.json"]);
+    assert_eq!(out.trim(), "Wrote 2 records to backup.json");
+
+    let out = run(
+        &dir,
+        &["restore", "--in", "backup.json", "--to", "csv:restored"],
+    );
+    assert_eq!(out.trim(), "Restored 2 records into sheet sheet1");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("restored/sheet1.csv")).unwrap(),
+        std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap()
+    );
+
+    // Restoring again must not overwrite the sheet written above.
+    let output = Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(&dir)
+        .args(["restore", "--in", "backup.json", "--to", "csv:restored"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(stderr.contains("is not empty"), "{stderr}");
+}
@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "[google_sheets]\ncredentials_path = \"unused.json\"\n\n\
+         [[ledgers]]\nname = \"home\"\n\n[[ledgers]]\nname = \"work\"\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = cli(dir, args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Stands in for the token cache written by `login`, which is never parsed.
+const TOKEN_FILE: &str = "secret-access secret-refresh";
+
+#[test]
+fn tokens_list_and_logout_manage_saved_logins() {
+    let dir = workspace("tokens");
+    std::fs::write(dir.join("tokens-home.json"), TOKEN_FILE).unwrap();
+    std::fs::write(dir.join("tokens-work.json"), TOKEN_FILE).unwrap();
+
+    let out = run(&dir, &["tokens", "list"]);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2, "{out}");
+    assert!(lines[0].starts_with("home | saved "), "{out}");
+    assert!(lines[1].starts_with("work | saved "), "{out}");
+    assert!(!out.contains("secret"), "{out}");
+
+    run(&dir, &["tokens", "logout", "--user", "home"]);
+    assert!(!dir.join("tokens-home.json").exists());
+    assert!(dir.join("tokens-work.json").exists());
+    let failed = cli(&dir, &["tokens", "logout", "--user", "home"]);
+    assert!(!failed.status.success());
+
+    let out = run(&dir, &["logout"]);
+    assert!(out.contains("Removed 1 saved logins"), "{out}");
+    assert_eq!(run(&dir, &["tokens", "list"]).trim(), "No saved logins");
+
+    let _ = std::fs::remove_dir_all(dir);
+}
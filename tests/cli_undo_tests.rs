@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("fml_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("data")).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "name = \"undo\"\n[google_sheets]\ncredentials_path = \"unused.json\"\nspreadsheet_id = \"ledger\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("data/ledger.csv"),
+        "id,timestamp,description,debit_account,credit_account,amount,currency,\
+         reference_id,external_reference,tags,splits,transaction_description,\
+         transaction_date,schema_version,metadata,hash\n",
+    )
+    .unwrap();
+    dir
+}
+
+fn cli(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_feed-my-ledger"))
+        .current_dir(dir)
+        .args(["--local-dir", "data"])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let output = cli(dir, args);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn add(dir: &Path, description: &str, amount: &str) {
+    run(
+        dir,
+        &[
+            "add",
+            "--description",
+            description,
+            "--debit",
+            "expenses:food",
+            "--credit",
+            "cash",
+            "--amount",
+            amount,
+            "--currency",
+            "USD",
+        ],
+    );
+}
+
+fn balance(dir: &Path, account: &str) -> String {
+    run(dir, &["balance", "--account", account])
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn undo_restores_previous_balances() {
+    let dir = workspace("undo");
+    add(&dir, "Groceries", "40");
+    let food = balance(&dir, "expenses:food");
+    let cash = balance(&dir, "cash");
+
+    add(&dir, "Coffee", "3.5");
+    add(&dir, "Lunch", "12");
+    assert_ne!(balance(&dir, "expenses:food"), food);
+
+    let out = run(&dir, &["undo", "--count", "2"]);
+    assert!(out.contains("Lunch"), "{out}");
+    assert!(out.contains("Coffee"), "{out}");
+    assert!(!out.contains("Groceries"), "{out}");
+    assert_eq!(balance(&dir, "expenses:food"), food);
+    assert_eq!(balance(&dir, "cash"), cash);
+
+    // the original records are already reversed, so nothing is appended
+    let before = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    let output = cli(&dir, &["undo", "--count", "4"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already been reversed"));
+    let after = std::fs::read_to_string(dir.join("data/ledger.csv")).unwrap();
+    assert_eq!(before, after);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
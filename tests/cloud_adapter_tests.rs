@@ -4,6 +4,8 @@ use feed_my_ledger::cloud_adapters::{
     CloudSpreadsheetService, Excel365Adapter, GoogleSheets4Adapter, GoogleSheetsAdapter,
     SpreadsheetError,
 };
+use feed_my_ledger::core::{Permission, ROW_HEADER, Record, SharedLedger};
+use std::time::Duration;
 use uuid::Uuid;
 
 #[test]
@@ -39,6 +41,55 @@ fn reading_nonexistent_row_fails() {
     assert_eq!(err, SpreadsheetError::RowNotFound);
 }
 
+#[test]
+fn read_rows_slices_listed_rows() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let id = adapter.create_sheet("test").unwrap();
+    for value in ["a", "b", "c"] {
+        adapter.append_row(&id, vec![value.into()]).unwrap();
+    }
+
+    assert_eq!(
+        adapter.read_rows(&id, 1, 3).unwrap(),
+        vec![vec!["b"], vec!["c"]]
+    );
+    // ranges past the end are truncated
+    assert_eq!(adapter.read_rows(&id, 2, 10).unwrap(), vec![vec!["c"]]);
+    assert!(adapter.read_rows(&id, 5, 10).unwrap().is_empty());
+    assert_eq!(
+        adapter.read_rows("missing", 0, 1).unwrap_err(),
+        SpreadsheetError::SheetNotFound
+    );
+}
+
+#[test]
+fn update_row_replaces_middle_row() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let id = adapter.create_sheet("test").unwrap();
+    for value in ["a", "b", "c"] {
+        adapter.append_row(&id, vec![value.into()]).unwrap();
+    }
+
+    adapter
+        .update_row(&id, 1, vec!["x".into(), "y".into()])
+        .unwrap();
+    assert_eq!(adapter.read_row(&id, 1).unwrap(), vec!["x", "y"]);
+    assert_eq!(
+        adapter.list_rows(&id).unwrap(),
+        vec![vec!["a"], vec!["x", "y"], vec!["c"]]
+    );
+    assert_eq!(
+        adapter.update_row(&id, 3, vec!["d".into()]).unwrap_err(),
+        SpreadsheetError::RowNotFound
+    );
+    assert_eq!(
+        adapter
+            .update_row("missing", 0, vec!["d".into()])
+            .unwrap_err(),
+        SpreadsheetError::SheetNotFound
+    );
+}
+
 #[test]
 fn sharing_nonexistent_sheet_fails() {
     let adapter = GoogleSheetsAdapter::new();
@@ -90,6 +141,86 @@ async fn share_sheet_sends_request() {
     server.verify().await;
 }
 
+#[tokio::test]
+async fn request_within_timeout_succeeds() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/sheet123/permissions"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(50)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter =
+        GoogleSheets4Adapter::with_drive_base_url(StaticToken, format!("{}/", server.uri()))
+            .with_timeout(Duration::from_secs(5));
+    tokio::task::spawn_blocking(move || {
+        adapter.share_sheet("sheet123", "user@example.com").unwrap();
+    })
+    .await
+    .unwrap();
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn slow_response_times_out_as_transient() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/sheet123/permissions"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+        .mount(&server)
+        .await;
+
+    let adapter =
+        GoogleSheets4Adapter::with_drive_base_url(StaticToken, format!("{}/", server.uri()))
+            .with_timeout(Duration::from_millis(100));
+    let err = tokio::task::spawn_blocking(move || {
+        adapter
+            .share_sheet("sheet123", "user@example.com")
+            .unwrap_err()
+    })
+    .await
+    .unwrap();
+    assert_eq!(err, SpreadsheetError::Transient("request timed out".into()));
+}
+
+#[tokio::test]
+async fn stalled_response_body_times_out_as_transient() {
+    use std::io::{Read, Write};
+
+    // Sends the response head and part of the body, then stops writing.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buf = [0u8; 4096];
+        let _ = stream.read(&mut buf);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n{\"sheets\"")
+            .unwrap();
+        std::thread::sleep(Duration::from_secs(2));
+    });
+
+    let base = format!("http://{addr}/");
+    let adapter = GoogleSheets4Adapter::with_base_urls_and_sheet_name(
+        StaticToken,
+        base.clone(),
+        base,
+        "Ledger",
+    )
+    .with_timeout(Duration::from_millis(100));
+    let err = tokio::task::spawn_blocking(move || adapter.list_rows("sheet123").unwrap_err())
+        .await
+        .unwrap();
+    assert_eq!(err, SpreadsheetError::Transient("request timed out".into()));
+}
+
 #[tokio::test]
 async fn share_sheet_propagates_failure() {
     use wiremock::matchers::{method, path};
@@ -113,6 +244,51 @@ async fn share_sheet_propagates_failure() {
     server.verify().await;
 }
 
+#[tokio::test]
+async fn revoke_share_deletes_permission() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/files/sheet123/permissions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "permissions": [
+                {"id": "p1", "emailAddress": "owner@example.com", "role": "owner"},
+                {"id": "p2", "emailAddress": "user@example.com", "role": "reader"}
+            ]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/files/sheet123/permissions/p2"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter =
+        GoogleSheets4Adapter::with_drive_base_url(StaticToken, format!("{}/", server.uri()));
+    let shares = tokio::task::spawn_blocking(move || {
+        let shares = adapter.list_shares("sheet123").unwrap();
+        adapter
+            .revoke_share("sheet123", "user@example.com")
+            .unwrap();
+        shares
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        shares,
+        vec![
+            ("owner@example.com".to_string(), Permission::Write),
+            ("user@example.com".to_string(), Permission::Read),
+        ]
+    );
+    server.verify().await;
+}
+
 #[tokio::test]
 async fn append_rows_insert_option() {
     use serde_json::json;
@@ -155,6 +331,9 @@ async fn append_rows_insert_option() {
                 "splits",
                 "transaction_description",
                 "transaction_date",
+                "schema_version",
+                "metadata",
+                "cleared",
                 "hash"
             ], ["a"], ["b"]],
         })))
@@ -238,6 +417,274 @@ async fn append_rows_skips_header_when_not_empty() {
     server.verify().await;
 }
 
+#[tokio::test]
+async fn list_rows_concatenates_pages() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sheets": [{"properties": {"title": "Ledger"}}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A1:Z2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["a"], ["b"]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A3:Z4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["c"]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter = GoogleSheets4Adapter::with_base_urls_and_sheet_name(
+        StaticToken,
+        format!("{}/", server.uri()),
+        format!("{}/", server.uri()),
+        "Ledger",
+    )
+    .with_page_size(2);
+
+    let rows = tokio::task::spawn_blocking(move || adapter.list_rows("sheet123").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(rows, vec![vec!["a"], vec!["b"], vec!["c"]]);
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn excel_list_rows_concatenates_pages() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/me/drive/items/book123/workbook/worksheets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "value": [{"name": "Ledger"}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(
+            "/me/drive/items/book123/workbook/worksheets/Ledger/range(address='A1:Z2')",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["a"], ["b"]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(
+            "/me/drive/items/book123/workbook/worksheets/Ledger/range(address='A3:Z4')",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["c"], [""]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter =
+        Excel365Adapter::with_base_url(StaticToken, format!("{}/", server.uri())).with_page_size(2);
+
+    let rows = tokio::task::spawn_blocking(move || adapter.list_rows("book123").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(rows, vec![vec!["a"], vec!["b"], vec!["c"]]);
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn excel_append_creates_missing_table() {
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    let worksheet = "/me/drive/items/book123/workbook/worksheets/Ledger";
+
+    Mock::given(method("GET"))
+        .and(path("/me/drive/items/book123/workbook/worksheets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "value": [{"name": "Ledger"}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("{worksheet}/tables")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "value": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .and(path(format!("{worksheet}/range(address='A1:Q1')")))
+        .and(body_json(
+            json!({ "values": [feed_my_ledger::core::ROW_HEADER] }),
+        ))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{worksheet}/tables/add")))
+        .and(body_json(json!({ "address": "A1:Q1", "hasHeaders": true })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+            "name": "Table2"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PATCH"))
+        .and(path(format!("{worksheet}/tables/Table2")))
+        .and(body_json(json!({ "name": "Table1" })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path(format!("{worksheet}/tables/Table1/rows/add")))
+        .and(body_json(json!({ "values": [["a", "b"]] })))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut adapter = Excel365Adapter::with_base_url(StaticToken, format!("{}/", server.uri()));
+    tokio::task::spawn_blocking(move || {
+        adapter
+            .append_row("book123", vec!["a".into(), "b".into()])
+            .unwrap();
+    })
+    .await
+    .unwrap();
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn read_rows_requests_range() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sheets": [{"properties": {"title": "Ledger"}}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A101:Z150"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["x", "1"], ["y", "2"]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter = GoogleSheets4Adapter::with_base_urls_and_sheet_name(
+        StaticToken,
+        format!("{}/", server.uri()),
+        format!("{}/", server.uri()),
+        "Ledger",
+    );
+
+    let rows =
+        tokio::task::spawn_blocking(move || adapter.read_rows("sheet123", 100, 150).unwrap())
+            .await
+            .unwrap();
+    assert_eq!(rows, vec![vec!["x", "1"], vec!["y", "2"]]);
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn configured_columns_limit_requested_ranges() {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sheets": [{"properties": {"title": "Ledger"}}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A3:N3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["x", "1"]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A:N"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["id"]]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A:N:append"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter = GoogleSheets4Adapter::with_base_urls_and_sheet_name(
+        StaticToken,
+        format!("{}/", server.uri()),
+        format!("{}/", server.uri()),
+        "Ledger",
+    )
+    .with_columns("a", "n");
+
+    let row = tokio::task::spawn_blocking(move || {
+        let mut adapter = adapter;
+        adapter.append_row("sheet123", vec!["a".into()]).unwrap();
+        adapter.read_row("sheet123", 2).unwrap()
+    })
+    .await
+    .unwrap();
+    assert_eq!(row, vec!["x", "1"]);
+    server.verify().await;
+}
+
 #[test]
 fn excel365_adapter_is_service() {
     fn assert_impl<T: CloudSpreadsheetService>() {}
@@ -287,11 +734,49 @@ async fn excel_share_sheet_propagates_failure() {
     server.verify().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn adapters_share_one_runtime() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/sheet123/permissions"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/me/drive/items/book123/invite"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let handle = tokio::runtime::Handle::current();
+    let google = GoogleSheets4Adapter::with_runtime(
+        handle.clone(),
+        StaticToken,
+        format!("{}/", server.uri()),
+        format!("{}/", server.uri()),
+        "Ledger",
+    );
+    let excel =
+        Excel365Adapter::with_runtime(handle, StaticToken, format!("{}/", server.uri()), "Ledger");
+    tokio::task::spawn_blocking(move || {
+        google.share_sheet("sheet123", "user@example.com").unwrap();
+        excel.share_sheet("book123", "user@example.com").unwrap();
+    })
+    .await
+    .unwrap();
+    server.verify().await;
+}
+
 #[test]
 fn file_adapter_round_trip() {
     let dir = std::env::temp_dir().join(format!("ledger_{}", Uuid::new_v4()));
     std::fs::create_dir(&dir).unwrap();
-    let mut adapter = FileAdapter::new(&dir);
+    let mut adapter = FileAdapter::new(&dir).without_header();
     let id = adapter.create_sheet("test").unwrap();
     adapter
         .append_row(&id, vec!["a".into(), "b".into()])
@@ -301,9 +786,237 @@ fn file_adapter_round_trip() {
     std::fs::remove_dir_all(dir).unwrap();
 }
 
+#[test]
+fn file_adapter_writes_header_on_first_append() {
+    let dir = std::env::temp_dir().join(format!("ledger_{}", Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let mut adapter = FileAdapter::new(&dir);
+    let id = adapter.create_sheet("test").unwrap();
+    let record = |description: &str| {
+        Record::new(
+            description.into(),
+            "expenses:food".parse().unwrap(),
+            "cash".parse().unwrap(),
+            3.5,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap()
+    };
+    let (coffee, tea) = (record("coffee"), record("tea"));
+    adapter
+        .append_row(&id, coffee.to_row_hashed("sig"))
+        .unwrap();
+    adapter.append_row(&id, tea.to_row_hashed("sig")).unwrap();
+
+    let rows = adapter.list_rows(&id).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], ROW_HEADER);
+    assert_eq!(rows[1], coffee.to_row_hashed("sig"));
+    assert_eq!(rows[2], tea.to_row_hashed("sig"));
+
+    // Loading the sheet skips the header.
+    let shared = SharedLedger::from_sheet(adapter, id, "owner").unwrap();
+    let descriptions: Vec<_> = shared
+        .records("owner")
+        .unwrap()
+        .into_iter()
+        .map(|r| r.description)
+        .collect();
+    assert_eq!(descriptions, vec!["coffee", "tea"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn file_adapter_keeps_rows_of_different_widths() {
+    let dir = std::env::temp_dir().join(format!("ledger_{}", Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let mut adapter = FileAdapter::new(&dir).without_header();
+    let id = adapter.create_sheet("test").unwrap();
+    adapter
+        .append_rows(
+            &id,
+            vec![
+                vec!["a".into(), "b".into(), "c".into()],
+                vec!["status".into(), "d".into()],
+            ],
+        )
+        .unwrap();
+    let rows = adapter.list_rows(&id).unwrap();
+    assert_eq!(rows, vec![vec!["a", "b", "c"], vec!["status", "d"]]);
+    assert_eq!(adapter.read_row(&id, 1).unwrap(), vec!["status", "d"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn file_adapter_updates_middle_row() {
+    let dir = std::env::temp_dir().join(format!("ledger_{}", Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let mut adapter = FileAdapter::new(&dir).without_header();
+    let id = adapter.create_sheet("test").unwrap();
+    adapter
+        .append_rows(
+            &id,
+            vec![
+                vec!["a".into(), "1".into()],
+                vec!["b".into(), "2".into()],
+                vec!["c".into(), "3".into()],
+            ],
+        )
+        .unwrap();
+
+    adapter.update_row(&id, 1, vec!["b,2".into()]).unwrap();
+    assert_eq!(adapter.read_row(&id, 1).unwrap(), vec!["b,2"]);
+    assert_eq!(
+        adapter.list_rows(&id).unwrap(),
+        vec![vec!["a", "1"], vec!["b,2"], vec!["c", "3"]]
+    );
+    assert_eq!(
+        adapter.update_row(&id, 3, vec!["d".into()]).unwrap_err(),
+        SpreadsheetError::RowNotFound
+    );
+    // Appends after an update land after the last row.
+    adapter.append_row(&id, vec!["d".into()]).unwrap();
+    assert_eq!(adapter.list_rows(&id).unwrap().len(), 4);
+    // Shrinking a row leaves no stale bytes behind the rewritten contents.
+    adapter.update_row(&id, 0, vec!["a".into()]).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join(format!("{id}.csv"))).unwrap(),
+        "a\n\"b,2\"\nc,3\nd\n"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
 #[test]
 fn file_adapter_missing_sheet() {
     let adapter = FileAdapter::new(std::env::temp_dir());
     let err = adapter.read_row("missing", 0).unwrap_err();
     assert_eq!(err, SpreadsheetError::SheetNotFound);
 }
+
+#[test]
+fn file_adapter_concurrent_appends_do_not_interleave() {
+    let dir = std::env::temp_dir().join(format!("ledger_{}", Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let id = FileAdapter::new(&dir).create_sheet("test").unwrap();
+    let filler = "x".repeat(4096);
+
+    let handles: Vec<_> = ["left", "right"]
+        .into_iter()
+        .map(|writer| {
+            let dir = dir.clone();
+            let id = id.clone();
+            let filler = filler.clone();
+            std::thread::spawn(move || {
+                let mut adapter = FileAdapter::new(&dir);
+                for i in 0..100 {
+                    let rows = (0..5)
+                        .map(|j| vec![writer.to_string(), format!("{i}-{j}"), filler.clone()])
+                        .collect();
+                    adapter.append_rows(&id, rows).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    // Both writers started on an empty sheet, but only one wrote the header.
+    let rows = FileAdapter::new(&dir).list_rows(&id).unwrap();
+    assert_eq!(rows.len(), 1001);
+    assert_eq!(rows[0], ROW_HEADER);
+    for row in &rows[1..] {
+        assert_eq!(row.len(), 3);
+        assert!(row[0] == "left" || row[0] == "right");
+        assert_eq!(row[2], filler);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn file_adapter_lock_timeout_is_transient() {
+    let dir = std::env::temp_dir().join(format!("ledger_{}", Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+    let mut adapter =
+        FileAdapter::new(&dir).with_lock_timeout(std::time::Duration::from_millis(50));
+    let id = adapter.create_sheet("test").unwrap();
+    let held = std::fs::File::open(dir.join(format!("{id}.csv"))).unwrap();
+    held.lock().unwrap();
+
+    let err = adapter.append_row(&id, vec!["a".into()]).unwrap_err();
+    assert!(matches!(err, SpreadsheetError::Transient(_)));
+    drop(held);
+    adapter.append_row(&id, vec!["a".into()]).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn update_row_puts_values_for_the_row_range() {
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sheets": [{"properties": {"title": "Ledger"}}]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A3:Z3"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [["old", "row", "hash"]]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A9:Z9"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("PUT"))
+        .and(path("/spreadsheets/sheet123/values/Ledger!A3:Z3"))
+        .and(query_param("valueInputOption", "USER_ENTERED"))
+        .and(body_json(json!({
+            "range": "Ledger!A3:Z3",
+            "majorDimension": "ROWS",
+            "values": [["new", "row", ""]],
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let adapter = GoogleSheets4Adapter::with_base_urls_and_sheet_name(
+        StaticToken,
+        format!("{}/", server.uri()),
+        format!("{}/", server.uri()),
+        "Ledger",
+    );
+
+    tokio::task::spawn_blocking(move || {
+        let mut adapter = adapter;
+        adapter
+            .update_row("sheet123", 2, vec!["new".into(), "row".into()])
+            .unwrap();
+        assert_eq!(
+            adapter
+                .update_row("sheet123", 8, vec!["new".into()])
+                .unwrap_err(),
+            SpreadsheetError::RowNotFound
+        );
+    })
+    .await
+    .unwrap();
+
+    server.verify().await;
+}
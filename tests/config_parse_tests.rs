@@ -7,11 +7,20 @@ struct GoogleSheetsConfig {
     sheet_name: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct LedgerConfig {
+    name: String,
+    spreadsheet_id: Option<String>,
+    sheet_name: Option<String>,
+}
+
 #[derive(Deserialize)]
 struct Config {
     name: String,
     password: Option<String>,
     google_sheets: GoogleSheetsConfig,
+    #[serde(default)]
+    ledgers: Vec<LedgerConfig>,
 }
 
 #[test]
@@ -72,3 +81,29 @@ sheet_name = "Custom"
     let result: Result<Config, _> = toml::from_str(toml);
     assert!(result.is_err(), "Config without 'name' should fail");
 }
+
+#[test]
+fn parses_multiple_ledgers() {
+    let toml = r#"
+name = "TestLedger"
+[google_sheets]
+credentials_path = "cred.json"
+
+[[ledgers]]
+name = "personal"
+spreadsheet_id = "abc"
+
+[[ledgers]]
+name = "business"
+spreadsheet_id = "def"
+sheet_name = "Books"
+"#;
+    let cfg: Config = toml::from_str(toml).unwrap();
+    assert_eq!(cfg.ledgers.len(), 2);
+    assert_eq!(cfg.ledgers[0].name, "personal");
+    assert_eq!(cfg.ledgers[0].spreadsheet_id.as_deref(), Some("abc"));
+    assert_eq!(cfg.ledgers[0].sheet_name, None);
+    assert_eq!(cfg.ledgers[1].name, "business");
+    assert_eq!(cfg.ledgers[1].sheet_name.as_deref(), Some("Books"));
+    assert_eq!(cfg.google_sheets.spreadsheet_id, None);
+}
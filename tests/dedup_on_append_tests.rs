@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use feed_my_ledger::cloud_adapters::{
+    CloudSpreadsheetService, DedupOnAppend, GoogleSheetsAdapter, RetryingService, SpreadsheetError,
+};
+use feed_my_ledger::core::Record;
+
+/// Writes every append but reports the first `lost` of them as failed, as if
+/// the response never arrived.
+struct LostResponses {
+    inner: GoogleSheetsAdapter,
+    lost: usize,
+    appends: usize,
+}
+
+impl CloudSpreadsheetService for LostResponses {
+    fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+        self.inner.create_sheet(title)
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        self.appends += 1;
+        self.inner.append_row(sheet_id, values)?;
+        if self.lost > 0 {
+            self.lost -= 1;
+            return Err(SpreadsheetError::Transient("connection reset".into()));
+        }
+        Ok(())
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        self.inner.read_row(sheet_id, index)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        self.inner.list_rows(sheet_id)
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.inner.share_sheet(sheet_id, email)
+    }
+}
+
+fn record(description: &str) -> Record {
+    Record::new(
+        description.into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        5.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+#[test]
+fn retried_append_after_lost_response_writes_one_row() {
+    let mut inner = GoogleSheetsAdapter::new();
+    let sheet = inner.create_sheet("ledger").unwrap();
+    let adapter = LostResponses {
+        inner,
+        lost: 1,
+        appends: 0,
+    };
+    let mut service =
+        RetryingService::new(DedupOnAppend::new(adapter), 3, Duration::from_millis(1));
+
+    let rec = record("lunch");
+    service.append_row(&sheet, rec.to_row()).unwrap();
+    // Status rows carry no record id and are never skipped.
+    service.append_row(&sheet, rec.status_row()).unwrap();
+    service.append_row(&sheet, rec.status_row()).unwrap();
+
+    let rows = service.list_rows(&sheet).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], rec.to_row());
+}
+
+#[test]
+fn appending_an_already_committed_record_is_skipped() {
+    let mut inner = GoogleSheetsAdapter::new();
+    let sheet = inner.create_sheet("ledger").unwrap();
+    let (kept, retried) = (record("kept"), record("retried"));
+    inner.append_row(&sheet, retried.to_row()).unwrap();
+    let adapter = LostResponses {
+        inner,
+        lost: 0,
+        appends: 0,
+    };
+    let mut service = DedupOnAppend::new(adapter);
+
+    service
+        .append_rows(&sheet, vec![retried.to_row(), kept.to_row()])
+        .unwrap();
+    service.append_row(&sheet, kept.to_row()).unwrap();
+
+    let adapter = service.into_inner();
+    assert_eq!(adapter.appends, 1);
+    let rows = adapter.list_rows(&sheet).unwrap();
+    assert_eq!(rows, vec![retried.to_row(), kept.to_row()]);
+}
@@ -1,9 +1,15 @@
 use std::str::FromStr;
 
+use chrono::{Local, TimeZone};
+
 use feed_my_ledger::{
     cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter},
     core::{Account, Record},
-    import::dedup::filter_new_records,
+    import::{
+        csv,
+        dedup::{DedupIndex, dedup_batch, filter_new_records},
+        qif,
+    },
 };
 
 #[test]
@@ -26,6 +32,8 @@ fn filter_new_records_skips_duplicates() {
         "splits",
         "transaction_description",
         "transaction_date",
+        "schema_version",
+        "metadata",
         "hash",
     ]
     .into_iter()
@@ -64,3 +72,146 @@ fn filter_new_records_skips_duplicates() {
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0][0], r2.id.to_string());
 }
+
+#[test]
+fn dedup_index_is_reused_across_batches() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet_id = adapter.create_sheet("test").unwrap();
+    let signature = "sig";
+    adapter
+        .append_row(&sheet_id, vec!["id".into(), "hash".into()])
+        .unwrap();
+
+    let make = |desc: &str, reference: Option<&str>| {
+        Record::new(
+            desc.to_string(),
+            Account::from_str("expenses:food").unwrap(),
+            Account::from_str("cash").unwrap(),
+            1.0,
+            "USD".to_string(),
+            None,
+            reference.map(String::from),
+            vec![],
+        )
+        .unwrap()
+    };
+    let stored = make("Stored", None);
+    adapter
+        .append_row(&sheet_id, stored.to_row_hashed(signature))
+        .unwrap();
+
+    let mut index = DedupIndex::from_sheet(&adapter, &sheet_id, signature).unwrap();
+    assert!(index.contains(&stored));
+
+    let coffee = make("Coffee", Some("INV-1"));
+    let first = index.new_rows(vec![stored.clone(), coffee.clone()]);
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0][0], coffee.id.to_string());
+    assert!(index.insert(&coffee));
+    assert!(!index.insert(&coffee));
+
+    // Same invoice re-exported under a new id is caught by its reference.
+    let coffee_again = make("Coffee", Some("INV-1"));
+    let tea = make("Tea", None);
+    let second = index.new_rows(vec![coffee.clone(), coffee_again, tea.clone()]);
+    assert_eq!(second.len(), 1);
+    assert_eq!(second[0][0], tea.id.to_string());
+}
+
+#[test]
+fn dedup_index_matches_stored_rows_by_content() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet_id = adapter.create_sheet("test").unwrap();
+    let signature = "sig";
+    let undated = |desc: &str| {
+        Record::new(
+            desc.to_string(),
+            Account::from_str("expenses:food").unwrap(),
+            Account::from_str("cash").unwrap(),
+            4.0,
+            "USD".to_string(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap()
+    };
+    let date = Local.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let make = |desc: &str| {
+        let mut record = undated(desc);
+        record.transaction_date = Some(date);
+        record
+    };
+    let mut lunch = make("Lunch");
+    adapter
+        .append_row(&sheet_id, undated("Netflix").to_row_hashed(signature))
+        .unwrap();
+    lunch.cleared = true;
+    adapter
+        .append_row(&sheet_id, lunch.to_row_hashed_with_status(signature))
+        .unwrap();
+
+    // A second import of the same statement line gets a new id and timestamp.
+    let index = DedupIndex::from_sheet(&adapter, &sheet_id, signature).unwrap();
+    let again = make("Lunch");
+    assert_ne!(again.id, lunch.id);
+    assert!(index.contains(&again));
+    assert!(!index.contains(&make("Dinner")));
+    // Undated lines are only matched by their exact row, as the same line
+    // recurs in every monthly statement.
+    assert!(!index.contains(&undated("Netflix")));
+}
+
+#[test]
+fn duplicate_lines_within_a_file_are_appended_once() {
+    let path = std::env::temp_dir().join(format!("dedup_batch_{}.qif", std::process::id()));
+    std::fs::write(
+        &path,
+        "!Type:Bank\n\
+         D03/01/2024\nT-3.50\nPCoffee\n^\n\
+         D03/01/2024\nT-2.00\nPTea\n^\n\
+         D03/01/2024\nT-3.50\nPCoffee\n^\n",
+    )
+    .unwrap();
+    let records = qif::parse(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(records.len(), 3);
+    assert_eq!(dedup_batch(records.clone()).len(), 2);
+
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet_id = adapter.create_sheet("test").unwrap();
+    adapter
+        .append_row(&sheet_id, vec!["id".into(), "hash".into()])
+        .unwrap();
+    let rows = filter_new_records(&adapter, &sheet_id, records.clone(), "sig").unwrap();
+    adapter.append_rows(&sheet_id, rows).unwrap();
+    let stored = adapter.list_rows(&sheet_id).unwrap();
+    let descriptions: Vec<&str> = stored[1..].iter().map(|r| r[2].as_str()).collect();
+    assert_eq!(descriptions, vec!["Coffee", "Tea"]);
+
+    // The index used by the CLI skips the repeat as it streams records in.
+    let mut index = DedupIndex::new("sig");
+    let inserted: Vec<bool> = records.iter().map(|r| index.insert(r)).collect();
+    assert_eq!(inserted, vec![true, true, false]);
+}
+
+#[test]
+fn identical_undated_lines_within_a_file_are_all_kept() {
+    let path = std::env::temp_dir().join(format!("dedup_undated_{}.csv", std::process::id()));
+    std::fs::write(
+        &path,
+        "description,debit_account,credit_account,amount,currency\n\
+         Coffee,expenses:food,cash,3.50,USD\n\
+         Coffee,expenses:food,cash,3.50,USD\n",
+    )
+    .unwrap();
+    let records = csv::parse(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(dedup_batch(records.clone()).len(), 2);
+
+    let mut index = DedupIndex::new("sig");
+    let inserted: Vec<bool> = records.iter().map(|r| index.insert(r)).collect();
+    assert_eq!(inserted, vec![true, true]);
+    // The very same record is still only written once.
+    assert!(!index.insert(&records[0]));
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "dropbox")]
+
+use feed_my_ledger::cloud_adapters::google_sheets4::TokenProvider;
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, DropboxAdapter, SpreadsheetError};
+use serde_json::json;
+use wiremock::matchers::{body_string, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+struct StaticToken;
+
+impl TokenProvider for StaticToken {
+    fn token<'a>(
+        &'a self,
+        _scopes: &'a [&str],
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<String, SpreadsheetError>> + Send + 'a>,
+    > {
+        Box::pin(async { Ok("test-token".to_string()) })
+    }
+}
+
+fn adapter(server: &MockServer) -> DropboxAdapter {
+    DropboxAdapter::with_base_urls(
+        StaticToken,
+        format!("{}/", server.uri()),
+        format!("{}/", server.uri()),
+    )
+}
+
+#[tokio::test]
+async fn create_sheet_returns_allocated_path() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/upload"))
+        .and(header("authorization", "Bearer test-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "ledger (1).csv",
+            "path_display": "/ledger (1).csv"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut adapter = adapter(&server);
+    let id = tokio::task::spawn_blocking(move || adapter.create_sheet("ledger").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(id, "/ledger (1).csv");
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn append_rows_uploads_existing_and_new_rows() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/download"))
+        .and(header("Dropbox-API-Arg", r#"{"path":"/ledger.csv"}"#))
+        .respond_with(ResponseTemplate::new(200).set_body_string("a,b\n"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/files/upload"))
+        .and(body_string("a,b\nc,\"d,e\"\nf,g\n"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "path_display": "/ledger.csv"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut adapter = adapter(&server);
+    tokio::task::spawn_blocking(move || {
+        adapter
+            .append_rows(
+                "/ledger.csv",
+                vec![vec!["c".into(), "d,e".into()], vec!["f".into(), "g".into()]],
+            )
+            .unwrap();
+    })
+    .await
+    .unwrap();
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn list_rows_parses_downloaded_csv() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/download"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("a,b\nc,\"d,e\"\nf\n"))
+        .mount(&server)
+        .await;
+
+    let adapter = adapter(&server);
+    let (rows, row) = tokio::task::spawn_blocking(move || {
+        (
+            adapter.list_rows("/ledger.csv").unwrap(),
+            adapter.read_row("/ledger.csv", 1).unwrap(),
+        )
+    })
+    .await
+    .unwrap();
+    assert_eq!(rows, vec![vec!["a", "b"], vec!["c", "d,e"], vec!["f"]]);
+    assert_eq!(row, vec!["c", "d,e"]);
+}
+
+#[tokio::test]
+async fn missing_file_is_sheet_not_found() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/download"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "error_summary": "path/not_found/.."
+        })))
+        .mount(&server)
+        .await;
+
+    let adapter = adapter(&server);
+    let err = tokio::task::spawn_blocking(move || adapter.list_rows("/missing.csv").unwrap_err())
+        .await
+        .unwrap();
+    assert_eq!(err, SpreadsheetError::SheetNotFound);
+}
@@ -29,3 +29,96 @@ fn loading_missing_file_is_empty() {
     let store = FileTokenStore::new(&path, *b"an example very very secret key!");
     assert!(store.get_token("user").is_none());
 }
+
+#[test]
+fn rotated_key_loads_tokens_and_reencrypts() {
+    let path = std::env::temp_dir().join(format!("rotate_{}.json", Uuid::new_v4()));
+    let key_a = *b"an example very very secret key!";
+    let key_b = *b"a different rotated secret key!!";
+    {
+        let mut store = FileTokenStore::new(&path, key_a);
+        store.save_token(
+            "user",
+            OAuth2Token {
+                access_token: "t1".into(),
+                refresh_token: "r1".into(),
+                expires_at: Utc::now() + Duration::hours(1),
+            },
+        );
+    }
+    assert!(
+        FileTokenStore::new(&path, key_b)
+            .get_token("user")
+            .is_none()
+    );
+
+    let mut store = FileTokenStore::with_keys(&path, key_b, vec![key_a]);
+    let token = store.get_token("user").unwrap();
+    assert_eq!(token.access_token, "t1");
+    store.save_token("user", token);
+
+    let store = FileTokenStore::new(&path, key_b);
+    assert_eq!(store.get_token("user").unwrap().access_token, "t1");
+    assert!(
+        FileTokenStore::new(&path, key_a)
+            .get_token("user")
+            .is_none()
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn removing_one_token_keeps_the_other_after_reload() {
+    let path = std::env::temp_dir().join(format!("remove_{}.json", Uuid::new_v4()));
+    let key = *b"an example very very secret key!";
+    let token = |access: &str| OAuth2Token {
+        access_token: access.into(),
+        refresh_token: "r".into(),
+        expires_at: Utc::now() + Duration::hours(1),
+    };
+    {
+        let mut store = FileTokenStore::new(&path, key);
+        store.save_token("alice", token("a"));
+        store.save_token("bob", token("b"));
+        assert_eq!(store.user_ids(), vec!["alice", "bob"]);
+        assert_eq!(store.remove("alice").unwrap().access_token, "a");
+        assert!(store.remove("alice").is_none());
+    }
+    let mut store = FileTokenStore::new(&path, key);
+    assert!(store.get_token("alice").is_none());
+    assert_eq!(store.get_token("bob").unwrap().access_token, "b");
+    assert_eq!(store.user_ids(), vec!["bob"]);
+
+    store.clear();
+    assert!(FileTokenStore::new(&path, key).user_ids().is_empty());
+    let _ = std::fs::remove_file(path);
+}
+
+/// A store written before `remove_token` and `user_ids` existed.
+struct SingleTokenStore(Option<OAuth2Token>);
+
+impl TokenStore for SingleTokenStore {
+    fn save_token(&mut self, _user_id: &str, token: OAuth2Token) {
+        self.0 = Some(token);
+    }
+
+    fn get_token(&self, _user_id: &str) -> Option<OAuth2Token> {
+        self.0.clone()
+    }
+}
+
+#[test]
+fn stores_without_listing_keep_working() {
+    let mut store = SingleTokenStore(None);
+    store.save_token(
+        "user",
+        OAuth2Token {
+            access_token: "t".into(),
+            refresh_token: "r".into(),
+            expires_at: Utc::now() + Duration::hours(1),
+        },
+    );
+    assert!(store.user_ids().is_empty());
+    assert!(store.remove_token("user").is_none());
+    assert_eq!(store.get_token("user").unwrap().access_token, "t");
+}
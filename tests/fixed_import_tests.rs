@@ -0,0 +1,76 @@
+use feed_my_ledger::import::fixed::{FixedSpec, parse_str_with_spec, parse_with_spec};
+
+const SPEC: &str = r#"
+date = { start = 0, len = 10 }
+description = { start = 10, len = 20 }
+amount = { start = 30, len = 10 }
+account = { start = 40, len = 16 }
+"#;
+
+#[test]
+fn parses_two_line_fixture() {
+    let data = format!(
+        "{:<10}{:<20}{:>10}{:<16}\n{:<10}{:<20}{:>10}{:<16}\n",
+        "2024-01-05",
+        "GROCERY STORE",
+        "42.10-",
+        "expenses:food",
+        "2024-01-06",
+        "PAYROLL ACME",
+        "1,500.00",
+        ""
+    );
+    let path = std::env::temp_dir().join(format!("fixed_{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, &data).unwrap();
+    let spec = FixedSpec::from_toml_str(SPEC).unwrap();
+    let records = parse_with_spec(&path, &spec).unwrap();
+    let _ = std::fs::remove_file(path);
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].description, "GROCERY STORE");
+    assert_eq!(records[0].amount, 42.10);
+    assert_eq!(records[0].debit_account.to_string(), "expenses:food");
+    assert_eq!(records[0].credit_account.to_string(), "bank");
+    assert_eq!(
+        records[0]
+            .transaction_date
+            .unwrap()
+            .format("%Y-%m-%d")
+            .to_string(),
+        "2024-01-05"
+    );
+
+    assert_eq!(records[1].description, "PAYROLL ACME");
+    assert_eq!(records[1].amount, 1500.0);
+    assert_eq!(records[1].debit_account.to_string(), "bank");
+    assert_eq!(records[1].credit_account.to_string(), "income");
+}
+
+#[test]
+fn trailing_cr_marks_negative_amount() {
+    let spec = FixedSpec::from_toml_str(
+        r#"
+description = { start = 0, len = 10 }
+amount = { start = 10, len = 8 }
+currency = "EUR"
+"#,
+    )
+    .unwrap();
+    let records = parse_str_with_spec("RENT        950CR\n", &spec).unwrap();
+    assert_eq!(records[0].description, "RENT");
+    assert_eq!(records[0].amount, 950.0);
+    assert_eq!(records[0].debit_account.to_string(), "expenses");
+    assert_eq!(records[0].currency, "EUR");
+}
+
+#[test]
+fn invalid_amount_is_an_error() {
+    let spec = FixedSpec::from_toml_str(
+        r#"
+description = { start = 0, len = 4 }
+amount = { start = 4, len = 4 }
+"#,
+    )
+    .unwrap();
+    assert!(parse_str_with_spec("RENTabcd\n", &spec).is_err());
+}
@@ -1,6 +1,8 @@
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter};
 use feed_my_ledger::core::{
-    Record,
-    utils::{generate_signature, hash_row},
+    Record, repair_sheet,
+    utils::{HashAlgo, generate_signature, hash_row, hash_row_with},
+    verify_sheet, verify_sheet_from, verify_sheet_full, verify_sheet_with,
 };
 
 #[test]
@@ -68,3 +70,175 @@ fn to_row_hashed_appends_hash() {
     assert_eq!(row.len(), record.to_row().len() + 1);
     assert!(!row.last().unwrap().is_empty());
 }
+
+#[test]
+fn hmac_hash_differs_from_salted() {
+    let sig = generate_signature("ledger", None).unwrap();
+    let values = vec!["a".to_string(), "b".to_string()];
+    let salted = hash_row_with(&values, &sig, HashAlgo::Sha256Salted);
+    let hmac = hash_row_with(&values, &sig, HashAlgo::HmacSha256);
+    assert_eq!(salted, hash_row(&values, &sig));
+    assert_ne!(salted, hmac);
+    assert_eq!(hmac.len(), 64);
+}
+
+#[test]
+fn rows_verify_under_their_own_algorithm() {
+    let record = Record::new(
+        "desc".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    let sig = generate_signature("ledger", None).unwrap();
+
+    let mut salted = GoogleSheetsAdapter::new();
+    let salted_sheet = salted.create_sheet("salted").unwrap();
+    salted
+        .append_row(&salted_sheet, record.to_row_hashed(&sig))
+        .unwrap();
+    assert!(
+        verify_sheet(&salted, &salted_sheet, &sig)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        verify_sheet_with(&salted, &salted_sheet, &sig, HashAlgo::HmacSha256).unwrap(),
+        vec![0]
+    );
+
+    let mut keyed = GoogleSheetsAdapter::new();
+    let keyed_sheet = keyed.create_sheet("keyed").unwrap();
+    keyed
+        .append_row(
+            &keyed_sheet,
+            record.to_row_hashed_with(&sig, HashAlgo::HmacSha256),
+        )
+        .unwrap();
+    assert!(
+        verify_sheet_with(&keyed, &keyed_sheet, &sig, HashAlgo::HmacSha256)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(verify_sheet(&keyed, &keyed_sheet, &sig).unwrap(), vec![0]);
+}
+
+#[test]
+fn verify_from_checks_only_the_suffix() {
+    let sig = generate_signature("ledger", None).unwrap();
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("suffix").unwrap();
+    for (i, desc) in ["rent", "coffee", "tea", "lunch"].into_iter().enumerate() {
+        let record = Record::new(
+            desc.into(),
+            "expenses".parse().unwrap(),
+            "cash".parse().unwrap(),
+            1.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        let mut row = record.to_row_hashed(&sig);
+        // Tamper with one row before the start index and one after it.
+        if i == 0 || i == 3 {
+            row[2] = "changed".into();
+        }
+        adapter.append_row(&sheet, row).unwrap();
+        if i == 2 {
+            adapter
+                .append_row(&sheet, vec!["status".into(), "id".into(), "true".into()])
+                .unwrap();
+        }
+    }
+
+    let (mismatched, total) = verify_sheet_from(&adapter, &sheet, &sig, 2).unwrap();
+    assert_eq!(mismatched, vec![4]);
+    assert_eq!(total, 5);
+    assert_eq!(verify_sheet(&adapter, &sheet, &sig).unwrap(), vec![0, 4]);
+
+    let (mismatched, total) = verify_sheet_from(&adapter, &sheet, &sig, total).unwrap();
+    assert!(mismatched.is_empty());
+    assert_eq!(total, 5);
+}
+
+#[test]
+fn repair_rehashes_edited_rows() {
+    let sig = generate_signature("ledger", None).unwrap();
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("repair").unwrap();
+    let record = Record::new(
+        "coffee".into(),
+        "expenses".parse().unwrap(),
+        "cash".parse().unwrap(),
+        3.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    adapter
+        .append_row(&sheet, record.to_row_hashed(&sig))
+        .unwrap();
+    let mut edited = record.to_row_hashed(&sig);
+    edited[2] = "tea".into();
+    adapter.append_row(&sheet, edited).unwrap();
+    adapter
+        .append_row(&sheet, vec!["status".into(), "id".into(), "true".into()])
+        .unwrap();
+    assert_eq!(verify_sheet(&adapter, &sheet, &sig).unwrap(), vec![1]);
+
+    assert!(repair_sheet(&mut adapter, &sheet, &sig, &[2]).is_err());
+    repair_sheet(&mut adapter, &sheet, &sig, &[1]).unwrap();
+    assert!(verify_sheet(&adapter, &sheet, &sig).unwrap().is_empty());
+    assert_eq!(adapter.read_row(&sheet, 1).unwrap()[2], "tea");
+}
+
+#[test]
+fn full_verify_reports_rehashed_garbage_as_parse_failure() {
+    let sig = generate_signature("ledger", None).unwrap();
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("full").unwrap();
+    let record = Record::new(
+        "coffee".into(),
+        "expenses".parse().unwrap(),
+        "cash".parse().unwrap(),
+        3.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    adapter
+        .append_row(&sheet, record.to_row_hashed(&sig))
+        .unwrap();
+    // A corrupt currency whose hash was recomputed still verifies.
+    let mut garbage = record.to_row();
+    garbage[0] = uuid::Uuid::new_v4().to_string();
+    garbage[6] = "XXQ".into();
+    let hash = hash_row(&garbage, &sig);
+    garbage.push(hash);
+    adapter.append_row(&sheet, garbage).unwrap();
+    let mut tampered = record.to_row_hashed(&sig);
+    tampered[2] = "tea".into();
+    adapter.append_row(&sheet, tampered).unwrap();
+    adapter
+        .append_row(&sheet, vec!["status".into(), "id".into(), "true".into()])
+        .unwrap();
+    assert_eq!(verify_sheet(&adapter, &sheet, &sig).unwrap(), vec![2]);
+
+    let report = verify_sheet_full(&adapter, &sheet, &sig).unwrap();
+    assert!(!report.is_ok());
+    assert_eq!(report.tampered, vec![2]);
+    assert_eq!(report.unparsable.len(), 1);
+    assert_eq!(report.unparsable[0].0, 1);
+    assert!(report.unparsable[0].1.contains("XXQ"), "{report:?}");
+}
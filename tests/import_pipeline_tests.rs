@@ -0,0 +1,67 @@
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter};
+use feed_my_ledger::core::Record;
+use feed_my_ledger::import::ImportError;
+use feed_my_ledger::import::pipeline::{self, PipelineError};
+
+fn synthetic(n: usize) -> Vec<Result<Record, ImportError>> {
+    (0..n)
+        .map(|i| {
+            Ok(Record::new(
+                format!("record {i}"),
+                "expenses".parse().unwrap(),
+                "cash".parse().unwrap(),
+                1.0 + i as f64,
+                "USD".into(),
+                None,
+                None,
+                vec![],
+            )
+            .unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn pipeline_preserves_order() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    let report = pipeline::run(synthetic(1000), &mut adapter, &sheet, "sig", 4);
+    assert!(report.error.is_none());
+    assert_eq!(report.appended, 1000);
+    let rows = adapter.list_rows(&sheet).unwrap();
+    assert_eq!(rows.len(), 1000);
+    for (i, row) in rows.iter().enumerate() {
+        assert_eq!(row[2], format!("record {i}"));
+    }
+}
+
+#[test]
+fn serial_pipeline_matches_concurrent_output() {
+    let mut serial = GoogleSheetsAdapter::new();
+    let sheet = serial.create_sheet("ledger").unwrap();
+    let records = synthetic(250);
+    let expected: Vec<Vec<String>> = records
+        .iter()
+        .map(|r| r.as_ref().unwrap().to_row_hashed("sig"))
+        .collect();
+    let report = pipeline::run_serial(records, &mut serial, &sheet, "sig", 30);
+    assert_eq!(report.appended, 250);
+    assert_eq!(serial.list_rows(&sheet).unwrap(), expected);
+}
+
+#[test]
+fn pipeline_stops_at_first_parse_error() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    let mut records = synthetic(500);
+    records[300] = Err(ImportError::Parse("bad row".into()));
+    let report = pipeline::run_batched(records, &mut adapter, &sheet, "sig", 3, 64);
+    assert!(matches!(
+        report.error,
+        Some(PipelineError::Import(ImportError::Parse(ref msg))) if msg == "bad row"
+    ));
+    assert_eq!(report.appended, 300);
+    let rows = adapter.list_rows(&sheet).unwrap();
+    assert_eq!(rows.len(), 300);
+    assert_eq!(rows[299][2], "record 299");
+}
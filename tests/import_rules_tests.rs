@@ -0,0 +1,53 @@
+use feed_my_ledger::import::{ofx, rules::RuleSet};
+
+const STATEMENT: &str = "<OFX><BANKTRANLIST>\
+<STMTTRN><TRNAMT>-4.50</TRNAMT><NAME>SQ *STARBUCKS 1234</NAME></STMTTRN>\
+<STMTTRN><TRNAMT>-60.00</TRNAMT><NAME>SHELL OIL 5678</NAME></STMTTRN>\
+<STMTTRN><TRNAMT>-12.00</TRNAMT><NAME>UNKNOWN MERCHANT</NAME></STMTTRN>\
+</BANKTRANLIST></OFX>";
+
+#[test]
+fn second_rule_assigns_accounts() {
+    let rules = RuleSet::from_toml_str(
+        r#"
+[[rules]]
+pattern = "(?i)starbucks"
+debit = "expenses:coffee"
+
+[[rules]]
+pattern = "^SHELL"
+debit = "expenses:fuel"
+credit = "assets:checking"
+"#,
+    )
+    .unwrap();
+    assert_eq!(rules.rules().len(), 2);
+
+    let records = rules.apply(ofx::parse_str(STATEMENT).unwrap());
+    assert_eq!(records[1].description, "SHELL OIL 5678");
+    assert_eq!(records[1].debit_account.to_string(), "expenses:fuel");
+    assert_eq!(records[1].credit_account.to_string(), "assets:checking");
+
+    assert_eq!(records[0].debit_account.to_string(), "expenses:coffee");
+    assert_eq!(records[0].credit_account.to_string(), "bank");
+
+    assert_eq!(records[2].debit_account.to_string(), "expenses");
+    assert_eq!(records[2].credit_account.to_string(), "bank");
+}
+
+#[test]
+fn loads_json_rules() {
+    let rules = RuleSet::from_json_str(
+        r#"{"rules": [{"pattern": "MERCHANT$", "debit": "expenses:misc"}]}"#,
+    )
+    .unwrap();
+    let records = rules.apply(ofx::parse_str(STATEMENT).unwrap());
+    assert_eq!(records[2].debit_account.to_string(), "expenses:misc");
+    assert_eq!(records[0].debit_account.to_string(), "expenses");
+}
+
+#[test]
+fn invalid_pattern_is_rejected() {
+    let result = RuleSet::from_toml_str("[[rules]]\npattern = \"(\"\ndebit = \"x\"\n");
+    assert!(result.is_err());
+}
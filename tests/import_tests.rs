@@ -1,4 +1,5 @@
-use feed_my_ledger::import::{csv, json, ledger, ofx, qif};
+use feed_my_ledger::import::amount::{self, DecimalSeparator};
+use feed_my_ledger::import::{ImportError, SignConvention, csv, json, jsonl, ledger, ofx, qif};
 use std::fs::write;
 
 fn write_temp(name: &str, content: &str) -> std::path::PathBuf {
@@ -32,6 +33,24 @@ fn qif_parsing() {
     let _ = std::fs::remove_file(path);
 }
 
+#[test]
+fn qif_tries_date_formats_in_order() {
+    use chrono::NaiveDate;
+
+    let qif_content =
+        "!Type:Bank\nD2024-01-31\nT-10.00\nPCoffee\n^\nD31.01.2024\nT-4.00\nPBagel\n^\n";
+    let records = qif::parse_str_with_date_formats(qif_content, &["%Y-%m-%d", "%d.%m.%Y"]).unwrap();
+    let expected = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+    assert_eq!(records.len(), 2);
+    for rec in &records {
+        assert_eq!(rec.transaction_date.unwrap().date_naive(), expected);
+    }
+
+    // With only the first format the second date is not understood.
+    let records = qif::parse_str_with_date_format(qif_content, "%Y-%m-%d").unwrap();
+    assert!(records[1].transaction_date.is_none());
+}
+
 #[test]
 fn qif_memo_overrides_vendor() {
     let qif_content = "!Type:Bank\nD01/02/2024\nT5.00\nPVend\nMMemo text\n^\n";
@@ -56,6 +75,54 @@ fn ofx_parsing() {
     let _ = std::fs::remove_file(path);
 }
 
+#[test]
+fn ofx_dates_honor_time_and_timezone() {
+    use chrono::{TimeZone, Utc};
+
+    let ofx_content = "<OFX><BANKTRANLIST>
+<STMTTRN><DTPOSTED>20240502120000[-5:EST]</DTPOSTED><TRNAMT>-7.00</TRNAMT><NAME>Lunch</NAME></STMTTRN>
+<STMTTRN><DTPOSTED>20240502233000.000[-5:EST]</DTPOSTED><TRNAMT>-9.00</TRNAMT><NAME>Taxi</NAME></STMTTRN>
+<STMTTRN><DTPOSTED>20240502093000[+5.5:IST]</DTPOSTED><TRNAMT>-3.00</TRNAMT><NAME>Tea</NAME></STMTTRN>
+</BANKTRANLIST></OFX>";
+    let records = ofx::parse_str(ofx_content).unwrap();
+    let utc: Vec<_> = records
+        .iter()
+        .map(|r| r.transaction_date.unwrap().with_timezone(&Utc))
+        .collect();
+    assert_eq!(utc[0], Utc.with_ymd_and_hms(2024, 5, 2, 17, 0, 0).unwrap());
+    // Late evening in New York is already the next day in UTC.
+    assert_eq!(utc[1], Utc.with_ymd_and_hms(2024, 5, 3, 4, 30, 0).unwrap());
+    assert_eq!(utc[2], Utc.with_ymd_and_hms(2024, 5, 2, 4, 0, 0).unwrap());
+}
+
+#[test]
+fn ofx_uses_statement_currency() {
+    let ofx_content = "<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>EUR
+<BANKTRANLIST>
+<STMTTRN><TRNAMT>-7.00</TRNAMT><NAME>Snack</NAME></STMTTRN>
+<STMTTRN><TRNAMT>-20.00</TRNAMT><NAME>Book</NAME><CURRENCY><CURRATE>1.2<CURSYM>GBP</CURRENCY></STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+    let path = write_temp("curdef.ofx", ofx_content);
+    let records = ofx::parse(&path).unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].currency, "EUR");
+    assert_eq!(records[1].currency, "GBP");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn ofx_rejects_unknown_currency() {
+    let ofx_content = "<OFX><CURDEF>XYZ</CURDEF><BANKTRANLIST>
+<STMTTRN><TRNAMT>-7.00</TRNAMT><NAME>Snack</NAME></STMTTRN>
+</BANKTRANLIST></OFX>";
+    let err = ofx::parse_str(ofx_content).unwrap_err();
+    assert!(
+        matches!(&err, ImportError::Parse(msg) if msg.contains("XYZ")),
+        "{err:?}"
+    );
+}
+
 #[test]
 fn csv_parsing_with_mapping() {
     let data = "desc,credit,debit,value,curr\nCoffee,cash,expenses:food,4.20,USD\n";
@@ -66,6 +133,9 @@ fn csv_parsing_with_mapping() {
         credit_account: "credit".into(),
         amount: "value".into(),
         currency: "curr".into(),
+        debit_amount: None,
+        credit_amount: None,
+        statement_account: None,
     };
     let records = csv::parse_with_mapping(&path, &mapping).unwrap();
     assert_eq!(records.len(), 1);
@@ -77,6 +147,56 @@ fn csv_parsing_with_mapping() {
     let _ = std::fs::remove_file(path);
 }
 
+#[test]
+fn csv_parsing_with_debit_and_credit_columns() {
+    let data = "description,debit_account,credit_account,paid_out,paid_in,currency\n\
+                Coffee,expenses:food,assets:bank,3.50,,USD\n\
+                Salary,expenses:food,assets:bank,,1000.00,USD\n\
+                Lunch,expenses:food,assets:bank,12.00,0.00,USD\n\
+                Refund,expenses:food,assets:bank,,4.25,USD\n";
+    let path = write_temp("test_split_amounts.csv", data);
+    let mapping = csv::CsvMapping {
+        debit_amount: Some("paid_out".into()),
+        credit_amount: Some("paid_in".into()),
+        ..csv::CsvMapping::default()
+    };
+    let records = csv::parse_with_mapping(&path, &mapping).unwrap();
+    let rows: Vec<_> = records
+        .iter()
+        .map(|r| {
+            (
+                r.debit_account.to_string(),
+                r.credit_account.to_string(),
+                r.amount,
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("expenses:food".into(), "assets:bank".into(), 3.5),
+            ("assets:bank".into(), "expenses:food".into(), 1000.0),
+            ("expenses:food".into(), "assets:bank".into(), 12.0),
+            ("assets:bank".into(), "expenses:food".into(), 4.25),
+        ]
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn csv_rejects_rows_with_both_amount_columns() {
+    let data = "description,debit_account,credit_account,paid_out,paid_in,currency\n\
+                Odd,expenses:food,assets:bank,1.00,2.00,USD\n";
+    let path = write_temp("test_both_amounts.csv", data);
+    let mapping = csv::CsvMapping {
+        debit_amount: Some("paid_out".into()),
+        credit_amount: Some("paid_in".into()),
+        ..csv::CsvMapping::default()
+    };
+    assert!(csv::parse_with_mapping(&path, &mapping).is_err());
+    let _ = std::fs::remove_file(path);
+}
+
 #[test]
 fn csv_parsing_with_currency_override() {
     let data = "description,debit_account,credit_account,amount\nCoffee,expenses:food,cash,3.50\n";
@@ -89,6 +209,170 @@ fn csv_parsing_with_currency_override() {
     let _ = std::fs::remove_file(path);
 }
 
+#[test]
+fn csv_streaming_invokes_callback_per_row() {
+    let mut data = String::from("description,debit_account,credit_account,amount,currency\n");
+    for i in 0..50 {
+        data.push_str(&format!("Item {i},expenses:food,cash,{i}.25,USD\n"));
+    }
+    let path = write_temp("test_stream.csv", &data);
+    let mut seen = Vec::new();
+    let count = csv::CsvImporter::parse_streaming(&path, &csv::CsvMapping::default(), |rec| {
+        seen.push(rec.description);
+        Ok::<(), ImportError>(())
+    })
+    .unwrap();
+    assert_eq!(count, 50);
+    assert_eq!(seen.len(), 50);
+    assert_eq!(seen[0], "Item 0");
+    assert_eq!(seen[49], "Item 49");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn csv_streaming_stops_on_callback_error() {
+    let data = "description,debit_account,credit_account,amount,currency\nA,expenses,cash,1,USD\nB,expenses,cash,2,USD\nC,expenses,cash,3,USD\n";
+    let path = write_temp("test_stream_abort.csv", data);
+    let mut calls = 0;
+    let result = csv::CsvImporter::parse_streaming(&path, &csv::CsvMapping::default(), |_| {
+        calls += 1;
+        if calls == 2 {
+            return Err(ImportError::Parse("stop".into()));
+        }
+        Ok(())
+    });
+    assert!(matches!(result, Err(ImportError::Parse(msg)) if msg == "stop"));
+    assert_eq!(calls, 2);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn qif_sign_convention_swaps_accounts() {
+    let qif_content =
+        "!Type:Bank\nD01/01/2024\nT-10.00\nPCoffee\n^\nD01/02/2024\nT25.00\nPRefund\n^\n";
+    let path = write_temp("sign.qif", qif_content);
+    let debit_pos =
+        qif::parse_with_sign_convention(&path, None, SignConvention::DebitPositive).unwrap();
+    let credit_pos =
+        qif::parse_with_sign_convention(&path, None, SignConvention::CreditPositive).unwrap();
+    let default = qif::parse(&path).unwrap();
+    for (a, b) in default.iter().zip(&debit_pos) {
+        assert_eq!(a.debit_account, b.debit_account);
+        assert_eq!(a.credit_account, b.credit_account);
+    }
+    for (a, b) in debit_pos.iter().zip(&credit_pos) {
+        assert_eq!(a.debit_account, b.credit_account);
+        assert_eq!(a.credit_account, b.debit_account);
+        assert_eq!(a.amount, b.amount);
+    }
+    assert_eq!(credit_pos[0].debit_account.to_string(), "Coffee");
+    assert_eq!(credit_pos[0].credit_account.to_string(), "bank");
+    assert_eq!(credit_pos[1].debit_account.to_string(), "bank");
+    assert_eq!(credit_pos[1].credit_account.to_string(), "Refund");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn ofx_sign_convention_swaps_accounts() {
+    let ofx = "<OFX><STMTTRN><TRNAMT>-5.00</TRNAMT><NAME>Shop</NAME></STMTTRN></OFX>";
+    let default = ofx::parse_str(ofx).unwrap();
+    let flipped = ofx::parse_str_with_sign_convention(ofx, SignConvention::CreditPositive).unwrap();
+    assert_eq!(default[0].debit_account.to_string(), "expenses");
+    assert_eq!(flipped[0].debit_account.to_string(), "bank");
+    assert_eq!(flipped[0].credit_account.to_string(), "expenses");
+}
+
+#[test]
+fn sign_convention_from_str() {
+    assert_eq!(
+        "credit-positive".parse::<SignConvention>().unwrap(),
+        SignConvention::CreditPositive
+    );
+    assert_eq!(
+        "Debit_Positive".parse::<SignConvention>().unwrap(),
+        SignConvention::DebitPositive
+    );
+    assert!("sideways".parse::<SignConvention>().is_err());
+}
+
+#[test]
+fn csv_infers_currency_from_amount_symbol() {
+    let data = "description,debit_account,credit_account,amount\nCoffee,expenses:food,cash,$3.50\nTea,expenses:food,cash,£2\n";
+    let path = write_temp("test_symbol.csv", data);
+    let records = csv::parse_with_mapping(&path, &csv::CsvMapping::default()).unwrap();
+    assert_eq!(records[0].amount, 3.5);
+    assert_eq!(records[0].currency, "USD");
+    assert_eq!(records[1].amount, 2.0);
+    assert_eq!(records[1].currency, "GBP");
+    let overridden = csv::parse_with_currency(&path, "CAD").unwrap();
+    assert_eq!(overridden[0].currency, "CAD");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn csv_parses_european_decimal_amounts() {
+    let data = "description,debit_account,credit_account,amount\nBread,expenses:food,cash,\"€4,20\"\nRent,expenses:rent,bank,\"1.250,00 €\"\n";
+    let path = write_temp("test_euro.csv", data);
+    let records = csv::parse_with_decimal_separator(
+        &path,
+        &csv::CsvMapping::default(),
+        None,
+        DecimalSeparator::Comma,
+    )
+    .unwrap();
+    assert_eq!(records[0].amount, 4.2);
+    assert_eq!(records[0].currency, "EUR");
+    assert_eq!(records[1].amount, 1250.0);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn csv_currency_column_takes_precedence_over_symbol() {
+    let data = "description,debit_account,credit_account,amount,currency\nCoffee,expenses:food,cash,$3.50,CAD\n";
+    let path = write_temp("test_symbol_column.csv", data);
+    let records = csv::parse(&path).unwrap();
+    assert_eq!(records[0].currency, "CAD");
+    assert_eq!(records[0].amount, 3.5);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn csv_without_currency_source_fails() {
+    let data = "description,debit_account,credit_account,amount\nCoffee,expenses:food,cash,3.50\n";
+    let path = write_temp("test_no_currency.csv", data);
+    assert!(csv::parse(&path).is_err());
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn amount_parser_handles_symbols() {
+    assert_eq!(
+        amount::parse_amount("$3.50", DecimalSeparator::Dot).unwrap(),
+        (3.5, Some("USD"))
+    );
+    assert_eq!(
+        amount::parse_amount("-€4,20", DecimalSeparator::Comma).unwrap(),
+        (-4.2, Some("EUR"))
+    );
+    assert_eq!(
+        amount::parse_amount("¥1,000", DecimalSeparator::Dot).unwrap(),
+        (1000.0, Some("JPY"))
+    );
+    assert_eq!(
+        amount::parse_amount("12.5", DecimalSeparator::Dot).unwrap(),
+        (12.5, None)
+    );
+    assert!(amount::parse_amount("$", DecimalSeparator::Dot).is_err());
+}
+
+#[test]
+fn ofx_infers_currency_from_amount_symbol() {
+    let ofx = "<OFX><STMTTRN><TRNAMT>-£10</TRNAMT><NAME>Shop</NAME></STMTTRN></OFX>";
+    let records = ofx::parse_str(ofx).unwrap();
+    assert_eq!(records[0].currency, "GBP");
+    assert_eq!(records[0].amount, 10.0);
+}
+
 #[test]
 fn qif_parsing_with_currency_override() {
     let qif_content = "!Type:Bank\nD01/01/2024\nT-10.00\nPCoffee\nM\n^\n";
@@ -148,6 +432,42 @@ fn ledger_and_json_roundtrip() {
     let _ = std::fs::remove_file(jpath);
 }
 
+#[test]
+fn jsonl_roundtrip_matches_json_records() {
+    let ledger_text = "2024-01-01 Coffee\n    expenses:food  5.00 USD\n    cash\n\n\
+                       2024-01-02 Tea\n    expenses:food  2.50 USD\n    cash\n";
+    let records = ledger::parse_str(ledger_text).unwrap();
+
+    let path = write_temp("roundtrip.jsonl", "");
+    jsonl::export(&path, &records).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), 2);
+    // Each line is a record in the `json` format's schema.
+    let as_json = format!("[{}]", text.lines().collect::<Vec<_>>().join(","));
+    assert_eq!(json::parse_str(&as_json).unwrap(), records);
+
+    let loaded = jsonl::parse(&path).unwrap();
+    assert_eq!(loaded, records);
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn jsonl_reports_line_of_malformed_record() {
+    let rec = ledger::parse_str("2024-01-01 Coffee\n    expenses:food  5.00 USD\n    cash\n")
+        .unwrap()
+        .remove(0);
+    let good = serde_json::to_string(&rec).unwrap();
+    let data = format!("{good}\n\n{{\"description\": \"Broken\"}}\n{good}\n");
+    match jsonl::parse_str(&data) {
+        Err(ImportError::Parse(msg)) => assert!(msg.starts_with("line 3:"), "{msg}"),
+        other => panic!("expected a parse error, got {other:?}"),
+    }
+    let (records, errors) = jsonl::JsonlImporter::parse_str_collect_errors(&data);
+    assert_eq!(records.len(), 2);
+    let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![3]);
+}
+
 #[test]
 fn csv_export_roundtrip() {
     let ledger_text = "2024-01-01 Coffee\n    expenses:food  5.00 USD\n    cash\n";
@@ -162,3 +482,228 @@ fn csv_export_roundtrip() {
     let _ = std::fs::remove_file(lpath);
     let _ = std::fs::remove_file(cpath);
 }
+
+#[test]
+fn csv_export_roundtrip_keeps_commas_quotes_and_newlines() {
+    use feed_my_ledger::core::Record;
+
+    let mut record = Record::new(
+        "Coffee, large\nrefill".into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        4.5,
+        "USD".into(),
+        None,
+        Some("ref \"7\"".into()),
+        vec!["cafe, downtown".into(), "\"daily\"".into()],
+    )
+    .unwrap();
+    record.transaction_description = Some("SQ *COFFEE,\r\nLARGE".into());
+    let mut plain = record.clone();
+    plain.id = uuid::Uuid::new_v4();
+    plain.description = "Tea".into();
+    plain.tags = vec!["a".into(), "b".into()];
+
+    let path = write_temp("quoted_export.csv", "");
+    csv::export(&path, &[record.clone(), plain.clone()]).unwrap();
+    let loaded = csv::parse(&path).unwrap();
+    assert_eq!(loaded, vec![record, plain]);
+    // Tags without commas keep the plain comma-joined cell.
+    assert_eq!(loaded[1].to_row()[9], "a,b");
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn csv_full_export_roundtrip_keeps_all_columns() {
+    use feed_my_ledger::core::{Posting, Record};
+
+    let mut record = Record::new_split(
+        "Invoice 7".into(),
+        vec![
+            Posting {
+                debit_account: "expenses:office".parse().unwrap(),
+                credit_account: "liabilities:card".parse().unwrap(),
+                amount: 30.0,
+                currency: None,
+                memo: None,
+            },
+            Posting {
+                debit_account: "expenses:travel".parse().unwrap(),
+                credit_account: "liabilities:card".parse().unwrap(),
+                amount: 12.5,
+                currency: Some("EUR".into()),
+                memo: Some("train".into()),
+            },
+        ],
+        "USD".into(),
+        Some(uuid::Uuid::new_v4()),
+        Some("INV-7".into()),
+        vec!["work".into(), "q1".into()],
+    )
+    .unwrap();
+    record.metadata.insert("vendor".into(), "Acme, Inc.".into());
+    record.transaction_description = Some("ACME 0042".into());
+
+    let path = write_temp("full_export.csv", "");
+    csv::export_full(&path, std::slice::from_ref(&record)).unwrap();
+    assert!(csv::is_full_export(&path).unwrap());
+    let loaded = csv::parse_full(&path).unwrap();
+    assert_eq!(loaded, vec![record]);
+
+    let minimal = write_temp("minimal_export.csv", "");
+    csv::export_minimal(&minimal, &loaded).unwrap();
+    assert!(!csv::is_full_export(&minimal).unwrap());
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(minimal);
+}
+
+#[test]
+fn csv_collects_errors_past_bad_rows() {
+    let data = "description,debit_account,credit_account,amount,currency\n\
+                Coffee,expenses:food,cash,3.50,USD\n\
+                Broken,expenses:food,cash,abc,USD\n\
+                Tea,expenses:food,cash,2.00,USD\n\
+                Nowhere,,cash,1.00,USD\n\
+                Lunch,expenses:food,cash,12.00,USD\n";
+    let path = write_temp("collect_errors.csv", data);
+    let (records, errors) = csv::parse_collect_errors(&path);
+    let descriptions: Vec<&str> = records.iter().map(|r| r.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["Coffee", "Tea", "Lunch"]);
+    let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![3, 5]);
+    assert!(
+        errors
+            .iter()
+            .all(|(_, e)| matches!(e, ImportError::Parse(_)))
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn json_collects_errors_past_bad_entries() {
+    let good = |desc: &str| {
+        let rec = feed_my_ledger::core::Record::new(
+            desc.into(),
+            "expenses:food".parse().unwrap(),
+            "cash".parse().unwrap(),
+            1.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        serde_json::to_string(&rec).unwrap()
+    };
+    let data = format!(
+        "[\n{},\n{{\"description\": \"Broken\"}},\n{},\n42,\n{}\n]\n",
+        good("Coffee"),
+        good("Tea"),
+        good("Lunch")
+    );
+    let path = write_temp("collect_errors.json", &data);
+    let (records, errors) = json::parse_collect_errors(&path);
+    let descriptions: Vec<&str> = records.iter().map(|r| r.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["Coffee", "Tea", "Lunch"]);
+    let lines: Vec<usize> = errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![3, 5]);
+    let _ = std::fs::remove_file(path);
+}
+
+fn postings(records: &[feed_my_ledger::core::Record]) -> Vec<(String, String, f64, String)> {
+    records
+        .iter()
+        .map(|r| {
+            (
+                r.debit_account.to_string(),
+                r.credit_account.to_string(),
+                r.amount,
+                r.currency.clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn paypal_preset_posts_signed_gross_against_the_wallet() {
+    let data = "\"Date\",\"Time\",\"TimeZone\",\"Name\",\"Type\",\"Status\",\"Currency\",\"Gross\",\"Fee\",\"Net\"\n\
+                \"01/03/2024\",\"10:00:00\",\"PST\",\"Coffee Shop\",\"Payment\",\"Completed\",\"USD\",\"-4.50\",\"0.00\",\"-4.50\"\n\
+                \"02/03/2024\",\"11:00:00\",\"PST\",\"Alice\",\"Payment Received\",\"Completed\",\"EUR\",\"1,250.00\",\"-30.00\",\"1,220.00\"\n";
+    let path = write_temp("test_preset_paypal.csv", data);
+    let mapping = csv::presets::Preset::Paypal.mapping();
+    let records = csv::parse_with_mapping(&path, &mapping).unwrap();
+    assert_eq!(records[0].description, "Coffee Shop");
+    assert_eq!(
+        postings(&records),
+        vec![
+            ("expenses".into(), "assets:paypal".into(), 4.5, "USD".into()),
+            (
+                "assets:paypal".into(),
+                "income".into(),
+                1250.0,
+                "EUR".into()
+            ),
+        ]
+    );
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn applecard_preset_treats_positive_amounts_as_purchases() {
+    let data = "Transaction Date,Clearing Date,Description,Merchant,Category,Type,Amount (USD),Purchased By\n\
+                03/01/2024,03/02/2024,GROCER 123 MAIN ST,Grocer,Grocery,Purchase,82.10,Sam\n\
+                03/05/2024,03/05/2024,ACH DEPOSIT,Apple Card,Payment,Payment,-500.00,Sam\n";
+    let path = write_temp("test_preset_applecard.csv", data);
+    let preset: csv::presets::Preset = "applecard".parse().unwrap();
+    let records =
+        csv::parse_with_mapping_and_currency(&path, &preset.mapping(), preset.currency().unwrap())
+            .unwrap();
+    assert_eq!(records[0].description, "Grocer");
+    assert_eq!(
+        postings(&records),
+        vec![
+            (
+                "expenses".into(),
+                "liabilities:applecard".into(),
+                82.1,
+                "USD".into()
+            ),
+            (
+                "liabilities:applecard".into(),
+                "income".into(),
+                500.0,
+                "USD".into()
+            ),
+        ]
+    );
+    assert!("venmo".parse::<csv::presets::Preset>().is_err());
+    let _ = std::fs::remove_file(path);
+}
+
+#[test]
+fn template_imports_back_with_its_mapping() {
+    let path = write_temp("template.csv", "");
+    csv::write_template(&path, &csv::CsvMapping::default()).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "description,debit_account,credit_account,amount,currency\n"
+    );
+    assert!(csv::parse(&path).unwrap().is_empty());
+
+    csv::write_template_with_example(&path, &csv::CsvMapping::default()).unwrap();
+    let records = csv::parse(&path).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].debit_account.to_string(), "expenses:food");
+    assert_eq!(records[0].amount, 3.5);
+
+    let split = csv::CsvMapping {
+        debit_amount: Some("Paid in".into()),
+        credit_amount: Some("Paid out".into()),
+        ..csv::CsvMapping::default()
+    };
+    csv::write_template_with_example(&path, &split).unwrap();
+    let records = csv::parse_with_mapping(&path, &split).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].currency, "USD");
+    let _ = std::fs::remove_file(path);
+}
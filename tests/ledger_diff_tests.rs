@@ -0,0 +1,48 @@
+use feed_my_ledger::core::diff::{LedgerDiff, diff};
+use feed_my_ledger::core::{Ledger, Record};
+
+fn record(desc: &str, amount: f64) -> Record {
+    Record::new(
+        desc.into(),
+        "expenses".parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+fn ledger_of(records: impl IntoIterator<Item = Record>) -> Ledger {
+    let mut ledger = Ledger::default();
+    for r in records {
+        ledger.commit(r);
+    }
+    ledger
+}
+
+#[test]
+fn reports_extra_and_tampered_records() {
+    let shared = record("Groceries", 40.0);
+    let original = record("Rent", 900.0);
+    let removed = record("Cinema", 12.0);
+    let extra = record("Coffee", 3.5);
+    let mut tampered = original.clone();
+    tampered.amount = 90.0;
+    let mut reconciled = shared.clone();
+    reconciled.cleared = true;
+
+    let a = ledger_of([shared.clone(), original.clone(), removed.clone()]);
+    let b = ledger_of([reconciled, tampered, extra.clone()]);
+    assert_eq!(
+        diff(&a, &b),
+        LedgerDiff {
+            only_in_a: vec![removed.id],
+            only_in_b: vec![extra.id],
+            changed: vec![original.id],
+        }
+    );
+    assert!(diff(&a, &a).is_empty());
+}
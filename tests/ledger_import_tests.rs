@@ -0,0 +1,225 @@
+use feed_my_ledger::import::ledger;
+
+#[test]
+fn parses_three_posting_transaction() {
+    let journal = "\
+; opening comment
+2024-03-01 * (101) Grocery run ; weekly
+    expenses:food         40.00 USD
+    expenses:household    10.00 USD  ; soap
+    ; a comment between postings
+    assets:checking      -50.00 USD
+";
+    let records = ledger::parse_str(journal).unwrap();
+    assert_eq!(records.len(), 1);
+    let rec = &records[0];
+    assert_eq!(rec.description, "Grocery run");
+    assert_eq!(rec.currency, "USD");
+    let postings: Vec<_> = rec
+        .postings()
+        .map(|p| {
+            (
+                p.debit_account.to_string(),
+                p.credit_account.to_string(),
+                p.amount,
+            )
+        })
+        .collect();
+    assert_eq!(
+        postings,
+        vec![
+            ("expenses:food".into(), "assets:checking".into(), 40.0),
+            ("expenses:household".into(), "assets:checking".into(), 10.0),
+        ]
+    );
+    assert_eq!(rec.splits.len(), 1);
+}
+
+#[test]
+fn infers_elided_amount() {
+    let journal = "\
+2024-03-02 Paycheck
+    assets:checking        1800 EUR
+    expenses:tax            200 EUR
+    income:salary
+
+2024-03-03 Coffee
+    expenses:food  $3.50
+    assets:cash
+";
+    let records = ledger::parse_str(journal).unwrap();
+    assert_eq!(records.len(), 2);
+    let pay = &records[0];
+    assert_eq!(pay.currency, "EUR");
+    let total: f64 = pay.postings().map(|p| p.amount).sum();
+    assert_eq!(total, 2000.0);
+    assert!(
+        pay.postings()
+            .all(|p| p.credit_account.to_string() == "income:salary")
+    );
+    assert_eq!(records[1].amount, 3.5);
+    assert_eq!(records[1].currency, "USD");
+    assert_eq!(records[1].credit_account.to_string(), "assets:cash");
+}
+
+#[test]
+fn rejects_unbalanced_transaction() {
+    let journal = "\
+2024-03-04 Oops
+    expenses:food  5 USD
+    assets:cash   -4 USD
+";
+    assert!(ledger::parse_str(journal).is_err());
+}
+
+#[test]
+fn rejects_two_elided_amounts() {
+    let journal = "\
+2024-03-04 Oops
+    expenses:food  5 USD
+    assets:cash
+    assets:bank
+";
+    assert!(ledger::parse_str(journal).is_err());
+}
+
+#[test]
+fn skips_comments_and_directives_and_follows_includes() {
+    let dir = std::env::temp_dir().join(format!("journal_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(
+        dir.join("sub/extra.journal"),
+        "2024-04-02 Rent\n    expenses:rent  900 USD\n    assets:checking\n",
+    )
+    .unwrap();
+    let main = dir.join("main.journal");
+    std::fs::write(
+        &main,
+        "\
+; leading comment
+# another comment
+% and another
+account assets:checking
+    note Main account
+commodity USD
+include sub/extra.journal
+
+2024-04-01 Coffee
+    # comment inside a transaction
+    expenses:food  4 USD
+    assets:cash
+
+comment
+this block is ignored
+end comment
+",
+    )
+    .unwrap();
+
+    let records = ledger::parse(&main).unwrap();
+    let descriptions: Vec<_> = records.iter().map(|r| r.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["Rent", "Coffee"]);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn circular_include_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("journal_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.journal"), "include b.journal\n").unwrap();
+    std::fs::write(dir.join("b.journal"), "include a.journal\n").unwrap();
+
+    let err = ledger::parse(&dir.join("a.journal")).unwrap_err();
+    assert!(err.to_string().contains("circular include"), "{err}");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn unknown_directive_is_an_error() {
+    assert!(ledger::parse_str("frobnicate everything\n").is_err());
+}
+
+#[test]
+fn export_keeps_every_posting_of_a_split_record() {
+    use feed_my_ledger::core::{Posting, Record};
+
+    let posting = |debit: &str, credit: &str, amount: f64| Posting {
+        debit_account: debit.parse().unwrap(),
+        credit_account: credit.parse().unwrap(),
+        amount,
+        currency: None,
+        memo: None,
+    };
+    let record = Record::new_split(
+        "Grocery run".into(),
+        vec![
+            posting("expenses:food", "assets:checking", 40.0),
+            posting("expenses:household", "assets:checking", 10.0),
+            posting("expenses:fees", "liabilities:card", 1.5),
+        ],
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+
+    let path = std::env::temp_dir().join(format!("split_export_{}.ledger", std::process::id()));
+    ledger::export(&path, std::slice::from_ref(&record)).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    // The last line is elided because the transaction balances.
+    assert!(text.ends_with("    liabilities:card\n\n"), "{text}");
+    let loaded = ledger::parse(&path).unwrap();
+    let _ = std::fs::remove_file(path);
+
+    assert_eq!(loaded.len(), 1);
+    assert_eq!(loaded[0].description, record.description);
+    assert_eq!(loaded[0].currency, record.currency);
+    assert_eq!(
+        loaded[0].clone().into_postings(),
+        record.clone().into_postings()
+    );
+}
+
+#[test]
+fn export_writes_cleared_marker_tags_and_statement_date() {
+    use chrono::{Local, TimeZone, Utc};
+    use feed_my_ledger::core::Record;
+
+    let mut cleared = Record::new(
+        "Hotel".into(),
+        "expenses:travel".parse().unwrap(),
+        "assets:checking".parse().unwrap(),
+        120.0,
+        "EUR".into(),
+        None,
+        None,
+        vec!["trip".into()],
+    )
+    .unwrap();
+    cleared.timestamp = Utc.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap();
+    cleared.transaction_date = Local.with_ymd_and_hms(2024, 3, 7, 12, 0, 0).single();
+    cleared.metadata.insert("invoice".into(), "42".into());
+    cleared.cleared = true;
+    let mut pending = cleared.clone();
+    pending.description = "Taxi".into();
+    pending.tags.clear();
+    pending.metadata.clear();
+    pending.transaction_date = None;
+    pending.cleared = false;
+
+    let path = std::env::temp_dir().join(format!("tagged_export_{}.ledger", std::process::id()));
+    ledger::export(&path, &[cleared, pending]).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        text.contains("2024-03-07 * Hotel  ; trip:, invoice:42\n"),
+        "{text}"
+    );
+    assert!(text.contains("2024-03-09 ! Taxi\n"), "{text}");
+
+    // Markers and tag comments are skipped on re-import.
+    let loaded = ledger::parse(&path).unwrap();
+    let _ = std::fs::remove_file(path);
+    let descriptions: Vec<_> = loaded.iter().map(|r| r.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["Hotel", "Taxi"]);
+}
@@ -0,0 +1,104 @@
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter};
+use feed_my_ledger::core::{Ledger, MergeStrategy, Record, SharedLedger};
+
+fn record(desc: &str, amount: f64) -> Record {
+    Record::new(
+        desc.into(),
+        "expenses".parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+fn ledger_of(records: impl IntoIterator<Item = Record>) -> Ledger {
+    let mut ledger = Ledger::default();
+    for r in records {
+        ledger.commit(r);
+    }
+    ledger
+}
+
+#[test]
+fn merges_mock_sheets() {
+    let overlap = record("Groceries", 40.0);
+    let unique = record("Cinema", 12.0);
+    let load = |rows: &[&Record]| {
+        let mut adapter = GoogleSheetsAdapter::new();
+        let sheet = adapter.create_sheet("ledger").unwrap();
+        for r in rows {
+            adapter.append_row(&sheet, r.to_row()).unwrap();
+        }
+        let shared = SharedLedger::from_sheet(adapter, sheet, "me").unwrap();
+        ledger_of(shared.records("me").unwrap())
+    };
+    let mut target = load(&[&overlap]);
+    let source = load(&[&overlap, &unique]);
+
+    let report = target.merge(&source, MergeStrategy::default());
+    assert_eq!(report.merged, vec![unique.id]);
+    assert_eq!(report.duplicates, vec![overlap.id]);
+    assert!(report.conflicts.is_empty());
+    assert_eq!(target.records().count(), 2);
+    assert_eq!(target.get_record(unique.id).unwrap().description, "Cinema");
+}
+
+#[test]
+fn reports_conflicting_ids() {
+    let original = record("Rent", 1000.0);
+    let mut edited = original.clone();
+    edited.amount = 1100.0;
+    let mut target = ledger_of([original.clone()]);
+    let report = target.merge(&ledger_of([edited]), MergeStrategy::default());
+    assert_eq!(report.conflicts, vec![original.id]);
+    assert!(report.merged.is_empty());
+    assert_eq!(target.get_record(original.id).unwrap().amount, 1000.0);
+}
+
+#[test]
+fn content_duplicates_depend_on_strategy() {
+    let mine = record("Coffee", 3.5);
+    let mut theirs = mine.clone();
+    theirs.id = uuid::Uuid::new_v4();
+
+    let mut by_content = ledger_of([mine.clone()]);
+    let report = by_content.merge(&ledger_of([theirs.clone()]), MergeStrategy::ByIdOrContent);
+    assert_eq!(report.duplicates, vec![theirs.id]);
+
+    let mut by_id = ledger_of([mine]);
+    let report = by_id.merge(&ledger_of([theirs.clone()]), MergeStrategy::ById);
+    assert_eq!(report.merged, vec![theirs.id]);
+}
+
+#[test]
+fn adjustments_stay_linked() {
+    let mine = record("Coffee", 3.5);
+    let mut theirs = mine.clone();
+    theirs.id = uuid::Uuid::new_v4();
+    let mut refund = record("Refund", 3.5);
+    refund.reference_id = Some(theirs.id);
+    let original = record("Hotel", 200.0);
+    let mut correction = record("Hotel correction", 20.0);
+    correction.reference_id = Some(original.id);
+
+    let mut target = ledger_of([mine.clone()]);
+    let source = ledger_of([theirs, refund.clone(), original.clone(), correction.clone()]);
+    let report = target.merge(&source, MergeStrategy::default());
+    assert_eq!(report.merged, vec![refund.id, original.id, correction.id]);
+    // The refund pointed at a record skipped as a duplicate; it now points at
+    // the matching record that was already present.
+    assert_eq!(
+        target.get_record(refund.id).unwrap().reference_id,
+        Some(mine.id)
+    );
+    let history: Vec<_> = target
+        .adjustment_history(original.id)
+        .iter()
+        .map(|r| r.id)
+        .collect();
+    assert_eq!(history, vec![correction.id]);
+}
@@ -1,6 +1,7 @@
 use chrono::{NaiveDate, TimeZone, Utc};
 use feed_my_ledger::core::{
-    Account, Ledger, LedgerError, Posting, PriceDatabase, Record, RecordError,
+    Account, Ledger, LedgerError, MissingRate, Posting, PriceDatabase, PriceLookup, Record,
+    RecordError,
 };
 use uuid::Uuid;
 
@@ -178,6 +179,62 @@ fn adjustment_chaining() {
     assert_eq!(history_adj1[0].id, adj2_id);
 }
 
+fn entry(description: &str, minute: u32) -> Record {
+    let mut rec = Record::new(
+        description.into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 0, minute, 0).unwrap();
+    rec
+}
+
+#[test]
+fn lineage_from_middle_of_chain() {
+    let mut ledger = Ledger::default();
+    let original = entry("orig", 0);
+    let orig_id = original.id;
+    ledger.commit(original);
+    let adj1 = entry("adj1", 1);
+    let adj1_id = adj1.id;
+    ledger.apply_adjustment(orig_id, adj1).unwrap();
+    let adj2 = entry("adj2", 2);
+    let adj2_id = adj2.id;
+    ledger.apply_adjustment(adj1_id, adj2).unwrap();
+    ledger.commit(entry("unrelated", 3));
+
+    assert_eq!(ledger.original_of(adj1_id), Some(orig_id));
+    assert_eq!(ledger.original_of(orig_id), Some(orig_id));
+    assert_eq!(ledger.original_of(Uuid::new_v4()), None);
+
+    let ids: Vec<Uuid> = ledger.lineage(adj1_id).iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec![orig_id, adj1_id, adj2_id]);
+    assert!(ledger.lineage(Uuid::new_v4()).is_empty());
+}
+
+#[test]
+fn lineage_stops_on_reference_cycle() {
+    let mut ledger = Ledger::default();
+    let mut a = entry("a", 0);
+    let mut b = entry("b", 1);
+    a.reference_id = Some(b.id);
+    b.reference_id = Some(a.id);
+    let (a_id, b_id) = (a.id, b.id);
+    ledger.commit(a);
+    ledger.commit(b);
+
+    assert_eq!(ledger.original_of(a_id), Some(b_id));
+    assert_eq!(ledger.adjustment_history(a_id).len(), 1);
+    let ids: Vec<Uuid> = ledger.lineage(a_id).iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec![a_id, b_id]);
+}
+
 #[test]
 fn adjustment_requires_existing_record() {
     let mut ledger = Ledger::default();
@@ -400,6 +457,98 @@ fn account_tree_balance_nested_accounts() {
     assert_eq!(ledger.account_tree_balance(&parent, "USD", &prices), 7.0);
 }
 
+#[test]
+fn as_of_balance_uses_statement_date() {
+    let mut ledger = Ledger::default();
+    let mut imported = Record::new(
+        "imported".into(),
+        "Assets:Bank".parse().unwrap(),
+        "income".parse().unwrap(),
+        5.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    // Imported in July from a statement line dated in June.
+    imported.timestamp = Utc.with_ymd_and_hms(2024, 7, 2, 9, 0, 0).unwrap();
+    imported.transaction_date = Some(
+        chrono::Local
+            .with_ymd_and_hms(2024, 6, 28, 0, 0, 0)
+            .unwrap(),
+    );
+    ledger.commit(imported);
+    let prices = PriceDatabase::default();
+    let bank: Account = "Assets".parse().unwrap();
+    let on = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+    assert_eq!(
+        ledger.account_tree_balance_as_of(&bank, on(6, 27), "USD", &prices),
+        0.0
+    );
+    assert_eq!(
+        ledger.account_tree_balance_as_of(&bank, on(6, 30), "USD", &prices),
+        5.0
+    );
+}
+
+#[test]
+fn case_insensitive_tree_balance_preserves_display() {
+    let mut ledger = Ledger::default().with_case_insensitive_accounts(true);
+    ledger.commit(
+        Record::new(
+            "check".into(),
+            "Assets:Bank:Checking".parse().unwrap(),
+            "income".parse().unwrap(),
+            5.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap(),
+    );
+    ledger.commit(
+        Record::new(
+            "save".into(),
+            "assets:bank:savings".parse().unwrap(),
+            "Income".parse().unwrap(),
+            2.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap(),
+    );
+    let prices = PriceDatabase::default();
+    let parent: Account = "ASSETS:bank".parse().unwrap();
+    assert_eq!(ledger.account_tree_balance(&parent, "USD", &prices), 7.0);
+    assert_eq!(ledger.account_balance("INCOME", "USD", &prices), -7.0);
+
+    let names: Vec<_> = ledger
+        .records()
+        .map(|r| r.debit_account.to_string())
+        .collect();
+    assert_eq!(names, vec!["Assets:Bank:Checking", "assets:bank:savings"]);
+
+    let strict = Ledger::default();
+    assert_eq!(strict.account_tree_balance(&parent, "USD", &prices), 0.0);
+}
+
+#[test]
+fn account_matches_optionally_ignores_case() {
+    let a: Account = "Assets:Bank".parse().unwrap();
+    let b: Account = "assets:bank".parse().unwrap();
+    assert!(!a.matches(&b, false));
+    assert!(a.matches(&b, true));
+    assert_ne!(a, b);
+    let child: Account = "assets:bank:checking".parse().unwrap();
+    assert!(!child.matches(&a, true));
+    assert!(child.starts_with_case(&a, true));
+    assert!(!child.starts_with(&a));
+}
+
 #[test]
 fn split_transaction_balance() {
     let mut ledger = Ledger::default();
@@ -410,11 +559,15 @@ fn split_transaction_balance() {
                 debit_account: "expenses:grocery".parse().unwrap(),
                 credit_account: "cash".parse().unwrap(),
                 amount: 30.0,
+                currency: None,
+                memo: None,
             },
             Posting {
                 debit_account: "expenses:supplies".parse().unwrap(),
                 credit_account: "cash".parse().unwrap(),
                 amount: 20.0,
+                currency: None,
+                memo: None,
             },
         ],
         "USD".into(),
@@ -435,3 +588,597 @@ fn split_transaction_balance() {
         20.0
     );
 }
+
+#[test]
+fn split_with_negative_contra_posting_balances() {
+    let mut ledger = Ledger::default();
+    // A 50.00 grocery purchase with a 10.00 coupon refunded on the same line.
+    let rec = Record::new_split(
+        "shopping".into(),
+        vec![
+            Posting {
+                debit_account: "expenses:grocery".parse().unwrap(),
+                credit_account: "cash".parse().unwrap(),
+                amount: 50.0,
+                currency: None,
+                memo: None,
+            },
+            Posting {
+                debit_account: "expenses:grocery".parse().unwrap(),
+                credit_account: "cash".parse().unwrap(),
+                amount: -10.0,
+                currency: None,
+                memo: Some("coupon".into()),
+            },
+        ],
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    assert_eq!(rec.splits[0].amount, -10.0);
+    ledger.commit(rec);
+    assert!(ledger.validate().is_empty());
+    let prices = PriceDatabase::default();
+    assert_eq!(ledger.account_balance("cash", "USD", &prices), -40.0);
+    assert_eq!(
+        ledger.account_balance("expenses:grocery", "USD", &prices),
+        40.0
+    );
+}
+
+#[test]
+fn commit_checked_rejects_duplicate_ids() {
+    let mut ledger = Ledger::default();
+    let record = Record::new(
+        "first".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    ledger.commit_checked(record.clone()).unwrap();
+    assert_eq!(
+        ledger.commit_checked(record.clone()),
+        Err(LedgerError::DuplicateId(record.id))
+    );
+    assert_eq!(ledger.records().count(), 1);
+    assert!(ledger.duplicate_ids().is_empty());
+
+    ledger.commit(record.clone());
+    assert_eq!(ledger.duplicate_ids(), vec![record.id]);
+}
+
+#[test]
+fn running_balance_accumulates_in_timestamp_order() {
+    let mut ledger = Ledger::default();
+    let entries = [
+        (2, "assets:cash", "income", 10.0),
+        (1, "assets:cash", "income", 5.0),
+        (3, "expenses:food", "assets:cash", 4.0),
+    ];
+    for (day, debit, credit, amount) in entries {
+        let mut rec = Record::new(
+            format!("day {day}"),
+            debit.parse().unwrap(),
+            credit.parse().unwrap(),
+            amount,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        rec.timestamp = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        ledger.commit(rec);
+    }
+    ledger.commit(
+        Record::new(
+            "unrelated".into(),
+            "expenses:rent".parse().unwrap(),
+            "bank".parse().unwrap(),
+            100.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap(),
+    );
+
+    let prices = PriceDatabase::default();
+    let account: Account = "assets".parse().unwrap();
+    let rows: Vec<_> = ledger
+        .running_balance(&account, "USD", &prices)
+        .map(|(r, bal)| (r.description.clone(), bal))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            ("day 1".to_string(), 5.0),
+            ("day 2".to_string(), 15.0),
+            ("day 3".to_string(), 11.0),
+        ]
+    );
+}
+
+fn naive_balance(
+    ledger: &Ledger,
+    select: impl Fn(&Account) -> bool,
+    target: &str,
+    prices: &PriceDatabase,
+) -> f64 {
+    let mut total = 0.0;
+    for r in ledger.records() {
+        for p in r.postings() {
+            let rate = if r.currency == target {
+                1.0
+            } else {
+                match prices.get_rate(r.timestamp.date_naive(), &r.currency, target) {
+                    Some(rate) => rate,
+                    None => continue,
+                }
+            };
+            if select(p.debit_account) {
+                total += p.amount * rate;
+            }
+            if select(p.credit_account) {
+                total -= p.amount * rate;
+            }
+        }
+    }
+    total
+}
+
+#[test]
+fn indexed_balances_match_naive_scan() {
+    let accounts = [
+        "assets:bank:checking",
+        "assets:bank:savings",
+        "assets:cash",
+        "expenses:food",
+        "expenses:rent",
+        "income:salary",
+    ];
+    let mut ledger = Ledger::default();
+    for i in 0..300usize {
+        let debit = accounts[i % accounts.len()];
+        let credit = accounts[(i * 7 + 1) % accounts.len()];
+        if debit == credit {
+            continue;
+        }
+        let currency = if i % 3 == 0 { "EUR" } else { "USD" };
+        let splits = if i % 5 == 0 {
+            vec![Posting {
+                debit_account: "expenses:food".parse().unwrap(),
+                credit_account: "assets:cash".parse().unwrap(),
+                amount: 2.5,
+                currency: None,
+                memo: None,
+            }]
+        } else {
+            vec![]
+        };
+        let mut rec = Record::new_split(
+            format!("tx {i}"),
+            std::iter::once(Posting {
+                debit_account: debit.parse().unwrap(),
+                credit_account: credit.parse().unwrap(),
+                amount: i as f64 + 1.0,
+                currency: None,
+                memo: None,
+            })
+            .chain(splits)
+            .collect(),
+            currency.into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        rec.timestamp = Utc
+            .with_ymd_and_hms(2024, 1, 1 + (i % 28) as u32, 0, 0, 0)
+            .unwrap();
+        ledger.commit(rec);
+    }
+    let mut prices = PriceDatabase::default();
+    prices.add_rate(
+        NaiveDate::from_ymd_opt(2024, 1, 10).unwrap(),
+        "EUR",
+        "USD",
+        1.1,
+    );
+
+    for name in accounts
+        .iter()
+        .chain(["assets", "assets:bank", "expenses"].iter())
+    {
+        let account: Account = name.parse().unwrap();
+        let exact = ledger.account_balance(name, "USD", &prices);
+        let expected = naive_balance(&ledger, |a| a == &account, "USD", &prices);
+        assert!(
+            (exact - expected).abs() < 1e-6,
+            "{name}: {exact} != {expected}"
+        );
+        let tree = ledger.account_tree_balance(&account, "USD", &prices);
+        let expected = naive_balance(&ledger, |a| a.starts_with(&account), "USD", &prices);
+        assert!(
+            (tree - expected).abs() < 1e-6,
+            "{name}: {tree} != {expected}"
+        );
+    }
+}
+
+#[test]
+fn split_postings_convert_with_their_own_currency() {
+    let mut ledger = Ledger::default();
+    let mut rec = Record::new_split(
+        "convert EUR to USD".into(),
+        vec![
+            Posting {
+                debit_account: "assets:usd".parse().unwrap(),
+                credit_account: "equity:conversion".parse().unwrap(),
+                amount: 110.0,
+                currency: None,
+                memo: None,
+            },
+            Posting {
+                debit_account: "equity:conversion".parse().unwrap(),
+                credit_account: "assets:eur".parse().unwrap(),
+                amount: 100.0,
+                currency: Some("EUR".into()),
+                memo: None,
+            },
+        ],
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+    let currencies: Vec<_> = rec.postings().map(|p| p.currency).collect();
+    assert_eq!(currencies, vec!["USD", "EUR"]);
+    ledger.commit(rec);
+
+    let mut prices = PriceDatabase::default();
+    prices.add_rate(
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        "EUR",
+        "USD",
+        1.1,
+    );
+    prices.add_rate(
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        "USD",
+        "EUR",
+        1.0 / 1.1,
+    );
+
+    let usd = ledger.account_balance("assets:usd", "USD", &prices);
+    let eur = ledger.account_balance("assets:eur", "USD", &prices);
+    assert_eq!(usd, 110.0);
+    assert!((eur + 110.0).abs() < 1e-9);
+    assert!((ledger.account_balance("assets:eur", "EUR", &prices) + 100.0).abs() < 1e-9);
+    assert!((ledger.account_balance("assets:usd", "EUR", &prices) - 100.0).abs() < 1e-9);
+    let assets: Account = "assets".parse().unwrap();
+    assert!(ledger.account_tree_balance(&assets, "USD", &prices).abs() < 1e-9);
+}
+
+#[test]
+fn missing_rates_are_reported_by_try_balances() {
+    let mut ledger = Ledger::default();
+    for (amount, currency, day) in [(10.0, "USD", 1), (20.0, "GBP", 2), (5.0, "GBP", 3)] {
+        let mut rec = Record::new(
+            "purchase".into(),
+            "expenses:food".parse().unwrap(),
+            "cash".parse().unwrap(),
+            amount,
+            currency.into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        rec.timestamp = Utc.with_ymd_and_hms(2024, 5, day, 0, 0, 0).unwrap();
+        ledger.commit(rec);
+    }
+    let mut prices = PriceDatabase::default();
+    let may = |day| NaiveDate::from_ymd_opt(2024, 5, day).unwrap();
+    prices.add_rate(may(3), "GBP", "USD", 1.25);
+
+    // The lenient balance silently drops the GBP posting of May 2.
+    assert_eq!(
+        ledger.account_balance("expenses:food", "USD", &prices),
+        16.25
+    );
+    let expected = MissingRate {
+        pairs: vec![(may(2), "GBP".into(), "USD".into())],
+    };
+    let err = ledger
+        .try_account_balance("expenses:food", "USD", &prices)
+        .unwrap_err();
+    assert_eq!(err, expected);
+    assert_eq!(
+        err.to_string(),
+        "no exchange rate for GBP to USD on 2024-05-02"
+    );
+    let food: Account = "expenses:food".parse().unwrap();
+    assert_eq!(
+        ledger.try_account_tree_balance(&food, "USD", &prices),
+        Err(expected.clone())
+    );
+    assert_eq!(ledger.try_trial_balance("USD", &prices), Err(expected));
+    // Converting on a date with a rate succeeds.
+    assert_eq!(
+        ledger.try_account_tree_balance_as_of(&food, may(31), "USD", &prices),
+        Ok(41.25)
+    );
+
+    prices.add_rate(may(1), "GBP", "USD", 1.25);
+    assert_eq!(
+        ledger.try_account_balance("expenses:food", "USD", &prices),
+        Ok(41.25)
+    );
+}
+
+#[test]
+fn borrowed_postings_match_owned_postings() {
+    let rec = Record::new_split(
+        "office supplies".into(),
+        vec![
+            Posting {
+                debit_account: "expenses:office".parse().unwrap(),
+                credit_account: "liabilities:card".parse().unwrap(),
+                amount: 20.0,
+                currency: None,
+                memo: None,
+            },
+            Posting {
+                debit_account: "expenses:travel".parse().unwrap(),
+                credit_account: "liabilities:card".parse().unwrap(),
+                amount: 5.0,
+                currency: Some("EUR".into()),
+                memo: Some("taxi".into()),
+            },
+        ],
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    let borrowed: Vec<Posting> = rec
+        .postings()
+        .map(|p| Posting {
+            debit_account: p.debit_account.clone(),
+            credit_account: p.credit_account.clone(),
+            amount: p.amount,
+            currency: Some(p.currency.to_string()),
+            memo: p.memo.map(String::from),
+        })
+        .collect();
+    let owned = rec.into_postings();
+    assert_eq!(borrowed, owned);
+    assert_eq!(owned[0].currency.as_deref(), Some("USD"));
+    assert_eq!(owned[1].currency.as_deref(), Some("EUR"));
+    assert_eq!(owned[1].memo.as_deref(), Some("taxi"));
+}
+
+#[test]
+fn first_posting_must_use_record_currency() {
+    let err = Record::new_split(
+        "bad".into(),
+        vec![Posting {
+            debit_account: "cash".parse().unwrap(),
+            credit_account: "income".parse().unwrap(),
+            amount: 1.0,
+            currency: Some("EUR".into()),
+            memo: None,
+        }],
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap_err();
+    assert_eq!(err, RecordError::PrimaryCurrencyMismatch);
+}
+
+#[test]
+fn reversal_cancels_split_record() {
+    let original = Record::new_split(
+        "rent".into(),
+        vec![
+            Posting {
+                debit_account: "expenses:rent".parse().unwrap(),
+                credit_account: "cash".parse().unwrap(),
+                amount: 900.0,
+                currency: None,
+                memo: None,
+            },
+            Posting {
+                debit_account: "expenses:fees".parse().unwrap(),
+                credit_account: "cash".parse().unwrap(),
+                amount: 10.0,
+                currency: None,
+                memo: None,
+            },
+        ],
+        "USD".into(),
+        None,
+        Some("stmt-1".into()),
+        vec![],
+    )
+    .unwrap();
+    let reversal = original.reverse();
+    assert_ne!(reversal.id, original.id);
+    assert_eq!(reversal.reference_id, Some(original.id));
+    assert_eq!(reversal.external_reference, None);
+    assert!(reversal.reverses(&original));
+    assert!(!original.reverses(&reversal));
+
+    let mut ledger = Ledger::default();
+    let prices = PriceDatabase::default();
+    ledger.commit(original.clone());
+    assert!(!ledger.is_reversed(original.id));
+    ledger.commit(reversal);
+    assert!(ledger.is_reversed(original.id));
+    assert_eq!(ledger.account_balance("cash", "USD", &prices), 0.0);
+    assert_eq!(ledger.account_balance("expenses:rent", "USD", &prices), 0.0);
+}
+
+#[test]
+fn price_lookup_modes_on_sparse_rates() {
+    let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+    let mut prices = PriceDatabase::default();
+    prices.add_rate(day(2), "EUR", "USD", 1.1);
+    prices.add_rate(day(10), "EUR", "USD", 1.3);
+
+    let rate = |d, lookup| prices.get_rate_with(d, "EUR", "USD", lookup);
+    use PriceLookup::{Exact, Nearest, OnOrBefore};
+    // Between the rates, closer to the earlier one.
+    assert_eq!(rate(day(4), OnOrBefore), Some(1.1));
+    assert_eq!(rate(day(4), Exact), None);
+    assert_eq!(rate(day(4), Nearest), Some(1.1));
+    // Closer to the later one.
+    assert_eq!(rate(day(8), OnOrBefore), Some(1.1));
+    assert_eq!(rate(day(8), Exact), None);
+    assert_eq!(rate(day(8), Nearest), Some(1.3));
+    // Equally close: the earlier rate wins.
+    assert_eq!(rate(day(6), Nearest), Some(1.1));
+    // On a rate date every mode agrees.
+    for lookup in [OnOrBefore, Exact, Nearest] {
+        assert_eq!(rate(day(10), lookup), Some(1.3));
+    }
+    // Before the first rate only the nearest mode finds one.
+    assert_eq!(rate(day(1), OnOrBefore), None);
+    assert_eq!(rate(day(1), Nearest), Some(1.1));
+    assert_eq!(prices.get_rate_exact(day(2), "EUR", "USD"), Some(1.1));
+    assert_eq!(prices.get_rate_exact(day(2), "USD", "EUR"), None);
+
+    // Balances convert with the database's lookup mode.
+    let mut ledger = Ledger::default();
+    let mut rec = Record::new(
+        "Dinner".into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        100.0,
+        "EUR".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, 1, 8, 12, 0, 0).unwrap();
+    ledger.commit(rec);
+    let balance = |lookup| {
+        let mut prices = PriceDatabase::default().with_lookup(lookup);
+        prices.add_rate(day(2), "EUR", "USD", 1.1);
+        prices.add_rate(day(10), "EUR", "USD", 1.3);
+        ledger.try_account_balance("expenses:food", "USD", &prices)
+    };
+    assert!((balance(OnOrBefore).unwrap() - 110.0).abs() < 1e-9);
+    assert!((balance(Nearest).unwrap() - 130.0).abs() < 1e-9);
+    assert_eq!(
+        balance(Exact).unwrap_err().pairs,
+        vec![(day(8), "EUR".to_string(), "USD".to_string())]
+    );
+}
+
+#[test]
+fn totals_by_tag_count_records_towards_each_of_their_tags() {
+    let date = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+    let tagged = |amount: f64, currency: &str, tags: &[&str]| {
+        let mut record = Record::new(
+            "Work".into(),
+            "expenses:contractors".parse().unwrap(),
+            "cash".parse().unwrap(),
+            amount,
+            currency.into(),
+            None,
+            None,
+            tags.iter().map(|t| t.to_string()).collect(),
+        )
+        .unwrap();
+        record.timestamp = date;
+        record
+    };
+    let mut ledger = Ledger::default();
+    ledger.commit(tagged(100.0, "USD", &["alpha"]));
+    ledger.commit(tagged(50.0, "USD", &["alpha", "beta"]));
+    ledger.commit(tagged(20.0, "EUR", &["beta"]));
+    ledger.commit(tagged(7.0, "USD", &[]));
+    let split = Record::new_split(
+        "Hardware".into(),
+        vec![
+            Posting {
+                debit_account: "expenses:hardware".parse().unwrap(),
+                credit_account: "cash".parse().unwrap(),
+                amount: 30.0,
+                currency: None,
+                memo: None,
+            },
+            Posting {
+                debit_account: "expenses:shipping".parse().unwrap(),
+                credit_account: "cash".parse().unwrap(),
+                amount: 5.0,
+                currency: None,
+                memo: None,
+            },
+        ],
+        "USD".into(),
+        None,
+        None,
+        vec!["beta".into()],
+    )
+    .unwrap();
+    ledger.commit(split);
+
+    let mut prices = PriceDatabase::default();
+    prices.add_rate(date.date_naive(), "EUR", "USD", 1.5);
+    let totals = ledger.try_totals_by_tag("USD", &prices).unwrap();
+    assert_eq!(totals.len(), 2);
+    assert_eq!(totals["alpha"], 150.0);
+    // 50 shared with alpha, 20 EUR at 1.5 and both postings of the split.
+    assert_eq!(totals["beta"], 50.0 + 30.0 + 35.0);
+
+    let err = ledger
+        .try_totals_by_tag("USD", &PriceDatabase::default())
+        .unwrap_err();
+    assert_eq!(err.pairs.len(), 1);
+    assert_eq!(
+        ledger.totals_by_tag("USD", &PriceDatabase::default())["beta"],
+        85.0
+    );
+}
+
+#[test]
+fn stale_uncleared_lists_only_old_pending_records_oldest_first() {
+    let mut ledger = Ledger::default();
+    let dated = |description: &str, month: u32, day: u32, cleared: bool| {
+        let mut rec = entry(description, 0);
+        rec.timestamp = Utc.with_ymd_and_hms(2024, month, day, 9, 0, 0).unwrap();
+        rec.cleared = cleared;
+        rec
+    };
+    ledger.commit(dated("february", 2, 10, false));
+    ledger.commit(dated("recent", 3, 20, false));
+    ledger.commit(dated("january", 1, 5, false));
+    ledger.commit(dated("cleared", 1, 2, true));
+    // Exactly 30 days old is not yet stale.
+    ledger.commit(dated("boundary", 3, 2, false));
+
+    let as_of = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+    let stale: Vec<&str> = ledger
+        .stale_uncleared(as_of, 30)
+        .into_iter()
+        .map(|r| r.description.as_str())
+        .collect();
+    assert_eq!(stale, vec!["january", "february"]);
+}
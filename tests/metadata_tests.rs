@@ -0,0 +1,84 @@
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter};
+use feed_my_ledger::core::{
+    Ledger, Query, Record, SharedLedger, utils::generate_signature, verify_sheet,
+};
+use std::str::FromStr;
+
+fn record_with_metadata() -> Record {
+    let mut record = Record::new(
+        "consulting".into(),
+        "bank".parse().unwrap(),
+        "income".parse().unwrap(),
+        500.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    record.metadata.insert("invoice_no".into(), "123".into());
+    record.metadata.insert("project".into(), "Acme".into());
+    record
+}
+
+#[test]
+fn metadata_round_trips_through_rows() {
+    let record = record_with_metadata();
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    adapter.append_row(&sheet, record.to_row()).unwrap();
+
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    let loaded = ledger.get_record("owner@example.com", record.id).unwrap();
+    assert_eq!(loaded.metadata, record.metadata);
+    assert_eq!(loaded, record);
+}
+
+#[test]
+fn metadata_round_trips_through_json() {
+    let record = record_with_metadata();
+    let json = record.to_json().unwrap();
+    assert_eq!(Record::from_json(&json).unwrap(), record);
+}
+
+#[test]
+fn metadata_is_covered_by_hash() {
+    let record = record_with_metadata();
+    let sig = generate_signature("ledger", None).unwrap();
+    let mut row = record.to_row_hashed(&sig);
+    row[14] = row[14].replace("Acme", "Other");
+
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    adapter.append_row(&sheet, row).unwrap();
+    assert_eq!(verify_sheet(&adapter, &sheet, &sig).unwrap(), vec![0]);
+}
+
+#[test]
+fn query_filters_by_metadata() {
+    let mut ledger = Ledger::default();
+    let tagged = record_with_metadata();
+    ledger.commit(tagged.clone());
+    ledger.commit(
+        Record::new(
+            "other".into(),
+            "bank".parse().unwrap(),
+            "income".parse().unwrap(),
+            10.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap(),
+    );
+
+    let q = Query::from_str("meta:project=Acme meta:invoice_no=123").unwrap();
+    let ids: Vec<_> = q.filter(&ledger).iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec![tagged.id]);
+
+    let q = Query::from_str("meta:project=Other").unwrap();
+    assert!(q.filter(&ledger).is_empty());
+
+    assert!(Query::from_str("meta:project").is_err());
+}
@@ -87,3 +87,53 @@ fn propagate_refresh_error() {
     let err = manager.authenticate("user").unwrap_err();
     assert_eq!(err, AuthError::RefreshFailed);
 }
+
+#[derive(Default)]
+struct ShortLivedProvider {
+    authorize_calls: usize,
+    refresh_calls: usize,
+}
+
+impl AuthProvider for ShortLivedProvider {
+    fn authorize(&mut self) -> Result<OAuth2Token, AuthError> {
+        self.authorize_calls += 1;
+        Ok(OAuth2Token {
+            access_token: "short".into(),
+            refresh_token: "refresh1".into(),
+            expires_at: Utc::now() + Duration::seconds(30),
+        })
+    }
+
+    fn refresh(&mut self, refresh_token: &str) -> Result<OAuth2Token, AuthError> {
+        assert_eq!(refresh_token, "refresh1");
+        self.refresh_calls += 1;
+        Ok(OAuth2Token {
+            access_token: "fresh".into(),
+            refresh_token: "refresh2".into(),
+            expires_at: Utc::now() + Duration::hours(1),
+        })
+    }
+}
+
+#[tokio::test]
+async fn refreshing_provider_renews_token_near_expiry() {
+    use feed_my_ledger::cloud_adapters::auth::RefreshingTokenProvider;
+    use feed_my_ledger::cloud_adapters::google_sheets4::TokenProvider;
+
+    let provider = RefreshingTokenProvider::with_skew(
+        ShortLivedProvider::default(),
+        MemoryTokenStore::new(),
+        "user",
+        Duration::seconds(60),
+    );
+    let first = provider.token(&[]).await.unwrap();
+    assert_eq!(first, "short");
+    let second = provider.token(&[]).await.unwrap();
+    assert_eq!(second, "fresh");
+    let third = provider.token(&[]).await.unwrap();
+    assert_eq!(third, "fresh");
+
+    let manager = provider.into_manager();
+    assert_eq!(manager.provider.authorize_calls, 1);
+    assert_eq!(manager.provider.refresh_calls, 1);
+}
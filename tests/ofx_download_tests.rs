@@ -0,0 +1,64 @@
+#![cfg(feature = "bank-api")]
+
+use feed_my_ledger::import::ofx;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const STATEMENT: &str = "<OFX><STMTTRN><TRNAMT>-5.00</TRNAMT><NAME>Coffee</NAME></STMTTRN></OFX>";
+
+#[tokio::test]
+async fn download_sends_basic_auth() {
+    let server = MockServer::start().await;
+    // "user:secret" base64-encoded
+    Mock::given(method("GET"))
+        .and(path("/statement.ofx"))
+        .and(header("authorization", "Basic dXNlcjpzZWNyZXQ="))
+        .respond_with(ResponseTemplate::new(200).set_body_string(STATEMENT))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/statement.ofx", server.uri());
+    let records = ofx::download_with_auth(&url, "user", "secret")
+        .await
+        .unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].description, "Coffee");
+    assert_eq!(records[0].amount, 5.0);
+}
+
+#[tokio::test]
+async fn download_follows_one_redirect() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/old"))
+        .respond_with(ResponseTemplate::new(302).insert_header("location", "/new"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/new"))
+        .and(header("authorization", "Bearer tok"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(STATEMENT))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/old", server.uri());
+    let records = ofx::download_with_bearer(&url, "tok").await.unwrap();
+    assert_eq!(records.len(), 1);
+}
+
+#[tokio::test]
+async fn download_reports_error_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/statement.ofx", server.uri());
+    let err = ofx::download_with_auth(&url, "user", "wrong")
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("401"), "{err}");
+}
@@ -0,0 +1,74 @@
+use chrono::NaiveDate;
+use feed_my_ledger::core::{Ledger, OPENING_BALANCE_ACCOUNT, PriceDatabase, Record};
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+}
+
+#[test]
+fn opening_balance_posts_against_equity() {
+    let asset = Record::opening_balance(
+        "assets:bank".parse().unwrap(),
+        1000.0,
+        "USD".into(),
+        date(1),
+    )
+    .unwrap();
+    assert_eq!(asset.debit_account.to_string(), "assets:bank");
+    assert_eq!(asset.credit_account.to_string(), OPENING_BALANCE_ACCOUNT);
+    assert_eq!(asset.timestamp.date_naive(), date(1));
+    assert!(asset.is_opening_balance());
+
+    let liability = Record::opening_balance(
+        "liabilities:card".parse().unwrap(),
+        -250.0,
+        "USD".into(),
+        date(1),
+    )
+    .unwrap();
+    assert_eq!(liability.debit_account.to_string(), OPENING_BALANCE_ACCOUNT);
+    assert_eq!(liability.credit_account.to_string(), "liabilities:card");
+    assert_eq!(liability.amount, 250.0);
+}
+
+#[test]
+fn trial_balance_with_opening_balance_nets_to_zero() {
+    let mut ledger = Ledger::default();
+    for (account, amount, currency) in [
+        ("assets:bank", 1000.0, "USD"),
+        ("assets:savings", 500.0, "EUR"),
+        ("liabilities:card", -250.0, "USD"),
+    ] {
+        ledger.commit(
+            Record::opening_balance(account.parse().unwrap(), amount, currency.into(), date(1))
+                .unwrap(),
+        );
+    }
+    let mut coffee = Record::new(
+        "coffee".into(),
+        "expenses:food".parse().unwrap(),
+        "liabilities:card".parse().unwrap(),
+        4.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    coffee.timestamp = date(5).and_hms_opt(12, 0, 0).unwrap().and_utc();
+    assert!(!coffee.is_opening_balance());
+    ledger.commit(coffee);
+
+    let mut prices = PriceDatabase::default();
+    prices.add_rate(date(1), "EUR", "USD", 1.1);
+    let balances = ledger.trial_balance("USD", &prices);
+
+    let total: f64 = balances.values().sum();
+    assert!(total.abs() < 1e-9, "{balances:?}");
+    assert!((balances[OPENING_BALANCE_ACCOUNT] + 1300.0).abs() < 1e-9);
+    assert_eq!(balances["assets:bank"], 1000.0);
+    assert_eq!(balances["liabilities:card"], -254.0);
+    // opening balances leave income and expenses untouched
+    assert_eq!(balances["expenses:food"], 4.0);
+    assert!(!balances.contains_key("income"));
+}
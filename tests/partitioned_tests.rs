@@ -0,0 +1,173 @@
+use chrono::{Local, NaiveDate, TimeZone, Utc};
+use feed_my_ledger::cloud_adapters::{
+    CloudSpreadsheetService, FileAdapter, GoogleSheetsAdapter, PartitionedService,
+};
+use feed_my_ledger::core::{ROW_HEADER, Record};
+
+fn row(description: &str, month: u32, day: u32) -> Vec<String> {
+    let mut rec = Record::new(
+        description.into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, month, day, 12, 0, 0).unwrap();
+    rec.to_row()
+}
+
+fn header() -> Vec<String> {
+    ROW_HEADER.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn rows_are_routed_to_monthly_partitions() {
+    let mut service = PartitionedService::new(GoogleSheetsAdapter::new(), "Ledger");
+    let sheet = service.create_sheet("Ledger").unwrap();
+    let jan = row("January", 1, 15);
+    let feb = row("February", 2, 3);
+    let jan_late = row("Late January", 1, 31);
+    service.append_row(&sheet, header()).unwrap();
+    service
+        .append_rows(&sheet, vec![jan.clone(), feb.clone()])
+        .unwrap();
+    service.append_row(&sheet, jan_late.clone()).unwrap();
+
+    let partitions = service.partitions(&sheet).unwrap();
+    let keys: Vec<&str> = partitions.keys().map(String::as_str).collect();
+    assert_eq!(keys, vec!["", "2024-01", "2024-02"]);
+
+    assert_eq!(
+        service.list_rows(&sheet).unwrap(),
+        vec![header(), jan.clone(), jan_late.clone(), feb.clone()]
+    );
+    assert_eq!(service.read_row(&sheet, 3).unwrap(), feb);
+    assert_eq!(
+        service
+            .list_rows_between(
+                &sheet,
+                NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+            )
+            .unwrap(),
+        vec![feb.clone()]
+    );
+
+    let inner = service.into_inner();
+    assert_eq!(
+        inner.list_rows(&partitions["2024-01"]).unwrap(),
+        vec![jan, jan_late]
+    );
+    assert_eq!(inner.list_rows(&partitions["2024-02"]).unwrap(), vec![feb]);
+    assert_eq!(inner.list_rows(&sheet).unwrap().len(), 3);
+}
+
+#[test]
+fn partitions_survive_rewrapping() {
+    let mut service = PartitionedService::new(GoogleSheetsAdapter::new(), "Ledger");
+    let sheet = service.create_sheet("Ledger").unwrap();
+    service.append_row(&sheet, row("March", 3, 1)).unwrap();
+
+    let mut service = PartitionedService::new(service.into_inner(), "Ledger");
+    service
+        .append_row(&sheet, row("March again", 3, 2))
+        .unwrap();
+    assert_eq!(service.partitions(&sheet).unwrap().len(), 1);
+    assert_eq!(service.list_rows(&sheet).unwrap().len(), 2);
+}
+
+#[test]
+fn shares_cover_the_index_and_every_partition() {
+    let mut service = PartitionedService::new(GoogleSheetsAdapter::new(), "Ledger");
+    let sheet = service.create_sheet("Ledger").unwrap();
+    service.append_row(&sheet, row("January", 1, 5)).unwrap();
+    service.append_row(&sheet, row("February", 2, 5)).unwrap();
+
+    service.share_sheet(&sheet, "bob@example.com").unwrap();
+    service.share_sheet(&sheet, "eve@example.com").unwrap();
+    let shares = service.list_shares(&sheet).unwrap();
+    let emails: Vec<&str> = shares.iter().map(|(e, _)| e.as_str()).collect();
+    assert_eq!(emails, vec!["bob@example.com", "eve@example.com"]);
+
+    service.revoke_share(&sheet, "eve@example.com").unwrap();
+    let partitions = service.partitions(&sheet).unwrap();
+    let inner = service.into_inner();
+    for id in std::iter::once(&sheet).chain(partitions.values()) {
+        let shares = inner.list_shares(id).unwrap();
+        let emails: Vec<&str> = shares.iter().map(|(e, _)| e.as_str()).collect();
+        assert_eq!(emails, vec!["bob@example.com"], "{id}");
+    }
+
+    // A user missing from one partition lacks access to the whole ledger.
+    inner
+        .revoke_share(&partitions["2024-02"], "bob@example.com")
+        .unwrap();
+    let service = PartitionedService::new(inner, "Ledger");
+    assert!(service.list_shares(&sheet).unwrap().is_empty());
+}
+
+#[test]
+fn rows_are_routed_by_transaction_date_when_present() {
+    let mut service = PartitionedService::new(GoogleSheetsAdapter::new(), "Ledger");
+    let sheet = service.create_sheet("Ledger").unwrap();
+    let mut rec = Record::new(
+        "Back-dated".into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    rec.timestamp = Utc.with_ymd_and_hms(2024, 3, 5, 12, 0, 0).unwrap();
+    rec.transaction_date = Local.with_ymd_and_hms(2023, 12, 28, 0, 0, 0).single();
+    service.append_row(&sheet, rec.to_row()).unwrap();
+    service.append_row(&sheet, row("Imported", 3, 5)).unwrap();
+
+    let keys: Vec<String> = service.partitions(&sheet).unwrap().into_keys().collect();
+    assert_eq!(keys, vec!["2023-12", "2024-03"]);
+}
+
+#[test]
+fn file_adapter_headers_are_skipped() {
+    let dir = std::env::temp_dir().join(format!("partitioned_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut service = PartitionedService::new(FileAdapter::new(&dir), "Ledger");
+    let sheet = service.create_sheet("Ledger").unwrap();
+    let jan = row("January", 1, 15);
+    let feb = row("February", 2, 3);
+    service.append_row(&sheet, jan.clone()).unwrap();
+    service.append_row(&sheet, feb.clone()).unwrap();
+
+    // The index sheet and each month start with the header FileAdapter writes.
+    let keys: Vec<String> = service.partitions(&sheet).unwrap().into_keys().collect();
+    assert_eq!(keys, vec!["2024-01", "2024-02"]);
+    assert_eq!(
+        service.list_rows(&sheet).unwrap(),
+        vec![header(), jan.clone(), feb.clone()]
+    );
+    assert_eq!(service.read_row(&sheet, 2).unwrap(), feb);
+
+    let mut updated = feb.clone();
+    updated[2] = "February, revised".into();
+    service.update_row(&sheet, 2, updated.clone()).unwrap();
+    assert_eq!(service.read_row(&sheet, 2).unwrap(), updated);
+    assert_eq!(
+        service
+            .list_rows_between(
+                &sheet,
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+            )
+            .unwrap(),
+        vec![jan, updated]
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -128,3 +128,64 @@ fn cache_respects_lru_policy() {
     assert_eq!(r1_third, vec!["a"]);
     assert_eq!(*read_calls.borrow(), 3);
 }
+
+#[test]
+fn indexed_balance_lookup_is_fast_on_large_ledgers() {
+    use feed_my_ledger::core::{Account, Ledger, PriceDatabase, Record};
+
+    let mut ledger = Ledger::default();
+    for i in 0..20_000 {
+        let debit = if i % 1000 == 0 {
+            "assets:rare".to_string()
+        } else {
+            format!("expenses:cat{}", i % 50)
+        };
+        ledger.commit(
+            Record::new(
+                format!("tx {i}"),
+                debit.parse().unwrap(),
+                "bank".parse().unwrap(),
+                1.0,
+                "USD".into(),
+                None,
+                None,
+                vec![],
+            )
+            .unwrap(),
+        );
+    }
+    let prices = PriceDatabase::default();
+    let rare: Account = "assets:rare".parse().unwrap();
+
+    let start = std::time::Instant::now();
+    for _ in 0..2_000 {
+        assert_eq!(ledger.account_tree_balance(&rare, "USD", &prices), 20.0);
+        assert_eq!(ledger.account_balance("assets:rare", "USD", &prices), 20.0);
+    }
+    assert!(
+        start.elapsed() < std::time::Duration::from_secs(5),
+        "balance lookups took {:?}",
+        start.elapsed()
+    );
+}
+
+#[test]
+fn read_rows_fills_row_cache() {
+    let append_calls = Rc::new(RefCell::new(0));
+    let read_calls = Rc::new(RefCell::new(0));
+    let adapter = CountingAdapter::new(Rc::clone(&append_calls), Rc::clone(&read_calls));
+    let mut service = BatchingCacheService::new(adapter, 1, EvictionPolicy::None);
+    let sheet = service.create_sheet("test").unwrap();
+
+    for value in ["a", "b", "c"] {
+        service.append_row(&sheet, vec![value.into()]).unwrap();
+    }
+
+    let rows = service.read_rows(&sheet, 1, 3).unwrap();
+    assert_eq!(rows, vec![vec!["b"], vec!["c"]]);
+
+    // both rows are served from the cache
+    assert_eq!(service.read_row(&sheet, 1).unwrap(), vec!["b"]);
+    assert_eq!(service.read_row(&sheet, 2).unwrap(), vec!["c"]);
+    assert_eq!(*read_calls.borrow(), 0);
+}
@@ -1,5 +1,5 @@
 use chrono::{TimeZone, Utc};
-use feed_my_ledger::core::{Ledger, Query, Record};
+use feed_my_ledger::core::{Account, Ledger, Query, Record};
 use std::str::FromStr;
 
 #[test]
@@ -47,3 +47,151 @@ fn filter_by_tag_and_date() {
     assert_eq!(res.len(), 1);
     assert_eq!(res[0].description, "coffee");
 }
+
+#[test]
+fn sorted_filter_orders_by_timestamp() {
+    let mut ledger = Ledger::default();
+    for (desc, day) in [("third", 20), ("first", 1), ("second", 10)] {
+        let mut rec = Record::new(
+            desc.into(),
+            "expenses".parse().unwrap(),
+            "cash".parse().unwrap(),
+            1.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        rec.timestamp = Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap();
+        ledger.commit(rec);
+    }
+
+    let sorted: Vec<_> = ledger
+        .records_sorted()
+        .iter()
+        .map(|r| r.description.as_str())
+        .collect();
+    assert_eq!(sorted, vec!["first", "second", "third"]);
+
+    let committed: Vec<_> = ledger.records().map(|r| r.description.as_str()).collect();
+    assert_eq!(committed, vec!["third", "first", "second"]);
+
+    let q = Query::from_str("account:cash sort:date").unwrap();
+    assert!(q.sorted);
+    let filtered: Vec<_> = q
+        .filter(&ledger)
+        .iter()
+        .map(|r| r.description.as_str())
+        .collect();
+    assert_eq!(filtered, vec!["first", "second", "third"]);
+
+    let unsorted: Vec<_> = Query::default()
+        .filter(&ledger)
+        .iter()
+        .map(|r| r.description.as_str())
+        .collect();
+    assert_eq!(unsorted, vec!["third", "first", "second"]);
+}
+
+fn cleared_ledger() -> Ledger {
+    let mut ledger = Ledger::default();
+    for (desc, cleared) in [("rent", true), ("coffee", false), ("salary", true)] {
+        let mut rec = Record::new(
+            desc.into(),
+            "expenses".parse().unwrap(),
+            "cash".parse().unwrap(),
+            1.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        rec.cleared = cleared;
+        ledger.commit(rec);
+    }
+    ledger
+}
+
+#[test]
+fn filter_cleared_records() {
+    let ledger = cleared_ledger();
+    let q = Query::from_str("cleared:true").unwrap();
+    assert_eq!(q.cleared, Some(true));
+    let matched: Vec<_> = q
+        .filter(&ledger)
+        .iter()
+        .map(|r| r.description.as_str())
+        .collect();
+    assert_eq!(matched, vec!["rent", "salary"]);
+}
+
+#[test]
+fn filter_uncleared_records() {
+    let ledger = cleared_ledger();
+    let q = Query::from_str("account:cash cleared:false").unwrap();
+    let matched: Vec<_> = q
+        .filter(&ledger)
+        .iter()
+        .map(|r| r.description.as_str())
+        .collect();
+    assert_eq!(matched, vec!["coffee"]);
+    assert!(Query::from_str("cleared:maybe").is_err());
+}
+
+fn transfer_ledger() -> Ledger {
+    let mut ledger = Ledger::default();
+    for (desc, debit) in [
+        ("savings", "assets:savings"),
+        ("groceries", "expenses:food"),
+    ] {
+        let rec = Record::new(
+            desc.into(),
+            debit.parse().unwrap(),
+            "assets:checking".parse().unwrap(),
+            50.0,
+            "USD".into(),
+            None,
+            None,
+            vec![],
+        )
+        .unwrap();
+        ledger.commit(rec);
+    }
+    ledger
+}
+
+#[test]
+fn detect_transfers_between_own_accounts() {
+    let ledger = transfer_ledger();
+    let assets: Account = "assets".parse().unwrap();
+    let records: Vec<_> = ledger.records().collect();
+    assert!(records[0].is_transfer(std::slice::from_ref(&assets)));
+    assert!(!records[1].is_transfer(&[assets]));
+    assert!(!records[0].is_transfer(&["assets:checking".parse().unwrap()]));
+}
+
+#[test]
+fn filter_out_transfers() {
+    let ledger = transfer_ledger();
+    let descriptions = |q: &Query| -> Vec<String> {
+        q.filter(&ledger)
+            .iter()
+            .map(|r| r.description.clone())
+            .collect()
+    };
+    assert_eq!(
+        descriptions(&Query::from_str("transfer:false").unwrap()),
+        vec!["groceries"]
+    );
+    assert_eq!(
+        descriptions(&Query::from_str("transfer:true").unwrap()),
+        vec!["savings"]
+    );
+
+    let mut q = Query::from_str("transfer:true").unwrap();
+    q.transfer_accounts = vec!["assets:checking".parse().unwrap()];
+    assert!(descriptions(&q).is_empty());
+    assert!(Query::from_str("transfer:yes").is_err());
+}
@@ -1,5 +1,56 @@
-use feed_my_ledger::cloud_adapters::GoogleSheetsAdapter;
+use feed_my_ledger::cloud_adapters::{
+    CloudSpreadsheetService, GoogleSheetsAdapter, SpreadsheetError,
+};
 use feed_my_ledger::core::{Permission, Record, SharedLedger};
+use uuid::Uuid;
+
+fn record() -> Record {
+    Record::new(
+        "desc".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+fn rows_for(adapter: &impl CloudSpreadsheetService, sheet: &str, id: Uuid) -> usize {
+    adapter
+        .list_rows(sheet)
+        .unwrap()
+        .iter()
+        .filter(|row| row.iter().any(|cell| cell == &id.to_string()))
+        .count()
+}
+
+/// Backend without `update_row`, like an append-only log.
+struct AppendOnly(GoogleSheetsAdapter);
+
+impl CloudSpreadsheetService for AppendOnly {
+    fn create_sheet(&mut self, title: &str) -> Result<String, SpreadsheetError> {
+        self.0.create_sheet(title)
+    }
+
+    fn append_row(&mut self, sheet_id: &str, values: Vec<String>) -> Result<(), SpreadsheetError> {
+        self.0.append_row(sheet_id, values)
+    }
+
+    fn read_row(&self, sheet_id: &str, index: usize) -> Result<Vec<String>, SpreadsheetError> {
+        self.0.read_row(sheet_id, index)
+    }
+
+    fn list_rows(&self, sheet_id: &str) -> Result<Vec<Vec<String>>, SpreadsheetError> {
+        self.0.list_rows(sheet_id)
+    }
+
+    fn share_sheet(&self, sheet_id: &str, email: &str) -> Result<(), SpreadsheetError> {
+        self.0.share_sheet(sheet_id, email)
+    }
+}
 
 #[test]
 fn cleared_status_persists() {
@@ -29,3 +80,65 @@ fn cleared_status_persists() {
     let rec = ledger2.get_record("owner@example.com", id).unwrap();
     assert!(rec.cleared);
 }
+
+#[test]
+fn in_place_status_rewrites_the_record_row() {
+    let ledger = SharedLedger::new(GoogleSheetsAdapter::new(), "owner@example.com")
+        .unwrap()
+        .with_in_place_status(true);
+    let record = record();
+    let id = record.id;
+    ledger.commit("owner@example.com", record).unwrap();
+    ledger.mark_cleared("owner@example.com", id).unwrap();
+    ledger.mark_pending("owner@example.com", id).unwrap();
+    assert!(!ledger.get_record("owner@example.com", id).unwrap().cleared);
+
+    let (adapter, sheet) = ledger.into_parts();
+    assert_eq!(adapter.list_rows(&sheet).unwrap().len(), 1);
+    assert_eq!(rows_for(&adapter, &sheet, id), 1);
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com")
+        .unwrap()
+        .with_in_place_status(true);
+    assert!(!ledger.get_record("owner@example.com", id).unwrap().cleared);
+
+    ledger.mark_cleared("owner@example.com", id).unwrap();
+    let (adapter, sheet) = ledger.into_parts();
+    assert_eq!(rows_for(&adapter, &sheet, id), 1);
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    assert!(ledger.get_record("owner@example.com", id).unwrap().cleared);
+}
+
+#[test]
+fn in_place_status_overrides_older_status_rows() {
+    let ledger = SharedLedger::new(GoogleSheetsAdapter::new(), "owner@example.com").unwrap();
+    let record = record();
+    let id = record.id;
+    ledger.commit("owner@example.com", record).unwrap();
+    ledger.mark_cleared("owner@example.com", id).unwrap();
+
+    let (adapter, sheet) = ledger.into_parts();
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com")
+        .unwrap()
+        .with_in_place_status(true);
+    ledger.mark_pending("owner@example.com", id).unwrap();
+
+    let (adapter, sheet) = ledger.into_parts();
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    assert!(!ledger.get_record("owner@example.com", id).unwrap().cleared);
+}
+
+#[test]
+fn in_place_status_falls_back_to_status_rows() {
+    let ledger = SharedLedger::new(AppendOnly(GoogleSheetsAdapter::new()), "owner@example.com")
+        .unwrap()
+        .with_in_place_status(true);
+    let record = record();
+    let id = record.id;
+    ledger.commit("owner@example.com", record).unwrap();
+    ledger.mark_cleared("owner@example.com", id).unwrap();
+
+    let (adapter, sheet) = ledger.into_parts();
+    assert_eq!(rows_for(&adapter, &sheet, id), 2);
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    assert!(ledger.get_record("owner@example.com", id).unwrap().cleared);
+}
@@ -7,11 +7,21 @@ use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, RetryingService, S
 struct FlakyAdapter {
     fail_times: usize,
     calls: Rc<RefCell<usize>>,
+    error: SpreadsheetError,
 }
 
 impl FlakyAdapter {
     fn new(fail_times: usize, calls: Rc<RefCell<usize>>) -> Self {
-        Self { fail_times, calls }
+        Self {
+            fail_times,
+            calls,
+            error: SpreadsheetError::Transient("network".into()),
+        }
+    }
+
+    fn failing_with(mut self, error: SpreadsheetError) -> Self {
+        self.error = error;
+        self
     }
 }
 
@@ -20,7 +30,7 @@ impl CloudSpreadsheetService for FlakyAdapter {
         let mut c = self.calls.borrow_mut();
         *c += 1;
         if *c <= self.fail_times {
-            Err(SpreadsheetError::Transient("network".into()))
+            Err(self.error.clone())
         } else {
             Ok(format!("sheet{c}"))
         }
@@ -66,3 +76,38 @@ fn gives_up_after_max_retries() {
     assert!(matches!(err, SpreadsheetError::Transient(_)));
     assert_eq!(*calls.borrow(), 4);
 }
+
+#[test]
+fn retry_predicate_overrides_default_rule() {
+    let rate_limited = |e: &SpreadsheetError, attempt: u32| {
+        matches!(e, SpreadsheetError::Permanent(m) if m == "rate exceeded") && attempt < 2
+    };
+
+    let calls = Rc::new(RefCell::new(0));
+    let adapter = FlakyAdapter::new(2, Rc::clone(&calls))
+        .failing_with(SpreadsheetError::Permanent("rate exceeded".into()));
+    let mut retry = RetryingService::new(adapter, 0, Duration::from_millis(1))
+        .with_retry_predicate(rate_limited);
+    assert_eq!(retry.create_sheet("test").unwrap(), "sheet3");
+    assert_eq!(*calls.borrow(), 3);
+
+    // The predicate gives up after two retries.
+    let calls = Rc::new(RefCell::new(0));
+    let adapter = FlakyAdapter::new(5, Rc::clone(&calls))
+        .failing_with(SpreadsheetError::Permanent("rate exceeded".into()));
+    let mut retry = RetryingService::new(adapter, 0, Duration::from_millis(1))
+        .with_retry_predicate(rate_limited);
+    assert!(matches!(
+        retry.create_sheet("test"),
+        Err(SpreadsheetError::Permanent(_))
+    ));
+    assert_eq!(*calls.borrow(), 3);
+
+    // Transient errors are no longer retried unless the predicate says so.
+    let calls = Rc::new(RefCell::new(0));
+    let adapter = FlakyAdapter::new(1, Rc::clone(&calls));
+    let mut retry = RetryingService::new(adapter, 3, Duration::from_millis(1))
+        .with_retry_predicate(rate_limited);
+    assert!(retry.create_sheet("test").is_err());
+    assert_eq!(*calls.borrow(), 1);
+}
@@ -0,0 +1,128 @@
+use chrono::{TimeZone, Utc};
+use feed_my_ledger::core::{
+    BusinessDayAdjust, RecordTemplate, ScheduleEntry, ScheduleError, Scheduler,
+};
+
+fn entry(cron: &str) -> ScheduleEntry {
+    ScheduleEntry {
+        cron: cron.into(),
+        template: RecordTemplate {
+            description: "rent".into(),
+            debit: "expenses:rent".parse().unwrap(),
+            credit: "assets:bank".parse().unwrap(),
+            amount: 1000.0,
+            currency: "USD".into(),
+        },
+        adjust: BusinessDayAdjust::None,
+    }
+}
+
+#[test]
+fn cron_over_a_month_yields_expected_dates() {
+    let scheduler = Scheduler {
+        entries: vec![entry("0 0 9 1,15 * *")],
+    };
+    let records = scheduler
+        .try_generate(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 31, 23, 59, 59).unwrap(),
+        )
+        .unwrap();
+    let dates: Vec<_> = records.iter().map(|r| r.timestamp).collect();
+    assert_eq!(
+        dates,
+        vec![
+            Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap(),
+        ]
+    );
+    assert!(records.iter().all(|r| r.description == "rent"));
+}
+
+#[test]
+fn invalid_cron_reports_entry() {
+    let scheduler = Scheduler {
+        entries: vec![entry("0 0 9 1 * *"), entry("not a cron")],
+    };
+    let since = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let until = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+    let err = scheduler.try_generate(since, until).unwrap_err();
+    assert!(matches!(err, ScheduleError::InvalidCron { index: 1, .. }));
+    assert!(err.to_string().contains("not a cron"));
+    assert_eq!(scheduler.generate(since, until).len(), 1);
+}
+
+#[test]
+fn validate_collects_every_invalid_entry() {
+    assert!(entry("0 0 9 1 * *").validate().is_ok());
+    let err = entry("0 0 1 * *").validate().unwrap_err();
+    assert_eq!(err.cron, "0 0 1 * *");
+
+    let scheduler = Scheduler {
+        entries: vec![entry("bad"), entry("0 0 9 1 * *"), entry("worse")],
+    };
+    let errors = scheduler.validate().unwrap_err();
+    let indices: Vec<_> = errors
+        .iter()
+        .map(|e| match e {
+            ScheduleError::InvalidCron { index, .. } => *index,
+            other => panic!("unexpected error {other}"),
+        })
+        .collect();
+    assert_eq!(indices, vec![0, 2]);
+}
+
+#[test]
+fn saturday_occurrence_moves_according_to_adjustment() {
+    // 1 June 2024 is a Saturday.
+    let cases = [
+        (BusinessDayAdjust::None, 1),
+        (BusinessDayAdjust::Previous, 31),
+        (BusinessDayAdjust::Next, 3),
+        (BusinessDayAdjust::Nearest, 31),
+    ];
+    for (adjust, day) in cases {
+        let scheduler = Scheduler {
+            entries: vec![ScheduleEntry {
+                adjust,
+                ..entry("0 0 9 1 * *")
+            }],
+        };
+        let records = scheduler
+            .try_generate(
+                Utc.with_ymd_and_hms(2024, 5, 15, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 6, 15, 0, 0, 0).unwrap(),
+            )
+            .unwrap();
+        let month = if day == 31 { 5 } else { 6 };
+        let dates: Vec<_> = records.iter().map(|r| r.timestamp).collect();
+        assert_eq!(
+            dates,
+            vec![Utc.with_ymd_and_hms(2024, month, day, 9, 0, 0).unwrap()],
+            "{adjust:?}"
+        );
+    }
+}
+
+#[test]
+fn occurrences_moved_onto_one_day_are_generated_once() {
+    let scheduler = Scheduler {
+        entries: vec![ScheduleEntry {
+            adjust: BusinessDayAdjust::Previous,
+            ..entry("0 0 9 * * *")
+        }],
+    };
+    // Friday 31 May to Monday 3 June 2024.
+    let records = scheduler.generate(
+        Utc.with_ymd_and_hms(2024, 5, 31, 0, 0, 0).unwrap(),
+        Utc.with_ymd_and_hms(2024, 6, 3, 23, 0, 0).unwrap(),
+    );
+    let dates: Vec<_> = records.iter().map(|r| r.timestamp).collect();
+    assert_eq!(
+        dates,
+        vec![
+            Utc.with_ymd_and_hms(2024, 5, 31, 9, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap(),
+        ]
+    );
+}
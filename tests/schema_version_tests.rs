@@ -0,0 +1,149 @@
+use chrono::NaiveDate;
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter};
+use feed_my_ledger::core::{
+    Record, SCHEMA_VERSION, SCHEMA_VERSION_COLUMN, SharedLedger, row_schema_version,
+    utils::generate_signature, verify_sheet,
+};
+use uuid::Uuid;
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn current_rows_carry_schema_version() {
+    let record = Record::new(
+        "desc".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    let row = record.to_row();
+    assert_eq!(row[SCHEMA_VERSION_COLUMN], SCHEMA_VERSION.to_string());
+    assert_eq!(row_schema_version(&row), SCHEMA_VERSION);
+}
+
+#[test]
+fn parses_legacy_v1_and_v2_rows() {
+    let v1_id = Uuid::new_v4();
+    let v2_id = Uuid::new_v4();
+    // v1: no transaction_description, date at index 11, trailing hash.
+    let v1 = strings(&[
+        &v1_id.to_string(),
+        "2024-01-01T00:00:00+00:00",
+        "legacy",
+        "cash",
+        "revenue",
+        "3",
+        "USD",
+        "",
+        "",
+        "",
+        "",
+        "2024-01-02",
+        "deadbeef",
+    ]);
+    let v2 = strings(&[
+        &v2_id.to_string(),
+        "2024-02-01T00:00:00+00:00",
+        "current",
+        "cash",
+        "revenue",
+        "4",
+        "USD",
+        "",
+        "",
+        "",
+        "",
+        "ACME PAYMENT",
+        "2024-02-03",
+        "2",
+        "cafebabe",
+    ]);
+    assert_eq!(row_schema_version(&v1), 1);
+    assert_eq!(row_schema_version(&v2), 2);
+
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    adapter.append_row(&sheet, v1).unwrap();
+    adapter.append_row(&sheet, v2).unwrap();
+
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    let legacy = ledger.get_record("owner@example.com", v1_id).unwrap();
+    assert_eq!(legacy.transaction_description, None);
+    assert_eq!(
+        legacy.transaction_date.unwrap().date_naive(),
+        NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()
+    );
+    let current = ledger.get_record("owner@example.com", v2_id).unwrap();
+    assert_eq!(
+        current.transaction_description.as_deref(),
+        Some("ACME PAYMENT")
+    );
+    assert_eq!(
+        current.transaction_date.unwrap().date_naive(),
+        NaiveDate::from_ymd_opt(2024, 2, 3).unwrap()
+    );
+}
+
+#[test]
+fn downgrading_version_breaks_hash() {
+    let record = Record::new(
+        "desc".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        1.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    let sig = generate_signature("ledger", None).unwrap();
+    let mut row = record.to_row_hashed(&sig);
+    row[SCHEMA_VERSION_COLUMN] = "1".into();
+
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    adapter.append_row(&sheet, row).unwrap();
+    assert_eq!(verify_sheet(&adapter, &sheet, &sig).unwrap(), vec![0]);
+}
+
+#[test]
+fn loads_accounts_stored_before_validation() {
+    let id = Uuid::new_v4();
+    // Written before account names were validated: a comma inside the
+    // quoted cell and a segment padded with spaces.
+    let row = strings(&[
+        &id.to_string(),
+        "2024-03-01T00:00:00+00:00",
+        "dinner",
+        "Joe's Diner, Inc.",
+        "assets: bank",
+        "30",
+        "USD",
+        "",
+        "",
+        "",
+        r#"[{"debit_account":"Joe's Diner, Inc.","credit_account":"assets: bank","amount":5.0}]"#,
+        "",
+        "2024-03-01",
+        "2",
+        "cafebabe",
+    ]);
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    adapter.append_row(&sheet, row).unwrap();
+
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    let record = ledger.get_record("owner@example.com", id).unwrap();
+    assert_eq!(record.debit_account.to_string(), "Joe's Diner Inc.");
+    assert_eq!(record.credit_account.to_string(), "assets:bank");
+    assert_eq!(record.splits[0].debit_account, record.debit_account);
+    assert_eq!(record.splits[0].credit_account, record.credit_account);
+}
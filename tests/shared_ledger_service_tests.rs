@@ -226,6 +226,29 @@ fn from_sheet_loads_existing_rows() {
     assert_eq!(records[0], record);
 }
 
+#[test]
+fn from_sheet_reports_duplicate_ids() {
+    let mut adapter = GoogleSheetsAdapter::new();
+    let sheet = adapter.create_sheet("ledger").unwrap();
+    let record = Record::new(
+        "desc".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        2.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    adapter.append_row(&sheet, record.to_row()).unwrap();
+    adapter.append_row(&sheet, record.to_row()).unwrap();
+
+    let ledger = SharedLedger::from_sheet(adapter, &sheet, "owner@example.com").unwrap();
+    assert_eq!(ledger.duplicate_ids(), &[record.id]);
+    assert_eq!(ledger.records("owner@example.com").unwrap().len(), 1);
+}
+
 #[derive(Default)]
 struct FailingList;
 
@@ -279,3 +302,187 @@ fn from_sheet_propagates_errors() {
         feed_my_ledger::cloud_adapters::SpreadsheetError::SheetNotFound
     );
 }
+
+struct BulkCountingAdapter {
+    inner: GoogleSheetsAdapter,
+    row_calls: Rc<RefCell<usize>>,
+    bulk_calls: Rc<RefCell<usize>>,
+}
+
+impl CloudSpreadsheetService for BulkCountingAdapter {
+    fn create_sheet(
+        &mut self,
+        title: &str,
+    ) -> Result<String, feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.inner.create_sheet(title)
+    }
+
+    fn append_row(
+        &mut self,
+        sheet_id: &str,
+        values: Vec<String>,
+    ) -> Result<(), feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        *self.row_calls.borrow_mut() += 1;
+        self.inner.append_row(sheet_id, values)
+    }
+
+    fn read_row(
+        &self,
+        sheet_id: &str,
+        index: usize,
+    ) -> Result<Vec<String>, feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.inner.read_row(sheet_id, index)
+    }
+
+    fn list_rows(
+        &self,
+        sheet_id: &str,
+    ) -> Result<Vec<Vec<String>>, feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.inner.list_rows(sheet_id)
+    }
+
+    fn share_sheet(
+        &self,
+        sheet_id: &str,
+        email: &str,
+    ) -> Result<(), feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.inner.share_sheet(sheet_id, email)
+    }
+
+    fn append_rows(
+        &mut self,
+        sheet_id: &str,
+        rows: Vec<Vec<String>>,
+    ) -> Result<(), feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        *self.bulk_calls.borrow_mut() += 1;
+        self.inner.append_rows(sheet_id, rows)
+    }
+}
+
+#[test]
+fn commit_many_uses_single_append_rows() {
+    let row_calls = Rc::new(RefCell::new(0));
+    let bulk_calls = Rc::new(RefCell::new(0));
+    let adapter = BulkCountingAdapter {
+        inner: GoogleSheetsAdapter::new(),
+        row_calls: row_calls.clone(),
+        bulk_calls: bulk_calls.clone(),
+    };
+    let ledger = SharedLedger::new(adapter, "owner@example.com").unwrap();
+    let records: Vec<Record> = (0..50)
+        .map(|i| {
+            Record::new(
+                format!("import {i}"),
+                "expenses".parse().unwrap(),
+                "bank".parse().unwrap(),
+                1.0,
+                "USD".into(),
+                None,
+                None,
+                vec![],
+            )
+            .unwrap()
+        })
+        .collect();
+    ledger
+        .commit_many("owner@example.com", records.clone())
+        .unwrap();
+
+    assert_eq!(*bulk_calls.borrow(), 1);
+    assert_eq!(*row_calls.borrow(), 0);
+    let stored = ledger.records("owner@example.com").unwrap();
+    assert_eq!(stored, records);
+
+    let (adapter, sheet) = ledger.into_parts();
+    assert_eq!(adapter.inner.list_rows(&sheet).unwrap().len(), 50);
+}
+
+#[test]
+fn commit_many_requires_write_permission() {
+    let adapter = GoogleSheetsAdapter::new();
+    let ledger = SharedLedger::new(adapter, "owner@example.com").unwrap();
+    let err = ledger
+        .commit_many("stranger@example.com", Vec::new())
+        .unwrap_err();
+    assert_eq!(err, AccessError::Unauthorized);
+}
+
+/// Lets two ledgers talk to the same mock sheet, like two clients of one
+/// spreadsheet.
+#[derive(Clone)]
+struct SharedAdapter(Rc<RefCell<GoogleSheetsAdapter>>);
+
+impl CloudSpreadsheetService for SharedAdapter {
+    fn create_sheet(
+        &mut self,
+        title: &str,
+    ) -> Result<String, feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.0.borrow_mut().create_sheet(title)
+    }
+
+    fn append_row(
+        &mut self,
+        sheet_id: &str,
+        values: Vec<String>,
+    ) -> Result<(), feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.0.borrow_mut().append_row(sheet_id, values)
+    }
+
+    fn read_row(
+        &self,
+        sheet_id: &str,
+        index: usize,
+    ) -> Result<Vec<String>, feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.0.borrow().read_row(sheet_id, index)
+    }
+
+    fn list_rows(
+        &self,
+        sheet_id: &str,
+    ) -> Result<Vec<Vec<String>>, feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.0.borrow().list_rows(sheet_id)
+    }
+
+    fn share_sheet(
+        &self,
+        sheet_id: &str,
+        email: &str,
+    ) -> Result<(), feed_my_ledger::cloud_adapters::SpreadsheetError> {
+        self.0.borrow().share_sheet(sheet_id, email)
+    }
+}
+
+#[test]
+fn refresh_pulls_records_committed_by_another_client() {
+    let owner = "owner@example.com";
+    let adapter = SharedAdapter(Rc::new(RefCell::new(GoogleSheetsAdapter::new())));
+    let first = SharedLedger::new(adapter.clone(), owner).unwrap();
+    let second = SharedLedger::from_sheet(adapter, first.sheet_id(), owner).unwrap();
+    assert!(second.records(owner).unwrap().is_empty());
+
+    let record = Record::new(
+        "desc".into(),
+        "cash".parse().unwrap(),
+        "revenue".parse().unwrap(),
+        2.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap();
+    first.commit(owner, record.clone()).unwrap();
+    first.mark_cleared(owner, record.id).unwrap();
+
+    assert_eq!(second.refresh().unwrap(), 1);
+    let records = second.records(owner).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].id, record.id);
+    assert!(records[0].cleared);
+
+    // Nothing new: records already loaded are not added again.
+    assert_eq!(second.refresh().unwrap(), 0);
+    assert_eq!(first.refresh().unwrap(), 0);
+    assert_eq!(second.records(owner).unwrap().len(), 1);
+    assert_eq!(first.records(owner).unwrap().len(), 1);
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use feed_my_ledger::cloud_adapters::{CloudSpreadsheetService, GoogleSheetsAdapter};
+use feed_my_ledger::core::snapshot::{self, SNAPSHOT_VERSION, SnapshotError};
+use feed_my_ledger::core::utils::generate_signature;
+use feed_my_ledger::core::{Ledger, PriceDatabase, Record, verify_sheet};
+
+fn record(description: &str, amount: f64) -> Record {
+    Record::new(
+        description.into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        "USD".into(),
+        None,
+        None,
+        vec!["groceries".into()],
+    )
+    .unwrap()
+}
+
+#[test]
+fn restore_reproduces_rows_and_hashes() {
+    let sig = generate_signature("ledger", None).unwrap();
+    let mut cleared = record("Groceries", 42.5);
+    cleared.cleared = true;
+    let pending = record("Coffee", 3.1);
+
+    let mut source = GoogleSheetsAdapter::new();
+    let sheet = source.create_sheet("ledger").unwrap();
+    let original = vec![
+        cleared.to_row_hashed(&sig),
+        pending.to_row_hashed(&sig),
+        cleared.status_row(),
+    ];
+    source.append_rows(&sheet, original.clone()).unwrap();
+
+    let mut ledger = Ledger::default();
+    ledger.commit(cleared.clone());
+    ledger.commit(pending);
+    let statuses = HashMap::from([(cleared.id, true)]);
+    let mut prices = PriceDatabase::default();
+    let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    prices.add_rate(date, "EUR", "USD", 1.1);
+
+    let json = snapshot::export(&ledger, &statuses, &prices)
+        .to_json()
+        .unwrap();
+    let restored = snapshot::import(&json).unwrap();
+    assert_eq!(restored.version, SNAPSHOT_VERSION);
+    assert_eq!(
+        restored.price_database().get_rate(date, "EUR", "USD"),
+        Some(1.1)
+    );
+    assert!(
+        restored
+            .ledger()
+            .get_record(cleared.id)
+            .is_ok_and(|r| r.cleared)
+    );
+
+    let mut target = GoogleSheetsAdapter::new();
+    let restored_sheet = restored.restore(&mut target, "ledger", &sig).unwrap();
+    assert_eq!(target.list_rows(&restored_sheet).unwrap(), original);
+    assert!(
+        verify_sheet(&target, &restored_sheet, &sig)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn newer_snapshot_versions_are_rejected() {
+    let json = format!("{{\"version\":{},\"records\":[]}}", SNAPSHOT_VERSION + 1);
+    assert_eq!(
+        snapshot::import(&json).unwrap_err(),
+        SnapshotError::UnsupportedVersion(SNAPSHOT_VERSION + 1)
+    );
+    assert!(matches!(
+        snapshot::import("not json"),
+        Err(SnapshotError::Parse(_))
+    ));
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "tui")]
+
+use feed_my_ledger::core::{Ledger, PriceDatabase, Record};
+use feed_my_ledger::tui::filter_register;
+
+fn record(description: &str, debit: &str, amount: f64) -> Record {
+    Record::new(
+        description.into(),
+        debit.parse().unwrap(),
+        "cash".parse().unwrap(),
+        amount,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+#[test]
+fn filter_narrows_register() {
+    let mut ledger = Ledger::default();
+    ledger.commit(record("coffee", "expenses:food", 3.0));
+    ledger.commit(record("rent", "expenses:rent", 100.0));
+    ledger.commit(record("bagel", "expenses:food", 2.0));
+    let prices = PriceDatabase::default();
+
+    let all = filter_register(&ledger, "", "USD", &prices).unwrap();
+    assert_eq!(all.len(), 3);
+    assert!(all.iter().all(|line| line.balance.is_none()));
+
+    let food = filter_register(&ledger, "account:expenses:food", "USD", &prices).unwrap();
+    let descriptions: Vec<_> = food.iter().map(|l| l.record.description.as_str()).collect();
+    assert_eq!(descriptions, vec!["coffee", "bagel"]);
+    assert_eq!(food.last().unwrap().balance, Some(5.0));
+
+    assert!(filter_register(&ledger, "start:not-a-date", "USD", &prices).is_err());
+}
@@ -0,0 +1,83 @@
+use feed_my_ledger::core::{Ledger, Record, ValidationIssue};
+use uuid::Uuid;
+
+fn record(description: &str) -> Record {
+    Record::new(
+        description.into(),
+        "expenses:food".parse().unwrap(),
+        "cash".parse().unwrap(),
+        12.0,
+        "USD".into(),
+        None,
+        None,
+        vec![],
+    )
+    .unwrap()
+}
+
+#[test]
+fn clean_ledger_has_no_issues() {
+    let mut ledger = Ledger::default();
+    let original = record("groceries");
+    let id = original.id;
+    ledger.commit(original);
+    ledger.apply_adjustment(id, record("refund")).unwrap();
+    ledger.commit(ledger.get_record(id).unwrap().reverse());
+    assert!(ledger.validate().is_empty());
+}
+
+#[test]
+fn dangling_reference_is_reported() {
+    let mut ledger = Ledger::default();
+    let missing = Uuid::new_v4();
+    let mut adjustment = record("refund");
+    adjustment.reference_id = Some(missing);
+    let id = adjustment.id;
+    ledger.commit(record("groceries"));
+    ledger.commit(adjustment);
+    let issues = ledger.validate();
+    assert_eq!(
+        issues,
+        vec![ValidationIssue::DanglingReference {
+            id,
+            reference_id: missing
+        }]
+    );
+    assert_eq!(
+        issues[0].to_string(),
+        format!("record {id} references missing record {missing}")
+    );
+}
+
+#[test]
+fn unbalanced_postings_and_unknown_currencies_are_reported() {
+    // Rows loaded from a sheet skip the checks of `Record::new`.
+    let mut same_account = record("transfer");
+    same_account.credit_account = same_account.debit_account.clone();
+    let mut not_a_number = record("typo");
+    not_a_number.amount = f64::NAN;
+    let mut unknown = record("arcade");
+    unknown.currency = "XYZ".into();
+
+    let mut ledger = Ledger::default();
+    for rec in [&same_account, &not_a_number, &unknown] {
+        ledger.commit(rec.clone());
+    }
+    assert_eq!(
+        ledger.validate(),
+        vec![
+            ValidationIssue::UnbalancedPosting {
+                id: same_account.id,
+                posting: 0
+            },
+            ValidationIssue::UnbalancedPosting {
+                id: not_a_number.id,
+                posting: 0
+            },
+            ValidationIssue::UnsupportedCurrency {
+                id: unknown.id,
+                currency: "XYZ".into()
+            },
+        ]
+    );
+}